@@ -8,7 +8,7 @@ use axum::{
     Json, Router,
 };
 use axum_extra::extract::CookieJar;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tower_http::services::ServeDir;
 
 #[tokio::main]
@@ -74,14 +74,42 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
         reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
             StatusCode::UNAUTHORIZED.into_response()
         }
-        reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
-            img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
-        })
-        .into_response(),
+        reqwest::StatusCode::OK => {
+            let verified = match response.json::<VerifyTokenResponse>().await {
+                Ok(verified) => verified,
+                Err(_) => {
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            };
+
+            // Tokens machine clients got for themselves don't log anyone in
+            if verified.kind != "user" {
+                return StatusCode::UNAUTHORIZED.into_response();
+            }
+
+            // Optionally restrict the page to users holding a role, e.g. PROTECTED_ROLE=admin
+            if let Ok(required_role) = env::var("PROTECTED_ROLE") {
+                if !required_role.is_empty() && !verified.roles.contains(&required_role) {
+                    return StatusCode::FORBIDDEN.into_response();
+                }
+            }
+
+            Json(ProtectedRouteResponse {
+                img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
+            })
+            .into_response()
+        }
         _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 }
 
+#[derive(Deserialize)]
+struct VerifyTokenResponse {
+    // `user`, or `client` for machine clients
+    kind: String,
+    roles: Vec<String>,
+}
+
 #[derive(Serialize)]
 pub struct ProtectedRouteResponse {
     pub img_url: String,
@@ -1,10 +1,16 @@
 const loginSection = document.getElementById("login-section");
 const twoFASection = document.getElementById("2fa-section");
 const signupSection = document.getElementById("signup-section");
+const forgotPasswordSection = document.getElementById("forgot-password-section");
+const resetPasswordSection = document.getElementById("reset-password-section");
 
 const signupLink = document.getElementById("signup-link");
 const twoFALoginLink = document.getElementById("2fa-login-link");
 const signupLoginLink = document.getElementById("signup-login-link");
+const forgotPasswordLink = document.getElementById("forgot-password-link");
+const forgotPasswordLoginLink = document.getElementById("forgot-password-login-link");
+const resetPasswordLoginLink = document.getElementById("reset-password-login-link");
+const resendVerificationLink = document.getElementById("resend-verification-link");
 
 signupLink.addEventListener("click", (e) => {
     e.preventDefault();
@@ -30,8 +36,41 @@ signupLoginLink.addEventListener("click", (e) => {
     signupSection.style.display = "none";
 });
 
+forgotPasswordLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    loginSection.style.display = "none";
+    forgotPasswordSection.style.display = "block";
+});
+
+forgotPasswordLoginLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    loginSection.style.display = "block";
+    forgotPasswordSection.style.display = "none";
+});
+
+resetPasswordLoginLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    loginSection.style.display = "block";
+    resetPasswordSection.style.display = "none";
+});
+
 // -----------------------------------------------------
 
+// An app that asked the user to log in through `/authorize` sends them here with the request to
+// carry on with once they have. The device verification page does the same.
+let nextPath = new URLSearchParams(window.location.search).get("next");
+
+function loggedIn() {
+    if (nextPath !== null && (nextPath.startsWith("/authorize?") || nextPath.startsWith("/device"))) {
+        window.location.assign(nextPath);
+    } else {
+        alert("You have successfully logged in.");
+    }
+}
+
 const loginForm = document.getElementById("login-form");
 const loginButton = document.getElementById("login-form-submit");
 const loginErrAlter = document.getElementById("login-err-alert");
@@ -53,6 +92,9 @@ loginButton.addEventListener("click", (e) => {
             TwoFAForm.email.value = email;
             response.json().then(data => {
                 TwoFAForm.login_attempt_id.value = data.loginAttemptId;
+                TwoFAHint.innerText = data.twoFAMethod === "totp"
+                    ? "Enter the code from your authenticator app."
+                    : "Enter the code we sent to your email.";
             });
 
             loginForm.email.value = "";
@@ -66,7 +108,7 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            loggedIn();
         } else {
             response.json().then(data => {
                 let error_msg = data.error;
@@ -104,7 +146,14 @@ signupButton.addEventListener("click", (e) => {
             signupForm.password.value = "";
             signupForm.twoFA.checked = false;
             signupErrAlter.style.display = "none";
-            alert("You have successfully created a user.");
+            response.json().then(data => {
+                let message = "You have successfully created a user. Check your inbox to verify your email address.";
+                if (data.recoveryCodes) {
+                    message += "\n\nSave these recovery codes somewhere safe. Each one can be used once instead of a 2FA code:\n\n"
+                        + data.recoveryCodes.join("\n");
+                }
+                alert(message);
+            });
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
@@ -125,6 +174,7 @@ signupButton.addEventListener("click", (e) => {
 const TwoFAForm = document.getElementById("2fa-form");
 const TwoFAButton = document.getElementById("2fa-form-submit");
 const TwoFAErrAlter = document.getElementById("2fa-err-alert");
+const TwoFAHint = document.getElementById("2fa-hint");
 
 TwoFAButton.addEventListener("click", (e) => {
     e.preventDefault();
@@ -145,7 +195,7 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
+            loggedIn();
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
             signupSection.style.display = "none";
@@ -161,4 +211,416 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+const forgotPasswordForm = document.getElementById("forgot-password-form");
+const forgotPasswordButton = document.getElementById("forgot-password-form-submit");
+const forgotPasswordErrAlter = document.getElementById("forgot-password-err-alert");
+
+forgotPasswordButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = forgotPasswordForm.email.value;
+
+    fetch('/forgot-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email }),
+    }).then(response => {
+        if (response.ok) {
+            forgotPasswordForm.email.value = "";
+            forgotPasswordErrAlter.style.display = "none";
+            alert("If an account exists for this email, a password reset link has been sent.");
+            loginSection.style.display = "block";
+            forgotPasswordSection.style.display = "none";
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    forgotPasswordErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    forgotPasswordErrAlter.style.display = "block";
+                } else {
+                    forgotPasswordErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+});
+
+const resetPasswordForm = document.getElementById("reset-password-form");
+const resetPasswordButton = document.getElementById("reset-password-form-submit");
+const resetPasswordErrAlter = document.getElementById("reset-password-err-alert");
+
+// Show the reset form when the page is opened from a password reset link
+const resetToken = new URLSearchParams(window.location.search).get("reset_token");
+if (resetToken) {
+    resetPasswordForm.token.value = resetToken;
+    loginSection.style.display = "none";
+    resetPasswordSection.style.display = "block";
+}
+
+resetPasswordButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const token = resetPasswordForm.token.value;
+    const newPassword = resetPasswordForm.password.value;
+
+    fetch('/reset-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token, newPassword }),
+    }).then(response => {
+        if (response.ok) {
+            resetPasswordForm.token.value = "";
+            resetPasswordForm.password.value = "";
+            resetPasswordErrAlter.style.display = "none";
+            window.history.replaceState({}, "", "/");
+            alert("Your password has been reset.");
+            loginSection.style.display = "block";
+            resetPasswordSection.style.display = "none";
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    resetPasswordErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    resetPasswordErrAlter.style.display = "block";
+                } else {
+                    resetPasswordErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+});
+
+const magicLinkSection = document.getElementById("magic-link-section");
+const magicLinkForm = document.getElementById("magic-link-form");
+const magicLinkButton = document.getElementById("magic-link-form-submit");
+const magicLinkErrAlter = document.getElementById("magic-link-err-alert");
+const magicLinkLoginLink = document.getElementById("magic-link-login-link");
+
+// Ask before logging in from a magic link, so mail scanners that open the link can't use it up
+const magicLinkToken = new URLSearchParams(window.location.search).get("magic_link_token");
+if (magicLinkToken) {
+    magicLinkForm.id.value = new URLSearchParams(window.location.search).get("magic_link_id");
+    magicLinkForm.token.value = magicLinkToken;
+    loginSection.style.display = "none";
+    magicLinkSection.style.display = "block";
+}
+
+magicLinkLoginLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    window.history.replaceState({}, "", "/");
+    loginSection.style.display = "block";
+    magicLinkSection.style.display = "none";
+});
+
+magicLinkButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const id = magicLinkForm.id.value;
+    const token = magicLinkForm.token.value;
+
+    fetch('/login/magic-link/callback', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ id, token }),
+    }).then(response => {
+        if (response.ok) {
+            magicLinkForm.id.value = "";
+            magicLinkForm.token.value = "";
+            magicLinkErrAlter.style.display = "none";
+            window.history.replaceState({}, "", "/");
+            loginSection.style.display = "block";
+            magicLinkSection.style.display = "none";
+            loggedIn();
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    magicLinkErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    magicLinkErrAlter.style.display = "block";
+                } else {
+                    magicLinkErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+});
+
+// Confirm the email address when the page is opened from a verification link
+const verificationToken = new URLSearchParams(window.location.search).get("verification_token");
+if (verificationToken) {
+    fetch('/verify-email', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token: verificationToken }),
+    }).then(response => {
+        window.history.replaceState({}, "", "/");
+        if (response.ok) {
+            alert("Your email address has been verified. You can now log in.");
+        } else {
+            alert("This verification link is invalid or has expired.");
+        }
+    });
+}
+
+// Apply an email change when the page is opened from the link sent to the new address
+const emailChangeToken = new URLSearchParams(window.location.search).get("email_change_token");
+if (emailChangeToken) {
+    fetch('/confirm-email-change', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token: emailChangeToken }),
+    }).then(response => {
+        window.history.replaceState({}, "", "/");
+        if (response.ok) {
+            alert("Your email address has been changed. From now on, log in with your new address.");
+        } else {
+            alert("This confirmation link is invalid or has expired.");
+        }
+    });
+}
+
+resendVerificationLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = loginForm.email.value;
+
+    fetch('/resend-verification', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email }),
+    }).then(response => {
+        if (response.ok) {
+            loginErrAlter.style.display = "none";
+            alert("If this email belongs to an unverified account, a verification link has been sent.");
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    loginErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    loginErrAlter.style.display = "block";
+                } else {
+                    loginErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+});
+
+// -----------------------------------------------------
+// Social login. Each configured identity provider gets a button, which leaves the page to log in
+// with the provider. The provider sends the browser back to the home page, logged in.
+
+const socialLoginButtons = document.getElementById("social-login-buttons");
+
+fetch('/login/social').then(response => response.json()).then(data => {
+    data.providers.forEach(provider => {
+        const button = document.createElement("a");
+        button.className = "btn btn-outline-dark d-block w-100 mb-3";
+        button.href = `/login/social/${encodeURIComponent(provider)}`;
+        button.textContent = `Log in with ${provider}`;
+        socialLoginButtons.appendChild(button);
+    });
+}).catch(() => {});
+
+// -----------------------------------------------------
+// Passkeys. The server sends and expects binary WebAuthn fields as base64url strings.
+
+const passkeyLoginButton = document.getElementById("passkey-login-button");
+const addPasskeyLink = document.getElementById("add-passkey-link");
+const TwoFAPasskeyButton = document.getElementById("2fa-passkey-button");
+
+function base64urlToBuffer(value) {
+    const base64 = value.replace(/-/g, "+").replace(/_/g, "/");
+    const binary = atob(base64.padEnd(base64.length + (4 - base64.length % 4) % 4, "="));
+    return Uint8Array.from(binary, c => c.charCodeAt(0)).buffer;
+}
+
+function bufferToBase64url(buffer) {
+    const binary = String.fromCharCode(...new Uint8Array(buffer));
+    return btoa(binary).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+}
+
+function showError(alertElement, error_msg) {
+    alertElement.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+    alertElement.style.display = "block";
+}
+
+function postJSON(path, body) {
+    return fetch(path, {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify(body),
+    }).then(response => response.json().catch(() => ({})).then(data => {
+        if (!response.ok) {
+            throw new Error(data.error || "Request failed");
+        }
+        return data;
+    }));
+}
+
+// Ask the browser for a passkey assertion. `body` is empty for a passwordless login, or holds
+// the email and login attempt id of a 2FA login.
+function logInWithPasskey(body) {
+    return postJSON('/webauthn/login/start', body).then(options => navigator.credentials.get({
+        publicKey: {
+            challenge: base64urlToBuffer(options.challenge),
+            rpId: options.rpId,
+            timeout: options.timeout,
+            userVerification: options.userVerification,
+            allowCredentials: options.allowCredentials.map(credential => ({
+                type: credential.type,
+                id: base64urlToBuffer(credential.id),
+            })),
+        },
+    })).then(credential => postJSON('/webauthn/login/finish', {
+        id: credential.id,
+        response: {
+            clientDataJSON: bufferToBase64url(credential.response.clientDataJSON),
+            authenticatorData: bufferToBase64url(credential.response.authenticatorData),
+            signature: bufferToBase64url(credential.response.signature),
+        },
+    }));
+}
+
+passkeyLoginButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    logInWithPasskey({}).then(() => {
+        loginErrAlter.style.display = "none";
+        loggedIn();
+    }).catch(error => showError(loginErrAlter, error.message));
+});
+
+TwoFAPasskeyButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = TwoFAForm.email.value;
+    const loginAttemptId = TwoFAForm.login_attempt_id.value;
+
+    logInWithPasskey({ email, loginAttemptId }).then(() => {
+        TwoFAForm.email.value = "";
+        TwoFAForm.email_code.value = "";
+        TwoFAForm.login_attempt_id.value = "";
+        TwoFAErrAlter.style.display = "none";
+        loggedIn();
+        loginSection.style.display = "block";
+        twoFASection.style.display = "none";
+    }).catch(error => showError(TwoFAErrAlter, error.message));
+});
+
+// Register a passkey for the logged-in user
+addPasskeyLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    postJSON('/webauthn/register/start', {}).then(options => navigator.credentials.create({
+        publicKey: {
+            ...options,
+            challenge: base64urlToBuffer(options.challenge),
+            user: { ...options.user, id: base64urlToBuffer(options.user.id) },
+            excludeCredentials: options.excludeCredentials.map(credential => ({
+                type: credential.type,
+                id: base64urlToBuffer(credential.id),
+            })),
+        },
+    })).then(credential => postJSON('/webauthn/register/finish', {
+        name: prompt("Name this passkey", "My passkey") || "",
+        id: credential.id,
+        response: {
+            clientDataJSON: bufferToBase64url(credential.response.clientDataJSON),
+            attestationObject: bufferToBase64url(credential.response.attestationObject),
+        },
+    })).then(() => {
+        loginErrAlter.style.display = "none";
+        alert("Your passkey has been added. You can now use it to log in.");
+    }).catch(error => showError(loginErrAlter, error.message));
+});
+
+// -----------------------------------------------------
+// Device login. CLIs and other devices without a browser send the user to `/device` with a code
+// to enter, and log in once the user allows them.
+
+const deviceSection = document.getElementById("device-section");
+const deviceForm = document.getElementById("device-form");
+const deviceButton = document.getElementById("device-form-submit");
+const deviceErrAlter = document.getElementById("device-err-alert");
+const deviceApproval = document.getElementById("device-approval");
+const deviceApprovalPrompt = document.getElementById("device-approval-prompt");
+const deviceApproveButton = document.getElementById("device-approve-button");
+const deviceDenyButton = document.getElementById("device-deny-button");
+
+if (window.location.pathname === "/device") {
+    loginSection.style.display = "none";
+    deviceSection.style.display = "block";
+    deviceForm.user_code.value = new URLSearchParams(window.location.search).get("user_code") || "";
+}
+
+deviceButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const userCode = deviceForm.user_code.value;
+
+    fetch('/device/verify', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ userCode }),
+    }).then(response => response.json().then(data => {
+        if (response.ok) {
+            deviceErrAlter.style.display = "none";
+            deviceApprovalPrompt.innerText = `Allow ${data.clientName} to log in as you?`;
+            deviceForm.style.display = "none";
+            deviceApproval.style.display = "block";
+        } else if (data.error === "Missing auth token" || data.error === "Invalid auth token") {
+            // Log in first, then come back with the code filled in
+            nextPath = `/device?user_code=${encodeURIComponent(userCode)}`;
+            deviceErrAlter.style.display = "none";
+            deviceSection.style.display = "none";
+            loginSection.style.display = "block";
+        } else {
+            showError(deviceErrAlter, data.error);
+        }
+    }));
+});
+
+function answerDevice(decision) {
+    const userCode = deviceForm.user_code.value;
+
+    postJSON('/device/approve', { userCode, decision }).then(() => {
+        deviceErrAlter.style.display = "none";
+        deviceApproval.style.display = "none";
+        deviceForm.user_code.value = "";
+        deviceForm.style.display = "block";
+        window.history.replaceState({}, "", "/device");
+        alert(decision === "approve"
+            ? "Your device is now logged in. You can return to it."
+            : "Your device was not allowed to log in.");
+    }).catch(error => showError(deviceErrAlter, error.message));
+}
+
+deviceApproveButton.addEventListener("click", (e) => {
+    e.preventDefault();
+    answerDevice("approve");
+});
+
+deviceDenyButton.addEventListener("click", (e) => {
+    e.preventDefault();
+    answerDevice("deny");
+});
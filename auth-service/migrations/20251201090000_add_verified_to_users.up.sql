@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Accounts created before email verification existed are trusted as-is
+UPDATE users
+SET verified = TRUE;
@@ -0,0 +1,11 @@
+-- Add down migration script here
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS requires_2fa BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- TOTP users fall back to email codes rather than losing 2FA altogether
+UPDATE users
+SET requires_2fa = two_fa_method <> 'none';
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS two_fa_method,
+    DROP COLUMN IF EXISTS totp_secret;
@@ -0,0 +1,11 @@
+-- Add up migration script here
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS two_fa_method TEXT NOT NULL DEFAULT 'none',
+    ADD COLUMN IF NOT EXISTS totp_secret TEXT;
+
+-- Users who opted into 2FA so far all received their codes by email
+UPDATE users
+SET two_fa_method = CASE WHEN requires_2fa THEN 'email' ELSE 'none' END;
+
+ALTER TABLE users
+    DROP COLUMN IF EXISTS requires_2fa;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS recovery_codes
+(
+    email     TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    PRIMARY KEY (email, code_hash)
+);
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS sessions
+(
+    id           TEXT        NOT NULL PRIMARY KEY,
+    email        TEXT        NOT NULL REFERENCES users (email) ON DELETE CASCADE,
+    user_agent   TEXT,
+    ip_address   TEXT,
+    created_at   TIMESTAMPTZ NOT NULL,
+    last_seen_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions (email);
@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS signing_keys
+(
+    kid         TEXT        NOT NULL PRIMARY KEY,
+    algorithm   TEXT        NOT NULL,
+    private_key TEXT        NOT NULL,
+    public_key  TEXT,
+    created_at  TIMESTAMPTZ NOT NULL,
+    expires_at  TIMESTAMPTZ
+);
+
+-- Only one key signs new tokens at a time
+CREATE UNIQUE INDEX IF NOT EXISTS signing_keys_active_idx ON signing_keys ((expires_at IS NULL))
+    WHERE expires_at IS NULL;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS user_roles
+(
+    email TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE,
+    role  TEXT NOT NULL,
+    PRIMARY KEY (email, role)
+);
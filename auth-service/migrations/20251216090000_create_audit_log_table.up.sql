@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- No foreign key to users: the record of an account's deletion has to outlive the account
+CREATE TABLE IF NOT EXISTS audit_log
+(
+    id          BIGSERIAL   NOT NULL PRIMARY KEY,
+    action      TEXT        NOT NULL,
+    email       TEXT        NOT NULL,
+    user_agent  TEXT,
+    ip_address  TEXT,
+    occurred_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_email_idx ON audit_log (email);
@@ -0,0 +1,15 @@
+-- Add down migration script here
+ALTER TABLE recovery_codes
+    DROP CONSTRAINT IF EXISTS recovery_codes_email_fkey,
+    ADD CONSTRAINT recovery_codes_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE;
+
+ALTER TABLE sessions
+    DROP CONSTRAINT IF EXISTS sessions_email_fkey,
+    ADD CONSTRAINT sessions_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE;
+
+ALTER TABLE user_roles
+    DROP CONSTRAINT IF EXISTS user_roles_email_fkey,
+    ADD CONSTRAINT user_roles_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE;
@@ -0,0 +1,26 @@
+-- Add up migration script here
+-- Users get a stable id, so tokens can name them without naming their address.
+-- Everything else still refers to users by email, which stays unique.
+ALTER TABLE users ADD COLUMN id UUID NOT NULL DEFAULT gen_random_uuid();
+ALTER TABLE users ALTER COLUMN id DROP DEFAULT;
+
+ALTER TABLE recovery_codes DROP CONSTRAINT IF EXISTS recovery_codes_email_fkey;
+ALTER TABLE sessions DROP CONSTRAINT IF EXISTS sessions_email_fkey;
+ALTER TABLE user_roles DROP CONSTRAINT IF EXISTS user_roles_email_fkey;
+
+ALTER TABLE users
+    DROP CONSTRAINT users_pkey,
+    ADD CONSTRAINT users_pkey PRIMARY KEY (id),
+    ADD CONSTRAINT users_email_key UNIQUE (email);
+
+ALTER TABLE recovery_codes
+    ADD CONSTRAINT recovery_codes_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE sessions
+    ADD CONSTRAINT sessions_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE;
+
+ALTER TABLE user_roles
+    ADD CONSTRAINT user_roles_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS passkeys
+(
+    id         TEXT        NOT NULL PRIMARY KEY,
+    email      TEXT        NOT NULL REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE,
+    name       TEXT        NOT NULL,
+    public_key BYTEA       NOT NULL,
+    sign_count BIGINT      NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS passkeys_email_idx ON passkeys (email);
@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS oauth_clients
+(
+    id            UUID        NOT NULL PRIMARY KEY,
+    name          TEXT        NOT NULL,
+    redirect_uris TEXT[]      NOT NULL,
+    created_at    TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS oauth_consents
+(
+    email      TEXT        NOT NULL REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE,
+    client_id  UUID        NOT NULL REFERENCES oauth_clients (id) ON DELETE CASCADE,
+    granted_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (email, client_id)
+);
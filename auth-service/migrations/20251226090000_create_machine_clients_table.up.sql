@@ -0,0 +1,9 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS machine_clients
+(
+    id          UUID        NOT NULL PRIMARY KEY,
+    name        TEXT        NOT NULL,
+    secret_hash TEXT        NOT NULL,
+    scopes      TEXT[]      NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,67 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS audit_log_user_id_idx;
+ALTER TABLE audit_log DROP COLUMN user_id;
+
+ALTER TABLE external_identities ADD COLUMN email TEXT;
+UPDATE external_identities SET email = users.email
+    FROM users WHERE users.id = external_identities.user_id;
+ALTER TABLE external_identities
+    DROP COLUMN user_id,
+    ALTER COLUMN email SET NOT NULL,
+    ADD CONSTRAINT external_identities_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE;
+CREATE INDEX IF NOT EXISTS external_identities_email_idx ON external_identities (email);
+
+ALTER TABLE oauth_consents ADD COLUMN email TEXT;
+UPDATE oauth_consents SET email = users.email FROM users WHERE users.id = oauth_consents.user_id;
+ALTER TABLE oauth_consents
+    DROP COLUMN user_id,
+    ALTER COLUMN email SET NOT NULL,
+    ADD CONSTRAINT oauth_consents_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE,
+    ADD PRIMARY KEY (email, client_id);
+
+ALTER TABLE api_keys ADD COLUMN email TEXT;
+UPDATE api_keys SET email = users.email FROM users WHERE users.id = api_keys.user_id;
+ALTER TABLE api_keys
+    DROP COLUMN user_id,
+    ALTER COLUMN email SET NOT NULL,
+    ADD CONSTRAINT api_keys_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE;
+CREATE INDEX IF NOT EXISTS api_keys_email_idx ON api_keys (email);
+
+ALTER TABLE passkeys ADD COLUMN email TEXT;
+UPDATE passkeys SET email = users.email FROM users WHERE users.id = passkeys.user_id;
+ALTER TABLE passkeys
+    DROP COLUMN user_id,
+    ALTER COLUMN email SET NOT NULL,
+    ADD CONSTRAINT passkeys_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE;
+CREATE INDEX IF NOT EXISTS passkeys_email_idx ON passkeys (email);
+
+ALTER TABLE user_roles ADD COLUMN email TEXT;
+UPDATE user_roles SET email = users.email FROM users WHERE users.id = user_roles.user_id;
+ALTER TABLE user_roles
+    DROP COLUMN user_id,
+    ALTER COLUMN email SET NOT NULL,
+    ADD CONSTRAINT user_roles_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE,
+    ADD PRIMARY KEY (email, role);
+
+ALTER TABLE sessions ADD COLUMN email TEXT;
+UPDATE sessions SET email = users.email FROM users WHERE users.id = sessions.user_id;
+ALTER TABLE sessions
+    DROP COLUMN user_id,
+    ALTER COLUMN email SET NOT NULL,
+    ADD CONSTRAINT sessions_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE;
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions (email);
+
+ALTER TABLE recovery_codes ADD COLUMN email TEXT;
+UPDATE recovery_codes SET email = users.email FROM users WHERE users.id = recovery_codes.user_id;
+ALTER TABLE recovery_codes
+    DROP COLUMN user_id,
+    ALTER COLUMN email SET NOT NULL,
+    ADD CONSTRAINT recovery_codes_email_fkey FOREIGN KEY (email) REFERENCES users (email)
+        ON DELETE CASCADE ON UPDATE CASCADE,
+    ADD PRIMARY KEY (email, code_hash);
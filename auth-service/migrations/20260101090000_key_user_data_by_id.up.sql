@@ -0,0 +1,72 @@
+-- Add up migration script here
+-- Everything stored for a user refers to them by id rather than by address, so nothing has to
+-- follow them around when they change it
+ALTER TABLE recovery_codes ADD COLUMN user_id UUID;
+UPDATE recovery_codes SET user_id = users.id FROM users WHERE users.email = recovery_codes.email;
+ALTER TABLE recovery_codes
+    DROP COLUMN email,
+    ALTER COLUMN user_id SET NOT NULL,
+    ADD CONSTRAINT recovery_codes_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id)
+        ON DELETE CASCADE,
+    ADD PRIMARY KEY (user_id, code_hash);
+
+ALTER TABLE sessions ADD COLUMN user_id UUID;
+UPDATE sessions SET user_id = users.id FROM users WHERE users.email = sessions.email;
+ALTER TABLE sessions
+    DROP COLUMN email,
+    ALTER COLUMN user_id SET NOT NULL,
+    ADD CONSTRAINT sessions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id)
+        ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS sessions_user_id_idx ON sessions (user_id);
+
+ALTER TABLE user_roles ADD COLUMN user_id UUID;
+UPDATE user_roles SET user_id = users.id FROM users WHERE users.email = user_roles.email;
+ALTER TABLE user_roles
+    DROP COLUMN email,
+    ALTER COLUMN user_id SET NOT NULL,
+    ADD CONSTRAINT user_roles_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id)
+        ON DELETE CASCADE,
+    ADD PRIMARY KEY (user_id, role);
+
+ALTER TABLE passkeys ADD COLUMN user_id UUID;
+UPDATE passkeys SET user_id = users.id FROM users WHERE users.email = passkeys.email;
+ALTER TABLE passkeys
+    DROP COLUMN email,
+    ALTER COLUMN user_id SET NOT NULL,
+    ADD CONSTRAINT passkeys_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id)
+        ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS passkeys_user_id_idx ON passkeys (user_id);
+
+ALTER TABLE api_keys ADD COLUMN user_id UUID;
+UPDATE api_keys SET user_id = users.id FROM users WHERE users.email = api_keys.email;
+ALTER TABLE api_keys
+    DROP COLUMN email,
+    ALTER COLUMN user_id SET NOT NULL,
+    ADD CONSTRAINT api_keys_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id)
+        ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS api_keys_user_id_idx ON api_keys (user_id);
+
+ALTER TABLE oauth_consents ADD COLUMN user_id UUID;
+UPDATE oauth_consents SET user_id = users.id FROM users WHERE users.email = oauth_consents.email;
+ALTER TABLE oauth_consents
+    DROP COLUMN email,
+    ALTER COLUMN user_id SET NOT NULL,
+    ADD CONSTRAINT oauth_consents_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id)
+        ON DELETE CASCADE,
+    ADD PRIMARY KEY (user_id, client_id);
+
+ALTER TABLE external_identities ADD COLUMN user_id UUID;
+UPDATE external_identities SET user_id = users.id
+    FROM users WHERE users.email = external_identities.email;
+ALTER TABLE external_identities
+    DROP COLUMN email,
+    ALTER COLUMN user_id SET NOT NULL,
+    ADD CONSTRAINT external_identities_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id)
+        ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS external_identities_user_id_idx ON external_identities (user_id);
+
+-- Still no foreign key, so events outlive the account. Events about accounts that are already
+-- gone, or recorded against an address the user has since moved away from, can't be matched up.
+ALTER TABLE audit_log ADD COLUMN user_id UUID;
+UPDATE audit_log SET user_id = users.id FROM users WHERE users.email = audit_log.email;
+CREATE INDEX IF NOT EXISTS audit_log_user_id_idx ON audit_log (user_id);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE oauth_consents DROP COLUMN scopes;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Consents given before scopes were recorded cover none, so those users are asked again
+ALTER TABLE oauth_consents ADD COLUMN scopes TEXT[] NOT NULL DEFAULT '{}';
@@ -1,4 +1,12 @@
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::domain::{
+    ApiKeyStore, AuditLogStore, AuthorizationCodeStore, BannedTokenStore, DeviceCodeStore, Email,
+    EmailChange, EmailChangeToken, EmailClient, EmailVerificationToken, ExternalIdentityStore,
+    LoginAttemptStore, MachineClientStore, MagicLinkToken, OAuthClientStore, PasskeyStore,
+    PasswordResetToken, RateLimitStore, RecoveryCodeStore, RefreshTokenStore, SessionStore,
+    SigningKeyStore, SingleUseTokenStore, SocialLoginStateStore, TwoFACodeStore, UserStore,
+    WebAuthnChallengeStore,
+};
+use crate::services::identity_provider_client::IdentityProviderClient;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -8,28 +16,79 @@ pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 
+pub type SingleUseTokenStoreType<K, V> = Arc<RwLock<dyn SingleUseTokenStore<K, V> + Send + Sync>>;
+
+pub type PasswordResetTokenStoreType = SingleUseTokenStoreType<PasswordResetToken, Email>;
+
+pub type EmailVerificationTokenStoreType = SingleUseTokenStoreType<EmailVerificationToken, Email>;
+
+pub type EmailChangeTokenStoreType = SingleUseTokenStoreType<EmailChangeToken, EmailChange>;
+
+// Each user has at most one live magic link, so the token is stored under their address
+pub type MagicLinkTokenStoreType = SingleUseTokenStoreType<Email, MagicLinkToken>;
+
+pub type WebAuthnChallengeStoreType = Arc<RwLock<dyn WebAuthnChallengeStore + Send + Sync>>;
+
+pub type AuthorizationCodeStoreType = Arc<RwLock<dyn AuthorizationCodeStore + Send + Sync>>;
+
+pub type DeviceCodeStoreType = Arc<RwLock<dyn DeviceCodeStore + Send + Sync>>;
+
+pub type SocialLoginStateStoreType = Arc<RwLock<dyn SocialLoginStateStore + Send + Sync>>;
+
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore + Send + Sync>>;
+
+pub type PasskeyStoreType = Arc<RwLock<dyn PasskeyStore + Send + Sync>>;
+
+pub type ExternalIdentityStoreType = Arc<RwLock<dyn ExternalIdentityStore + Send + Sync>>;
+
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore + Send + Sync>>;
+
+pub type OAuthClientStoreType = Arc<RwLock<dyn OAuthClientStore + Send + Sync>>;
+
+pub type MachineClientStoreType = Arc<RwLock<dyn MachineClientStore + Send + Sync>>;
+
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
+
+pub type SigningKeyStoreType = Arc<RwLock<dyn SigningKeyStore + Send + Sync>>;
+
+pub type LoginAttemptStoreType = Arc<RwLock<dyn LoginAttemptStore + Send + Sync>>;
+
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore + Send + Sync>>;
+
+pub type AuditLogStoreType = Arc<RwLock<dyn AuditLogStore + Send + Sync>>;
+
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
 
-#[derive(Clone)] 
+pub type IdentityProviderClientType = Arc<IdentityProviderClient>;
+
+// Built with a struct literal, so adding a store doesn't touch every other one
+#[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub email_change_token_store: EmailChangeTokenStoreType,
+    pub magic_link_token_store: MagicLinkTokenStoreType,
+    pub webauthn_challenge_store: WebAuthnChallengeStoreType,
+    pub authorization_code_store: AuthorizationCodeStoreType,
+    pub device_code_store: DeviceCodeStoreType,
+    pub social_login_state_store: SocialLoginStateStoreType,
+    pub recovery_code_store: RecoveryCodeStoreType,
+    pub passkey_store: PasskeyStoreType,
+    pub external_identity_store: ExternalIdentityStoreType,
+    pub api_key_store: ApiKeyStoreType,
+    pub oauth_client_store: OAuthClientStoreType,
+    pub machine_client_store: MachineClientStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_store: SessionStoreType,
+    pub signing_key_store: SigningKeyStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
+    pub rate_limit_store: RateLimitStoreType,
+    pub audit_log_store: AuditLogStoreType,
     pub email_client: EmailClientType,
-}
-
-impl AppState {
-    pub fn new(
-        user_store: UserStoreType,
-        banned_token_store: BannedTokenStoreType,
-        two_fa_code_store: TwoFACodeStoreType,
-        email_client: EmailClientType,
-    ) -> Self {
-        Self {
-            user_store,
-            banned_token_store,
-            two_fa_code_store,
-            email_client,
-        }
-    }
+    pub identity_provider_client: IdentityProviderClientType,
 }
@@ -0,0 +1,72 @@
+use super::{Email, UserId};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+// Something done to an account that has to be on record, even after the account itself is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    AccountDeleted,
+    // Recorded against the address the account moved away from
+    EmailChanged,
+}
+
+impl AuditAction {
+    pub fn parse(action: &str) -> Result<Self> {
+        match action {
+            "account_deleted" => Ok(Self::AccountDeleted),
+            "email_changed" => Ok(Self::EmailChanged),
+            _ => Err(eyre!("Invalid audit action: {}", action)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AccountDeleted => "account_deleted",
+            Self::EmailChanged => "email_changed",
+        }
+    }
+}
+
+// Who did what to which account, from where and when
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub action: AuditAction,
+    pub user_id: UserId,
+    // The account's address at the time
+    pub email: Email,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(
+        action: AuditAction,
+        user_id: UserId,
+        email: Email,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> Self {
+        Self {
+            action,
+            user_id,
+            email,
+            user_agent,
+            ip_address,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_action_round_trips_through_str() {
+        for action in [AuditAction::AccountDeleted, AuditAction::EmailChanged] {
+            assert_eq!(AuditAction::parse(action.as_str()).unwrap(), action);
+        }
+        assert!(AuditAction::parse("account_created").is_err());
+    }
+}
@@ -1,7 +1,16 @@
-use super::{Email, User};
+use super::{
+    ApiKey, ApiKeyId, AuditEvent, AuthorizationCode, AuthorizationGrant, DeviceAuthorization,
+    DeviceCode, Email, EncryptedTotpSecret, ExternalIdentity, FailedAttempts, HashedApiKey,
+    HashedPassword, HashedRecoveryCode, LoginAttemptKey, MachineClient, OAuthClient, OAuthClientId,
+    OAuthConsent, Passkey, PasskeyId, RateLimit, Role, Session, SessionId, SigningKey,
+    SocialLoginRequest, SocialLoginState, TwoFAMethod, User, UserCode, UserId, WebAuthnCeremony,
+    WebAuthnChallenge,
+};
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Report, Result};
 use rand::Rng;
 use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
 use thiserror::Error;
 
 #[async_trait::async_trait]
@@ -9,11 +18,41 @@ pub trait UserStore {
     // Make sure all methods are async so we can use async user stores in the future
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError>;
     async fn validate_user(
         &self,
         email: &Email,
         raw_password: &SecretString,
     ) -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: EncryptedTotpSecret,
+    ) -> Result<(), UserStoreError>;
+    async fn set_two_fa_method(
+        &mut self,
+        email: &Email,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError>;
+    // Granting a role the user already has, or revoking one they don't, is a no-op
+    async fn add_role(&mut self, email: &Email, role: Role) -> Result<(), UserStoreError>;
+    async fn remove_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError>;
+    // Move the user from `email` to `new_email`. Fails with `UserNotFound` unless `email` is still
+    // the user's address. Everything else refers to them by id, so it stays with them.
+    async fn update_email(
+        &mut self,
+        user_id: &UserId,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), UserStoreError>;
+    // Remove the user along with their roles
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -39,10 +78,26 @@ impl PartialEq for UserStoreError {
     }
 }
 
+// This trait represents the interface all concrete audit log stores should implement.
+// Events are kept after the account they are about is deleted.
+#[async_trait::async_trait]
+pub trait AuditLogStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError>;
+    // Oldest first
+    async fn get_events(&self, user_id: &UserId) -> Result<Vec<AuditEvent>, AuditLogStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditLogStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError>;
-    async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError>;
+    // Tokens are banned by their `jti` claim until they would have expired anyway
+    async fn add_token_id(&mut self, jti: &str) -> Result<(), BannedTokenStoreError>;
+    async fn contains_token_id(&self, jti: &str) -> Result<bool, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -51,6 +106,67 @@ pub enum BannedTokenStoreError {
     UnexpectedError(#[source] Report),
 }
 
+// This trait represents the interface all concrete failed login attempt stores should implement
+#[async_trait::async_trait]
+pub trait LoginAttemptStore {
+    async fn get_failures(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<Option<FailedAttempts>, LoginAttemptStoreError>;
+    // Count another failure. They are all forgotten once `FAILED_ATTEMPTS_TTL_SECONDS` passes
+    // without one.
+    async fn record_failure(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<FailedAttempts, LoginAttemptStoreError>;
+    // Count an attempt as a failure before checking it, returning the failures before it.
+    // Counting and reading happen at once, so parallel attempts can't all see the same count.
+    async fn reserve_attempt(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<Option<FailedAttempts>, LoginAttemptStoreError>;
+    // Take back a reserved attempt that succeeded
+    async fn release_attempt(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<(), LoginAttemptStoreError>;
+    async fn clear_failures(&mut self, key: &LoginAttemptKey)
+        -> Result<(), LoginAttemptStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum LoginAttemptStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// This trait represents the interface all concrete rate limit stores should implement
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    // Take a token from the bucket under `key`, failing with `RateLimitExceeded` when it is empty
+    async fn take_token(&mut self, key: &str, limit: &RateLimit)
+        -> Result<(), RateLimitStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    // Milliseconds until a token is available
+    #[error("Rate limit exceeded")]
+    RateLimitExceeded(u64),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::RateLimitExceeded(a), Self::RateLimitExceeded(b)) => a == b,
+            (Self::UnexpectedError(_), Self::UnexpectedError(_)) => true,
+            _ => false,
+        }
+    }
+}
+
 // This trait represents the interface all concrete 2FA code stores should implement
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
@@ -153,3 +269,820 @@ impl AsRef<SecretString> for TwoFACode {
         &self.0
     }
 }
+
+// This trait represents the interface all concrete single-use token stores should implement.
+// Each entry maps a key, usually the token itself, to what the token stands for, and is removed
+// once the token is used. Password reset, email verification, email change and magic link tokens
+// all live in one of these.
+#[async_trait::async_trait]
+pub trait SingleUseTokenStore<K, V>
+where
+    K: Send + Sync,
+    V: Send,
+{
+    // Adding a key that is already stored replaces its value
+    async fn add_token(&mut self, key: K, value: V) -> Result<(), SingleUseTokenStoreError>;
+    async fn remove_token(&mut self, key: &K) -> Result<(), SingleUseTokenStoreError>;
+    async fn get_token(&self, key: &K) -> Result<V, SingleUseTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SingleUseTokenStoreError {
+    #[error("Token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SingleUseTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// What a single-use token is for. Each purpose gets its own store, key space and lifetime.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenPurpose {
+    PasswordReset,
+    EmailVerification,
+    EmailChange,
+    MagicLink,
+}
+
+// The keys and values of single-use token stores, which stores like Redis keep as strings
+pub trait TokenStoreEntry: Sized {
+    fn encode(&self) -> String;
+    fn decode(encoded: String) -> Result<Self>;
+}
+
+impl TokenStoreEntry for Email {
+    fn encode(&self) -> String {
+        self.as_ref().expose_secret().to_owned()
+    }
+
+    fn decode(encoded: String) -> Result<Self> {
+        Email::parse(SecretString::new(encoded.into_boxed_str()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordResetToken(SecretString);
+
+impl PartialEq for PasswordResetToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl PasswordResetToken {
+    pub fn parse(token: SecretString) -> Result<Self> {
+        let token = uuid::Uuid::parse_str(token.expose_secret())
+            .map_err(|_| eyre!("Invalid password reset token"))?;
+        Ok(Self(SecretString::new(token.to_string().into_boxed_str())))
+    }
+}
+
+impl Default for PasswordResetToken {
+    fn default() -> Self {
+        Self(SecretString::new(
+            uuid::Uuid::new_v4().to_string().into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for PasswordResetToken {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+impl TokenStoreEntry for PasswordResetToken {
+    fn encode(&self) -> String {
+        self.0.expose_secret().to_owned()
+    }
+
+    fn decode(encoded: String) -> Result<Self> {
+        Self::parse(SecretString::new(encoded.into_boxed_str()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailVerificationToken(SecretString);
+
+impl PartialEq for EmailVerificationToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl EmailVerificationToken {
+    pub fn parse(token: SecretString) -> Result<Self> {
+        let token = uuid::Uuid::parse_str(token.expose_secret())
+            .map_err(|_| eyre!("Invalid email verification token"))?;
+        Ok(Self(SecretString::new(token.to_string().into_boxed_str())))
+    }
+}
+
+impl Default for EmailVerificationToken {
+    fn default() -> Self {
+        Self(SecretString::new(
+            uuid::Uuid::new_v4().to_string().into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for EmailVerificationToken {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+impl TokenStoreEntry for EmailVerificationToken {
+    fn encode(&self) -> String {
+        self.0.expose_secret().to_owned()
+    }
+
+    fn decode(encoded: String) -> Result<Self> {
+        Self::parse(SecretString::new(encoded.into_boxed_str()))
+    }
+}
+
+// A change of address the user has asked for, waiting for the new address to be confirmed
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailChange {
+    // The account that asked. Someone else may hold `old_email` by the time the link is followed.
+    pub user_id: UserId,
+    pub old_email: Email,
+    pub new_email: Email,
+}
+
+impl TokenStoreEntry for EmailChange {
+    fn encode(&self) -> String {
+        serde_json::json!({
+            "user_id": self.user_id.to_string(),
+            "old_email": self.old_email.encode(),
+            "new_email": self.new_email.encode(),
+        })
+        .to_string()
+    }
+
+    fn decode(encoded: String) -> Result<Self> {
+        let data: EmailChangeData = serde_json::from_str(&encoded)?;
+        Ok(Self {
+            user_id: UserId::parse(data.user_id)?,
+            old_email: Email::decode(data.old_email)?,
+            new_email: Email::decode(data.new_email)?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct EmailChangeData {
+    user_id: String,
+    old_email: String,
+    new_email: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailChangeToken(SecretString);
+
+impl PartialEq for EmailChangeToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl EmailChangeToken {
+    pub fn parse(token: SecretString) -> Result<Self> {
+        let token = uuid::Uuid::parse_str(token.expose_secret())
+            .map_err(|_| eyre!("Invalid email change token"))?;
+        Ok(Self(SecretString::new(token.to_string().into_boxed_str())))
+    }
+}
+
+impl Default for EmailChangeToken {
+    fn default() -> Self {
+        Self(SecretString::new(
+            uuid::Uuid::new_v4().to_string().into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for EmailChangeToken {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+impl TokenStoreEntry for EmailChangeToken {
+    fn encode(&self) -> String {
+        self.0.expose_secret().to_owned()
+    }
+
+    fn decode(encoded: String) -> Result<Self> {
+        Self::parse(SecretString::new(encoded.into_boxed_str()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MagicLinkToken(SecretString);
+
+impl PartialEq for MagicLinkToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl MagicLinkToken {
+    pub fn parse(token: SecretString) -> Result<Self> {
+        let token = uuid::Uuid::parse_str(token.expose_secret())
+            .map_err(|_| eyre!("Invalid magic link token"))?;
+        Ok(Self(SecretString::new(token.to_string().into_boxed_str())))
+    }
+}
+
+impl Default for MagicLinkToken {
+    fn default() -> Self {
+        Self(SecretString::new(
+            uuid::Uuid::new_v4().to_string().into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for MagicLinkToken {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+impl TokenStoreEntry for MagicLinkToken {
+    fn encode(&self) -> String {
+        self.0.expose_secret().to_owned()
+    }
+
+    fn decode(encoded: String) -> Result<Self> {
+        Self::parse(SecretString::new(encoded.into_boxed_str()))
+    }
+}
+
+// This trait represents the interface all concrete passkey stores should implement
+#[async_trait::async_trait]
+pub trait PasskeyStore {
+    async fn add_passkey(&mut self, passkey: Passkey) -> Result<(), PasskeyStoreError>;
+    async fn get_passkey(&self, id: &PasskeyId) -> Result<Passkey, PasskeyStoreError>;
+    // The user's passkeys, oldest first
+    async fn get_passkeys(&self, user_id: &UserId) -> Result<Vec<Passkey>, PasskeyStoreError>;
+    async fn update_sign_count(
+        &mut self,
+        id: &PasskeyId,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PasskeyStoreError {
+    #[error("Passkey already exists")]
+    PasskeyAlreadyExists,
+    #[error("Passkey not found")]
+    PasskeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasskeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::PasskeyAlreadyExists, Self::PasskeyAlreadyExists)
+                | (Self::PasskeyNotFound, Self::PasskeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete external identity stores should implement.
+// It records which of our users each identity provider account logs in as.
+#[async_trait::async_trait]
+pub trait ExternalIdentityStore {
+    async fn add_identity(
+        &mut self,
+        identity: ExternalIdentity,
+    ) -> Result<(), ExternalIdentityStoreError>;
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<ExternalIdentity, ExternalIdentityStoreError>;
+    // The identities linked to the user, oldest first
+    async fn get_identities(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<ExternalIdentity>, ExternalIdentityStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ExternalIdentityStoreError {
+    #[error("External identity already exists")]
+    IdentityAlreadyExists,
+    #[error("External identity not found")]
+    IdentityNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ExternalIdentityStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::IdentityAlreadyExists, Self::IdentityAlreadyExists)
+                | (Self::IdentityNotFound, Self::IdentityNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete API key stores should implement
+#[async_trait::async_trait]
+pub trait ApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError>;
+    // The user's keys, oldest first
+    async fn get_keys(&self, user_id: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    async fn get_key_by_hash(&self, key_hash: &HashedApiKey) -> Result<ApiKey, ApiKeyStoreError>;
+    // Only the user who owns a key can delete it
+    async fn delete_key(&mut self, user_id: &UserId, id: &ApiKeyId)
+        -> Result<(), ApiKeyStoreError>;
+    // Record that the key was just used
+    async fn touch_key(&mut self, id: &ApiKeyId) -> Result<(), ApiKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    #[error("API key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ApiKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyNotFound, Self::KeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete WebAuthn challenge stores should implement
+#[async_trait::async_trait]
+pub trait WebAuthnChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: WebAuthnChallenge,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<(), WebAuthnChallengeStoreError>;
+    async fn remove_challenge(
+        &mut self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<(), WebAuthnChallengeStoreError>;
+    async fn get_ceremony(
+        &self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<WebAuthnCeremony, WebAuthnChallengeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum WebAuthnChallengeStoreError {
+    #[error("WebAuthn challenge not found")]
+    ChallengeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for WebAuthnChallengeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ChallengeNotFound, Self::ChallengeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete OAuth client stores should implement.
+// It also records which clients each user has allowed to log them in.
+#[async_trait::async_trait]
+pub trait OAuthClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError>;
+    async fn get_client(&self, id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError>;
+    // Allowing a client again adds any new scopes to what it was allowed before
+    async fn add_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scopes: &[String],
+    ) -> Result<(), OAuthClientStoreError>;
+    // Whether the user has allowed the client every one of `scopes`
+    async fn has_consent(
+        &self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scopes: &[String],
+    ) -> Result<bool, OAuthClientStoreError>;
+    // The clients the user has allowed, oldest first
+    async fn get_consents(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<OAuthConsent>, OAuthClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthClientStoreError {
+    #[error("OAuth client not found")]
+    ClientNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OAuthClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete machine client stores should implement
+#[async_trait::async_trait]
+pub trait MachineClientStore {
+    async fn add_client(&mut self, client: MachineClient) -> Result<(), MachineClientStoreError>;
+    async fn get_client(
+        &self,
+        id: &OAuthClientId,
+    ) -> Result<MachineClient, MachineClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum MachineClientStoreError {
+    #[error("Machine client not found")]
+    ClientNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for MachineClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete authorization code stores should implement
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    // Fails with `CodeNotFound` if the code was already removed, so only one caller can use it
+    async fn remove_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    async fn get_grant(
+        &self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorizationCodeStoreError {
+    #[error("Authorization code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuthorizationCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete device code stores should implement
+#[async_trait::async_trait]
+pub trait DeviceCodeStore {
+    async fn add_code(
+        &mut self,
+        code: DeviceCode,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceCodeStoreError>;
+    async fn get_authorization(
+        &self,
+        code: &DeviceCode,
+    ) -> Result<DeviceAuthorization, DeviceCodeStoreError>;
+    // Find the device code the user was shown `user_code` for
+    async fn get_code(&self, user_code: &UserCode) -> Result<DeviceCode, DeviceCodeStoreError>;
+    // Replace the authorization, keeping the code's original expiry
+    async fn update_authorization(
+        &mut self,
+        code: &DeviceCode,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceCodeStoreError>;
+    // Fails with `CodeNotFound` if the code was already removed, so only one caller can use it
+    async fn remove_code(&mut self, code: &DeviceCode) -> Result<(), DeviceCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum DeviceCodeStoreError {
+    #[error("Device code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for DeviceCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete social login state stores should implement.
+// It keeps the logins we have sent to identity providers until the user comes back.
+#[async_trait::async_trait]
+pub trait SocialLoginStateStore {
+    async fn add_state(
+        &mut self,
+        state: SocialLoginState,
+        request: SocialLoginRequest,
+    ) -> Result<(), SocialLoginStateStoreError>;
+    async fn remove_state(
+        &mut self,
+        state: &SocialLoginState,
+    ) -> Result<(), SocialLoginStateStoreError>;
+    async fn get_request(
+        &self,
+        state: &SocialLoginState,
+    ) -> Result<SocialLoginRequest, SocialLoginStateStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SocialLoginStateStoreError {
+    #[error("Social login state not found")]
+    StateNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SocialLoginStateStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::StateNotFound, Self::StateNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete 2FA recovery code stores should implement
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore {
+    // Replace all of the user's codes, invalidating any that were issued before
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<HashedRecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError>;
+    // Consume a code so it can't be used again
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &HashedRecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryCodeStoreError {
+    #[error("Recovery code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RecoveryCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete refresh token stores should implement
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        family: RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError>;
+    // Mark `token` as used and return its family.
+    // Fails with `TokenReused` if the token was already used before.
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError>;
+    // Return the family of `token` without using it up.
+    // Fails with `TokenReused` if the token was already used.
+    async fn get_family(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError>;
+    // Remove every token that was ever issued in the family of the login `id`
+    async fn revoke_family(&mut self, id: &SessionId) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token was already used")]
+    TokenReused(RefreshTokenFamily),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::TokenReused(_), Self::TokenReused(_))
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RefreshToken(SecretString);
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RefreshToken {
+    pub fn parse(token: SecretString) -> Result<Self> {
+        let token = uuid::Uuid::parse_str(token.expose_secret())
+            .map_err(|_| eyre!("Invalid refresh token"))?;
+        Ok(Self(SecretString::new(token.to_string().into_boxed_str())))
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        Self(SecretString::new(
+            uuid::Uuid::new_v4().to_string().into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for RefreshToken {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// Every refresh token descends from a single login. Rotating a token keeps it in the same family,
+// so a stolen token that gets reused can be traced back and the whole login revoked.
+// A family shares its id with the login's session, and names the user by id so it outlives a
+// change of email address. Logins the user allowed an OAuth client also record the client and the
+// scopes it was granted, so their tokens are only ever swapped by that client, for those scopes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshTokenFamily {
+    pub id: SessionId,
+    pub user_id: UserId,
+    // `None` for the user's own logins, whose tokens are only swapped at `/refresh`
+    pub client_id: Option<OAuthClientId>,
+    pub scopes: Vec<String>,
+}
+
+impl RefreshTokenFamily {
+    pub fn new(id: SessionId, user_id: UserId) -> Self {
+        Self {
+            id,
+            user_id,
+            client_id: None,
+            scopes: vec![],
+        }
+    }
+
+    pub fn for_client(
+        id: SessionId,
+        user_id: UserId,
+        client_id: OAuthClientId,
+        scopes: Vec<String>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            client_id: Some(client_id),
+            scopes,
+        }
+    }
+}
+
+// This trait represents the interface all concrete session stores should implement
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    // Most recently active sessions come first
+    async fn get_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError>;
+    // Record activity on a session.
+    // Fails with `SessionNotFound` if the session has been revoked.
+    async fn touch_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError>;
+    async fn delete_session(
+        &mut self,
+        user_id: &UserId,
+        id: &SessionId,
+    ) -> Result<(), SessionStoreError>;
+    // Delete every session belonging to the user and return their ids
+    async fn delete_sessions(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<Vec<SessionId>, SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SigningKeyStore {
+    // The key new tokens are signed with.
+    // Fails with `SigningKeyNotFound` until the first key has been promoted.
+    async fn get_active_key(&self) -> Result<SigningKey, SigningKeyStoreError>;
+    // Look up the key a token was signed with. Expired keys are never returned.
+    async fn get_key(&self, kid: &str) -> Result<SigningKey, SigningKeyStoreError>;
+    // The active key and every retired key that hasn't expired yet, newest first
+    async fn get_keys(&self) -> Result<Vec<SigningKey>, SigningKeyStoreError>;
+    // Make `key` the active key. The previously active key is retired until `retire_until`,
+    // and keys that have already expired are deleted.
+    async fn promote_key(
+        &mut self,
+        key: SigningKey,
+        retire_until: DateTime<Utc>,
+    ) -> Result<(), SigningKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SigningKeyStoreError {
+    #[error("Signing key not found")]
+    SigningKeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SigningKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SigningKeyNotFound, Self::SigningKeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
@@ -0,0 +1,197 @@
+use super::{OAuthClientId, UserId};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::Rng;
+use secrecy::{ExposeSecret, SecretString};
+use std::fmt;
+
+// The `grant_type` devices poll `/token` with
+pub const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+// How long the user has to approve a device, and the device to pick up its tokens
+pub const DEVICE_CODE_TTL_SECONDS: u64 = 600;
+
+// How often devices may poll `/token` while they wait for the user, to begin with
+pub const DEVICE_CODE_POLL_INTERVAL_SECONDS: u64 = 5;
+
+// How much longer a device has to wait between polls each time it is told to slow down, as
+// RFC 8628 section 3.5 requires
+pub const DEVICE_CODE_SLOW_DOWN_SECONDS: u64 = 5;
+
+// Consonants only, so user codes can't spell words and are hard to mistype, as RFC 8628
+// section 6.1 suggests
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LEN: usize = 8;
+
+// The secret a device polls `/token` with while it waits for the user to approve it
+#[derive(Debug, Clone)]
+pub struct DeviceCode(SecretString);
+
+impl PartialEq for DeviceCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl DeviceCode {
+    pub fn parse(code: SecretString) -> Result<Self> {
+        match URL_SAFE_NO_PAD.decode(code.expose_secret()) {
+            Ok(bytes) if bytes.len() == 32 => Ok(Self(code)),
+            _ => Err(eyre!("Invalid device code")),
+        }
+    }
+}
+
+impl Default for DeviceCode {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        Self(SecretString::new(
+            URL_SAFE_NO_PAD.encode(bytes).into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for DeviceCode {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// The short code the device shows the user, for them to type in on the verification page. It is
+// displayed as `WDJB-MJHT`, but case, spaces and dashes don't matter when it is typed back in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserCode(String);
+
+impl UserCode {
+    pub fn parse(code: &str) -> Result<Self> {
+        let code: String = code
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        let is_valid =
+            code.len() == USER_CODE_LEN && code.bytes().all(|b| USER_CODE_CHARSET.contains(&b));
+        if !is_valid {
+            return Err(eyre!("Invalid user code"));
+        }
+        Ok(Self(code))
+    }
+}
+
+impl Default for UserCode {
+    fn default() -> Self {
+        let mut rng = rand::rng();
+        let code = (0..USER_CODE_LEN)
+            .map(|_| USER_CODE_CHARSET[rng.random_range(0..USER_CODE_CHARSET.len())] as char)
+            .collect();
+        Self(code)
+    }
+}
+
+impl AsRef<str> for UserCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for UserCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, second) = self.0.split_at(USER_CODE_LEN / 2);
+        write!(f, "{}-{}", first, second)
+    }
+}
+
+// Where a device authorization request stands
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceAuthorizationStatus {
+    // Waiting for the user to enter the user code
+    Pending,
+    // The user allowed the device to log in as them
+    Approved(UserId),
+    Denied,
+}
+
+// A device's request to log a user in, from RFC 8628's device authorization grant
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceAuthorization {
+    pub client_id: OAuthClientId,
+    pub user_code: UserCode,
+    // The supported scopes the device asked for
+    pub scopes: Vec<String>,
+    pub status: DeviceAuthorizationStatus,
+    // When the device last polled `/token`, so it can be told to slow down
+    pub last_polled_at: Option<DateTime<Utc>>,
+    // How many seconds the device has to wait between polls. It grows each time it polls too fast.
+    pub interval: u64,
+}
+
+impl DeviceAuthorization {
+    pub fn new(client_id: OAuthClientId, scopes: Vec<String>) -> Self {
+        Self {
+            client_id,
+            user_code: UserCode::default(),
+            scopes,
+            status: DeviceAuthorizationStatus::Pending,
+            last_polled_at: None,
+            interval: DEVICE_CODE_POLL_INTERVAL_SECONDS,
+        }
+    }
+
+    // Whether a poll at `now` comes sooner than `interval` seconds after the last
+    pub fn is_polling_too_fast(&self, now: DateTime<Utc>) -> bool {
+        let interval = Duration::seconds(self.interval as i64);
+        self.last_polled_at
+            .is_some_and(|last_polled_at| now < last_polled_at + interval)
+    }
+
+    // Make the device wait longer between polls, for this poll and every one after it
+    pub fn slow_down(&mut self) {
+        self.interval += DEVICE_CODE_SLOW_DOWN_SECONDS;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_code() {
+        // Codes are shown with a dash, which parses back to the same code
+        let code = UserCode::default();
+        let shown = code.to_string();
+        assert_eq!(UserCode::parse(&shown).unwrap(), code);
+
+        let code = UserCode::parse("wdjb-mjht").unwrap();
+        assert_eq!(code.as_ref(), "WDJBMJHT");
+        assert_eq!(code.to_string(), "WDJB-MJHT");
+        assert_eq!(UserCode::parse(" WDJB MJHT ").unwrap(), code);
+
+        for code in ["", "WDJB-MJH", "WDJB-MJHTX", "WAJB-MJHT", "WDJB-MJH1"] {
+            assert!(UserCode::parse(code).is_err(), "Failed for: {}", code);
+        }
+    }
+
+    #[test]
+    fn test_is_polling_too_fast() {
+        let client_id = OAuthClientId::default();
+        let mut authorization = DeviceAuthorization::new(client_id, vec![]);
+        let now = Utc::now();
+        assert!(!authorization.is_polling_too_fast(now));
+
+        authorization.last_polled_at = Some(now - Duration::seconds(1));
+        assert!(authorization.is_polling_too_fast(now));
+
+        let interval = DEVICE_CODE_POLL_INTERVAL_SECONDS as i64;
+        authorization.last_polled_at = Some(now - Duration::seconds(interval));
+        assert!(!authorization.is_polling_too_fast(now));
+
+        // Once told to slow down, the device has to wait longer from then on
+        authorization.slow_down();
+        assert!(authorization.is_polling_too_fast(now));
+        let interval = interval + DEVICE_CODE_SLOW_DOWN_SECONDS as i64;
+        authorization.last_polled_at = Some(now - Duration::seconds(interval));
+        assert!(!authorization.is_polling_too_fast(now));
+    }
+}
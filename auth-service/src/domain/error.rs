@@ -13,6 +13,83 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("TOTP already enabled")]
+    TotpAlreadyEnabled,
+    #[error("Passkey already registered")]
+    PasskeyAlreadyRegistered,
+    #[error("Passkeys unavailable")]
+    PasskeysUnavailable,
+    #[error("TOTP not enrolled")]
+    TotpNotEnrolled,
+    #[error("2FA not enabled")]
+    TwoFANotEnabled,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("API key not found")]
+    ApiKeyNotFound,
+    #[error("Invalid API key request")]
+    InvalidApiKeyRequest,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Invalid role")]
+    InvalidRole,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("Unknown OAuth client")]
+    UnknownOAuthClient,
+    #[error("Invalid redirect URI")]
+    InvalidRedirectUri,
+    #[error("Invalid OAuth client registration")]
+    InvalidClientRegistration,
+    #[error("Invalid user code")]
+    InvalidUserCode,
+    #[error("Unknown identity provider")]
+    UnknownIdentityProvider,
+    // Seconds until the next attempt is allowed
+    #[error("Too many attempts")]
+    TooManyAttempts(u64),
+    #[error("Account locked")]
+    AccountLocked(u64),
+    #[error("Too many requests")]
+    RateLimited(u64),
+    #[error("Rate limit unavailable")]
+    RateLimitUnavailable,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Errors from the OAuth `/token` and `/userinfo` endpoints, which RFC 6749 and RFC 6750 ask to be
+// reported with their own codes
+#[derive(Debug, Error)]
+pub enum OAuthError {
+    #[error("Invalid request")]
+    InvalidRequest,
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("Invalid grant")]
+    InvalidGrant,
+    #[error("Unsupported grant type")]
+    UnsupportedGrantType,
+    #[error("Invalid scope")]
+    InvalidScope,
+    #[error("Invalid token")]
+    InvalidToken,
+    // RFC 6750 section 3.1: the token is good, but wasn't granted what the request needs
+    #[error("Insufficient scope")]
+    InsufficientScope,
+    // RFC 8628 section 3.5: a device polled before the user answered, or polled too often
+    #[error("Authorization pending")]
+    AuthorizationPending,
+    #[error("Slow down")]
+    SlowDown,
+    #[error("Access denied")]
+    AccessDenied,
+    #[error("Expired token")]
+    ExpiredToken,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
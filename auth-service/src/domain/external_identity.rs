@@ -0,0 +1,168 @@
+use super::UserId;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::Rng;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+// How long the user has to log in with the identity provider and be sent back to us
+pub const SOCIAL_LOGIN_STATE_TTL_SECONDS: u64 = 600;
+
+// An external OpenID Connect issuer users can log in with, e.g. Google. We are registered with
+// it as a confidential client whose redirect URI is `/login/social/<name>/callback`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdentityProvider {
+    // Names the provider in our URLs, e.g. `google`
+    pub name: String,
+    // Endpoints and keys are discovered from `<issuer>/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: SecretString,
+}
+
+// The `state` we send the user to the identity provider with. It comes back on the callback,
+// tying the callback to the login we started. A cookie with its hash ties it to the browser that
+// started the login too, so nobody can finish their own login in someone else's browser.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocialLoginState(String);
+
+impl SocialLoginState {
+    pub fn parse(state: String) -> Result<Self> {
+        match URL_SAFE_NO_PAD.decode(&state) {
+            Ok(bytes) if bytes.len() == 32 => Ok(Self(state)),
+            _ => Err(eyre!("Invalid social login state")),
+        }
+    }
+
+    // What the browser's cookie holds, base64url encoded, so the cookie alone can't be used as
+    // the state
+    pub fn hash(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.0.as_bytes()))
+    }
+}
+
+impl Default for SocialLoginState {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        Self(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for SocialLoginState {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// A login we sent to an identity provider, kept until the user comes back
+#[derive(Debug, Clone)]
+pub struct SocialLoginRequest {
+    // The name of the provider the user was sent to
+    pub provider: String,
+    // Must come back in the ID token, so a token issued for another login can't be replayed
+    pub nonce: String,
+    // The PKCE code verifier, sent with the code when swapping it for tokens
+    pub code_verifier: SecretString,
+}
+
+impl PartialEq for SocialLoginRequest {
+    fn eq(&self, other: &Self) -> bool {
+        self.provider == other.provider
+            && self.nonce == other.nonce
+            && self.code_verifier.expose_secret() == other.code_verifier.expose_secret()
+    }
+}
+
+impl SocialLoginRequest {
+    pub fn new(provider: String) -> Self {
+        let mut rng = rand::rng();
+        let nonce: [u8; 32] = rng.random();
+        let code_verifier: [u8; 32] = rng.random();
+        Self {
+            provider,
+            nonce: URL_SAFE_NO_PAD.encode(nonce),
+            code_verifier: SecretString::new(
+                URL_SAFE_NO_PAD.encode(code_verifier).into_boxed_str(),
+            ),
+        }
+    }
+
+    // The S256 PKCE code challenge for the code verifier
+    pub fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(
+            self.code_verifier.expose_secret().as_bytes(),
+        ))
+    }
+}
+
+// A user's account with an identity provider, linked to their account with us
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalIdentity {
+    // The name of the identity provider
+    pub provider: String,
+    // The `sub` claim of the provider's ID tokens, which is unique and never changes
+    pub subject: String,
+    pub user_id: UserId,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ExternalIdentity {
+    pub fn new(provider: String, subject: String, user_id: UserId) -> Self {
+        Self {
+            provider,
+            subject,
+            user_id,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_social_login_state() {
+        let state = SocialLoginState::default();
+        let parsed = SocialLoginState::parse(state.as_ref().to_owned()).unwrap();
+        assert_eq!(parsed, state);
+
+        for state in [
+            "",
+            "not-a-state",
+            "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjX",
+        ] {
+            assert!(
+                SocialLoginState::parse(state.to_owned()).is_err(),
+                "Failed for: {}",
+                state
+            );
+        }
+    }
+
+    #[test]
+    fn test_code_challenge() {
+        // The example from RFC 7636 appendix B
+        let request = SocialLoginRequest {
+            provider: "google".to_owned(),
+            nonce: "n-0S6_WzA2Mj".to_owned(),
+            code_verifier: SecretString::new(
+                "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"
+                    .to_owned()
+                    .into_boxed_str(),
+            ),
+        };
+        assert_eq!(
+            request.code_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        // Every request gets its own verifier and nonce
+        let first = SocialLoginRequest::new("google".to_owned());
+        let second = SocialLoginRequest::new("google".to_owned());
+        assert_ne!(first, second);
+        assert_ne!(first.nonce, second.nonce);
+    }
+}
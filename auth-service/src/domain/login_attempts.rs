@@ -0,0 +1,149 @@
+use super::{AuthAPIError, Email, LoginAttemptId};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+
+// Failed attempts are forgotten once this long has passed without another one.
+// It is also how long an account stays locked.
+pub const FAILED_ATTEMPTS_TTL_SECONDS: i64 = 15 * 60;
+
+// The longest we make anyone wait between attempts before locking them out
+const MAX_DELAY_SECONDS: i64 = 60;
+
+// What failed logins are counted against
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginAttemptKey {
+    Email(Email),
+    IpAddress(String),
+    // The 2FA step of a single login
+    TwoFA(LoginAttemptId),
+}
+
+impl LoginAttemptKey {
+    pub fn as_key(&self) -> String {
+        match self {
+            Self::Email(email) => format!("email:{}", email.as_ref().expose_secret()),
+            Self::IpAddress(ip_address) => format!("ip:{}", ip_address),
+            Self::TwoFA(login_attempt_id) => {
+                format!("2fa:{}", login_attempt_id.as_ref().expose_secret())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedAttempts {
+    pub count: u32,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+impl FailedAttempts {
+    pub fn is_expired(&self) -> bool {
+        self.last_failed_at + chrono::Duration::seconds(FAILED_ATTEMPTS_TTL_SECONDS) <= Utc::now()
+    }
+}
+
+// How many failures are tolerated before attempts are slowed down, then locked out
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    pub free_attempts: u32,
+    pub lockout_threshold: u32,
+}
+
+impl ThrottlePolicy {
+    // Each account gets a handful of tries
+    pub const EMAIL: Self = Self {
+        free_attempts: 3,
+        lockout_threshold: 10,
+    };
+
+    // Many users can share an address, so it gets more leeway
+    pub const IP_ADDRESS: Self = Self {
+        free_attempts: 20,
+        lockout_threshold: 100,
+    };
+
+    // Whether another attempt is allowed yet. Past the free attempts, each failure doubles the
+    // wait after it, up to a minute. At the lockout threshold, attempts are refused until the
+    // failures are forgotten.
+    pub fn check(&self, failures: &FailedAttempts) -> Result<(), AuthAPIError> {
+        let wait = if failures.count >= self.lockout_threshold {
+            FAILED_ATTEMPTS_TTL_SECONDS
+        } else if failures.count >= self.free_attempts {
+            let doublings = (failures.count - self.free_attempts).min(6);
+            (1 << doublings).min(MAX_DELAY_SECONDS)
+        } else {
+            return Ok(());
+        };
+
+        let retry_at = failures.last_failed_at + chrono::Duration::seconds(wait);
+        let retry_after = (retry_at - Utc::now()).num_milliseconds();
+        if retry_after <= 0 {
+            return Ok(());
+        }
+
+        // Round up, so clients don't come back a moment too early
+        let retry_after = (retry_after as u64).div_ceil(1000);
+        if failures.count >= self.lockout_threshold {
+            Err(AuthAPIError::AccountLocked(retry_after))
+        } else {
+            Err(AuthAPIError::TooManyAttempts(retry_after))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failures(count: u32, seconds_ago: i64) -> FailedAttempts {
+        FailedAttempts {
+            count,
+            last_failed_at: Utc::now() - chrono::Duration::seconds(seconds_ago),
+        }
+    }
+
+    #[test]
+    fn test_free_attempts_are_not_throttled() {
+        assert!(ThrottlePolicy::EMAIL.check(&failures(2, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_delay_doubles_after_free_attempts() {
+        let policy = ThrottlePolicy::EMAIL;
+
+        assert!(matches!(
+            policy.check(&failures(3, 0)),
+            Err(AuthAPIError::TooManyAttempts(1))
+        ));
+        assert!(matches!(
+            policy.check(&failures(5, 0)),
+            Err(AuthAPIError::TooManyAttempts(4))
+        ));
+        assert!(matches!(
+            policy.check(&failures(9, 0)),
+            Err(AuthAPIError::TooManyAttempts(60))
+        ));
+
+        // Once the delay has passed, the next attempt is allowed
+        assert!(policy.check(&failures(5, 4)).is_ok());
+    }
+
+    #[test]
+    fn test_lockout() {
+        let policy = ThrottlePolicy::EMAIL;
+
+        assert!(matches!(
+            policy.check(&failures(10, 60)),
+            Err(AuthAPIError::AccountLocked(840))
+        ));
+        assert!(policy
+            .check(&failures(10, FAILED_ATTEMPTS_TTL_SECONDS))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_is_expired() {
+        assert!(!failures(1, FAILED_ATTEMPTS_TTL_SECONDS - 1).is_expired());
+        assert!(failures(1, FAILED_ATTEMPTS_TTL_SECONDS).is_expired());
+    }
+}
@@ -1,13 +1,39 @@
-pub mod user;
-pub mod error;
+pub mod api_key;
+pub mod audit;
 pub mod data_stores;
+pub mod device_authorization;
 pub mod email;
-pub mod password;
 pub mod email_client;
+pub mod error;
+pub mod external_identity;
+pub mod login_attempts;
+pub mod oauth;
+pub mod password;
+pub mod rate_limit;
+pub mod recovery_code;
+pub mod role;
+pub mod session;
+pub mod signing_key;
+pub mod totp;
+pub mod user;
+pub mod webauthn;
 
+pub use api_key::*;
+pub use audit::*;
 pub use data_stores::*;
+pub use device_authorization::*;
 pub use email::*;
 pub use email_client::*;
 pub use error::*;
+pub use external_identity::*;
+pub use login_attempts::*;
+pub use oauth::*;
 pub use password::*;
+pub use rate_limit::*;
+pub use recovery_code::*;
+pub use role::*;
+pub use session::*;
+pub use signing_key::*;
+pub use totp::*;
 pub use user::*;
+pub use webauthn::*;
@@ -0,0 +1,433 @@
+use super::UserId;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::Rng;
+use reqwest::Url;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+
+// How long a client has to swap an authorization code for tokens
+pub const AUTHORIZATION_CODE_TTL_SECONDS: u64 = 60;
+
+// The scopes clients can ask for. `openid` gets them an OpenID Connect ID token and `/userinfo`;
+// `email` adds the user's email address to both.
+pub const SUPPORTED_SCOPES: [&str; 2] = ["openid", "email"];
+
+// Longest name a client can be registered with
+const MAX_CLIENT_NAME_LEN: usize = 100;
+
+// Identifies an application registered to log users in through us
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OAuthClientId(String);
+
+impl OAuthClientId {
+    pub fn parse(id: String) -> Result<Self> {
+        let id = uuid::Uuid::parse_str(&id).map_err(|_| eyre!("Invalid client id"))?;
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl Default for OAuthClientId {
+    fn default() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for OAuthClientId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// An application registered to log users in through us. Clients are public: they prove they
+// started an authorization with PKCE rather than a client secret.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthClient {
+    pub id: OAuthClientId,
+    // Shown to users when they are asked to allow the client
+    pub name: String,
+    // The only places authorization codes for this client may be sent
+    pub redirect_uris: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OAuthClient {
+    pub fn new(name: String, redirect_uris: Vec<String>) -> Result<Self> {
+        let name = name.trim().to_owned();
+        if name.is_empty() || name.chars().count() > MAX_CLIENT_NAME_LEN {
+            return Err(eyre!("Invalid client name"));
+        }
+        if redirect_uris.is_empty() {
+            return Err(eyre!("A client needs at least one redirect URI"));
+        }
+        for redirect_uri in &redirect_uris {
+            validate_redirect_uri(redirect_uri)?;
+        }
+
+        Ok(Self {
+            id: OAuthClientId::default(),
+            name,
+            redirect_uris,
+            created_at: Utc::now(),
+        })
+    }
+
+    // Redirect URIs are compared exactly, as OAuth security best practice asks
+    pub fn has_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+}
+
+// A client the user allowed to log them in, so they aren't asked again for the same scopes
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthConsent {
+    pub client_id: OAuthClientId,
+    // Every scope the user has allowed the client, across all the times they were asked, sorted
+    pub scopes: Vec<String>,
+    pub granted_at: DateTime<Utc>,
+}
+
+impl OAuthConsent {
+    // Whether the user already allowed everything in `scopes`
+    pub fn covers(&self, scopes: &[String]) -> bool {
+        scopes.iter().all(|scope| self.scopes.contains(scope))
+    }
+}
+
+// Codes may only be sent over HTTPS, or plain HTTP to an app on the user's own machine
+fn validate_redirect_uri(redirect_uri: &str) -> Result<()> {
+    let url = Url::parse(redirect_uri).map_err(|_| eyre!("Invalid redirect URI"))?;
+    let is_loopback = matches!(
+        url.host_str(),
+        Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+    );
+
+    match url.scheme() {
+        "https" => {}
+        "http" if is_loopback => {}
+        _ => return Err(eyre!("Redirect URIs must use HTTPS")),
+    }
+    if url.fragment().is_some() {
+        return Err(eyre!("Redirect URIs can't have a fragment"));
+    }
+
+    Ok(())
+}
+
+// The secret a machine client authenticates with. It is only ever shown once, when the client is
+// registered.
+#[derive(Debug, Clone)]
+pub struct ClientSecret(SecretString);
+
+impl ClientSecret {
+    pub fn new(secret: SecretString) -> Self {
+        Self(secret)
+    }
+
+    // Client secrets are long random strings rather than user-chosen secrets, so a fast hash is
+    // enough and lets stores keep nothing that could be used to authenticate
+    pub fn hash(&self) -> HashedClientSecret {
+        let digest = Sha256::digest(self.0.expose_secret().as_bytes());
+        HashedClientSecret(format!("{:x}", digest))
+    }
+}
+
+impl Default for ClientSecret {
+    fn default() -> Self {
+        let bytes: [u8; 32] = rand::rng().random();
+        Self(SecretString::new(
+            URL_SAFE_NO_PAD.encode(bytes).into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for ClientSecret {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// The hex encoded SHA-256 hash of a `ClientSecret`, which is all the stores ever see
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashedClientSecret(String);
+
+impl HashedClientSecret {
+    // Parse a hash previously produced by `ClientSecret::hash`, e.g. one read back from the database
+    pub fn parse(hash: String) -> Result<Self> {
+        if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(Self(hash))
+        } else {
+            Err(eyre!("Invalid client secret hash"))
+        }
+    }
+}
+
+impl AsRef<str> for HashedClientSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// A backend service that gets tokens for itself with the client credentials grant, rather than
+// on behalf of a user
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineClient {
+    pub id: OAuthClientId,
+    pub name: String,
+    pub secret_hash: HashedClientSecret,
+    // The scopes the client's tokens may be issued with
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MachineClient {
+    // Register a client, returning it along with the secret it will authenticate with
+    pub fn new(name: String, scopes: Vec<String>) -> Result<(Self, ClientSecret)> {
+        let name = name.trim().to_owned();
+        if name.is_empty() || name.chars().count() > MAX_CLIENT_NAME_LEN {
+            return Err(eyre!("Invalid client name"));
+        }
+        let mut unique_scopes: Vec<String> = vec![];
+        for scope in scopes {
+            if !is_valid_scope(&scope) {
+                return Err(eyre!("Invalid scope"));
+            }
+            if !unique_scopes.contains(&scope) {
+                unique_scopes.push(scope);
+            }
+        }
+
+        let secret = ClientSecret::default();
+        let client = Self {
+            id: OAuthClientId::default(),
+            name,
+            secret_hash: secret.hash(),
+            scopes: unique_scopes,
+            created_at: Utc::now(),
+        };
+        Ok((client, secret))
+    }
+
+    pub fn verify_secret(&self, secret: &ClientSecret) -> bool {
+        secret.hash() == self.secret_hash
+    }
+
+    // The scopes to issue a token with, given the space separated `scope` the client asked for.
+    // Clients that don't ask get every scope they are allowed; asking for any other is an error.
+    pub fn grant_scopes(&self, scope: Option<&str>) -> Result<Vec<String>> {
+        let Some(scope) = scope else {
+            return Ok(self.scopes.clone());
+        };
+
+        let mut scopes: Vec<String> = vec![];
+        for scope in scope.split_whitespace() {
+            if !self.scopes.iter().any(|s| s == scope) {
+                return Err(eyre!("Scope {} is not allowed for this client", scope));
+            }
+            if !scopes.iter().any(|s| s == scope) {
+                scopes.push(scope.to_owned());
+            }
+        }
+        Ok(scopes)
+    }
+}
+
+// Scope tokens are printable ASCII other than space, `"` and `\`, as RFC 6749 section 3.3 says
+pub fn is_valid_scope(scope: &str) -> bool {
+    !scope.is_empty()
+        && scope
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && b != b'"' && b != b'\\')
+}
+
+// A single-use code handed to the client's redirect URI, to be swapped for tokens
+#[derive(Debug, Clone)]
+pub struct AuthorizationCode(SecretString);
+
+impl PartialEq for AuthorizationCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AuthorizationCode {
+    pub fn parse(code: SecretString) -> Result<Self> {
+        let code = uuid::Uuid::parse_str(code.expose_secret())
+            .map_err(|_| eyre!("Invalid authorization code"))?;
+        Ok(Self(SecretString::new(code.to_string().into_boxed_str())))
+    }
+}
+
+impl Default for AuthorizationCode {
+    fn default() -> Self {
+        Self(SecretString::new(
+            uuid::Uuid::new_v4().to_string().into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for AuthorizationCode {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// The PKCE code challenge a client sent to `/authorize`: the base64url encoded SHA-256 of a
+// secret code verifier it will send to `/token`. Only the S256 method is supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeChallenge(String);
+
+impl CodeChallenge {
+    pub fn parse(challenge: String) -> Result<Self> {
+        match URL_SAFE_NO_PAD.decode(&challenge) {
+            Ok(bytes) if bytes.len() == 32 => Ok(Self(challenge)),
+            _ => Err(eyre!("Invalid code challenge")),
+        }
+    }
+
+    // Whether `code_verifier` is the one this challenge was made from
+    pub fn verify(&self, code_verifier: &str) -> bool {
+        // RFC 7636 code verifiers are 43 to 128 unreserved characters
+        let is_valid = (43..=128).contains(&code_verifier.len())
+            && code_verifier
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b));
+
+        is_valid && URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes())) == self.0
+    }
+}
+
+impl AsRef<str> for CodeChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// What an authorization code stands for: a user's permission for a client to log them in
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthorizationGrant {
+    pub client_id: OAuthClientId,
+    pub user_id: UserId,
+    // The client must send the same redirect URI to `/token`
+    pub redirect_uri: String,
+    pub code_challenge: CodeChallenge,
+    // The supported scopes the client asked for
+    pub scopes: Vec<String>,
+    // Copied into the ID token, so the client can tie it to the request it made
+    pub nonce: Option<String>,
+}
+
+// The scopes in a space separated `scope` parameter that we support. Others are dropped, as
+// RFC 6749 allows.
+pub fn parse_scopes(scope: Option<&str>) -> Vec<String> {
+    let mut scopes: Vec<String> = vec![];
+    for scope in scope.unwrap_or_default().split_whitespace() {
+        if SUPPORTED_SCOPES.contains(&scope) && !scopes.iter().any(|s| s == scope) {
+            scopes.push(scope.to_owned());
+        }
+    }
+    scopes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from RFC 7636 appendix B
+    const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    #[test]
+    fn test_verify_code_challenge() {
+        let challenge = CodeChallenge::parse(CODE_CHALLENGE.to_owned()).unwrap();
+
+        assert!(challenge.verify(CODE_VERIFIER));
+        assert!(!challenge.verify(&CODE_VERIFIER.replace('d', "e")));
+        // Too short, even though it is the challenge's own verifier
+        let short =
+            CodeChallenge::parse(URL_SAFE_NO_PAD.encode(Sha256::digest("short".as_bytes())))
+                .unwrap();
+        assert!(!short.verify("short"));
+    }
+
+    #[test]
+    fn test_parse_invalid_code_challenge() {
+        for challenge in [
+            "",
+            "plain-text-challenge",
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw",
+        ] {
+            assert!(
+                CodeChallenge::parse(challenge.to_owned()).is_err(),
+                "Failed for: {}",
+                challenge
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_scopes() {
+        assert_eq!(parse_scopes(Some("openid email")), vec!["openid", "email"]);
+        assert_eq!(
+            parse_scopes(Some(" openid  profile openid ")),
+            vec!["openid"]
+        );
+        assert!(parse_scopes(Some("")).is_empty());
+        assert!(parse_scopes(None).is_empty());
+    }
+
+    #[test]
+    fn test_new_client_validates_redirect_uris() {
+        let valid = [
+            "https://app.example.com/callback",
+            "http://localhost:8000/callback",
+            "http://127.0.0.1/callback",
+        ];
+        for redirect_uri in valid {
+            assert!(
+                OAuthClient::new("App".to_owned(), vec![redirect_uri.to_owned()]).is_ok(),
+                "Failed for: {}",
+                redirect_uri
+            );
+        }
+
+        let invalid = [
+            "not a url",
+            "http://app.example.com/callback",
+            "https://app.example.com/callback#fragment",
+            "javascript:alert(1)",
+        ];
+        for redirect_uri in invalid {
+            assert!(
+                OAuthClient::new("App".to_owned(), vec![redirect_uri.to_owned()]).is_err(),
+                "Failed for: {}",
+                redirect_uri
+            );
+        }
+
+        assert!(OAuthClient::new("App".to_owned(), vec![]).is_err());
+        assert!(OAuthClient::new(" ".to_owned(), vec![valid[0].to_owned()]).is_err());
+    }
+
+    #[test]
+    fn test_machine_client_grant_scopes() {
+        let scopes = vec!["orders:read".to_owned(), "orders:write".to_owned()];
+        let (client, secret) = MachineClient::new("Billing".to_owned(), scopes.clone()).unwrap();
+
+        assert!(client.verify_secret(&secret));
+        assert!(!client.verify_secret(&ClientSecret::default()));
+
+        assert_eq!(client.grant_scopes(None).unwrap(), scopes);
+        assert_eq!(
+            client
+                .grant_scopes(Some("orders:read orders:read"))
+                .unwrap(),
+            vec!["orders:read"]
+        );
+        assert!(client.grant_scopes(Some("orders:read admin")).is_err());
+
+        let invalid = vec!["orders read".to_owned()];
+        assert!(MachineClient::new("Billing".to_owned(), invalid).is_err());
+    }
+}
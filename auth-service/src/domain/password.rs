@@ -20,7 +20,7 @@ impl HashedPassword {
         if validate_password(&s) {
             let result = compute_password_hash(&s)
                 .await
-                .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+                .map_err(UserStoreError::UnexpectedError)?;
             Ok(Self(result))
         } else {
             Err(eyre!("Failed to parse string to a HashedPassword type"))
@@ -30,7 +30,7 @@ impl HashedPassword {
     #[tracing::instrument(name = "HashedPassword Parse password hash", skip_all)]
     pub fn parse_password_hash(hash: SecretString) -> Result<HashedPassword> {
         // Parse the stored password hash a string
-        if let Ok(hashed_string) = PasswordHash::new(hash.expose_secret().as_ref()) {
+        if let Ok(hashed_string) = PasswordHash::new(hash.expose_secret()) {
             Ok(Self(SecretString::new(
                 hashed_string.to_string().into_boxed_str(),
             )))
@@ -54,7 +54,7 @@ impl HashedPassword {
                     .wrap_err("failed to verify password hash")
             })
         })
-            .await;
+        .await;
 
         result?
     }
@@ -82,13 +82,13 @@ pub async fn compute_password_hash(password: &SecretString) -> Result<SecretStri
                 Version::V0x13,
                 Params::new(15000, 2, 1, None)?,
             )
-                .hash_password(password.as_bytes(), &salt)?
-                .to_string();
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string();
 
             Ok(SecretString::new(password_hash.into_boxed_str()))
         })
     })
-        .await?;
+    .await?;
 
     result
 }
@@ -136,7 +136,7 @@ mod tests {
         let hash_password = HashedPassword::parse_password_hash(SecretString::new(
             hash_string.clone().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
 
         assert_eq!(hash_password.as_ref().expose_secret(), hash_string);
         assert!(hash_password
@@ -166,7 +166,7 @@ mod tests {
         let hash_password = HashedPassword::parse_password_hash(SecretString::new(
             hash_string.clone().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
 
         assert_eq!(hash_password.as_ref().expose_secret(), hash_string);
         assert!(hash_password
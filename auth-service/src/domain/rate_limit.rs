@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+
+// A token bucket holding up to `capacity` requests, refilled evenly over `period_seconds`.
+// Clients can burst up to the capacity, then are held to the refill rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period_seconds: u64,
+}
+
+impl RateLimit {
+    pub const fn per_minute(capacity: u32) -> Self {
+        Self {
+            capacity,
+            period_seconds: 60,
+        }
+    }
+
+    pub const fn per_hour(capacity: u32) -> Self {
+        Self {
+            capacity,
+            period_seconds: 60 * 60,
+        }
+    }
+
+    pub fn period_milliseconds(&self) -> u64 {
+        self.period_seconds * 1000
+    }
+
+    // Take a token from `bucket`, which is full if the client hasn't been seen before.
+    // Returns the bucket to store, or how many milliseconds until a token is available.
+    pub fn take(
+        &self,
+        bucket: Option<&TokenBucket>,
+        now: DateTime<Utc>,
+    ) -> Result<TokenBucket, u64> {
+        let capacity = self.capacity as f64;
+        let period = self.period_milliseconds() as f64;
+        let tokens = match bucket {
+            Some(bucket) => {
+                let elapsed = (now - bucket.updated_at).num_milliseconds().max(0) as f64;
+                (bucket.tokens + elapsed * capacity / period).min(capacity)
+            }
+            None => capacity,
+        };
+
+        if tokens < 1.0 {
+            return Err(((1.0 - tokens) * period / capacity).ceil() as u64);
+        }
+
+        Ok(TokenBucket {
+            tokens: tokens - 1.0,
+            updated_at: now,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_allows_a_burst_up_to_capacity() {
+        let limit = RateLimit::per_minute(3);
+        let now = Utc::now();
+
+        let mut bucket = None;
+        for _ in 0..3 {
+            bucket = Some(limit.take(bucket.as_ref(), now).unwrap());
+        }
+
+        // One token comes back every 20 seconds
+        assert_eq!(limit.take(bucket.as_ref(), now), Err(20_000));
+    }
+
+    #[test]
+    fn test_take_refills_over_time() {
+        let limit = RateLimit::per_minute(3);
+        let now = Utc::now();
+        let empty = TokenBucket {
+            tokens: 0.0,
+            updated_at: now,
+        };
+
+        let later = now + chrono::Duration::seconds(5);
+        assert_eq!(limit.take(Some(&empty), later), Err(15_000));
+
+        let later = now + chrono::Duration::seconds(20);
+        let bucket = limit.take(Some(&empty), later).unwrap();
+        assert!(bucket.tokens.abs() < f64::EPSILON);
+
+        // The bucket never holds more than its capacity
+        let much_later = now + chrono::Duration::hours(1);
+        let bucket = limit.take(Some(&empty), much_later).unwrap();
+        assert_eq!(bucket.tokens, 2.0);
+    }
+}
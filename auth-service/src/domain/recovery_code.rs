@@ -0,0 +1,157 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::Rng;
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+
+// How many codes a user gets each time they are (re)generated
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+// Lowercase letters and digits, minus the ones that are easy to misread (0/o, 1/i/l)
+const RECOVERY_CODE_ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+const RECOVERY_CODE_GROUP_LENGTH: usize = 5;
+
+// A single-use 2FA fallback code, formatted as `xxxxx-xxxxx`
+#[derive(Debug, Clone)]
+pub struct RecoveryCode(SecretString);
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RecoveryCode {
+    // Accepts codes regardless of case and with or without the separator
+    pub fn parse(code: SecretString) -> Result<Self> {
+        let normalized: String = code
+            .expose_secret()
+            .chars()
+            .filter(|c| *c != '-')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        if normalized.len() != RECOVERY_CODE_GROUP_LENGTH * 2
+            || !normalized
+                .bytes()
+                .all(|b| RECOVERY_CODE_ALPHABET.contains(&b))
+        {
+            return Err(eyre!("Invalid recovery code"));
+        }
+
+        let (first, second) = normalized.split_at(RECOVERY_CODE_GROUP_LENGTH);
+        Ok(Self(SecretString::new(
+            format!("{}-{}", first, second).into_boxed_str(),
+        )))
+    }
+
+    pub fn generate_set() -> Vec<Self> {
+        (0..RECOVERY_CODE_COUNT).map(|_| Self::default()).collect()
+    }
+
+    // Recovery codes are long random strings rather than user-chosen secrets, so a fast
+    // hash is enough and lets stores look codes up directly.
+    pub fn hash(&self) -> HashedRecoveryCode {
+        let digest = Sha256::digest(self.0.expose_secret().as_bytes());
+        HashedRecoveryCode(format!("{:x}", digest))
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::rng();
+        let mut group = || -> String {
+            (0..RECOVERY_CODE_GROUP_LENGTH)
+                .map(|_| {
+                    RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())]
+                        as char
+                })
+                .collect()
+        };
+        let code = format!("{}-{}", group(), group());
+        Self(SecretString::new(code.into_boxed_str()))
+    }
+}
+
+impl AsRef<SecretString> for RecoveryCode {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// The hex encoded SHA-256 hash of a `RecoveryCode`, which is all the stores ever see
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HashedRecoveryCode(String);
+
+impl HashedRecoveryCode {
+    // Parse a hash previously produced by `RecoveryCode::hash`, e.g. one read back from the database
+    pub fn parse(hash: String) -> Result<Self> {
+        if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(Self(hash))
+        } else {
+            Err(eyre!("Invalid recovery code hash"))
+        }
+    }
+}
+
+impl AsRef<str> for HashedRecoveryCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(code: &str) -> Result<RecoveryCode> {
+        RecoveryCode::parse(SecretString::new(code.to_owned().into_boxed_str()))
+    }
+
+    #[test]
+    fn generated_codes_are_valid() {
+        for code in RecoveryCode::generate_set() {
+            assert_eq!(parse(code.as_ref().expose_secret()).unwrap(), code);
+        }
+    }
+
+    #[test]
+    fn generate_set_returns_distinct_codes() {
+        let codes = RecoveryCode::generate_set();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        let hashes: std::collections::HashSet<_> = codes.iter().map(|c| c.hash()).collect();
+        assert_eq!(hashes.len(), RECOVERY_CODE_COUNT);
+    }
+
+    #[test]
+    fn parse_normalizes_case_and_separator() {
+        let code = parse("abcde-fghjk").unwrap();
+        assert_eq!(parse("ABCDEFGHJK").unwrap(), code);
+        assert_eq!(parse("AbCdE-fGhJk").unwrap().hash(), code.hash());
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        for code in [
+            "",
+            "abcde",
+            "abcde-fghjkm",
+            "abcde-fghi1",
+            "123456",
+            "abcde_fghjk",
+        ] {
+            assert!(parse(code).is_err(), "Accepted {:?}", code);
+        }
+    }
+
+    #[test]
+    fn hash_is_stable_and_parseable() {
+        let code = RecoveryCode::default();
+        assert_eq!(code.hash(), code.hash());
+        assert_eq!(
+            HashedRecoveryCode::parse(code.hash().as_ref().to_owned()).unwrap(),
+            code.hash()
+        );
+        assert!(HashedRecoveryCode::parse("not a hash".to_owned()).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use color_eyre::eyre::{eyre, Result};
+
+const ADMIN_ROLE: &str = "admin";
+const MAX_ROLE_LENGTH: usize = 64;
+
+// A role granted to a user, embedded in their auth tokens so services can make authorization
+// decisions. Lowercase letters, digits, `_`, `-` and `:`, so it is also usable as an OAuth scope.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Role(String);
+
+impl Role {
+    pub fn parse(role: String) -> Result<Self> {
+        let valid = !role.is_empty()
+            && role.len() <= MAX_ROLE_LENGTH
+            && role
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-:".contains(c));
+        if valid {
+            Ok(Self(role))
+        } else {
+            Err(eyre!("Invalid role: {}", role))
+        }
+    }
+
+    // Allowed to grant and revoke roles
+    pub fn admin() -> Self {
+        Self(ADMIN_ROLE.to_owned())
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_roles_are_accepted() {
+        for role in ["admin", "support", "billing:read", "beta_tester", "tier-2"] {
+            assert!(
+                Role::parse(role.to_owned()).is_ok(),
+                "Failed for role: {}",
+                role
+            );
+        }
+        assert_eq!(Role::parse("admin".to_owned()).unwrap(), Role::admin());
+    }
+
+    #[test]
+    fn invalid_roles_are_rejected() {
+        let too_long = "a".repeat(MAX_ROLE_LENGTH + 1);
+        for role in ["", "Admin", "read write", "role!", too_long.as_str()] {
+            assert!(
+                Role::parse(role.to_owned()).is_err(),
+                "Failed for role: {}",
+                role
+            );
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use super::UserId;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+
+// Identifies a single login. It is carried in the JWT `sid` claim and doubles as the id of the
+// login's refresh token family.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionId(String);
+
+impl SessionId {
+    pub fn parse(id: String) -> Result<Self> {
+        let id = uuid::Uuid::parse_str(&id).map_err(|_| eyre!("Invalid session id"))?;
+        Ok(Self(id.to_string()))
+    }
+}
+
+impl Default for SessionId {
+    fn default() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl AsRef<str> for SessionId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Server-side record of a login, so users can see where they are logged in and revoke it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: SessionId,
+    pub user_id: UserId,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn new(user_id: UserId, user_agent: Option<String>, ip_address: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: SessionId::default(),
+            user_id,
+            user_agent,
+            ip_address,
+            created_at: now,
+            last_seen_at: now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_session_id() {
+        let id = SessionId::default();
+        assert_eq!(SessionId::parse(id.as_ref().to_owned()).unwrap(), id);
+    }
+
+    #[test]
+    fn test_parse_invalid_session_id() {
+        for id in ["", "invalid", "12345678-1234-1234-1234"] {
+            assert!(
+                SessionId::parse(id.to_owned()).is_err(),
+                "Failed for: {}",
+                id
+            );
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context, Result};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// A key JWT auth tokens are signed with, identified in their header by `kid`.
+// The active key has no expiry. Once a newer key is promoted, the old one is retired and kept
+// until `expires_at` so the tokens it signed can still be verified.
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    // The HS256 shared secret, or a PEM encoded private key
+    pub private_key: SecretString,
+    // PEM encoded public key. None for HS256, whose secret must never be published.
+    pub public_key: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl PartialEq for SigningKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.kid == other.kid
+            && self.algorithm == other.algorithm
+            && self.private_key.expose_secret() == other.private_key.expose_secret()
+            && self.public_key == other.public_key
+            && self.created_at == other.created_at
+            && self.expires_at == other.expires_at
+    }
+}
+
+impl SigningKey {
+    // HS256 with a secret shared by every service that validates tokens
+    pub fn from_secret(secret: SecretString) -> Self {
+        Self::new(Algorithm::HS256, secret, None)
+    }
+
+    // RS256 or EdDSA with a PEM encoded key pair
+    pub fn from_pem(algorithm: Algorithm, private_key: &[u8], public_key: &[u8]) -> Result<Self> {
+        if !matches!(algorithm, Algorithm::RS256 | Algorithm::EdDSA) {
+            return Err(eyre!("unsupported JWT algorithm: {:?}", algorithm));
+        }
+        let private_key =
+            String::from_utf8(private_key.to_vec()).wrap_err("private key is not PEM encoded")?;
+        let public_key =
+            String::from_utf8(public_key.to_vec()).wrap_err("public key is not PEM encoded")?;
+
+        let key = Self::new(
+            algorithm,
+            SecretString::new(private_key.into_boxed_str()),
+            Some(public_key),
+        );
+        key.check_key_pair()?;
+
+        Ok(key)
+    }
+
+    fn new(algorithm: Algorithm, private_key: SecretString, public_key: Option<String>) -> Self {
+        Self {
+            kid: Uuid::new_v4().to_string(),
+            algorithm,
+            private_key,
+            public_key,
+            created_at: Utc::now(),
+            expires_at: None,
+        }
+    }
+
+    // Whether this is the same key material, whatever id it was given
+    pub fn same_key_as(&self, other: &SigningKey) -> bool {
+        self.algorithm == other.algorithm
+            && self.private_key.expose_secret() == other.private_key.expose_secret()
+            && self.public_key == other.public_key
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    pub fn encoding_key(&self) -> Result<EncodingKey> {
+        let private_key = self.private_key.expose_secret().as_bytes();
+        match self.algorithm {
+            Algorithm::HS256 => Ok(EncodingKey::from_secret(private_key)),
+            Algorithm::RS256 => {
+                EncodingKey::from_rsa_pem(private_key).wrap_err("failed to parse RSA private key")
+            }
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(private_key)
+                .wrap_err("failed to parse Ed25519 private key"),
+            algorithm => Err(eyre!("unsupported JWT algorithm: {:?}", algorithm)),
+        }
+    }
+
+    pub fn decoding_key(&self) -> Result<DecodingKey> {
+        if self.algorithm == Algorithm::HS256 {
+            return Ok(DecodingKey::from_secret(
+                self.private_key.expose_secret().as_bytes(),
+            ));
+        }
+
+        let public_key = self
+            .public_key
+            .as_deref()
+            .ok_or(eyre!("signing key {} has no public key", self.kid))?
+            .as_bytes();
+        match self.algorithm {
+            Algorithm::RS256 => {
+                DecodingKey::from_rsa_pem(public_key).wrap_err("failed to parse RSA public key")
+            }
+            Algorithm::EdDSA => {
+                DecodingKey::from_ed_pem(public_key).wrap_err("failed to parse Ed25519 public key")
+            }
+            algorithm => Err(eyre!("unsupported JWT algorithm: {:?}", algorithm)),
+        }
+    }
+
+    // The public key other services can verify our tokens with, or None for a shared secret
+    pub fn jwk(&self) -> Result<Option<Jwk>> {
+        let mut jwk = match self.algorithm {
+            Algorithm::HS256 => return Ok(None),
+            Algorithm::RS256 => Jwk::from_encoding_key(&self.encoding_key()?, self.algorithm)
+                .wrap_err("failed to create JWK from RSA key")?,
+            // `Jwk::from_encoding_key` doesn't support EdDSA keys, so build it from the public key
+            Algorithm::EdDSA => Jwk {
+                common: CommonParameters {
+                    key_algorithm: Some(KeyAlgorithm::EdDSA),
+                    ..Default::default()
+                },
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(self.decoding_key()?.as_bytes()),
+                }),
+            },
+            algorithm => return Err(eyre!("unsupported JWT algorithm: {:?}", algorithm)),
+        };
+        jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+        jwk.common.key_id = Some(self.kid.clone());
+
+        Ok(Some(jwk))
+    }
+
+    // Catch a private and public key that don't belong together when the key is configured,
+    // rather than when the first token fails to validate
+    fn check_key_pair(&self) -> Result<()> {
+        #[derive(Serialize, Deserialize)]
+        struct Probe {
+            exp: usize,
+        }
+
+        let token = encode(
+            &Header::new(self.algorithm),
+            &Probe { exp: usize::MAX },
+            &self.encoding_key()?,
+        )
+        .wrap_err("failed to sign with the JWT private key")?;
+
+        decode::<Probe>(
+            &token,
+            &self.decoding_key()?,
+            &Validation::new(self.algorithm),
+        )
+        .map(|_| ())
+        .wrap_err("JWT public key does not match the private key")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RS256_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_rs256_private.pem");
+    const RS256_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_rs256_public.pem");
+    const EDDSA_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_eddsa_private.pem");
+    const EDDSA_PUBLIC_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt_eddsa_public.pem");
+    const OTHER_EDDSA_PUBLIC_KEY: &[u8] =
+        include_bytes!("../../tests/fixtures/jwt_eddsa_other_public.pem");
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Claims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn claims() -> Claims {
+        Claims {
+            sub: "test@example.com".to_owned(),
+            exp: usize::MAX,
+        }
+    }
+
+    fn secret(secret: &str) -> SecretString {
+        SecretString::new(secret.to_owned().into_boxed_str())
+    }
+
+    // A consumer holding only the published JWK can verify our tokens
+    fn assert_verifiable_with_jwk(key: &SigningKey) {
+        let token = encode(
+            &Header::new(key.algorithm),
+            &claims(),
+            &key.encoding_key().unwrap(),
+        )
+        .unwrap();
+
+        let jwk = key.jwk().unwrap().expect("No JWK for key");
+        assert_eq!(jwk.common.key_id.as_deref(), Some(key.kid.as_str()));
+
+        let decoding_key = DecodingKey::from_jwk(&jwk).unwrap();
+        let result = decode::<Claims>(&token, &decoding_key, &Validation::new(key.algorithm));
+
+        assert_eq!(result.unwrap().claims, claims());
+    }
+
+    #[test]
+    fn test_from_secret() {
+        let key = SigningKey::from_secret(secret("secret"));
+
+        assert_eq!(key.algorithm, Algorithm::HS256);
+        assert!(key.expires_at.is_none());
+        assert!(key.jwk().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_from_pem_rs256() {
+        let key =
+            SigningKey::from_pem(Algorithm::RS256, RS256_PRIVATE_KEY, RS256_PUBLIC_KEY).unwrap();
+
+        assert_eq!(key.algorithm, Algorithm::RS256);
+        assert_verifiable_with_jwk(&key);
+    }
+
+    #[test]
+    fn test_from_pem_eddsa() {
+        let key =
+            SigningKey::from_pem(Algorithm::EdDSA, EDDSA_PRIVATE_KEY, EDDSA_PUBLIC_KEY).unwrap();
+
+        assert_eq!(key.algorithm, Algorithm::EdDSA);
+        assert_verifiable_with_jwk(&key);
+    }
+
+    #[test]
+    fn test_from_pem_rejects_invalid_keys() {
+        // Mismatched key pair
+        assert!(
+            SigningKey::from_pem(Algorithm::EdDSA, EDDSA_PRIVATE_KEY, OTHER_EDDSA_PUBLIC_KEY)
+                .is_err()
+        );
+        // Wrong key type for the algorithm
+        assert!(
+            SigningKey::from_pem(Algorithm::RS256, EDDSA_PRIVATE_KEY, EDDSA_PUBLIC_KEY).is_err()
+        );
+        // Unsupported algorithm
+        assert!(
+            SigningKey::from_pem(Algorithm::HS256, RS256_PRIVATE_KEY, RS256_PUBLIC_KEY).is_err()
+        );
+        assert!(SigningKey::from_pem(Algorithm::RS256, b"not a key", RS256_PUBLIC_KEY).is_err());
+    }
+
+    #[test]
+    fn test_same_key_as() {
+        let key = SigningKey::from_secret(secret("secret"));
+
+        // The same material configured again gets a new id
+        let same = SigningKey::from_secret(secret("secret"));
+        assert_ne!(key.kid, same.kid);
+        assert!(key.same_key_as(&same));
+
+        assert!(!key.same_key_as(&SigningKey::from_secret(secret("other secret"))));
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let mut key = SigningKey::from_secret(secret("secret"));
+        assert!(!key.is_expired());
+
+        key.expires_at = Some(Utc::now() + chrono::Duration::minutes(10));
+        assert!(!key.is_expired());
+
+        key.expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+        assert!(key.is_expired());
+    }
+}
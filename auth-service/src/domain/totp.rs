@@ -0,0 +1,244 @@
+use super::Email;
+use crate::utils::constants::{TOTP_ENCRYPTION_KEY, TOTP_ISSUER};
+use crate::utils::encryption;
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::{ExposeSecret, SecretString};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+// RFC 6238 defaults, which is what authenticator apps expect
+const TOTP_DIGITS: usize = 6;
+const TOTP_SKEW: u8 = 1;
+const TOTP_STEP_SECONDS: u64 = 30;
+
+// A base32 encoded TOTP shared secret
+#[derive(Debug, Clone)]
+pub struct TotpSecret(SecretString);
+
+impl PartialEq for TotpSecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl TotpSecret {
+    pub fn parse(secret: SecretString) -> Result<Self> {
+        Secret::Encoded(secret.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("Invalid TOTP secret"))?;
+        Ok(Self(secret))
+    }
+
+    // Check `code` against the current time step (allowing for a little clock drift)
+    #[tracing::instrument(name = "TotpSecret Verify code", skip_all)]
+    pub fn verify_code(&self, email: &Email, code: &TotpCode) -> Result<bool> {
+        self.totp(email)?
+            .check_current(code.as_ref().expose_secret())
+            .wrap_err("failed to read system time")
+    }
+
+    // The `otpauth://` URI that authenticator apps use to enroll the secret
+    pub fn otpauth_uri(&self, email: &Email) -> Result<String> {
+        Ok(self.totp(email)?.get_url())
+    }
+
+    #[cfg(test)]
+    pub fn generate_current_code(&self, email: &Email) -> Result<String> {
+        self.totp(email)?
+            .generate_current()
+            .wrap_err("failed to read system time")
+    }
+
+    fn totp(&self, email: &Email) -> Result<TOTP> {
+        let secret = Secret::Encoded(self.0.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("Invalid TOTP secret"))?;
+
+        TOTP::new(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            TOTP_SKEW,
+            TOTP_STEP_SECONDS,
+            secret,
+            Some(TOTP_ISSUER.to_owned()),
+            email.as_ref().expose_secret().to_owned(),
+        )
+        .wrap_err("failed to build TOTP")
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        // 160 bits, as recommended by RFC 4226
+        Self(SecretString::new(
+            Secret::generate_secret()
+                .to_encoded()
+                .to_string()
+                .into_boxed_str(),
+        ))
+    }
+}
+
+impl AsRef<SecretString> for TotpSecret {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// A 6-digit code from an authenticator app. Unlike `TwoFACode` it may start with 0.
+#[derive(Debug, Clone)]
+pub struct TotpCode(SecretString);
+
+impl TotpCode {
+    pub fn parse(code: SecretString) -> Result<Self> {
+        let value = code.expose_secret();
+        if value.len() == TOTP_DIGITS && value.chars().all(|c| c.is_ascii_digit()) {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid TOTP code"))
+        }
+    }
+}
+
+impl AsRef<SecretString> for TotpCode {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+// A TOTP secret encrypted with AES-256-GCM, stored as base64(nonce || ciphertext)
+#[derive(Debug, Clone)]
+pub struct EncryptedTotpSecret(SecretString);
+
+impl PartialEq for EncryptedTotpSecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl EncryptedTotpSecret {
+    #[tracing::instrument(name = "EncryptedTotpSecret Encrypt", skip_all)]
+    pub fn encrypt(secret: &TotpSecret) -> Result<Self> {
+        let encrypted = encryption::encrypt(
+            &TOTP_ENCRYPTION_KEY,
+            secret.as_ref().expose_secret().as_bytes(),
+        )
+        .wrap_err("failed to encrypt TOTP secret")?;
+
+        Ok(Self(SecretString::new(encrypted.into_boxed_str())))
+    }
+
+    // Parse a previously encrypted secret, e.g. one read back from the database
+    pub fn parse(encrypted: SecretString) -> Result<Self> {
+        encryption::check_encrypted(encrypted.expose_secret())
+            .wrap_err("invalid encrypted TOTP secret")?;
+        Ok(Self(encrypted))
+    }
+
+    #[tracing::instrument(name = "EncryptedTotpSecret Decrypt", skip_all)]
+    pub fn decrypt(&self) -> Result<TotpSecret> {
+        let secret = encryption::decrypt(&TOTP_ENCRYPTION_KEY, self.0.expose_secret())
+            .wrap_err("failed to decrypt TOTP secret")?;
+        let secret = String::from_utf8(secret).wrap_err("decrypted TOTP secret is not UTF-8")?;
+
+        TotpSecret::parse(SecretString::new(secret.into_boxed_str()))
+    }
+}
+
+impl AsRef<SecretString> for EncryptedTotpSecret {
+    fn as_ref(&self) -> &SecretString {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    fn email() -> Email {
+        Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    fn totp_code(code: String) -> TotpCode {
+        TotpCode::parse(SecretString::new(code.into_boxed_str())).unwrap()
+    }
+
+    #[test]
+    fn codes_with_leading_zeros_are_accepted() {
+        assert!(TotpCode::parse(SecretString::new("012345".to_owned().into_boxed_str())).is_ok());
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        for code in ["", "12345", "1234567", "12a456", " 12345"] {
+            assert!(TotpCode::parse(SecretString::new(code.to_owned().into_boxed_str())).is_err());
+        }
+    }
+
+    #[test]
+    fn invalid_secret_is_rejected() {
+        let secret = SecretString::new("not base32!".to_owned().into_boxed_str());
+        assert!(TotpSecret::parse(secret).is_err());
+    }
+
+    #[test]
+    fn current_code_is_accepted() {
+        let secret = TotpSecret::default();
+        let code = secret.generate_current_code(&email()).unwrap();
+
+        assert!(secret.verify_code(&email(), &totp_code(code)).unwrap());
+    }
+
+    #[test]
+    fn wrong_code_is_rejected() {
+        let secret = TotpSecret::default();
+        let code = secret.generate_current_code(&email()).unwrap();
+        let wrong_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+        assert!(!secret
+            .verify_code(&email(), &totp_code(wrong_code))
+            .unwrap());
+    }
+
+    #[test]
+    fn otpauth_uri_contains_issuer_and_secret() {
+        let secret = TotpSecret::default();
+        let uri = secret.otpauth_uri(&email()).unwrap();
+
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref().expose_secret())));
+        assert!(uri.contains("issuer="));
+    }
+
+    #[test]
+    fn encrypted_secret_round_trips() {
+        let secret = TotpSecret::default();
+        let encrypted = EncryptedTotpSecret::encrypt(&secret).unwrap();
+
+        assert_ne!(
+            encrypted.as_ref().expose_secret(),
+            secret.as_ref().expose_secret()
+        );
+
+        let parsed = EncryptedTotpSecret::parse(encrypted.as_ref().to_owned()).unwrap();
+        assert_eq!(parsed.decrypt().unwrap(), secret);
+    }
+
+    #[test]
+    fn tampered_secret_fails_to_decrypt() {
+        let secret = TotpSecret::default();
+        let encrypted = EncryptedTotpSecret::encrypt(&secret).unwrap();
+
+        let mut data = STANDARD.decode(encrypted.as_ref().expose_secret()).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        let tampered =
+            EncryptedTotpSecret::parse(SecretString::new(STANDARD.encode(data).into_boxed_str()))
+                .unwrap();
+
+        assert!(tampered.decrypt().is_err());
+    }
+}
@@ -1,22 +1,133 @@
-use super::{Email, HashedPassword};
+use super::{Email, EncryptedTotpSecret, HashedPassword, Role};
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::fmt;
+use uuid::Uuid;
 
-// The User struct should contain 3 fields. email, which is a String;
-// password, which is also a String; and requires_2fa, which is a boolean.
+// Identifies a user for good. Unlike their email address it never changes and reveals nothing
+// about them, so it is what auth tokens name the user by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UserId(Uuid);
+
+impl UserId {
+    pub fn parse(id: String) -> Result<Self> {
+        let id = Uuid::parse_str(&id).map_err(|_| eyre!("Invalid user id"))?;
+        Ok(Self(id))
+    }
+}
+
+impl Default for UserId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for UserId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for UserId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// The User struct should contain 7 fields. id, the UserId, which never changes; email, the
+// user's Email address; password, the HashedPassword; two_fa_method, the second factor the user
+// has chosen; totp_secret, the encrypted authenticator-app secret (if the user has enrolled one);
+// verified, which tells whether the user has confirmed their email address;
+// and roles, what the user is authorized to do beyond managing their own account.
 #[derive(Clone, Debug, PartialEq, FromRow)]
 pub struct User {
+    pub id: UserId,
     pub email: Email,
     pub password: HashedPassword,
-    pub requires_2fa: bool,
+    pub two_fa_method: TwoFAMethod,
+    pub totp_secret: Option<EncryptedTotpSecret>,
+    pub verified: bool,
+    pub roles: Vec<Role>,
 }
 
 impl User {
     // add a constructor function called `new`
-    pub fn new(email: Email, password: HashedPassword, requires_2fa: bool) -> Self {
+    // New users always start out with a fresh id, an unverified email address and no roles
+    pub fn new(email: Email, password: HashedPassword, two_fa_method: TwoFAMethod) -> Self {
         Self {
+            id: UserId::default(),
             email,
             password,
-            requires_2fa,
+            two_fa_method,
+            totp_secret: None,
+            verified: false,
+            roles: vec![],
+        }
+    }
+
+    pub fn requires_2fa(&self) -> bool {
+        self.two_fa_method != TwoFAMethod::None
+    }
+}
+
+// How a user proves the second factor when logging in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAMethod {
+    #[default]
+    None,
+    // A 6-digit code sent by email
+    Email,
+    // A 6-digit RFC 6238 code from an authenticator app
+    Totp,
+}
+
+impl TwoFAMethod {
+    pub fn parse(method: &str) -> Result<Self> {
+        match method {
+            "none" => Ok(Self::None),
+            "email" => Ok(Self::Email),
+            "totp" => Ok(Self::Totp),
+            _ => Err(eyre!("Invalid 2FA method: {}", method)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Email => "email",
+            Self::Totp => "totp",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_id_round_trips_through_str() {
+        let id = UserId::default();
+        assert_eq!(UserId::parse(id.to_string()).unwrap(), id);
+        assert!(UserId::parse("test@example.com".to_owned()).is_err());
+    }
+
+    #[test]
+    fn two_fa_method_round_trips_through_str() {
+        for method in [TwoFAMethod::None, TwoFAMethod::Email, TwoFAMethod::Totp] {
+            assert_eq!(TwoFAMethod::parse(method.as_str()).unwrap(), method);
+        }
+    }
+
+    #[test]
+    fn unknown_two_fa_method_is_rejected() {
+        assert!(TwoFAMethod::parse("sms").is_err());
+    }
+}
@@ -0,0 +1,469 @@
+use super::{Email, LoginAttemptId, UserId};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use ciborium::Value;
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+// The only signature algorithm we accept: ECDSA with P-256 and SHA-256 (ES256).
+// Every passkey provider supports it.
+pub const COSE_ALG_ES256: i64 = -7;
+
+// Bits of the authenticator data flags byte
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// rpIdHash (32 bytes), flags (1 byte) and signCount (4 bytes)
+const AUTHENTICATOR_DATA_MIN_LEN: usize = 37;
+
+const CHALLENGE_LEN: usize = 32;
+
+// How long a ceremony can take, from handing out its challenge to getting the answer back
+pub const WEBAUTHN_CHALLENGE_TTL_SECONDS: u64 = 300;
+
+// The id an authenticator gave a credential, base64url encoded
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PasskeyId(String);
+
+impl PasskeyId {
+    pub fn parse(id: String) -> Result<Self> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(&id)
+            .map_err(|_| eyre!("Invalid passkey id"))?;
+        // The spec caps credential ids at 1023 bytes
+        if bytes.is_empty() || bytes.len() > 1023 {
+            return Err(eyre!("Invalid passkey id"));
+        }
+        Ok(Self(id))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for PasskeyId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// A passkey registered to a user
+#[derive(Debug, Clone, PartialEq)]
+pub struct Passkey {
+    pub id: PasskeyId,
+    pub user_id: UserId,
+    // A label the user picked, so they can tell their passkeys apart
+    pub name: String,
+    // SEC1 encoded P-256 public key
+    pub public_key: Vec<u8>,
+    // The authenticator's signature counter, as of the passkey's last use
+    pub sign_count: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+// A random, single-use challenge for the authenticator to sign, base64url encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebAuthnChallenge(String);
+
+impl WebAuthnChallenge {
+    pub fn parse(challenge: String) -> Result<Self> {
+        match URL_SAFE_NO_PAD.decode(&challenge) {
+            Ok(bytes) if bytes.len() == CHALLENGE_LEN => Ok(Self(challenge)),
+            _ => Err(eyre!("Invalid WebAuthn challenge")),
+        }
+    }
+}
+
+impl Default for WebAuthnChallenge {
+    fn default() -> Self {
+        let mut bytes = [0u8; CHALLENGE_LEN];
+        rand::rng().fill_bytes(&mut bytes);
+        Self(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for WebAuthnChallenge {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// What a challenge was issued for
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebAuthnCeremony {
+    // Adding a passkey to the logged-in user's account
+    Registration(UserId),
+    // Logging in with a passkey alone
+    Login,
+    // Finishing a password login with a passkey instead of a 2FA code
+    SecondFactor(Email, LoginAttemptId),
+}
+
+// The public key credential an authenticator created during registration
+#[derive(Debug, PartialEq)]
+pub struct NewPasskeyCredential {
+    pub id: PasskeyId,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[derive(Deserialize)]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+// Check the client data the browser collected for a ceremony of `ceremony_type`
+// ("webauthn.create" or "webauthn.get") on `origin`, and return the challenge it answers
+pub fn verify_client_data(
+    client_data_json: &[u8],
+    ceremony_type: &str,
+    origin: &str,
+) -> Result<WebAuthnChallenge> {
+    let client_data: CollectedClientData =
+        serde_json::from_slice(client_data_json).wrap_err("failed to parse client data")?;
+
+    if client_data.ceremony_type != ceremony_type {
+        return Err(eyre!(
+            "client data is for a {} ceremony",
+            client_data.ceremony_type
+        ));
+    }
+    // A phishing site can relay our challenge, but the browser reports its own origin
+    if client_data.origin != origin {
+        return Err(eyre!("client data is from origin {}", client_data.origin));
+    }
+
+    WebAuthnChallenge::parse(client_data.challenge)
+}
+
+// Read the credential an authenticator created out of its attestation object. Only "none"
+// attestation is requested, so any attestation statement is ignored: we don't restrict which
+// authenticators users may register.
+pub fn verify_attestation(attestation_object: &[u8], rp_id: &str) -> Result<NewPasskeyCredential> {
+    let attestation: Value =
+        ciborium::from_reader(attestation_object).wrap_err("failed to decode attestation")?;
+    let auth_data = map_get(&attestation, &Value::Text("authData".to_owned()))
+        .and_then(Value::as_bytes)
+        .wrap_err("attestation has no authenticator data")?;
+
+    let (flags, sign_count, rest) = parse_authenticator_data(auth_data, rp_id)?;
+    if flags & FLAG_ATTESTED_CREDENTIAL_DATA == 0 {
+        return Err(eyre!("authenticator data has no credential"));
+    }
+
+    // AAGUID (16 bytes), credential id length (2 bytes), credential id, COSE public key
+    if rest.len() < 18 {
+        return Err(eyre!("attested credential data is truncated"));
+    }
+    let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+    let credential_id = rest
+        .get(18..18 + id_len)
+        .wrap_err("attested credential data is truncated")?;
+    let cose_key: Value = ciborium::from_reader(&rest[18 + id_len..])
+        .wrap_err("failed to decode credential public key")?;
+
+    Ok(NewPasskeyCredential {
+        id: PasskeyId::from_bytes(credential_id),
+        public_key: cose_key_to_sec1(&cose_key)?,
+        sign_count,
+    })
+}
+
+// Check an authenticator's signature over `authenticator_data` and the client data, returning
+// the authenticator's new signature counter
+pub fn verify_assertion(
+    passkey: &Passkey,
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+    rp_id: &str,
+    require_user_verification: bool,
+) -> Result<u32> {
+    let (flags, sign_count, _) = parse_authenticator_data(authenticator_data, rp_id)?;
+    if require_user_verification && flags & FLAG_USER_VERIFIED == 0 {
+        return Err(eyre!("authenticator did not verify the user"));
+    }
+
+    let public_key = VerifyingKey::from_sec1_bytes(&passkey.public_key)
+        .wrap_err("failed to load passkey public key")?;
+    let signature = Signature::from_der(signature).wrap_err("failed to decode signature")?;
+    let signed_data = [authenticator_data, &Sha256::digest(client_data_json)[..]].concat();
+    public_key
+        .verify(&signed_data, &signature)
+        .wrap_err("signature does not match")?;
+
+    // Authenticators that keep a counter bump it on every use. One that goes backwards
+    // suggests the passkey has been cloned.
+    if (sign_count != 0 || passkey.sign_count != 0) && sign_count <= passkey.sign_count {
+        return Err(eyre!("signature counter did not increase"));
+    }
+
+    Ok(sign_count)
+}
+
+// Check the authenticator data is for our relying party and that the user was present,
+// returning its flags, signature counter and whatever follows them
+fn parse_authenticator_data<'a>(data: &'a [u8], rp_id: &str) -> Result<(u8, u32, &'a [u8])> {
+    if data.len() < AUTHENTICATOR_DATA_MIN_LEN {
+        return Err(eyre!("authenticator data is truncated"));
+    }
+    if data[..32] != Sha256::digest(rp_id.as_bytes())[..] {
+        return Err(eyre!("authenticator data is for another relying party"));
+    }
+
+    let flags = data[32];
+    if flags & FLAG_USER_PRESENT == 0 {
+        return Err(eyre!("user was not present"));
+    }
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    Ok((flags, sign_count, &data[AUTHENTICATOR_DATA_MIN_LEN..]))
+}
+
+// Convert an ES256 COSE_Key into an uncompressed SEC1 point
+fn cose_key_to_sec1(key: &Value) -> Result<Vec<u8>> {
+    let field = |label: i64| map_get(key, &Value::Integer(label.into()));
+
+    // kty 2 is an elliptic curve key with x and y coordinates, crv 1 is P-256
+    let is_es256 = field(1).and_then(Value::as_integer) == Some(2.into())
+        && field(3).and_then(Value::as_integer) == Some(COSE_ALG_ES256.into())
+        && field(-1).and_then(Value::as_integer) == Some(1.into());
+    if !is_es256 {
+        return Err(eyre!("credential public key is not an ES256 key"));
+    }
+
+    let x = field(-2)
+        .and_then(Value::as_bytes)
+        .wrap_err("public key has no x")?;
+    let y = field(-3)
+        .and_then(Value::as_bytes)
+        .wrap_err("public key has no y")?;
+    let sec1 = [&[0x04], x.as_slice(), y.as_slice()].concat();
+
+    VerifyingKey::from_sec1_bytes(&sec1).wrap_err("public key is not on P-256")?;
+
+    Ok(sec1)
+}
+
+fn map_get<'a>(map: &'a Value, key: &Value) -> Option<&'a Value> {
+    map.as_map()?
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::Signer, SigningKey};
+
+    const RP_ID: &str = "localhost";
+    const ORIGIN: &str = "http://localhost:3000";
+
+    // Just enough of an authenticator to produce the data browsers hand us
+    struct Authenticator {
+        key: SigningKey,
+        credential_id: Vec<u8>,
+    }
+
+    impl Authenticator {
+        fn new() -> Self {
+            Self {
+                key: SigningKey::from_slice(&[7; 32]).unwrap(),
+                credential_id: vec![1, 2, 3, 4],
+            }
+        }
+
+        fn authenticator_data(&self, rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+            [
+                &Sha256::digest(rp_id.as_bytes())[..],
+                &[flags],
+                &sign_count.to_be_bytes(),
+            ]
+            .concat()
+        }
+
+        fn attestation_object(&self, rp_id: &str) -> Vec<u8> {
+            let point = self.key.verifying_key().to_encoded_point(false);
+            let cose_key = Value::Map(vec![
+                (Value::Integer(1.into()), Value::Integer(2.into())),
+                (
+                    Value::Integer(3.into()),
+                    Value::Integer(COSE_ALG_ES256.into()),
+                ),
+                (Value::Integer((-1).into()), Value::Integer(1.into())),
+                (
+                    Value::Integer((-2).into()),
+                    Value::Bytes(point.x().unwrap().to_vec()),
+                ),
+                (
+                    Value::Integer((-3).into()),
+                    Value::Bytes(point.y().unwrap().to_vec()),
+                ),
+            ]);
+            let mut auth_data = self.authenticator_data(
+                rp_id,
+                FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA,
+                0,
+            );
+            auth_data.extend_from_slice(&[0; 16]);
+            auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            auth_data.extend_from_slice(&self.credential_id);
+            ciborium::into_writer(&cose_key, &mut auth_data).unwrap();
+
+            let attestation = Value::Map(vec![
+                (
+                    Value::Text("fmt".to_owned()),
+                    Value::Text("none".to_owned()),
+                ),
+                (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+                (Value::Text("authData".to_owned()), Value::Bytes(auth_data)),
+            ]);
+            let mut bytes = vec![];
+            ciborium::into_writer(&attestation, &mut bytes).unwrap();
+            bytes
+        }
+
+        fn sign(&self, authenticator_data: &[u8], client_data_json: &[u8]) -> Vec<u8> {
+            let signed_data = [authenticator_data, &Sha256::digest(client_data_json)[..]].concat();
+            let signature: Signature = self.key.sign(&signed_data);
+            signature.to_der().as_bytes().to_vec()
+        }
+    }
+
+    fn passkey(authenticator: &Authenticator) -> Passkey {
+        let credential =
+            verify_attestation(&authenticator.attestation_object(RP_ID), RP_ID).unwrap();
+        Passkey {
+            id: credential.id,
+            user_id: UserId::default(),
+            name: "Laptop".to_owned(),
+            public_key: credential.public_key,
+            sign_count: credential.sign_count,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn client_data(ceremony_type: &str, challenge: &WebAuthnChallenge, origin: &str) -> Vec<u8> {
+        serde_json::json!({
+            "type": ceremony_type,
+            "challenge": challenge.as_ref(),
+            "origin": origin,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_verify_attestation() {
+        let authenticator = Authenticator::new();
+
+        let credential =
+            verify_attestation(&authenticator.attestation_object(RP_ID), RP_ID).unwrap();
+
+        assert_eq!(
+            credential.id,
+            PasskeyId::from_bytes(&authenticator.credential_id)
+        );
+        assert_eq!(
+            credential.public_key,
+            authenticator
+                .key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+        );
+        assert!(verify_attestation(&authenticator.attestation_object("evil.com"), RP_ID).is_err());
+    }
+
+    #[test]
+    fn test_verify_client_data() {
+        let challenge = WebAuthnChallenge::default();
+
+        let client_data_json = client_data("webauthn.get", &challenge, ORIGIN);
+        assert_eq!(
+            verify_client_data(&client_data_json, "webauthn.get", ORIGIN).unwrap(),
+            challenge
+        );
+        assert!(verify_client_data(&client_data_json, "webauthn.create", ORIGIN).is_err());
+
+        let phished = client_data("webauthn.get", &challenge, "https://evil.com");
+        assert!(verify_client_data(&phished, "webauthn.get", ORIGIN).is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion() {
+        let authenticator = Authenticator::new();
+        let mut passkey = passkey(&authenticator);
+        let client_data_json = client_data("webauthn.get", &WebAuthnChallenge::default(), ORIGIN);
+
+        let present = authenticator.authenticator_data(RP_ID, FLAG_USER_PRESENT, 1);
+        let signature = authenticator.sign(&present, &client_data_json);
+        assert_eq!(
+            verify_assertion(
+                &passkey,
+                &present,
+                &client_data_json,
+                &signature,
+                RP_ID,
+                false
+            )
+            .unwrap(),
+            1
+        );
+        // Logging in with a passkey alone needs the user to have been verified too
+        assert!(verify_assertion(
+            &passkey,
+            &present,
+            &client_data_json,
+            &signature,
+            RP_ID,
+            true
+        )
+        .is_err());
+
+        let tampered = authenticator.authenticator_data(RP_ID, FLAG_USER_PRESENT, 2);
+        assert!(verify_assertion(
+            &passkey,
+            &tampered,
+            &client_data_json,
+            &signature,
+            RP_ID,
+            false
+        )
+        .is_err());
+
+        // A signature counter that doesn't move forward is refused
+        passkey.sign_count = 1;
+        assert!(verify_assertion(
+            &passkey,
+            &present,
+            &client_data_json,
+            &signature,
+            RP_ID,
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let challenge = WebAuthnChallenge::default();
+
+        assert_eq!(
+            WebAuthnChallenge::parse(challenge.as_ref().to_owned()).unwrap(),
+            challenge
+        );
+        assert!(WebAuthnChallenge::parse("c2hvcnQ".to_owned()).is_err());
+        assert!(PasskeyId::parse("".to_owned()).is_err());
+        assert!(PasskeyId::parse("not base64!".to_owned()).is_err());
+    }
+}
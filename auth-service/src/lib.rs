@@ -1,25 +1,40 @@
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
+use domain::{AuthAPIError, OAuthError};
 use redis::{Client, RedisResult};
-use routes::{login, logout, signup, verify_2fa, verify_token};
+use routes::{
+    approve_device, authorize, authorize_consent, change_email, change_password,
+    confirm_email_change, confirm_totp, create_api_key, delete_account, delete_api_key,
+    delete_session, delete_sessions, device_authorization, enroll_totp, export_account,
+    finish_passkey_login, finish_passkey_registration, forgot_password, get_api_keys,
+    get_identity_providers, get_sessions, grant_role, introspect, jwks, login, logout,
+    magic_link_callback, openid_configuration, refresh, regenerate_recovery_codes,
+    register_machine_client, register_oauth_client, request_magic_link, resend_verification,
+    reset_password, revoke_role, signup, social_login_callback, start_passkey_login,
+    start_passkey_registration, start_social_login, token, userinfo, verify_2fa, verify_email,
+    verify_token, verify_user_code,
+};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::error::Error;
+use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tower_http::{
     cors::CorsLayer,
     services::{ServeDir, ServeFile},
     trace::TraceLayer,
 };
+use utils::rate_limit::rate_limit;
 use utils::tracing::{make_span_with_request_id, on_request, on_response};
 
 pub mod app_state;
@@ -30,7 +45,11 @@ pub mod utils;
 
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Serve<
+        TcpListener,
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     // address is exposed as a public field.
     // so we have access to it in tests.
     pub address: String,
@@ -47,8 +66,8 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
             // Allow cookies to be included in requests
             .allow_credentials(true)
             .allow_origin(allowed_origins);
@@ -57,9 +76,65 @@ impl Application {
             .fallback_service(asset_dir)
             .route("/signup", post(signup))
             .route("/login", post(login))
+            .route("/login/magic-link", post(request_magic_link))
+            .route("/login/magic-link/callback", post(magic_link_callback))
+            .route("/login/social", get(get_identity_providers))
+            .route("/login/social/{provider}", get(start_social_login))
+            .route(
+                "/login/social/{provider}/callback",
+                get(social_login_callback),
+            )
             .route("/verify-2fa", post(verify_2fa))
+            .route("/webauthn/login/start", post(start_passkey_login))
+            .route("/webauthn/login/finish", post(finish_passkey_login))
             .route("/logout", post(logout))
+            .route("/refresh", post(refresh))
+            .route("/sessions", get(get_sessions).delete(delete_sessions))
+            .route("/sessions/{id}", delete(delete_session))
+            .route("/api-keys", get(get_api_keys).post(create_api_key))
+            .route("/api-keys/{id}", delete(delete_api_key))
             .route("/verify-token", post(verify_token))
+            .route("/introspect", post(introspect))
+            .route("/.well-known/jwks.json", get(jwks))
+            .route("/authorize", get(authorize).post(authorize_consent))
+            .route("/token", post(token))
+            .route("/device/code", post(device_authorization))
+            .route_service("/device", ServeFile::new("assets/index.html"))
+            .route("/device/verify", post(verify_user_code))
+            .route("/device/approve", post(approve_device))
+            .route("/userinfo", get(userinfo).post(userinfo))
+            .route(
+                "/.well-known/openid-configuration",
+                get(openid_configuration),
+            )
+            .route("/forgot-password", post(forgot_password))
+            .route("/reset-password", post(reset_password))
+            .route("/change-password", post(change_password))
+            .route("/change-email", post(change_email))
+            .route("/confirm-email-change", post(confirm_email_change))
+            .route("/account", delete(delete_account))
+            .route("/account/export", get(export_account))
+            .route("/verify-email", post(verify_email))
+            .route("/resend-verification", post(resend_verification))
+            .route("/enroll-totp", post(enroll_totp))
+            .route("/confirm-totp", post(confirm_totp))
+            .route(
+                "/regenerate-recovery-codes",
+                post(regenerate_recovery_codes),
+            )
+            .route("/webauthn/register/start", post(start_passkey_registration))
+            .route(
+                "/webauthn/register/finish",
+                post(finish_passkey_registration),
+            )
+            .route("/admin/grant-role", post(grant_role))
+            .route("/admin/revoke-role", post(revoke_role))
+            .route("/admin/oauth-clients", post(register_oauth_client))
+            .route("/admin/machine-clients", post(register_machine_client))
+            .route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                rate_limit,
+            ))
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -74,7 +149,11 @@ impl Application {
 
         let listener = TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Connection info lets handlers see the client's IP address
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         // Create a new Application instance and return it
         Ok(Self { server, address })
@@ -94,6 +173,20 @@ pub struct ErrorResponse {
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let mut headers = HeaderMap::new();
+        match &self {
+            // Tell the client how to authenticate, as RFC 6749 asks
+            AuthAPIError::InvalidClient => {
+                headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Basic"));
+            }
+            // Tell throttled clients when to come back
+            AuthAPIError::TooManyAttempts(retry_after)
+            | AuthAPIError::AccountLocked(retry_after)
+            | AuthAPIError::RateLimited(retry_after) => {
+                headers.insert(header::RETRY_AFTER, HeaderValue::from(*retry_after));
+            }
+            _ => {}
+        }
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
@@ -105,11 +198,95 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::TotpAlreadyEnabled => (StatusCode::CONFLICT, "TOTP already enabled"),
+            AuthAPIError::PasskeyAlreadyRegistered => {
+                (StatusCode::CONFLICT, "Passkey already registered")
+            }
+            AuthAPIError::PasskeysUnavailable => {
+                (StatusCode::SERVICE_UNAVAILABLE, "Passkeys are unavailable")
+            }
+            AuthAPIError::TotpNotEnrolled => (StatusCode::BAD_REQUEST, "TOTP not enrolled"),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::BAD_REQUEST, "2FA not enabled"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::ApiKeyNotFound => (StatusCode::NOT_FOUND, "API key not found"),
+            AuthAPIError::InvalidApiKeyRequest => {
+                (StatusCode::BAD_REQUEST, "Invalid API key request")
+            }
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::InvalidRole => (StatusCode::BAD_REQUEST, "Invalid role"),
+            AuthAPIError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AuthAPIError::InvalidClient => (StatusCode::UNAUTHORIZED, "Invalid client"),
+            AuthAPIError::UnknownOAuthClient => (StatusCode::BAD_REQUEST, "Unknown client"),
+            AuthAPIError::InvalidRedirectUri => (StatusCode::BAD_REQUEST, "Invalid redirect URI"),
+            AuthAPIError::InvalidClientRegistration => {
+                (StatusCode::BAD_REQUEST, "Invalid client registration")
+            }
+            AuthAPIError::InvalidUserCode => (StatusCode::BAD_REQUEST, "Invalid user code"),
+            AuthAPIError::UnknownIdentityProvider => {
+                (StatusCode::NOT_FOUND, "Unknown identity provider")
+            }
+            AuthAPIError::TooManyAttempts(_) => {
+                (StatusCode::TOO_MANY_REQUESTS, "Too many attempts")
+            }
+            AuthAPIError::AccountLocked(_) => (StatusCode::LOCKED, "Account locked"),
+            AuthAPIError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AuthAPIError::RateLimitUnavailable => {
+                (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable")
+            }
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
         });
-        (status, body).into_response()
+        (status, headers, body).into_response()
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        log_error_chain(&self);
+        let mut headers = HeaderMap::new();
+        // Token responses must never be cached, errors included
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        headers.insert(header::PRAGMA, HeaderValue::from_static("no-cache"));
+        match &self {
+            // Tell the client its bearer token was refused, as RFC 6750 asks
+            OAuthError::InvalidToken => {
+                headers.insert(
+                    header::WWW_AUTHENTICATE,
+                    HeaderValue::from_static("Bearer error=\"invalid_token\""),
+                );
+            }
+            OAuthError::InsufficientScope => {
+                headers.insert(
+                    header::WWW_AUTHENTICATE,
+                    HeaderValue::from_static("Bearer error=\"insufficient_scope\""),
+                );
+            }
+            // Tell the client how to authenticate, as RFC 6749 asks
+            OAuthError::InvalidClient => {
+                headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Basic"));
+            }
+            _ => {}
+        }
+        let (status, error_code) = match self {
+            OAuthError::InvalidRequest => (StatusCode::BAD_REQUEST, "invalid_request"),
+            OAuthError::InvalidClient => (StatusCode::UNAUTHORIZED, "invalid_client"),
+            OAuthError::InvalidGrant => (StatusCode::BAD_REQUEST, "invalid_grant"),
+            OAuthError::UnsupportedGrantType => (StatusCode::BAD_REQUEST, "unsupported_grant_type"),
+            OAuthError::InvalidScope => (StatusCode::BAD_REQUEST, "invalid_scope"),
+            OAuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "invalid_token"),
+            OAuthError::InsufficientScope => (StatusCode::FORBIDDEN, "insufficient_scope"),
+            OAuthError::AuthorizationPending => (StatusCode::BAD_REQUEST, "authorization_pending"),
+            OAuthError::SlowDown => (StatusCode::BAD_REQUEST, "slow_down"),
+            OAuthError::AccessDenied => (StatusCode::BAD_REQUEST, "access_denied"),
+            OAuthError::ExpiredToken => (StatusCode::BAD_REQUEST, "expired_token"),
+            OAuthError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "server_error"),
+        };
+        let body = Json(ErrorResponse {
+            error: error_code.to_string(),
+        });
+        (status, headers, body).into_response()
     }
 }
 
@@ -6,14 +6,27 @@ use tokio::sync::RwLock;
 
 use auth_service::{
     app_state::AppState,
-    domain::Email,
+    domain::{Email, Role, TokenPurpose, UserStore},
     get_postgres_pool, get_redis_client,
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
+        data_stores::{
+            PostgresApiKeyStore, PostgresAuditLogStore, PostgresExternalIdentityStore,
+            PostgresMachineClientStore, PostgresOAuthClientStore, PostgresPasskeyStore,
+            PostgresRecoveryCodeStore, PostgresSessionStore, PostgresSigningKeyStore,
+            PostgresUserStore, RedisAuthorizationCodeStore, RedisBannedTokenStore,
+            RedisDeviceCodeStore, RedisLoginAttemptStore, RedisRateLimitStore,
+            RedisRefreshTokenStore, RedisSingleUseTokenStore, RedisSocialLoginStateStore,
+            RedisTwoFACodeStore, RedisWebAuthnChallengeStore,
+        },
+        identity_provider_client::IdentityProviderClient,
         resend_email_client::ResendEmailClient,
     },
     utils::{
-        constants::{prod, DATABASE_URL, REDIS_HOST_NAME, RESEND_API_KEY},
+        auth::{init_signing_key, rotate_signing_key},
+        constants::{
+            prod, DATABASE_URL, IDENTITY_PROVIDERS, JWT_SIGNING_KEY, REDIS_HOST_NAME,
+            RESEND_API_KEY, WEBAUTHN_ENABLED,
+        },
         tracing::init_tracing,
     },
     Application,
@@ -23,22 +36,123 @@ use auth_service::{
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing");
+    // Check the passkey settings now, so a misconfiguration is logged at startup rather than on
+    // the first passkey request
+    lazy_static::initialize(&WEBAUTHN_ENABLED);
     let pg_pool = configure_postgresql().await;
+    let signing_key_store = Arc::new(RwLock::new(PostgresSigningKeyStore::new(pg_pool.clone())));
+
+    // Admin commands, e.g. `auth-service rotate-signing-key`, do their job and exit
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        run_admin_command(&args, pg_pool, signing_key_store).await;
+        return;
+    }
+
+    init_signing_key(signing_key_store.clone(), JWT_SIGNING_KEY.clone())
+        .await
+        .expect("Failed to initialize signing key");
+
     let redis_conn = Arc::new(RwLock::new(configure_redis()));
     //let user_store = Arc::new(RwLock::new(HashmapUserStore::default()));
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
     //let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
     //let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn)));
+    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
+    //let password_reset_token_store = Arc::new(RwLock::new(HashmapSingleUseTokenStore::default()));
+    let password_reset_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+        redis_conn.clone(),
+        TokenPurpose::PasswordReset,
+    )));
+    //let email_verification_token_store = Arc::new(RwLock::new(HashmapSingleUseTokenStore::default()));
+    let email_verification_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+        redis_conn.clone(),
+        TokenPurpose::EmailVerification,
+    )));
+    //let email_change_token_store = Arc::new(RwLock::new(HashmapSingleUseTokenStore::default()));
+    let email_change_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+        redis_conn.clone(),
+        TokenPurpose::EmailChange,
+    )));
+    //let magic_link_token_store = Arc::new(RwLock::new(HashmapSingleUseTokenStore::default()));
+    let magic_link_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+        redis_conn.clone(),
+        TokenPurpose::MagicLink,
+    )));
+    //let webauthn_challenge_store = Arc::new(RwLock::new(HashmapWebAuthnChallengeStore::default()));
+    let webauthn_challenge_store = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(
+        redis_conn.clone(),
+    )));
+    //let authorization_code_store = Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default()));
+    let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(
+        redis_conn.clone(),
+    )));
+    //let device_code_store = Arc::new(RwLock::new(HashmapDeviceCodeStore::default()));
+    let device_code_store = Arc::new(RwLock::new(RedisDeviceCodeStore::new(redis_conn.clone())));
+    //let social_login_state_store = Arc::new(RwLock::new(HashmapSocialLoginStateStore::default()));
+    let social_login_state_store = Arc::new(RwLock::new(RedisSocialLoginStateStore::new(
+        redis_conn.clone(),
+    )));
+    //let recovery_code_store = Arc::new(RwLock::new(HashmapRecoveryCodeStore::default()));
+    let recovery_code_store =
+        Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+    //let passkey_store = Arc::new(RwLock::new(HashmapPasskeyStore::default()));
+    let passkey_store = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+    //let external_identity_store = Arc::new(RwLock::new(HashmapExternalIdentityStore::default()));
+    let external_identity_store = Arc::new(RwLock::new(PostgresExternalIdentityStore::new(
+        pg_pool.clone(),
+    )));
+    //let api_key_store = Arc::new(RwLock::new(HashmapApiKeyStore::default()));
+    let api_key_store = Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool.clone())));
+    //let oauth_client_store = Arc::new(RwLock::new(HashmapOAuthClientStore::default()));
+    let oauth_client_store = Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())));
+    //let machine_client_store = Arc::new(RwLock::new(HashmapMachineClientStore::default()));
+    let machine_client_store = Arc::new(RwLock::new(PostgresMachineClientStore::new(
+        pg_pool.clone(),
+    )));
+    //let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+    let refresh_token_store =
+        Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+    //let login_attempt_store = Arc::new(RwLock::new(HashmapLoginAttemptStore::default()));
+    let login_attempt_store =
+        Arc::new(RwLock::new(RedisLoginAttemptStore::new(redis_conn.clone())));
+    //let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
+    let rate_limit_store = Arc::new(RwLock::new(RedisRateLimitStore::new(redis_conn)));
+    //let audit_log_store = Arc::new(RwLock::new(HashmapAuditLogStore::default()));
+    let audit_log_store = Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool.clone())));
+    //let session_store = Arc::new(RwLock::new(HashmapSessionStore::default()));
+    let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool)));
     let email_client = Arc::new(configure_resend_email_client());
+    let identity_provider_client = Arc::new(configure_identity_provider_client());
 
-    let app_state = AppState::new(
+    let app_state = AppState {
         user_store,
         banned_token_store,
         two_fa_code_store,
+        password_reset_token_store,
+        email_verification_token_store,
+        email_change_token_store,
+        magic_link_token_store,
+        webauthn_challenge_store,
+        authorization_code_store,
+        device_code_store,
+        social_login_state_store,
+        recovery_code_store,
+        passkey_store,
+        external_identity_store,
+        api_key_store,
+        oauth_client_store,
+        machine_client_store,
+        refresh_token_store,
+        session_store,
+        signing_key_store,
+        login_attempt_store,
+        rate_limit_store,
+        audit_log_store,
         email_client,
-    );
+        identity_provider_client,
+    };
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
@@ -47,6 +161,35 @@ async fn main() {
     app.run().await.expect("Failed to run app");
 }
 
+async fn run_admin_command(
+    args: &[String],
+    pg_pool: PgPool,
+    signing_key_store: Arc<RwLock<PostgresSigningKeyStore>>,
+) {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        // Run after configuring a new signing key
+        ["rotate-signing-key"] => {
+            rotate_signing_key(signing_key_store, JWT_SIGNING_KEY.clone())
+                .await
+                .expect("Failed to rotate signing key");
+            tracing::info!("Signing key {} is now active", JWT_SIGNING_KEY.kid);
+        }
+        // Grant the first admin, who can then grant roles through `/admin/grant-role`
+        ["grant-role", email, role] => {
+            let email = Email::parse(SecretString::new(email.to_string().into_boxed_str()))
+                .expect("Invalid email");
+            let role = Role::parse(role.to_string()).expect("Invalid role");
+            PostgresUserStore::new(pg_pool)
+                .add_role(&email, role)
+                .await
+                .expect("Failed to grant role");
+            tracing::info!("Role granted");
+        }
+        _ => panic!("Usage: auth-service [rotate-signing-key | grant-role <email> <role>]"),
+    }
+}
+
 async fn configure_postgresql() -> PgPool {
     // Create a new database connection pool
     let pg_pool = get_postgres_pool(&DATABASE_URL)
@@ -82,8 +225,17 @@ fn configure_resend_email_client() -> ResendEmailClient {
                 .to_owned()
                 .into_boxed_str(),
         ))
-            .unwrap(),
+        .unwrap(),
         RESEND_API_KEY.to_owned(),
         http_client,
     )
-}
\ No newline at end of file
+}
+
+fn configure_identity_provider_client() -> IdentityProviderClient {
+    let http_client = Client::builder()
+        .timeout(prod::identity_provider_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    IdentityProviderClient::new(IDENTITY_PROVIDERS.clone(), http_client)
+}
@@ -0,0 +1,351 @@
+use super::api_keys::ApiKeyResponse;
+use super::change_password::reauthenticate;
+use super::login::clear_failed_logins;
+use super::sessions::{authenticate, end_all_sessions, remove_session_cookies, SessionResponse};
+use crate::app_state::AppState;
+use crate::domain::{
+    AuditAction, AuditEvent, AuthAPIError, ExternalIdentity, OAuthClientStoreError, Passkey,
+    TwoFACodeStoreError, TwoFAMethod,
+};
+use crate::utils::client_info::ClientInfo;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+// Permanently delete the logged-in user's account and everything stored about them.
+// The user has to confirm their password, so a stolen cookie isn't enough. Users created by
+// social login were never told their random password, so they set one with `/forgot-password`
+// first.
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (user, claims) = match authenticate(&jar, &state).await {
+        Ok(result) => result,
+        Err(e) => return (jar, Err(e)),
+    };
+    let email = user.email;
+
+    if let Err(e) = reauthenticate(&email, &request.password, &client, &state).await {
+        return (jar, Err(e));
+    }
+
+    // Log every device out, and make sure the token making this request can't be used again.
+    // The cookies are no good from here on, so they are cleared whatever happens next.
+    if let Err(e) = end_all_sessions(&user.id, &state).await {
+        return (jar, Err(e));
+    }
+    let jar = remove_session_cookies(jar);
+    if let Err(e) = state
+        .banned_token_store
+        .write()
+        .await
+        .add_token_id(&claims.jti)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // Purge what is kept outside the users table, and put the deletion on record, before the user
+    // goes. If any of it fails the account is still there, so the user can simply try again.
+    match state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(&email)
+        .await
+    {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+    if let Err(e) = state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(&user.id, vec![])
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    if let Err(e) = clear_failed_logins(&email, &state).await {
+        return (jar, Err(e));
+    }
+
+    let event = AuditEvent::new(
+        AuditAction::AccountDeleted,
+        user.id,
+        email.clone(),
+        client.user_agent,
+        client.ip_address,
+    );
+    if let Err(e) = state.audit_log_store.write().await.add_event(event).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    if let Err(e) = state.user_store.write().await.delete_user(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // The account is gone either way, so a notice that doesn't go out mustn't fail the request
+    if let Err(e) = state
+        .email_client
+        .send_email(
+            &email,
+            "Account Deleted",
+            "Your account and all of its data have been deleted. \
+            If this wasn't you, contact support right away.",
+        )
+        .await
+    {
+        tracing::warn!("Failed to send account deletion notice: {:?}", e);
+    }
+
+    let response = Json(DeleteAccountResponse {
+        message: "Account deleted successfully!".to_owned(),
+    });
+
+    (jar, Ok((StatusCode::OK, response)))
+}
+
+// Download everything stored about the logged-in user. Credentials (the password hash, the TOTP
+// secret and passkeys' public keys) are left out, as they are of no use to the user and dangerous
+// if leaked.
+#[tracing::instrument(name = "Export account", skip_all)]
+pub async fn export_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, claims) = authenticate(&jar, &state).await?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: session.id.as_ref() == claims.sid,
+            id: session.id.as_ref().to_owned(),
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at.to_rfc3339(),
+            last_seen_at: session.last_seen_at.to_rfc3339(),
+        })
+        .collect();
+
+    let api_keys = state
+        .api_key_store
+        .read()
+        .await
+        .get_keys(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(ApiKeyResponse::from)
+        .collect();
+
+    let passkeys = state
+        .passkey_store
+        .read()
+        .await
+        .get_passkeys(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(ExportedPasskey::from)
+        .collect();
+
+    let consents = state
+        .oauth_client_store
+        .read()
+        .await
+        .get_consents(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let mut oauth_consents = Vec::with_capacity(consents.len());
+    for consent in consents {
+        let client = match state
+            .oauth_client_store
+            .read()
+            .await
+            .get_client(&consent.client_id)
+            .await
+        {
+            Ok(client) => client,
+            // The client was removed along with the consent since they were listed
+            Err(OAuthClientStoreError::ClientNotFound) => continue,
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        };
+        oauth_consents.push(ExportedOAuthConsent {
+            client_id: client.id.as_ref().to_owned(),
+            client_name: client.name,
+            scopes: consent.scopes,
+            granted_at: consent.granted_at.to_rfc3339(),
+        });
+    }
+
+    let external_identities = state
+        .external_identity_store
+        .read()
+        .await
+        .get_identities(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(ExportedExternalIdentity::from)
+        .collect();
+
+    let audit_events = state
+        .audit_log_store
+        .read()
+        .await
+        .get_events(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(ExportedAuditEvent::from)
+        .collect();
+
+    let response = Json(AccountExportResponse {
+        id: user.id.to_string(),
+        email: user.email.as_ref().expose_secret().to_owned(),
+        verified: user.verified,
+        two_fa_method: user.two_fa_method,
+        totp_enrolled: user.totp_secret.is_some(),
+        roles: user
+            .roles
+            .iter()
+            .map(|role| role.as_ref().to_owned())
+            .collect(),
+        sessions,
+        api_keys,
+        passkeys,
+        oauth_consents,
+        external_identities,
+        audit_events,
+        exported_at: Utc::now().to_rfc3339(),
+    });
+
+    // Browsers save the export as a file rather than showing it
+    let headers = [(
+        header::CONTENT_DISPOSITION,
+        "attachment; filename=\"account-export.json\"",
+    )];
+
+    Ok((StatusCode::OK, headers, response))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct DeleteAccountResponse {
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountExportResponse {
+    pub id: String,
+    pub email: String,
+    pub verified: bool,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
+    #[serde(rename = "totpEnrolled")]
+    pub totp_enrolled: bool,
+    pub roles: Vec<String>,
+    pub sessions: Vec<SessionResponse>,
+    #[serde(rename = "apiKeys")]
+    pub api_keys: Vec<ApiKeyResponse>,
+    pub passkeys: Vec<ExportedPasskey>,
+    // The OAuth clients the user allowed to log them in
+    #[serde(rename = "oauthConsents")]
+    pub oauth_consents: Vec<ExportedOAuthConsent>,
+    // The identity provider accounts that log in as the user
+    #[serde(rename = "externalIdentities")]
+    pub external_identities: Vec<ExportedExternalIdentity>,
+    // What was done to the account, under any address it has had
+    #[serde(rename = "auditEvents")]
+    pub audit_events: Vec<ExportedAuditEvent>,
+    #[serde(rename = "exportedAt")]
+    pub exported_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedPasskey {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+impl From<Passkey> for ExportedPasskey {
+    fn from(passkey: Passkey) -> Self {
+        Self {
+            id: passkey.id.as_ref().to_owned(),
+            name: passkey.name,
+            created_at: passkey.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedOAuthConsent {
+    pub client_id: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    pub granted_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedExternalIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub created_at: String,
+}
+
+impl From<ExternalIdentity> for ExportedExternalIdentity {
+    fn from(identity: ExternalIdentity) -> Self {
+        Self {
+            provider: identity.provider,
+            subject: identity.subject,
+            created_at: identity.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedAuditEvent {
+    pub action: String,
+    // The account's address at the time
+    pub email: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub occurred_at: String,
+}
+
+impl From<AuditEvent> for ExportedAuditEvent {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            action: event.action.as_str().to_owned(),
+            email: event.email.as_ref().expose_secret().to_owned(),
+            user_agent: event.user_agent,
+            ip_address: event.ip_address,
+            occurred_at: event.occurred_at.to_rfc3339(),
+        }
+    }
+}
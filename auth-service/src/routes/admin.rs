@@ -0,0 +1,200 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, MachineClient, OAuthClient, Role, UserStoreError};
+use crate::utils::auth::{validate_auth_cookie, Claims};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+// Grant a role to a user. It is included in their auth tokens from the next login or refresh.
+#[tracing::instrument(name = "Grant role", skip_all)]
+pub async fn grant_role(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<RoleRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    require_admin(&jar, &state).await?;
+    let (email, role) = request.parse()?;
+
+    let result = state.user_store.write().await.add_role(&email, role).await;
+    roles_response(result, &email, &state).await
+}
+
+// Revoke a role from a user. Auth tokens already issued keep it until they expire.
+#[tracing::instrument(name = "Revoke role", skip_all)]
+pub async fn revoke_role(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<RoleRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    require_admin(&jar, &state).await?;
+    let (email, role) = request.parse()?;
+
+    let result = state
+        .user_store
+        .write()
+        .await
+        .remove_role(&email, &role)
+        .await;
+    roles_response(result, &email, &state).await
+}
+
+// Register an application that may log users in through `/authorize`
+#[tracing::instrument(name = "Register OAuth client", skip_all)]
+pub async fn register_oauth_client(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<OAuthClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    require_admin(&jar, &state).await?;
+    let client = OAuthClient::new(request.name, request.redirect_uris)
+        .map_err(|_| AuthAPIError::InvalidClientRegistration)?;
+
+    state
+        .oauth_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(OAuthClientResponse {
+        client_id: client.id.as_ref().to_owned(),
+        name: client.name,
+        redirect_uris: client.redirect_uris,
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+// Register a backend service that gets tokens for itself with the client credentials grant. The
+// secret is only returned here, so it has to be saved straight away.
+#[tracing::instrument(name = "Register machine client", skip_all)]
+pub async fn register_machine_client(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<MachineClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    require_admin(&jar, &state).await?;
+    let (client, client_secret) = MachineClient::new(request.name, request.scopes)
+        .map_err(|_| AuthAPIError::InvalidClientRegistration)?;
+
+    state
+        .machine_client_store
+        .write()
+        .await
+        .add_client(client.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(MachineClientResponse {
+        client_id: client.id.as_ref().to_owned(),
+        client_secret: client_secret.as_ref().expose_secret().to_owned(),
+        name: client.name,
+        scopes: client.scopes,
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+// Authenticate a request from the JWT cookie in `jar`, for routes only admins may call
+pub(crate) async fn require_admin(
+    jar: &CookieJar,
+    state: &AppState,
+) -> Result<Claims, AuthAPIError> {
+    let claims = validate_auth_cookie(
+        jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.signing_key_store.clone(),
+    )
+    .await?;
+
+    if !claims.has_role(&Role::admin()) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    Ok(claims)
+}
+
+// Respond with the roles the user has now
+async fn roles_response(
+    result: Result<(), UserStoreError>,
+    email: &Email,
+    state: &AppState,
+) -> Result<Json<RolesResponse>, AuthAPIError> {
+    match result {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::UserNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(RolesResponse {
+        roles: user
+            .roles
+            .iter()
+            .map(|role| role.as_ref().to_owned())
+            .collect(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct RoleRequest {
+    pub email: SecretString,
+    pub role: String,
+}
+
+impl RoleRequest {
+    fn parse(self) -> Result<(Email, Role), AuthAPIError> {
+        let email = Email::parse(self.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+        let role = Role::parse(self.role).map_err(|_| AuthAPIError::InvalidRole)?;
+        Ok((email, role))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RolesResponse {
+    pub roles: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthClientRequest {
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthClientResponse {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineClientRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineClientResponse {
+    pub client_id: String,
+    pub client_secret: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+}
@@ -0,0 +1,185 @@
+use super::sessions::authenticate;
+use crate::app_state::AppState;
+use crate::domain::{
+    ApiKey, ApiKeyId, ApiKeySecret, ApiKeyStoreError, AuthAPIError, User, UserStoreError,
+};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, Utc};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+// Longest a key can be set to last. Keys can also be created without an expiry.
+const MAX_API_KEY_TTL_DAYS: u32 = 365;
+
+// Create a long-lived key the user's scripts can authenticate with instead of a cookie. The key
+// is only returned here, so it has to be saved straight away.
+#[tracing::instrument(name = "Create API key", skip_all)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+
+    let expires_at = match request.expires_in_days {
+        Some(days @ 1..=MAX_API_KEY_TTL_DAYS) => Some(Utc::now() + Duration::days(days.into())),
+        Some(_) => return Err(AuthAPIError::InvalidApiKeyRequest),
+        None => None,
+    };
+    let (key, secret) = ApiKey::new(user.id, request.name, request.scopes, expires_at)
+        .map_err(|_| AuthAPIError::InvalidApiKeyRequest)?;
+
+    state
+        .api_key_store
+        .write()
+        .await
+        .add_key(key.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(CreateApiKeyResponse {
+        key: secret.as_ref().expose_secret().to_owned(),
+        api_key: ApiKeyResponse::from(key),
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+// List the user's keys. The keys themselves can't be shown again, only what they are called.
+#[tracing::instrument(name = "Get API keys", skip_all)]
+pub async fn get_api_keys(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+
+    let api_keys = state
+        .api_key_store
+        .read()
+        .await
+        .get_keys(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(ApiKeyResponse::from)
+        .collect();
+
+    Ok(Json(ApiKeysResponse { api_keys }))
+}
+
+// Revoke a key. It stops working straight away.
+#[tracing::instrument(name = "Delete API key", skip_all)]
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+    let id = ApiKeyId::parse(id).map_err(|_| AuthAPIError::ApiKeyNotFound)?;
+
+    match state
+        .api_key_store
+        .write()
+        .await
+        .delete_key(&user.id, &id)
+        .await
+    {
+        Ok(()) => Ok(StatusCode::OK),
+        Err(ApiKeyStoreError::KeyNotFound) => Err(AuthAPIError::ApiKeyNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Look up the user an API key belongs to, recording that the key was used
+#[tracing::instrument(name = "Validate API key", skip_all)]
+pub(crate) async fn validate_api_key(
+    secret: &ApiKeySecret,
+    state: &AppState,
+) -> Result<(User, ApiKey), AuthAPIError> {
+    let key = match state
+        .api_key_store
+        .read()
+        .await
+        .get_key_by_hash(&secret.hash())
+        .await
+    {
+        Ok(key) => key,
+        Err(ApiKeyStoreError::KeyNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if key.is_expired() {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let user = match state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&key.user_id)
+        .await
+    {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match state.api_key_store.write().await.touch_key(&key.id).await {
+        Ok(()) => {}
+        // Revoked while we were looking at it
+        Err(ApiKeyStoreError::KeyNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    Ok((user, key))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    // Keys without an expiry work until they are revoked
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyResponse {
+    // Only ever shown here
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeysResponse {
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id.as_ref().to_owned(),
+            name: key.name,
+            scopes: key.scopes,
+            created_at: key.created_at.to_rfc3339(),
+            expires_at: key.expires_at.map(|at| at.to_rfc3339()),
+            last_used_at: key.last_used_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use super::change_password::reauthenticate;
+use super::sessions::authenticate;
+use crate::app_state::AppState;
+use crate::domain::{
+    AuditAction, AuditEvent, AuthAPIError, Email, EmailChange, EmailChangeToken, UserStoreError,
+};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::AUTH_SERVICE_URL;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+// Ask to move the logged-in user's account to a new address. Nothing changes until the link
+// sent to the new address is followed, so users can't lock themselves out with a typo.
+#[tracing::instrument(name = "Change email", skip_all)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+    let email = user.email;
+
+    let new_email =
+        Email::parse(request.new_email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    if new_email == email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    reauthenticate(&email, &request.password, &client, &state).await?;
+
+    if state
+        .user_store
+        .read()
+        .await
+        .get_user(&new_email)
+        .await
+        .is_ok()
+    {
+        return Err(AuthAPIError::UserAlreadyExists);
+    }
+
+    let token = EmailChangeToken::default();
+    state
+        .email_change_token_store
+        .write()
+        .await
+        .add_token(
+            token.clone(),
+            EmailChange {
+                user_id: user.id,
+                old_email: email.clone(),
+                new_email: new_email.clone(),
+            },
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let confirmation_link = format!(
+        "{}/?email_change_token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.as_ref().expose_secret()
+    );
+
+    state
+        .email_client
+        .send_email(
+            &new_email,
+            "Confirm your new email address",
+            &format!(
+                "Use the following link to confirm your new email address: \
+                <a href=\"{0}\">{0}</a>",
+                confirmation_link
+            ),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    // Let the owner know, in case it wasn't them
+    state
+        .email_client
+        .send_email(
+            &email,
+            "Email Change Requested",
+            &format!(
+                "We received a request to change your account's email address to {}. It will \
+                only change once the new address is confirmed. If this wasn't you, change your \
+                password right away.",
+                new_email.as_ref().expose_secret()
+            ),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "Confirmation email sent!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Apply an email change from the link sent to the new address. Sessions and refresh tokens
+// follow the user rather than the address, so every device stays logged in.
+#[tracing::instrument(name = "Confirm email change", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(request): Json<ConfirmEmailChangeRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token =
+        EmailChangeToken::parse(request.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut email_change_token_store = state.email_change_token_store.write().await;
+
+    let change = email_change_token_store
+        .get_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    // Consume the token before touching the account so it can only ever be used once
+    email_change_token_store
+        .remove_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    drop(email_change_token_store);
+
+    let mut user_store = state.user_store.write().await;
+
+    // Only the account that asked is moved, and only if it still has the address it asked from
+    match user_store
+        .update_email(&change.user_id, &change.old_email, &change.new_email)
+        .await
+    {
+        Ok(()) => {}
+        // Someone signed up with the new address since the change was requested
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        // The account was deleted or moved to another address since the change was requested
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Following the link proved the user owns the new address
+    user_store
+        .mark_email_verified(&change.new_email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    let event = AuditEvent::new(
+        AuditAction::EmailChanged,
+        change.user_id,
+        change.old_email,
+        client.user_agent,
+        client.ip_address,
+    );
+    state
+        .audit_log_store
+        .write()
+        .await
+        .add_event(event)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(ConfirmEmailChangeResponse {
+        message: "Email changed successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: SecretString,
+    pub password: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmEmailChangeRequest {
+    pub token: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ConfirmEmailChangeResponse {
+    pub message: String,
+}
@@ -0,0 +1,111 @@
+use super::login::{login_attempt_keys, release_login_attempt, reserve_login_attempt};
+use super::sessions::{authenticate, end_session};
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, HashedPassword, SessionId};
+use crate::utils::client_info::ClientInfo;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+// Change the logged-in user's password. Every other device is logged out,
+// in case the password was changed because someone else knew it.
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, claims) = authenticate(&jar, &state).await?;
+    let email = user.email;
+
+    let new_password = HashedPassword::parse(request.new_password)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    reauthenticate(&email, &request.current_password, &client, &state).await?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, new_password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let current_session = SessionId::parse(claims.sid).map_err(|_| AuthAPIError::InvalidToken)?;
+    for session in sessions
+        .into_iter()
+        .filter(|session| session.id != current_session)
+    {
+        end_session(&user.id, &session.id, &state).await?;
+    }
+
+    // Let the owner know, in case it wasn't them
+    state
+        .email_client
+        .send_email(
+            &email,
+            "Password Changed",
+            "Your password was just changed and your other devices were logged out. \
+            If this wasn't you, reset your password right away.",
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password changed successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Make a logged-in user confirm their password before a sensitive change to their account.
+// A stolen cookie shouldn't allow guessing the password any faster than logging in.
+pub(crate) async fn reauthenticate(
+    email: &Email,
+    password: &SecretString,
+    client: &ClientInfo,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let attempt_keys = login_attempt_keys(email, client);
+    reserve_login_attempt(&attempt_keys, state).await?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if user.password.verify_raw_password(password).await.is_err() {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    release_login_attempt(&attempt_keys, state).await
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: SecretString,
+    #[serde(rename = "newPassword")]
+    pub new_password: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
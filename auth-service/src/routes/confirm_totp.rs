@@ -0,0 +1,90 @@
+use super::regenerate_recovery_codes::issue_recovery_codes;
+use super::sessions::authenticate;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, TotpCode, TwoFAMethod};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+// Finish authenticator-app enrollment by proving the app produces valid codes.
+// From then on the user's second factor is TOTP instead of email codes.
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+    let email = user.email;
+
+    let code = TotpCode::parse(request.code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if user.two_fa_method == TwoFAMethod::Totp {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    let secret = user
+        .totp_secret
+        .ok_or(AuthAPIError::TotpNotEnrolled)?
+        .decrypt()
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    if !secret
+        .verify_code(&email, &code)
+        .map_err(AuthAPIError::UnexpectedError)?
+    {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    user_store
+        .set_two_fa_method(&email, TwoFAMethod::Totp)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    // Users switching over from email codes keep the recovery codes they already have
+    let recovery_codes = match user.two_fa_method {
+        TwoFAMethod::None => Some(
+            issue_recovery_codes(&user.id, &state)
+                .await
+                .map_err(AuthAPIError::UnexpectedError)?,
+        ),
+        _ => None,
+    };
+
+    let response = Json(ConfirmTotpResponse {
+        message: "TOTP enabled successfully!".to_owned(),
+        recovery_codes,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: SecretString,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ConfirmTotpResponse {
+    pub message: String,
+    // Only present when this enabled 2FA for the first time
+    #[serde(
+        rename = "recoveryCodes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
+}
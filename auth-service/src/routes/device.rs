@@ -0,0 +1,201 @@
+use super::sessions::authenticate;
+use crate::app_state::AppState;
+use crate::domain::{
+    parse_scopes, AuthAPIError, DeviceAuthorization, DeviceAuthorizationStatus, DeviceCode,
+    DeviceCodeStoreError, OAuthClientId, OAuthClientStoreError, OAuthError, UserCode,
+    DEVICE_CODE_POLL_INTERVAL_SECONDS, DEVICE_CODE_TTL_SECONDS,
+};
+use crate::utils::constants::AUTH_SERVICE_URL;
+use axum::extract::State;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::IntoResponse;
+use axum::{Form, Json};
+use axum_extra::extract::CookieJar;
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+// The start of the RFC 8628 device authorization grant, for apps like CLIs that can't show the
+// user a browser. The device shows the user the user code and where to enter it, then polls
+// `/token` with the device code until the user has answered.
+#[tracing::instrument(name = "Device authorization", skip_all)]
+pub async fn device_authorization(
+    State(state): State<AppState>,
+    Form(request): Form<DeviceAuthorizationRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client_id = request.client_id.ok_or(OAuthError::InvalidRequest)?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+    {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(OAuthError::InvalidClient),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    let code = DeviceCode::default();
+    let authorization = DeviceAuthorization::new(client.id, parse_scopes(request.scope.as_deref()));
+    let user_code = authorization.user_code.to_string();
+
+    state
+        .device_code_store
+        .write()
+        .await
+        .add_code(code.clone(), authorization)
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+    // The verification page is the UI in `assets/`, which fills in the user code from the
+    // complete URI so devices that can show a QR code save the user typing it
+    let verification_uri = format!("{}/device", AUTH_SERVICE_URL.as_str());
+    let mut verification_uri_complete =
+        Url::parse(&verification_uri).map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+    verification_uri_complete
+        .query_pairs_mut()
+        .append_pair("user_code", &user_code);
+
+    let response = DeviceAuthorizationResponse {
+        device_code: code.as_ref().expose_secret().to_owned(),
+        user_code,
+        verification_uri,
+        verification_uri_complete: verification_uri_complete.to_string(),
+        expires_in: DEVICE_CODE_TTL_SECONDS,
+        interval: DEVICE_CODE_POLL_INTERVAL_SECONDS,
+    };
+    Ok((
+        [(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))],
+        Json(response),
+    ))
+}
+
+// Look up the request behind a user code, so the verification page can show the logged-in user
+// which client they are about to let in before they approve it
+#[tracing::instrument(name = "Verify device user code", skip_all)]
+pub async fn verify_user_code(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<UserCodeRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authenticate(&jar, &state).await?;
+    let (_, authorization) = find_pending_authorization(&request.user_code, &state).await?;
+
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&authorization.client_id)
+        .await
+    {
+        Ok(client) => client,
+        // The client was removed since the device asked
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(AuthAPIError::InvalidUserCode),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    Ok(Json(UserCodeResponse {
+        client_name: client.name,
+        scopes: authorization.scopes,
+    }))
+}
+
+// The user's answer from the verification page. The device picks it up the next time it polls.
+#[tracing::instrument(name = "Approve device", skip_all)]
+pub async fn approve_device(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<DeviceApprovalRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+    let (code, mut authorization) = find_pending_authorization(&request.user_code, &state).await?;
+
+    authorization.status = if request.decision == "approve" {
+        DeviceAuthorizationStatus::Approved(user.id)
+    } else {
+        DeviceAuthorizationStatus::Denied
+    };
+
+    match state
+        .device_code_store
+        .write()
+        .await
+        .update_authorization(&code, authorization)
+        .await
+    {
+        Ok(()) => Ok(StatusCode::OK),
+        // Expired while the user was looking at it
+        Err(DeviceCodeStoreError::CodeNotFound) => Err(AuthAPIError::InvalidUserCode),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Find the request a user code was shown for. Each request can only be answered once.
+async fn find_pending_authorization(
+    user_code: &str,
+    state: &AppState,
+) -> Result<(DeviceCode, DeviceAuthorization), AuthAPIError> {
+    let user_code = UserCode::parse(user_code).map_err(|_| AuthAPIError::InvalidUserCode)?;
+    let device_code_store = state.device_code_store.read().await;
+
+    let code = match device_code_store.get_code(&user_code).await {
+        Ok(code) => code,
+        Err(DeviceCodeStoreError::CodeNotFound) => return Err(AuthAPIError::InvalidUserCode),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    let authorization = match device_code_store.get_authorization(&code).await {
+        Ok(authorization) => authorization,
+        Err(DeviceCodeStoreError::CodeNotFound) => return Err(AuthAPIError::InvalidUserCode),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if authorization.status != DeviceAuthorizationStatus::Pending {
+        return Err(AuthAPIError::InvalidUserCode);
+    }
+    Ok((code, authorization))
+}
+
+#[derive(Deserialize)]
+pub struct DeviceAuthorizationRequest {
+    pub client_id: Option<String>,
+    // Space separated. Only the scopes in `SUPPORTED_SCOPES` are granted.
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    // Shown to the user as `WDJB-MJHT`
+    pub user_code: String,
+    pub verification_uri: String,
+    // The verification URI with the user code filled in
+    pub verification_uri_complete: String,
+    // Seconds until both codes expire
+    pub expires_in: u64,
+    // Seconds the device should wait between polls of `/token`
+    pub interval: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserCodeRequest {
+    pub user_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserCodeResponse {
+    pub client_name: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceApprovalRequest {
+    pub user_code: String,
+    // `approve` or `deny`
+    pub decision: String,
+}
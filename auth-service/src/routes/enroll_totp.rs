@@ -0,0 +1,58 @@
+use super::sessions::authenticate;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, EncryptedTotpSecret, TotpSecret, TwoFAMethod};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+// Start authenticator-app enrollment for the logged-in user.
+// The new secret only takes effect once a code generated from it is sent to `/confirm-totp`.
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+    let email = user.email;
+
+    let mut user_store = state.user_store.write().await;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    if user.two_fa_method == TwoFAMethod::Totp {
+        return Err(AuthAPIError::TotpAlreadyEnabled);
+    }
+
+    let secret = TotpSecret::default();
+    let otpauth_uri = secret
+        .otpauth_uri(&email)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let encrypted_secret =
+        EncryptedTotpSecret::encrypt(&secret).map_err(AuthAPIError::UnexpectedError)?;
+
+    user_store
+        .set_totp_secret(&email, encrypted_secret)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(EnrollTotpResponse {
+        secret: secret.as_ref().expose_secret().to_owned(),
+        otpauth_uri,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnrollTotpResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
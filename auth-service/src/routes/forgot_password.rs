@@ -0,0 +1,75 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, PasswordResetToken};
+use crate::utils::constants::AUTH_SERVICE_URL;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+#[tracing::instrument(name = "Forgot password", skip_all)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let response = Json(ForgotPasswordResponse {
+        message: "If an account exists for this email, a password reset link has been sent"
+            .to_owned(),
+    });
+
+    // Respond the same way for unknown emails so the route can't be used to discover accounts
+    if state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .is_err()
+    {
+        return Ok((StatusCode::OK, response));
+    }
+
+    let token = PasswordResetToken::default();
+
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .add_token(token.clone(), email.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let reset_link = format!(
+        "{}/?reset_token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.as_ref().expose_secret()
+    );
+
+    state
+        .email_client
+        .send_email(
+            &email,
+            "Password Reset",
+            &format!(
+                "Use the following link to reset your password: <a href=\"{0}\">{0}</a>",
+                reset_link
+            ),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ForgotPasswordResponse {
+    pub message: String,
+}
@@ -0,0 +1,244 @@
+use super::refresh::{inspect_refresh_token, RefreshError};
+use super::verify_token::{check_token, VerifiedToken};
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, RefreshToken};
+use crate::utils::constants::{INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET};
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::{Form, Json};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// RFC 7662 token introspection: tell an authenticated resource server whether a token is active
+// and, if it is, who it belongs to and what it is good for
+#[tracing::instrument(name = "Introspect", skip_all)]
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectRequest>,
+) -> Result<Json<IntrospectResponse>, AuthAPIError> {
+    authenticate_client(&headers, &request)?;
+
+    // Look for the token where the hint says first, then among every other kind of token, as the
+    // RFC asks. Any token we can't vouch for is simply inactive.
+    let response = match request.token_type_hint.as_deref() {
+        Some("refresh_token") => match introspect_refresh_token(&request.token, &state).await? {
+            Some(response) => Some(response),
+            None => introspect_access_token(&request.token, &state).await?,
+        },
+        _ => match introspect_access_token(&request.token, &state).await? {
+            Some(response) => Some(response),
+            None => introspect_refresh_token(&request.token, &state).await?,
+        },
+    };
+
+    Ok(Json(response.unwrap_or_else(IntrospectResponse::inactive)))
+}
+
+// Access tokens are checked just as `/verify-token` checks them, whoever they were issued to
+async fn introspect_access_token(
+    token: &SecretString,
+    state: &AppState,
+) -> Result<Option<IntrospectResponse>, AuthAPIError> {
+    let token = match check_token(token, state).await {
+        Ok(token) => token,
+        Err(AuthAPIError::InvalidToken) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let response = match token {
+        VerifiedToken::User(claims, user) => IntrospectResponse {
+            scope: Some(claims.roles.join(" ")),
+            username: Some(user.email.as_ref().expose_secret().to_owned()),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            nbf: Some(claims.nbf),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            iss: Some(claims.iss),
+            jti: Some(claims.jti),
+            sid: Some(claims.sid),
+            ..IntrospectResponse::active()
+        },
+        VerifiedToken::Delegated(claims, user) => IntrospectResponse {
+            scope: Some(claims.scope),
+            client_id: Some(claims.client_id),
+            username: Some(user.email.as_ref().expose_secret().to_owned()),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            nbf: Some(claims.nbf),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            iss: Some(claims.iss),
+            jti: Some(claims.jti),
+            sid: Some(claims.sid),
+            ..IntrospectResponse::active()
+        },
+        VerifiedToken::Client(claims) => IntrospectResponse {
+            scope: Some(claims.scope),
+            client_id: Some(claims.client_id),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            nbf: Some(claims.nbf),
+            sub: Some(claims.sub),
+            aud: Some(claims.aud),
+            iss: Some(claims.iss),
+            jti: Some(claims.jti),
+            ..IntrospectResponse::active()
+        },
+        VerifiedToken::ApiKey(key, user) => IntrospectResponse {
+            scope: Some(key.scopes.join(" ")),
+            username: Some(user.email.as_ref().expose_secret().to_owned()),
+            exp: key
+                .expires_at
+                .map(|expires_at| expires_at.timestamp() as usize),
+            iat: Some(key.created_at.timestamp() as usize),
+            sub: Some(user.id.to_string()),
+            ..IntrospectResponse::active()
+        },
+    };
+
+    Ok(Some(response))
+}
+
+// Refresh tokens are active until they are used or their login ends. Looking at one doesn't use
+// it up, so a resource server can't spend the client's token.
+async fn introspect_refresh_token(
+    token: &SecretString,
+    state: &AppState,
+) -> Result<Option<IntrospectResponse>, AuthAPIError> {
+    let token = match RefreshToken::parse(token.clone()) {
+        Ok(token) => token,
+        Err(_) => return Ok(None),
+    };
+
+    let (user, family) = match inspect_refresh_token(&token, state).await {
+        Ok(inspected) => inspected,
+        Err(RefreshError::InvalidToken | RefreshError::SessionEnded) => return Ok(None),
+        Err(RefreshError::UnexpectedError(e)) => return Err(AuthAPIError::UnexpectedError(e)),
+    };
+
+    // Tokens of the user's own logins are swapped for auth tokens carrying their roles,
+    // while a client's are only good for the scopes the user granted it
+    let scope = match family.client_id {
+        Some(_) => family.scopes.join(" "),
+        None => user
+            .roles
+            .iter()
+            .map(|role| role.as_ref())
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+
+    Ok(Some(IntrospectResponse {
+        active: true,
+        scope: Some(scope),
+        client_id: family.client_id.map(|id| id.as_ref().to_owned()),
+        username: Some(user.email.as_ref().expose_secret().to_owned()),
+        sub: Some(user.id.to_string()),
+        sid: Some(family.id.as_ref().to_owned()),
+        ..IntrospectResponse::default()
+    }))
+}
+
+// Resource servers authenticate with HTTP Basic auth (`client_secret_basic`),
+// or with `client_id` and `client_secret` in the form body (`client_secret_post`)
+fn authenticate_client(
+    headers: &HeaderMap,
+    request: &IntrospectRequest,
+) -> Result<(), AuthAPIError> {
+    let (client_id, client_secret) = match basic_auth(headers) {
+        Some(credentials) => credentials,
+        None => match (&request.client_id, &request.client_secret) {
+            (Some(client_id), Some(client_secret)) => {
+                (client_id.clone(), client_secret.expose_secret().to_owned())
+            }
+            _ => return Err(AuthAPIError::InvalidClient),
+        },
+    };
+
+    // Introspection is disabled until a client secret is configured
+    let expected_secret = INTROSPECTION_CLIENT_SECRET
+        .as_ref()
+        .ok_or(AuthAPIError::InvalidClient)?;
+
+    // Compare digests so the comparison time doesn't depend on how much of the secret matched
+    let secret_matches = Sha256::digest(client_secret.as_bytes())
+        == Sha256::digest(expected_secret.expose_secret().as_bytes());
+    if client_id != *INTROSPECTION_CLIENT_ID || !secret_matches {
+        return Err(AuthAPIError::InvalidClient);
+    }
+
+    Ok(())
+}
+
+// The client id and secret in an `Authorization: Basic` header (RFC 7617)
+pub(crate) fn basic_auth(headers: &HeaderMap) -> Option<(String, String)> {
+    let credentials = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let credentials = String::from_utf8(STANDARD.decode(credentials).ok()?).ok()?;
+    let (client_id, client_secret) = credentials.split_once(':')?;
+    Some((client_id.to_owned(), client_secret.to_owned()))
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectRequest {
+    pub token: SecretString,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<SecretString>,
+}
+
+// Only `active` is sent for inactive tokens, so nothing leaks about tokens we don't vouch for
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    // What the token is good for, space separated: the user's roles, or the scopes granted to a
+    // client or API key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    // The client the token was issued to, if it wasn't issued to the user themselves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    // The id of the session the token was issued for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+impl IntrospectResponse {
+    fn inactive() -> Self {
+        Self::default()
+    }
+
+    // Access tokens are all bearer tokens
+    fn active() -> Self {
+        Self {
+            active: true,
+            token_type: Some("Bearer".to_owned()),
+            ..Self::default()
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::app_state::AppState;
+use crate::domain::AuthAPIError;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::Json;
+use color_eyre::eyre::Result;
+use jsonwebtoken::jwk::JwkSet;
+
+// Publish the public keys JWT auth tokens can be verified with,
+// so other services don't need to call `/verify-token` or hold a secret.
+// Retired keys are listed until the tokens they signed have expired.
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks(State(state): State<AppState>) -> Result<impl IntoResponse, AuthAPIError> {
+    let signing_keys = state
+        .signing_key_store
+        .read()
+        .await
+        .get_keys()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut keys = vec![];
+    for signing_key in signing_keys {
+        if let Some(jwk) = signing_key.jwk().map_err(AuthAPIError::UnexpectedError)? {
+            keys.push(jwk);
+        }
+    }
+
+    Ok((
+        // Let consumers cache the keys for a while instead of fetching them for every token
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(JwkSet { keys }),
+    ))
+}
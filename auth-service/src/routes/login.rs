@@ -1,7 +1,10 @@
+use super::sessions::start_session;
 use crate::app_state::AppState;
-use crate::domain::{AuthAPIError, Email, HashedPassword, LoginAttemptId, TwoFACode};
-use crate::utils::auth;
-use auth::generate_auth_cookie;
+use crate::domain::{
+    AuthAPIError, Email, HashedPassword, LoginAttemptId, LoginAttemptKey, Session, ThrottlePolicy,
+    TwoFACode, TwoFAMethod, User,
+};
+use crate::utils::client_info::ClientInfo;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
@@ -14,6 +17,7 @@ use serde::{Deserialize, Serialize};
 pub async fn login(
     State(state): State<AppState>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email: Email = match Email::parse(request.email) {
@@ -26,6 +30,11 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
+    let attempt_keys = login_attempt_keys(&email, &client);
+    if let Err(e) = reserve_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+
     let user_store = &state.user_store.read().await;
 
     if user_store
@@ -36,29 +45,39 @@ pub async fn login(
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     };
 
+    if let Err(e) = release_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+
     let user = match user_store.get_user(&email).await {
         Ok(user) => user,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
+    if !user.verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
     // Handle request based on the user's 2FA configuration
-    match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+    match user.two_fa_method {
+        TwoFAMethod::None => handle_no_2fa(&user, client, &state, jar).await,
+        method => handle_2fa(&user.email, method, &state, jar).await,
     }
 }
 
 // New!
 #[tracing::instrument(name = "Handle 2FA flow", skip_all)]
 async fn handle_2fa(
-    email: &Email,    // New!
+    email: &Email, // New!
+    method: TwoFAMethod,
     state: &AppState, // New!
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    // First, we must generate a new random login attempt ID and 2FA code
+    // First, we must generate a new random login attempt ID and 2FA code.
+    // TOTP users never see this code; it is stored so the login attempt ID can be checked.
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
@@ -74,22 +93,26 @@ async fn handle_2fa(
     }
 
     // send 2FA code via the email client. Return `AuthAPIError::UnexpectedError` if the operation fails.
-    if let Err(e) = state
-        .email_client
-        .send_email(
-            email,
-            "2FA Authentication Code",
-            two_fa_code.as_ref().expose_secret(),
-        )
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    // TOTP users read their code from their authenticator app instead.
+    if method == TwoFAMethod::Email {
+        if let Err(e) = state
+            .email_client
+            .send_email(
+                email,
+                "2FA Authentication Code",
+                two_fa_code.as_ref().expose_secret(),
+            )
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
+        }
     }
 
     // Finally, we need to return the login attempt ID to the client
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(), // Add the generated login attempt ID
+        two_fa_method: method,
     }));
 
     (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
@@ -98,18 +121,26 @@ async fn handle_2fa(
 // New!
 #[tracing::instrument(name = "Handle non-2FA flow", skip_all)]
 async fn handle_no_2fa(
-    email: &Email,
+    user: &User,
+    client: ClientInfo,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(email) {
-        Ok(cookie) => cookie,
+    if let Err(e) = clear_failed_logins(&user.email, state).await {
+        return (jar, Err(e));
+    }
+
+    let session = Session::new(user.id, client.user_agent, client.ip_address);
+
+    let (auth_cookie, refresh_cookie) = match start_session(session, state).await {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     (
         updated_jar,
@@ -117,6 +148,75 @@ async fn handle_no_2fa(
     )
 }
 
+// Failed logins are counted against both the account and the address they came from,
+// so guessing is slowed down whether it targets one account or many
+pub(crate) fn login_attempt_keys(
+    email: &Email,
+    client: &ClientInfo,
+) -> Vec<(LoginAttemptKey, ThrottlePolicy)> {
+    let mut keys = vec![(LoginAttemptKey::Email(email.clone()), ThrottlePolicy::EMAIL)];
+    if let Some(ip_address) = &client.ip_address {
+        keys.push((
+            LoginAttemptKey::IpAddress(ip_address.clone()),
+            ThrottlePolicy::IP_ADDRESS,
+        ));
+    }
+    keys
+}
+
+// Count the attempt as a failure up front, so a burst of parallel attempts is throttled like
+// the same attempts made one after another. It is refused while any of its keys has failed too
+// often lately, and refused attempts stay counted.
+#[tracing::instrument(name = "Reserve login attempt", skip_all)]
+pub(crate) async fn reserve_login_attempt(
+    keys: &[(LoginAttemptKey, ThrottlePolicy)],
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let mut login_attempt_store = state.login_attempt_store.write().await;
+    for (key, policy) in keys {
+        let failures = login_attempt_store
+            .reserve_attempt(key)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        if let Some(failures) = failures {
+            policy.check(&failures)?;
+        }
+    }
+    Ok(())
+}
+
+// The reserved attempt got the credentials right, so it no longer counts as a failure
+#[tracing::instrument(name = "Release login attempt", skip_all)]
+pub(crate) async fn release_login_attempt(
+    keys: &[(LoginAttemptKey, ThrottlePolicy)],
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let mut login_attempt_store = state.login_attempt_store.write().await;
+    for (key, _) in keys {
+        login_attempt_store
+            .release_attempt(key)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+    Ok(())
+}
+
+// A successful login wipes the account's slate. The address's failures are kept, so an attacker
+// can't reset them by logging into their own account.
+#[tracing::instrument(name = "Clear failed logins", skip_all)]
+pub(crate) async fn clear_failed_logins(
+    email: &Email,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    state
+        .login_attempt_store
+        .write()
+        .await
+        .clear_failures(&LoginAttemptKey::Email(email.clone()))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     email: SecretString,
@@ -138,4 +238,7 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    // Tells the client where the user should look for their code
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
 }
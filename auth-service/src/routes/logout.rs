@@ -1,11 +1,12 @@
+use super::sessions::{end_session, remove_session_cookies, token_user};
 use crate::app_state::AppState;
 use crate::{
-    domain::AuthAPIError,
+    domain::{AuthAPIError, SessionId},
     utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
 };
 use axum::extract::State;
 use axum::{http::StatusCode, response::IntoResponse};
-use axum_extra::extract::{cookie, CookieJar};
+use axum_extra::extract::CookieJar;
 use secrecy::SecretString;
 
 #[tracing::instrument(name = "Logout", skip_all)]
@@ -22,9 +23,16 @@ pub async fn logout(
 
     let token = SecretString::new(cookie.value().to_owned().into_boxed_str());
 
-    // If the token is valid, you can ignore the returned claims for now.
+    // If the token is valid, end the session it belongs to.
     // Return AuthAPIError::InvalidToken is validation fails.
-    let _ = match validate_token(&token, state.banned_token_store.clone()).await {
+    let claims = match validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.signing_key_store.clone(),
+    )
+    .await
+    {
         Ok(claims) => claims,
         Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
     };
@@ -33,14 +41,26 @@ pub async fn logout(
         .banned_token_store
         .write()
         .await
-        .add_token(token.to_owned())
+        .add_token_id(&claims.jti)
         .await
     {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    // Remove JWT cookie from the CookieJar
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
+    // End the login's session too, so it can't be resumed through `/refresh`
+    let user = match token_user(&claims, &state).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+    let session_id = match SessionId::parse(claims.sid) {
+        Ok(session_id) => session_id,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+    match end_session(&user.id, &session_id, &state).await {
+        Ok(()) | Err(AuthAPIError::SessionNotFound) => {}
+        Err(e) => return (jar, Err(e)),
+    }
 
-    (jar, Ok(StatusCode::OK))
+    // Remove JWT and refresh cookies from the CookieJar
+    (remove_session_cookies(jar), Ok(StatusCode::OK))
 }
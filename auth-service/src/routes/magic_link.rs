@@ -0,0 +1,161 @@
+use super::login::{
+    clear_failed_logins, login_attempt_keys, release_login_attempt, reserve_login_attempt,
+};
+use super::sessions::start_session;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, MagicLinkToken, Session, TwoFAMethod, User, UserId};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::AUTH_SERVICE_URL;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Email a single-use link that logs the user in without their password. Links are only sent to
+// verified accounts without 2FA, since following one proves nothing beyond owning the inbox.
+#[tracing::instrument(name = "Request magic link", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let response = Json(MagicLinkResponse {
+        message: "If this email belongs to an account that can use magic links, one has been sent"
+            .to_owned(),
+    });
+
+    // Respond the same way for every address so the route can't be used to discover accounts
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) if can_use_magic_link(&user) => user,
+        _ => return Ok((StatusCode::OK, response)),
+    };
+
+    // A new link replaces any earlier one that hasn't been used yet
+    let token = MagicLinkToken::default();
+    state
+        .magic_link_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // The link opens a page that asks the user to confirm before the token is spent, so mail
+    // scanners that follow links can't use it up or log anyone in
+    let login_link = format!(
+        "{}/?magic_link_id={}&magic_link_token={}",
+        AUTH_SERVICE_URL.as_str(),
+        user.id,
+        token.as_ref().expose_secret()
+    );
+
+    state
+        .email_client
+        .send_email(
+            &email,
+            "Your Login Link",
+            &format!(
+                "Use the following link to log in. It expires in 10 minutes and only works once: \
+                <a href=\"{0}\">{0}</a>",
+                login_link
+            ),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((StatusCode::OK, response))
+}
+
+// Log the user in with the id and token from a magic link, setting the same cookies as `/login`
+#[tracing::instrument(name = "Magic link callback", skip_all)]
+pub async fn magic_link_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<MagicLinkCallbackRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (user_id, token) = match (
+        UserId::parse(request.id),
+        MagicLinkToken::parse(request.token),
+    ) {
+        (Ok(user_id), Ok(token)) => (user_id, token),
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let user = match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    // Links are throttled and locked out together with password logins
+    let attempt_keys = login_attempt_keys(&user.email, &client);
+    if let Err(e) = reserve_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+
+    let mut magic_link_token_store = state.magic_link_token_store.write().await;
+
+    // Compare digests so the comparison time doesn't depend on how much of the token matched
+    let valid = match magic_link_token_store.get_token(&user.email).await {
+        Ok(stored_token) => {
+            Sha256::digest(stored_token.as_ref().expose_secret().as_bytes())
+                == Sha256::digest(token.as_ref().expose_secret().as_bytes())
+        }
+        Err(_) => false,
+    };
+
+    // The account may have turned on 2FA since the link was sent
+    if !valid || !can_use_magic_link(&user) {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    // Consume the token before logging in so the link can only ever be used once
+    if let Err(e) = magic_link_token_store.remove_token(&user.email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    drop(magic_link_token_store);
+
+    if let Err(e) = release_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+    if let Err(e) = clear_failed_logins(&user.email, &state).await {
+        return (jar, Err(e));
+    }
+
+    let session = Session::new(user.id, client.user_agent, client.ip_address);
+
+    let (auth_cookie, refresh_cookie) = match start_session(session, &state).await {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+fn can_use_magic_link(user: &User) -> bool {
+    user.verified && user.two_fa_method == TwoFAMethod::None
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkCallbackRequest {
+    pub id: String,
+    pub token: SecretString,
+}
@@ -1,12 +1,55 @@
+mod account;
+mod admin;
+mod api_keys;
+mod change_email;
+mod change_password;
+mod confirm_totp;
+mod device;
+mod enroll_totp;
+mod forgot_password;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod refresh;
+mod regenerate_recovery_codes;
+mod resend_verification;
+mod reset_password;
+mod sessions;
 mod signup;
+mod social_login;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webauthn;
 
+pub use account::*;
+pub use admin::*;
+pub use api_keys::*;
+pub use change_email::*;
+pub use change_password::*;
+pub use confirm_totp::*;
+pub use device::*;
+pub use enroll_totp::*;
+pub use forgot_password::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use magic_link::*;
+pub use oauth::*;
+pub use oidc::*;
+pub use refresh::*;
+pub use regenerate_recovery_codes::*;
+pub use resend_verification::*;
+pub use reset_password::*;
+pub use sessions::*;
 pub use signup::*;
+pub use social_login::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
-
+pub use webauthn::*;
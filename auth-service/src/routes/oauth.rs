@@ -0,0 +1,707 @@
+use super::introspect::basic_auth;
+use super::refresh::{issue_refresh_token, redeem_refresh_token, RefreshError};
+use super::sessions::authenticate;
+use crate::app_state::AppState;
+use crate::domain::{
+    parse_scopes, AuthAPIError, AuthorizationCode, AuthorizationCodeStoreError, AuthorizationGrant,
+    ClientSecret, CodeChallenge, DeviceAuthorizationStatus, DeviceCode, DeviceCodeStoreError,
+    Email, MachineClientStoreError, OAuthClient, OAuthClientId, OAuthClientStoreError, OAuthError,
+    RefreshToken, RefreshTokenFamily, Session, UserId, UserStoreError, DEVICE_CODE_GRANT_TYPE,
+};
+use crate::utils::auth::{
+    generate_access_token, generate_client_token, generate_id_token, TOKEN_TTL_SECONDS,
+};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::AUTH_SERVICE_URL;
+use axum::extract::{OriginalUri, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::{Form, Json};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use reqwest::Url;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+// The start of the RFC 6749 authorization code flow. A registered client sends the user here to
+// log in; once they allow it, they are sent back to the client with a code it can swap for
+// tokens at `/token`. Every request must carry an S256 PKCE challenge (RFC 7636).
+#[tracing::instrument(name = "Authorize", skip_all)]
+pub async fn authorize(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    OriginalUri(uri): OriginalUri,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<Response, AuthAPIError> {
+    let (client, redirect_uri) = find_client(&request, &state).await?;
+    let code_challenge = match check_request(&request) {
+        Ok(code_challenge) => code_challenge,
+        Err(error) => return error_redirect(&redirect_uri, error, &request.state),
+    };
+
+    // Send the user to log in first, and back here once they have
+    let user = match authenticate(&jar, &state).await {
+        Ok((user, _)) => user,
+        Err(AuthAPIError::MissingToken | AuthAPIError::InvalidToken) => {
+            let path = uri
+                .path_and_query()
+                .map(|p| p.as_str())
+                .unwrap_or("/authorize");
+            let mut login_url = service_url("/")?;
+            login_url.query_pairs_mut().append_pair("next", path);
+            return Ok(Redirect::to(login_url.as_str()).into_response());
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Users are asked again whenever a client wants more than they allowed it before
+    let scopes = parse_scopes(request.scope.as_deref());
+    let has_consent = state
+        .oauth_client_store
+        .read()
+        .await
+        .has_consent(&user.id, &client.id, &scopes)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if has_consent {
+        let grant = authorization_grant(client.id, user.id, redirect_uri, code_challenge, &request);
+        return code_redirect(grant, &request.state, &state).await;
+    }
+
+    Ok(consent_page(&client, &user.email, &scopes, &request))
+}
+
+// The user's answer from the consent page. It needs the JWT cookie, which browsers leave off
+// cross-site form posts, so other sites can't answer on the user's behalf.
+#[tracing::instrument(name = "Authorize consent", skip_all)]
+pub async fn authorize_consent(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Form(consent): Form<ConsentRequest>,
+) -> Result<Response, AuthAPIError> {
+    let request = consent.request;
+    let (client, redirect_uri) = find_client(&request, &state).await?;
+    let code_challenge = match check_request(&request) {
+        Ok(code_challenge) => code_challenge,
+        Err(error) => return error_redirect(&redirect_uri, error, &request.state),
+    };
+
+    let (user, _) = authenticate(&jar, &state).await?;
+
+    if consent.decision != "approve" {
+        return error_redirect(&redirect_uri, "access_denied", &request.state);
+    }
+
+    // Remember the answer so the user isn't asked again for these scopes
+    state
+        .oauth_client_store
+        .write()
+        .await
+        .add_consent(
+            &user.id,
+            &client.id,
+            &parse_scopes(request.scope.as_deref()),
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let grant = authorization_grant(client.id, user.id, redirect_uri, code_challenge, &request);
+    code_redirect(grant, &request.state, &state).await
+}
+
+// Swap an authorization code, or a refresh token issued here, for an access token and a new
+// refresh token. Access tokens are for the client alone and carry the scopes the user granted it,
+// not the user's roles, so they can't be used as the auth cookie. `/verify-token` accepts them.
+// Codes granted the `openid` scope also get an OpenID Connect ID token.
+// Machine clients get access tokens of their own with the client credentials grant, and devices
+// that started a device authorization at `/device/code` poll here until the user answers.
+#[tracing::instrument(name = "Token", skip_all)]
+pub async fn token(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let response = match request.grant_type.as_deref() {
+        Some("authorization_code") => exchange_code(request, client, &state).await?,
+        Some("refresh_token") => exchange_refresh_token(request, &state).await?,
+        Some("client_credentials") => {
+            exchange_client_credentials(&headers, request, &state).await?
+        }
+        Some(DEVICE_CODE_GRANT_TYPE) => exchange_device_code(request, client, &state).await?,
+        Some(_) => return Err(OAuthError::UnsupportedGrantType),
+        None => return Err(OAuthError::InvalidRequest),
+    };
+
+    Ok((
+        [
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+            (header::PRAGMA, HeaderValue::from_static("no-cache")),
+        ],
+        Json(response),
+    ))
+}
+
+async fn exchange_code(
+    request: TokenRequest,
+    client: ClientInfo,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    let (code, redirect_uri, client_id, code_verifier) = match (
+        request.code,
+        request.redirect_uri,
+        request.client_id,
+        request.code_verifier,
+    ) {
+        (Some(code), Some(redirect_uri), Some(client_id), Some(code_verifier)) => {
+            (code, redirect_uri, client_id, code_verifier)
+        }
+        _ => return Err(OAuthError::InvalidRequest),
+    };
+    let code = AuthorizationCode::parse(code).map_err(|_| OAuthError::InvalidGrant)?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+
+    let mut authorization_code_store = state.authorization_code_store.write().await;
+    let grant = match authorization_code_store.get_grant(&code).await {
+        Ok(grant) => grant,
+        Err(AuthorizationCodeStoreError::CodeNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    // Consume the code before checking it, so a code that was sent with the wrong verifier
+    // can't be tried again
+    match authorization_code_store.remove_code(&code).await {
+        Ok(()) => {}
+        Err(AuthorizationCodeStoreError::CodeNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    }
+    drop(authorization_code_store);
+
+    // RFC 6749 treats a code issued to another client like any other bad code
+    if grant.client_id != client_id
+        || grant.redirect_uri != redirect_uri
+        || !grant.code_challenge.verify(code_verifier.expose_secret())
+    {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    log_in_user(
+        &grant.user_id,
+        &grant.client_id,
+        grant.scopes,
+        grant.nonce,
+        client,
+        state,
+    )
+    .await
+}
+
+// RFC 8628 section 3.4: a device polls with its device code until the user has answered on the
+// verification page. Polls that come sooner than the interval it was given are told to slow down,
+// and the interval grows by five seconds each time, as section 3.5 requires.
+async fn exchange_device_code(
+    request: TokenRequest,
+    client: ClientInfo,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    let (code, client_id) = match (request.device_code, request.client_id) {
+        (Some(code), Some(client_id)) => (code, client_id),
+        _ => return Err(OAuthError::InvalidRequest),
+    };
+    let code = DeviceCode::parse(code).map_err(|_| OAuthError::InvalidGrant)?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+
+    let mut device_code_store = state.device_code_store.write().await;
+    let mut authorization = match device_code_store.get_authorization(&code).await {
+        Ok(authorization) => authorization,
+        // Codes are forgotten once they expire, so the device has to start again
+        Err(DeviceCodeStoreError::CodeNotFound) => return Err(OAuthError::ExpiredToken),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    if authorization.client_id != client_id {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    let user_id = match authorization.status.clone() {
+        DeviceAuthorizationStatus::Pending => {
+            let now = Utc::now();
+            let error = if authorization.is_polling_too_fast(now) {
+                authorization.slow_down();
+                OAuthError::SlowDown
+            } else {
+                OAuthError::AuthorizationPending
+            };
+            authorization.last_polled_at = Some(now);
+            match device_code_store
+                .update_authorization(&code, authorization)
+                .await
+            {
+                Ok(()) => return Err(error),
+                Err(DeviceCodeStoreError::CodeNotFound) => return Err(OAuthError::ExpiredToken),
+                Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+            }
+        }
+        DeviceAuthorizationStatus::Approved(user_id) => user_id,
+        DeviceAuthorizationStatus::Denied => {
+            device_code_store
+                .remove_code(&code)
+                .await
+                .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+            return Err(OAuthError::AccessDenied);
+        }
+    };
+
+    // Like authorization codes, each device code gets the device logged in once
+    match device_code_store.remove_code(&code).await {
+        Ok(()) => {}
+        Err(DeviceCodeStoreError::CodeNotFound) => return Err(OAuthError::ExpiredToken),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    }
+    drop(device_code_store);
+
+    let scopes = authorization.scopes;
+    log_in_user(&user_id, &client_id, scopes, None, client, state).await
+}
+
+// Public clients have no secret, so they identify themselves with `client_id` (RFC 6749
+// section 6). Only the client a refresh token was issued to can swap it.
+async fn exchange_refresh_token(
+    request: TokenRequest,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    let (token, client_id) = match (request.refresh_token, request.client_id) {
+        (Some(token), Some(client_id)) => (token, client_id),
+        _ => return Err(OAuthError::InvalidRequest),
+    };
+    let token = RefreshToken::parse(token).map_err(|_| OAuthError::InvalidGrant)?;
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+
+    let (user, family) = match redeem_refresh_token(&token, Some(&client_id), state).await {
+        Ok(redeemed) => redeemed,
+        Err(RefreshError::InvalidToken | RefreshError::SessionEnded) => {
+            return Err(OAuthError::InvalidGrant)
+        }
+        Err(RefreshError::UnexpectedError(e)) => return Err(OAuthError::UnexpectedError(e)),
+    };
+
+    let scopes = family.scopes.clone();
+    issue_tokens(&user.id, &client_id, &scopes, family, state).await
+}
+
+// RFC 6749 section 4.4: a machine client authenticates and gets a token for itself, with the
+// scopes it asked for out of those it is allowed. It can just authenticate again when the token
+// expires, so no refresh token is issued.
+async fn exchange_client_credentials(
+    headers: &HeaderMap,
+    request: TokenRequest,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    // HTTP Basic auth (`client_secret_basic`), or the form body (`client_secret_post`)
+    let (client_id, client_secret) = match basic_auth(headers) {
+        Some((client_id, client_secret)) => {
+            (client_id, SecretString::new(client_secret.into_boxed_str()))
+        }
+        None => match (request.client_id, request.client_secret) {
+            (Some(client_id), Some(client_secret)) => (client_id, client_secret),
+            _ => return Err(OAuthError::InvalidClient),
+        },
+    };
+    let client_id = OAuthClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+
+    let client = match state
+        .machine_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+    {
+        Ok(client) => client,
+        Err(MachineClientStoreError::ClientNotFound) => return Err(OAuthError::InvalidClient),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    if !client.verify_secret(&ClientSecret::new(client_secret)) {
+        return Err(OAuthError::InvalidClient);
+    }
+
+    let scopes = client
+        .grant_scopes(request.scope.as_deref())
+        .map_err(|_| OAuthError::InvalidScope)?;
+    let access_token = generate_client_token(&client.id, &scopes, state.signing_key_store.clone())
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+
+    Ok(TokenResponse {
+        access_token: access_token.expose_secret().to_owned(),
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        refresh_token: None,
+        id_token: None,
+        scope: (!scopes.is_empty()).then(|| scopes.join(" ")),
+    })
+}
+
+// Log the user in to a client they allowed. Each such login is a session of its own, listed and
+// revoked like any other.
+async fn log_in_user(
+    user_id: &UserId,
+    client_id: &OAuthClientId,
+    scopes: Vec<String>,
+    nonce: Option<String>,
+    client: ClientInfo,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    // Users are named by id, so a change of email address in between doesn't lose them, and an
+    // address given up and signed up with again doesn't log the client in to the wrong account
+    let user = match state.user_store.read().await.get_user_by_id(user_id).await {
+        Ok(user) => user,
+        // The account was deleted since the user allowed the client
+        Err(UserStoreError::UserNotFound) => return Err(OAuthError::InvalidGrant),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    let session = Session::new(user.id, client.user_agent, client.ip_address);
+    let family = RefreshTokenFamily::for_client(
+        session.id.clone(),
+        user.id,
+        client_id.clone(),
+        scopes.clone(),
+    );
+
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+    let mut response = issue_tokens(&user.id, client_id, &scopes, family, state).await?;
+
+    // OpenID Connect clients also learn who logged in
+    if scopes.iter().any(|scope| scope == "openid") {
+        let id_token = generate_id_token(
+            &user,
+            client_id,
+            &scopes,
+            nonce,
+            state.signing_key_store.clone(),
+        )
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+        response.id_token = Some(id_token.expose_secret().to_owned());
+    }
+
+    Ok(response)
+}
+
+async fn issue_tokens(
+    user_id: &UserId,
+    client_id: &OAuthClientId,
+    scopes: &[String],
+    family: RefreshTokenFamily,
+    state: &AppState,
+) -> Result<TokenResponse, OAuthError> {
+    let access_token = generate_access_token(
+        user_id,
+        &family.id,
+        client_id,
+        scopes,
+        state.signing_key_store.clone(),
+    )
+    .await
+    .map_err(OAuthError::UnexpectedError)?;
+    let refresh_token = issue_refresh_token(family, state)
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+
+    Ok(TokenResponse {
+        access_token: access_token.expose_secret().to_owned(),
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        refresh_token: Some(refresh_token.as_ref().expose_secret().to_owned()),
+        id_token: None,
+        scope: (!scopes.is_empty()).then(|| scopes.join(" ")),
+    })
+}
+
+// Look up the client and check the redirect URI is one it registered. Until both are known to be
+// good, errors are shown to the user rather than sent to a redirect URI we can't trust.
+async fn find_client(
+    request: &AuthorizeRequest,
+    state: &AppState,
+) -> Result<(OAuthClient, String), AuthAPIError> {
+    let client_id = request
+        .client_id
+        .clone()
+        .and_then(|id| OAuthClientId::parse(id).ok())
+        .ok_or(AuthAPIError::UnknownOAuthClient)?;
+
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+    {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::ClientNotFound) => return Err(AuthAPIError::UnknownOAuthClient),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    match &request.redirect_uri {
+        Some(redirect_uri) if client.has_redirect_uri(redirect_uri) => {
+            let redirect_uri = redirect_uri.clone();
+            Ok((client, redirect_uri))
+        }
+        _ => Err(AuthAPIError::InvalidRedirectUri),
+    }
+}
+
+fn authorization_grant(
+    client_id: OAuthClientId,
+    user_id: UserId,
+    redirect_uri: String,
+    code_challenge: CodeChallenge,
+    request: &AuthorizeRequest,
+) -> AuthorizationGrant {
+    AuthorizationGrant {
+        client_id,
+        user_id,
+        redirect_uri,
+        code_challenge,
+        scopes: parse_scopes(request.scope.as_deref()),
+        nonce: request.nonce.clone(),
+    }
+}
+
+// Check the rest of an authorization request, returning the RFC 6749 error code to send back to
+// the client if it is bad
+fn check_request(request: &AuthorizeRequest) -> Result<CodeChallenge, &'static str> {
+    if request.response_type.as_deref() != Some("code") {
+        return Err("unsupported_response_type");
+    }
+    if request.code_challenge_method.as_deref() != Some("S256") {
+        return Err("invalid_request");
+    }
+
+    request
+        .code_challenge
+        .clone()
+        .and_then(|challenge| CodeChallenge::parse(challenge).ok())
+        .ok_or("invalid_request")
+}
+
+// Issue a code for `grant` and send the user back to the client with it
+async fn code_redirect(
+    grant: AuthorizationGrant,
+    request_state: &Option<String>,
+    state: &AppState,
+) -> Result<Response, AuthAPIError> {
+    let code = AuthorizationCode::default();
+    let mut redirect_url = parse_redirect_uri(&grant.redirect_uri)?;
+
+    state
+        .authorization_code_store
+        .write()
+        .await
+        .add_code(code.clone(), grant)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    redirect_url
+        .query_pairs_mut()
+        .append_pair("code", code.as_ref().expose_secret());
+    Ok(redirect(redirect_url, request_state))
+}
+
+// Send the user back to the client with an RFC 6749 error code
+fn error_redirect(
+    redirect_uri: &str,
+    error: &str,
+    request_state: &Option<String>,
+) -> Result<Response, AuthAPIError> {
+    let mut redirect_url = parse_redirect_uri(redirect_uri)?;
+    redirect_url.query_pairs_mut().append_pair("error", error);
+    Ok(redirect(redirect_url, request_state))
+}
+
+// The client's `state` goes back unchanged so it can match the response to its request
+fn redirect(mut redirect_url: Url, request_state: &Option<String>) -> Response {
+    if let Some(request_state) = request_state {
+        redirect_url
+            .query_pairs_mut()
+            .append_pair("state", request_state);
+    }
+    Redirect::to(redirect_url.as_str()).into_response()
+}
+
+fn parse_redirect_uri(redirect_uri: &str) -> Result<Url, AuthAPIError> {
+    Url::parse(redirect_uri).map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+fn service_url(path: &str) -> Result<Url, AuthAPIError> {
+    Url::parse(&format!("{}{}", AUTH_SERVICE_URL.as_str(), path))
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Ask the user whether the client may log them in, and what it will get. The page can't be
+// framed, so other sites can't trick the user into clicking "Allow".
+fn consent_page(
+    client: &OAuthClient,
+    email: &Email,
+    scopes: &[String],
+    request: &AuthorizeRequest,
+) -> Response {
+    let hidden_fields = [
+        ("client_id", request.client_id.as_deref()),
+        ("redirect_uri", request.redirect_uri.as_deref()),
+        ("response_type", request.response_type.as_deref()),
+        ("state", request.state.as_deref()),
+        ("code_challenge", request.code_challenge.as_deref()),
+        (
+            "code_challenge_method",
+            request.code_challenge_method.as_deref(),
+        ),
+        ("scope", request.scope.as_deref()),
+        ("nonce", request.nonce.as_deref()),
+    ]
+    .iter()
+    .filter_map(|(name, value)| {
+        value.map(|value| {
+            format!(
+                r#"<input type="hidden" name="{}" value="{}">"#,
+                name,
+                escape_html(value)
+            )
+        })
+    })
+    .collect::<Vec<_>>()
+    .join("\n                ");
+
+    let scope_items = scopes
+        .iter()
+        .map(|scope| {
+            format!(
+                r#"<li class="list-group-item"><strong>{}</strong>: {}</li>"#,
+                escape_html(scope),
+                scope_description(scope)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n                ");
+
+    let page = CONSENT_PAGE
+        .replace("{client_name}", &escape_html(&client.name))
+        .replace("{email}", &escape_html(email.as_ref().expose_secret()))
+        .replace("{scopes}", &scope_items)
+        .replace("{hidden_fields}", &hidden_fields);
+
+    (
+        [(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"))],
+        Html(page),
+    )
+        .into_response()
+}
+
+// What each of the `SUPPORTED_SCOPES` lets a client have, in the user's terms
+fn scope_description(scope: &str) -> &'static str {
+    match scope {
+        "openid" => "Know which account you logged in with",
+        "email" => "See your email address",
+        _ => "",
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
+
+const CONSENT_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Auth</title>
+    <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/bootstrap@5.2.2/dist/css/bootstrap.min.css">
+</head>
+
+<body>
+    <section class="position-relative py-4 py-xl-5">
+        <div class="container col-md-6 col-xl-4 text-center">
+            <h2>Allow {client_name}?</h2>
+            <p>{client_name} wants to log you in as <strong>{email}</strong>.</p>
+            <ul class="list-group text-start mb-3">
+                {scopes}
+            </ul>
+            <form method="post" action="/authorize">
+                {hidden_fields}
+                <button class="btn btn-dark d-block w-100 mb-3" type="submit" name="decision" value="approve">Allow</button>
+                <button class="btn btn-outline-dark d-block w-100" type="submit" name="decision" value="deny">Deny</button>
+            </form>
+        </div>
+    </section>
+</body>
+
+</html>
+"#;
+
+#[derive(Deserialize)]
+pub struct AuthorizeRequest {
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub response_type: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    // Space separated. Only the scopes in `SUPPORTED_SCOPES` are granted.
+    pub scope: Option<String>,
+    // OpenID Connect clients can send a nonce to be copied into the ID token
+    pub nonce: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConsentRequest {
+    #[serde(flatten)]
+    pub request: AuthorizeRequest,
+    // `approve` or `deny`
+    pub decision: String,
+}
+
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<SecretString>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub code_verifier: Option<SecretString>,
+    pub refresh_token: Option<SecretString>,
+    // For machine clients that don't use HTTP Basic auth
+    pub client_secret: Option<SecretString>,
+    // Space separated. Only used by the client credentials grant.
+    pub scope: Option<String>,
+    // For devices polling with the device authorization grant
+    pub device_code: Option<SecretString>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    // Seconds until the access token expires
+    pub expires_in: i64,
+    // Not issued to machine clients, which can authenticate again instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    // Only issued to clients the user allowed the `openid` scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    // The scopes granted, space separated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
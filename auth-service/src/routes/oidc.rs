@@ -0,0 +1,158 @@
+use super::sessions::subject_user;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, OAuthError, DEVICE_CODE_GRANT_TYPE, SUPPORTED_SCOPES};
+use crate::utils::auth::validate_access_token;
+use crate::utils::constants::{AUTH_SERVICE_URL, JWT_ISSUER};
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use axum::Json;
+use jsonwebtoken::Algorithm;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+// OpenID Connect discovery, so client libraries can configure themselves from the issuer URL.
+// The issuer is `JWT_ISSUER`, which has to be the service's own URL for discovery to work.
+#[tracing::instrument(name = "OpenID configuration", skip_all)]
+pub async fn openid_configuration(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // ID tokens are signed with the active key, so that is the only algorithm they can have
+    let active_key = state
+        .signing_key_store
+        .read()
+        .await
+        .get_active_key()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let endpoint = |path: &str| format!("{}{}", AUTH_SERVICE_URL.as_str(), path);
+    let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+    let configuration = OpenIdConfiguration {
+        issuer: JWT_ISSUER.to_owned(),
+        authorization_endpoint: endpoint("/authorize"),
+        token_endpoint: endpoint("/token"),
+        device_authorization_endpoint: endpoint("/device/code"),
+        userinfo_endpoint: endpoint("/userinfo"),
+        jwks_uri: endpoint("/.well-known/jwks.json"),
+        response_types_supported: strings(&["code"]),
+        grant_types_supported: strings(&[
+            "authorization_code",
+            "refresh_token",
+            "client_credentials",
+            DEVICE_CODE_GRANT_TYPE,
+        ]),
+        subject_types_supported: strings(&["public"]),
+        id_token_signing_alg_values_supported: vec![active_key.algorithm],
+        scopes_supported: strings(&SUPPORTED_SCOPES),
+        // Clients that log users in are public and prove themselves with PKCE instead. Only
+        // machine clients have a secret.
+        token_endpoint_auth_methods_supported: strings(&[
+            "none",
+            "client_secret_basic",
+            "client_secret_post",
+        ]),
+        code_challenge_methods_supported: strings(&["S256"]),
+        claims_supported: strings(&[
+            "iss",
+            "sub",
+            "aud",
+            "iat",
+            "exp",
+            "nonce",
+            "email",
+            "email_verified",
+        ]),
+    };
+
+    Ok((
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(configuration),
+    ))
+}
+
+// Tell an OpenID Connect client about the user an access token was issued to. Only tokens the
+// user granted the `openid` scope are accepted, and the email address is only shared with clients
+// granted the `email` scope.
+#[tracing::instrument(name = "Userinfo", skip_all)]
+pub async fn userinfo(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<UserInfoResponse>, OAuthError> {
+    let token = bearer_token(&headers).ok_or(OAuthError::InvalidToken)?;
+
+    let claims = validate_access_token(
+        &token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.signing_key_store.clone(),
+    )
+    .await
+    .map_err(|_| OAuthError::InvalidToken)?;
+
+    let scopes = claims.scopes();
+    if !scopes.iter().any(|scope| scope == "openid") {
+        return Err(OAuthError::InsufficientScope);
+    }
+
+    let user = match subject_user(&claims.sub, &state).await {
+        Ok(user) => user,
+        Err(AuthAPIError::InvalidToken) => return Err(OAuthError::InvalidToken),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    let (email, email_verified) = match scopes.iter().any(|scope| scope == "email") {
+        true => (
+            Some(user.email.as_ref().expose_secret().to_owned()),
+            Some(user.verified),
+        ),
+        false => (None, None),
+    };
+
+    Ok(Json(UserInfoResponse {
+        sub: claims.sub,
+        email,
+        email_verified,
+    }))
+}
+
+// The token in an `Authorization: Bearer` header (RFC 6750)
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<SecretString> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    Some(SecretString::new(token.to_owned().into_boxed_str()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    // Where devices start the RFC 8628 device authorization grant
+    pub device_authorization_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<Algorithm>,
+    pub scopes_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserInfoResponse {
+    // The id of the user, as in the `sub` claim of their tokens
+    pub sub: String,
+    // Only for clients granted the `email` scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
@@ -0,0 +1,210 @@
+use super::sessions::{end_session, remove_session_cookies};
+use crate::app_state::AppState;
+use crate::domain::{
+    AuthAPIError, OAuthClientId, RefreshToken, RefreshTokenFamily, RefreshTokenStoreError,
+    SessionStoreError, User, UserStoreError,
+};
+use crate::utils::auth::{create_refresh_cookie, generate_auth_cookie};
+use crate::utils::constants::REFRESH_COOKIE_NAME;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+use color_eyre::{eyre::Result, Report};
+use secrecy::SecretString;
+
+// Swap a refresh token for a new JWT auth token and a new refresh token
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match jar.get(REFRESH_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match RefreshToken::parse(SecretString::new(
+        cookie.value().to_owned().into_boxed_str(),
+    )) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let (user, family) = match redeem_refresh_token(&token, None, &state).await {
+        Ok(redeemed) => redeemed,
+        Err(RefreshError::InvalidToken) => return (jar, Err(AuthAPIError::InvalidToken)),
+        // The login is over, so its cookies are no use to anyone
+        Err(RefreshError::SessionEnded) => {
+            return (remove_session_cookies(jar), Err(AuthAPIError::InvalidToken))
+        }
+        Err(RefreshError::UnexpectedError(e)) => {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)))
+        }
+    };
+
+    let auth_cookie = match generate_auth_cookie(
+        &user.id,
+        &family.id,
+        &user.roles,
+        state.signing_key_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let refresh_cookie = match issue_refresh_cookie(family, &state).await {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+// Why a refresh token couldn't be swapped for new tokens
+pub(crate) enum RefreshError {
+    // The token was never issued, or has expired
+    InvalidToken,
+    // The login the token was issued for has ended
+    SessionEnded,
+    UnexpectedError(Report),
+}
+
+// Consume a refresh token, returning the user it was issued to and the login it belongs to,
+// so the caller can issue that login a new auth token and a new refresh token. `client_id` is the
+// OAuth client swapping the token, or `None` for the user's own logins.
+#[tracing::instrument(name = "Redeem refresh token", skip_all)]
+pub(crate) async fn redeem_refresh_token(
+    token: &RefreshToken,
+    client_id: Option<&OAuthClientId>,
+    state: &AppState,
+) -> Result<(User, RefreshTokenFamily), RefreshError> {
+    let result = state
+        .refresh_token_store
+        .write()
+        .await
+        .consume_token(token)
+        .await;
+
+    let family = match result {
+        Ok(family) => family,
+        Err(RefreshTokenStoreError::TokenNotFound) => return Err(RefreshError::InvalidToken),
+        // A token that was already rotated has been used by someone else: either the user
+        // or an attacker holds a stolen copy. End the whole login to be safe.
+        Err(RefreshTokenStoreError::TokenReused(family)) => {
+            tracing::warn!("Refresh token reused, revoking its session");
+            return match end_session(&family.user_id, &family.id, state).await {
+                Ok(()) | Err(AuthAPIError::SessionNotFound) => Err(RefreshError::SessionEnded),
+                Err(e) => Err(RefreshError::UnexpectedError(e.into())),
+            };
+        }
+        Err(e) => return Err(RefreshError::UnexpectedError(e.into())),
+    };
+
+    // Tokens only work for whoever they were issued to, so a client can't swap its tokens for the
+    // user's own cookies, or another client's. One presented elsewhere is spent all the same.
+    if family.client_id.as_ref() != client_id {
+        return Err(RefreshError::InvalidToken);
+    }
+
+    let user = family_user_if_active(&family, state).await?;
+
+    Ok((user, family))
+}
+
+// Look up the refresh token's family and user without using it up, for introspection.
+// Unlike redeeming it, looking at a token that was already used doesn't end its login.
+#[tracing::instrument(name = "Inspect refresh token", skip_all)]
+pub(crate) async fn inspect_refresh_token(
+    token: &RefreshToken,
+    state: &AppState,
+) -> Result<(User, RefreshTokenFamily), RefreshError> {
+    let family = match state
+        .refresh_token_store
+        .read()
+        .await
+        .get_family(token)
+        .await
+    {
+        Ok(family) => family,
+        Err(RefreshTokenStoreError::TokenNotFound | RefreshTokenStoreError::TokenReused(_)) => {
+            return Err(RefreshError::InvalidToken)
+        }
+        Err(e) => return Err(RefreshError::UnexpectedError(e.into())),
+    };
+
+    let user = family_user_if_active(&family, state).await?;
+
+    Ok((user, family))
+}
+
+// Look up the user of a family whose login is still going
+async fn family_user_if_active(
+    family: &RefreshTokenFamily,
+    state: &AppState,
+) -> Result<User, RefreshError> {
+    // The session may have been revoked from another device since this token was issued
+    match state
+        .session_store
+        .write()
+        .await
+        .touch_session(&family.id)
+        .await
+    {
+        Ok(()) => {}
+        Err(SessionStoreError::SessionNotFound) => return Err(RefreshError::SessionEnded),
+        Err(e) => return Err(RefreshError::UnexpectedError(e.into())),
+    }
+
+    // Pick up roles granted or revoked since the last auth token was issued
+    family_user(family, state).await
+}
+
+// Look up the user a refresh token family was issued to. Families name the user by id, so they
+// still find them after a change of email address.
+async fn family_user(family: &RefreshTokenFamily, state: &AppState) -> Result<User, RefreshError> {
+    match state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&family.user_id)
+        .await
+    {
+        Ok(user) => Ok(user),
+        Err(UserStoreError::UserNotFound) => Err(RefreshError::SessionEnded),
+        Err(e) => Err(RefreshError::UnexpectedError(e.into())),
+    }
+}
+
+// Store a new refresh token in `family` and wrap it in a cookie
+#[tracing::instrument(name = "Issue refresh cookie", skip_all)]
+pub(crate) async fn issue_refresh_cookie(
+    family: RefreshTokenFamily,
+    state: &AppState,
+) -> Result<Cookie<'static>> {
+    let token = issue_refresh_token(family, state).await?;
+    Ok(create_refresh_cookie(&token))
+}
+
+// Store a new refresh token in `family`
+#[tracing::instrument(name = "Issue refresh token", skip_all)]
+pub(crate) async fn issue_refresh_token(
+    family: RefreshTokenFamily,
+    state: &AppState,
+) -> Result<RefreshToken> {
+    let token = RefreshToken::default();
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .add_token(token.clone(), family)
+        .await?;
+
+    Ok(token)
+}
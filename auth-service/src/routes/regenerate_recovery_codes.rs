@@ -0,0 +1,59 @@
+use super::sessions::authenticate;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, RecoveryCode, UserId};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+#[tracing::instrument(name = "Regenerate recovery codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, _) = authenticate(&jar, &state).await?;
+
+    if !user.requires_2fa() {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    let recovery_codes = issue_recovery_codes(&user.id, &state)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(RecoveryCodesResponse { recovery_codes });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Generate a new set of recovery codes for the user, invalidating any issued before.
+// Only the hashes are stored, so the returned codes must be shown to the user now or never.
+#[tracing::instrument(name = "Issue recovery codes", skip_all)]
+pub(crate) async fn issue_recovery_codes(
+    user_id: &UserId,
+    state: &AppState,
+) -> Result<Vec<String>> {
+    let codes = RecoveryCode::generate_set();
+
+    state
+        .recovery_code_store
+        .write()
+        .await
+        .replace_codes(user_id, codes.iter().map(|code| code.hash()).collect())
+        .await?;
+
+    Ok(codes
+        .iter()
+        .map(|code| code.as_ref().expose_secret().to_owned())
+        .collect())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,47 @@
+use super::verify_email::send_verification_email;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+#[tracing::instrument(name = "Resend verification email", skip_all)]
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let response = Json(ResendVerificationResponse {
+        message:
+            "If this email belongs to an unverified account, a verification link has been sent"
+                .to_owned(),
+    });
+
+    // Respond the same way for unknown or already verified emails so the route can't be used to discover accounts
+    let needs_verification = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => !user.verified,
+        Err(_) => false,
+    };
+
+    if needs_verification {
+        send_verification_email(&email, &state)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+    }
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ResendVerificationResponse {
+    pub message: String,
+}
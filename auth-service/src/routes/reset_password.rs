@@ -0,0 +1,72 @@
+use super::login::clear_failed_logins;
+use super::sessions::end_all_sessions;
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, HashedPassword, PasswordResetToken};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+#[tracing::instrument(name = "Reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token =
+        PasswordResetToken::parse(request.token).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let password = HashedPassword::parse(request.new_password)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut password_reset_token_store = state.password_reset_token_store.write().await;
+
+    let email = password_reset_token_store
+        .get_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    // Consume the token before touching the password so it can only ever be used once
+    password_reset_token_store
+        .remove_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    let mut user_store = state.user_store.write().await;
+
+    user_store
+        .update_password(&email, password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    drop(user_store);
+
+    // Whoever knew the old password may still be logged in, so log every device out. The lockout
+    // was for guesses at the old password, so it no longer applies either.
+    end_all_sessions(&user.id, &state).await?;
+    clear_failed_logins(&email, &state).await?;
+
+    let response = Json(ResetPasswordResponse {
+        message: "Password reset successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: SecretString,
+    #[serde(rename = "newPassword")]
+    pub new_password: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ResetPasswordResponse {
+    pub message: String,
+}
@@ -0,0 +1,240 @@
+use super::refresh::issue_refresh_cookie;
+use crate::app_state::AppState;
+use crate::domain::{
+    AuthAPIError, RefreshTokenFamily, Session, SessionId, SessionStoreError, User, UserId,
+    UserStoreError,
+};
+use crate::utils::auth::{generate_auth_cookie, validate_auth_cookie, Claims};
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+
+// List every device the user is logged in on
+#[tracing::instrument(name = "Get sessions", skip_all)]
+pub async fn get_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (user, claims) = authenticate(&jar, &state).await?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: session.id.as_ref() == claims.sid,
+            id: session.id.as_ref().to_owned(),
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at.to_rfc3339(),
+            last_seen_at: session.last_seen_at.to_rfc3339(),
+        })
+        .collect();
+
+    Ok(Json(SessionsResponse { sessions }))
+}
+
+// Log a single device out
+#[tracing::instrument(name = "Delete session", skip_all)]
+pub async fn delete_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (user, claims) = match authenticate(&jar, &state).await {
+        Ok(result) => result,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let id = match SessionId::parse(id) {
+        Ok(id) => id,
+        Err(_) => return (jar, Err(AuthAPIError::SessionNotFound)),
+    };
+
+    if let Err(e) = end_session(&user.id, &id, &state).await {
+        return (jar, Err(e));
+    }
+
+    // The user logged out the device they are using right now
+    let jar = match id.as_ref() == claims.sid {
+        true => remove_session_cookies(jar),
+        false => jar,
+    };
+
+    (jar, Ok(StatusCode::OK))
+}
+
+// Log every device out, including this one
+#[tracing::instrument(name = "Delete all sessions", skip_all)]
+pub async fn delete_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (user, _) = match authenticate(&jar, &state).await {
+        Ok(result) => result,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if let Err(e) = end_all_sessions(&user.id, &state).await {
+        return (jar, Err(e));
+    }
+
+    (remove_session_cookies(jar), Ok(StatusCode::OK))
+}
+
+// Record a new login and issue its JWT auth and refresh cookies
+#[tracing::instrument(name = "Start session", skip_all)]
+pub(crate) async fn start_session(
+    session: Session,
+    state: &AppState,
+) -> Result<(Cookie<'static>, Cookie<'static>)> {
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&session.user_id)
+        .await?;
+    let auth_cookie = generate_auth_cookie(
+        &user.id,
+        &session.id,
+        &user.roles,
+        state.signing_key_store.clone(),
+    )
+    .await?;
+    let family = RefreshTokenFamily::new(session.id.clone(), user.id);
+
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session)
+        .await?;
+
+    let refresh_cookie = issue_refresh_cookie(family, state).await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
+// Revoke a login: its session record, and with it every refresh token issued for it
+#[tracing::instrument(name = "End session", skip_all)]
+pub(crate) async fn end_session(
+    user_id: &UserId,
+    id: &SessionId,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    match state
+        .session_store
+        .write()
+        .await
+        .delete_session(user_id, id)
+        .await
+    {
+        Ok(()) => {}
+        Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_family(id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Revoke every login the user has, on every device
+#[tracing::instrument(name = "End all sessions", skip_all)]
+pub(crate) async fn end_all_sessions(
+    user_id: &UserId,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let ids = state
+        .session_store
+        .write()
+        .await
+        .delete_sessions(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut refresh_token_store = state.refresh_token_store.write().await;
+    for id in ids {
+        refresh_token_store
+            .revoke_family(&id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(())
+}
+
+// Clear the JWT auth and refresh cookies. The path must match the one the cookies were set with,
+// or clients won't replace them when the request came from a nested URL like `/sessions/{id}`.
+pub(crate) fn remove_session_cookies(jar: CookieJar) -> CookieJar {
+    jar.remove(Cookie::build(JWT_COOKIE_NAME).path("/"))
+        .remove(Cookie::build(REFRESH_COOKIE_NAME).path("/"))
+}
+
+// Authenticate a request from the JWT cookie in `jar`, returning the user and the claims
+pub(crate) async fn authenticate(
+    jar: &CookieJar,
+    state: &AppState,
+) -> Result<(User, Claims), AuthAPIError> {
+    let claims = validate_auth_cookie(
+        jar,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.signing_key_store.clone(),
+    )
+    .await?;
+    let user = token_user(&claims, state).await?;
+
+    Ok((user, claims))
+}
+
+// Look up the user a validated token was issued to
+pub(crate) async fn token_user(claims: &Claims, state: &AppState) -> Result<User, AuthAPIError> {
+    subject_user(&claims.sub, state).await
+}
+
+// Look up the user named by the `sub` claim of any token issued on a user's behalf
+pub(crate) async fn subject_user(sub: &str, state: &AppState) -> Result<User, AuthAPIError> {
+    let user_id = UserId::parse(sub.to_owned()).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state.user_store.read().await.get_user_by_id(&user_id).await {
+        Ok(user) => Ok(user),
+        // The account was deleted since the token was issued
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: String,
+    // Whether this is the session making the request
+    pub current: bool,
+}
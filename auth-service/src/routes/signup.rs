@@ -1,6 +1,8 @@
+use super::regenerate_recovery_codes::issue_recovery_codes;
+use super::verify_email::send_verification_email;
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, HashedPassword, User},
+    domain::{AuthAPIError, Email, HashedPassword, TwoFAMethod, User},
 };
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use secrecy::SecretString;
@@ -16,7 +18,14 @@ pub async fn signup(
         .await
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user = User::new(email, password, request.requires_2fa);
+    // Authenticator apps can only be set up once logged in, so signup opts into email codes
+    let two_fa_method = match request.requires_2fa {
+        true => TwoFAMethod::Email,
+        false => TwoFAMethod::None,
+    };
+
+    let user = User::new(email.clone(), password, two_fa_method);
+    let user_id = user.id;
 
     let mut user_store = state.user_store.write().await;
 
@@ -28,8 +37,27 @@ pub async fn signup(
         return Err(AuthAPIError::UnexpectedError(e.into()));
     }
 
+    drop(user_store);
+
+    // The account already exists at this point, so a failed email is not fatal:
+    // the user can ask for a new link through `/resend-verification`.
+    if let Err(e) = send_verification_email(&email, &state).await {
+        tracing::error!("Failed to send verification email: {:?}", e);
+    }
+
+    // Users who opt into 2FA get their fallback codes straight away. If this fails they can
+    // still generate them later through `/regenerate-recovery-codes`.
+    let recovery_codes = match two_fa_method {
+        TwoFAMethod::None => None,
+        _ => issue_recovery_codes(&user_id, &state)
+            .await
+            .inspect_err(|e| tracing::error!("Failed to issue recovery codes: {:?}", e))
+            .ok(),
+    };
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes,
     });
 
     Ok((StatusCode::CREATED, response))
@@ -45,4 +73,11 @@ pub struct SignupRequest {
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct SignupResponse {
     pub message: String,
+    // Only present when the user signed up with 2FA
+    #[serde(
+        rename = "recoveryCodes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
 }
@@ -0,0 +1,285 @@
+use super::sessions::start_session;
+use crate::app_state::AppState;
+use crate::domain::{
+    AuthAPIError, Email, ExternalIdentity, ExternalIdentityStoreError, HashedPassword, Session,
+    SocialLoginRequest, SocialLoginState, SocialLoginStateStoreError, TwoFAMethod, User,
+    UserStoreError, SOCIAL_LOGIN_STATE_TTL_SECONDS,
+};
+use crate::services::identity_provider_client::ExternalIdTokenClaims;
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{AUTH_SERVICE_URL, SOCIAL_LOGIN_COOKIE_NAME};
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Redirect};
+use axum::Json;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::Rng;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+// The identity providers the login page can offer buttons for
+#[tracing::instrument(name = "Get identity providers", skip_all)]
+pub async fn get_identity_providers(State(state): State<AppState>) -> impl IntoResponse {
+    Json(IdentityProvidersResponse {
+        providers: state.identity_provider_client.provider_names(),
+    })
+}
+
+// Send the browser to log in with an external identity provider. It comes back to
+// `social_login_callback`, which only accepts it from the browser that was sent.
+#[tracing::instrument(name = "Start social login", skip_all)]
+pub async fn start_social_login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(provider_name): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let provider = state
+        .identity_provider_client
+        .get_provider(&provider_name)
+        .ok_or(AuthAPIError::UnknownIdentityProvider)?;
+
+    let login_state = SocialLoginState::default();
+    let request = SocialLoginRequest::new(provider.name.clone());
+    let url = state
+        .identity_provider_client
+        .authorization_url(
+            provider,
+            &redirect_uri(&provider.name),
+            &login_state,
+            &request,
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .social_login_state_store
+        .write()
+        .await
+        .add_state(login_state.clone(), request)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        jar.add(create_state_cookie(&login_state)),
+        Redirect::to(url.as_str()),
+    ))
+}
+
+// Log the user in once the identity provider sends them back, setting the same cookies as
+// `/login`, and send the browser on to the home page. Users logging in for the first time get an
+// account, and accounts are linked to the provider's by email address.
+#[tracing::instrument(name = "Social login callback", skip_all)]
+pub async fn social_login_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Path(provider_name): Path<String>,
+    Query(query): Query<SocialLoginCallbackQuery>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let provider = match state.identity_provider_client.get_provider(&provider_name) {
+        Some(provider) => provider,
+        None => return (jar, Err(AuthAPIError::UnknownIdentityProvider)),
+    };
+
+    // The provider reports the user cancelling, or any other failure, with `error` instead of
+    // a code
+    let (code, login_state) = match (query.code, query.state.map(SocialLoginState::parse)) {
+        (Some(code), Some(Ok(login_state))) if query.error.is_none() => (code, login_state),
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // A login started in another browser, e.g. an attacker's own login sent to the user in a
+    // link, would log the user in as someone else
+    let started_here = jar
+        .get(SOCIAL_LOGIN_COOKIE_NAME)
+        .is_some_and(|cookie| cookie.value() == login_state.hash());
+    if !started_here {
+        return (jar, Err(AuthAPIError::InvalidCredentials));
+    }
+
+    let mut social_login_state_store = state.social_login_state_store.write().await;
+    let request = match social_login_state_store.get_request(&login_state).await {
+        Ok(request) => request,
+        Err(SocialLoginStateStoreError::StateNotFound) => {
+            return (jar, Err(AuthAPIError::InvalidCredentials))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    // Consume the state first so each login can only come back once
+    if let Err(e) = social_login_state_store.remove_state(&login_state).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+    drop(social_login_state_store);
+    let jar = jar.remove(Cookie::build(SOCIAL_LOGIN_COOKIE_NAME).path(STATE_COOKIE_PATH));
+
+    if request.provider != provider.name {
+        return (jar, Err(AuthAPIError::InvalidCredentials));
+    }
+
+    let claims = match state
+        .identity_provider_client
+        .log_in(provider, &redirect_uri(&provider.name), &code, &request)
+        .await
+    {
+        Ok(claims) => claims,
+        Err(e) => {
+            tracing::warn!("Social login with {} failed: {:?}", provider.name, e);
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+    };
+
+    let user = match find_or_create_user(&provider.name, claims, &state).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    // The provider only proves who the user is, not their second factor
+    if user.requires_2fa() {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    let session = Session::new(user.id, client.user_agent, client.ip_address);
+
+    let (auth_cookie, refresh_cookie) = match start_session(session, &state).await {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(Redirect::to("/")))
+}
+
+// The user an identity provider account logs in as. An account the provider hasn't logged in
+// before is linked to the user with its email address, who is signed up if they don't exist yet.
+async fn find_or_create_user(
+    provider: &str,
+    claims: ExternalIdTokenClaims,
+    state: &AppState,
+) -> Result<User, AuthAPIError> {
+    let identity = state
+        .external_identity_store
+        .read()
+        .await
+        .get_identity(provider, &claims.sub)
+        .await;
+    match identity {
+        Ok(identity) => {
+            return state
+                .user_store
+                .read()
+                .await
+                .get_user_by_id(&identity.user_id)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()));
+        }
+        Err(ExternalIdentityStoreError::IdentityNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Only an address the provider has checked belongs to the user can be linked to an account
+    let email = match (claims.email, claims.email_verified) {
+        (Some(email), Some(true)) => Email::parse(SecretString::new(email.into_boxed_str()))
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?,
+        _ => return Err(AuthAPIError::EmailNotVerified),
+    };
+
+    // Hashed before taking the lock, so other requests don't wait on it
+    let password = random_password().await?;
+
+    let mut user_store = state.user_store.write().await;
+    let user = match user_store.get_user(&email).await {
+        Ok(user) if user.verified => user,
+        // Anyone could have signed up with the address without being able to verify it, so
+        // their password is replaced before the address's owner takes the account over
+        Ok(mut user) => {
+            user_store
+                .update_password(&email, password.clone())
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            user_store
+                .mark_email_verified(&email)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            user.password = password;
+            user.verified = true;
+            user
+        }
+        Err(UserStoreError::UserNotFound) => {
+            let mut user = User::new(email.clone(), password, TwoFAMethod::None);
+            user.verified = true;
+            user_store
+                .add_user(user.clone())
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            user
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    drop(user_store);
+
+    // The provider can't log in to an account that needs a second factor, so it isn't linked to
+    // one either
+    if user.requires_2fa() {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let identity = ExternalIdentity::new(provider.to_owned(), claims.sub, user.id);
+    state
+        .external_identity_store
+        .write()
+        .await
+        .add_identity(identity)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(user)
+}
+
+// Accounts made through social login get a password nobody knows. The user can set one with
+// `/forgot-password` if they ever want to log in without the provider.
+async fn random_password() -> Result<HashedPassword, AuthAPIError> {
+    let bytes: [u8; 32] = rand::rng().random();
+    let password = SecretString::new(URL_SAFE_NO_PAD.encode(bytes).into_boxed_str());
+    HashedPassword::parse(password)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+// Where the state cookie is sent, which covers every provider's callback
+const STATE_COOKIE_PATH: &str = "/login/social";
+
+// Remember the state of the login this browser started until it comes back from the provider.
+// Lax, so it is sent when the provider redirects the browser back to us.
+fn create_state_cookie(login_state: &SocialLoginState) -> Cookie<'static> {
+    Cookie::build((SOCIAL_LOGIN_COOKIE_NAME, login_state.hash()))
+        .path(STATE_COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(
+            SOCIAL_LOGIN_STATE_TTL_SECONDS as i64,
+        ))
+        .build()
+}
+
+// Where identity providers send the user back to. It has to be registered with the provider.
+fn redirect_uri(provider: &str) -> String {
+    format!(
+        "{}/login/social/{}/callback",
+        AUTH_SERVICE_URL.as_str(),
+        provider
+    )
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdentityProvidersResponse {
+    pub providers: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SocialLoginCallbackQuery {
+    pub code: Option<SecretString>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
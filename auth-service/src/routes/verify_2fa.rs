@@ -1,10 +1,18 @@
+use super::login::{
+    clear_failed_logins, login_attempt_keys, release_login_attempt, reserve_login_attempt,
+};
+use super::sessions::start_session;
 use crate::app_state::AppState;
-use crate::domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode};
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::{
+    AuthAPIError, Email, EncryptedTotpSecret, LoginAttemptId, LoginAttemptKey, RecoveryCode,
+    RecoveryCodeStoreError, Session, TotpCode, TwoFACode, TwoFAMethod,
+};
+use crate::utils::client_info::ClientInfo;
 use axum::extract::State;
 use axum::response::IntoResponse;
 use axum::Json;
 use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
 use secrecy::SecretString;
 use serde::Deserialize;
 
@@ -12,6 +20,7 @@ use serde::Deserialize;
 pub async fn verify_2fa(
     State(state): State<AppState>, // New!
     jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(request.email) {
@@ -24,11 +33,22 @@ pub async fn verify_2fa(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let two_fa_code = match TwoFACode::parse(request.two_fa_code) {
-        Ok(two_fa_code) => two_fa_code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    // Both emailed and authenticator-app codes are 6 digits, so check the format
+    // before we know which kind this user is sending. Anything else may be a recovery code.
+    let code = match TotpCode::parse(request.two_fa_code.clone()) {
+        Ok(code) => SecondFactor::Code(code),
+        Err(_) => match RecoveryCode::parse(request.two_fa_code) {
+            Ok(recovery_code) => SecondFactor::RecoveryCode(recovery_code),
+            Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        },
     };
 
+    // Wrong codes count against the account like wrong passwords do
+    let attempt_keys = login_attempt_keys(&email, &client);
+    if let Err(e) = reserve_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+
     // New!
     let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
@@ -38,10 +58,66 @@ pub async fn verify_2fa(
         Ok(code_tuple) => code_tuple,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
-    // Validate that the `login_attempt_id` and `two_fa_code`
-    // in the request body matches values in the `code_tuple`.
+    // Validate that the `login_attempt_id` in the request body matches the one in the `code_tuple`.
     // If not, return an `AuthAPIError::IncorrectCredentials`.
-    if !code_tuple.0.eq(&login_attempt_id) || !code_tuple.1.eq(&two_fa_code) {
+    if !code_tuple.0.eq(&login_attempt_id) {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    // Check the code against whichever second factor the user has set up,
+    // or consume one of their recovery codes
+    let code_is_valid = match (code, user.two_fa_method) {
+        (SecondFactor::RecoveryCode(recovery_code), _) => {
+            match state
+                .recovery_code_store
+                .write()
+                .await
+                .use_code(&user.id, &recovery_code.hash())
+                .await
+            {
+                Ok(()) => true,
+                Err(RecoveryCodeStoreError::CodeNotFound) => false,
+                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+            }
+        }
+        (SecondFactor::Code(code), TwoFAMethod::Totp) => {
+            match verify_totp_code(&user.email, user.totp_secret, &code) {
+                Ok(is_valid) => is_valid,
+                Err(e) => return (jar, Err(e)),
+            }
+        }
+        (SecondFactor::Code(code), _) => TwoFACode::parse(code.as_ref().to_owned())
+            .map(|two_fa_code| code_tuple.1.eq(&two_fa_code))
+            .unwrap_or(false),
+    };
+
+    let two_fa_key = LoginAttemptKey::TwoFA(login_attempt_id);
+
+    if !code_is_valid {
+        let failures = match state
+            .login_attempt_store
+            .write()
+            .await
+            .record_failure(&two_fa_key)
+            .await
+        {
+            Ok(failures) => failures,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        };
+
+        // Burn the code once it has been guessed at too often.
+        // The user has to log in again for a new one.
+        if failures.count >= MAX_TWO_FA_ATTEMPTS {
+            if let Err(e) = two_fa_code_store.remove_code(&email).await {
+                return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+        }
+
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
@@ -49,16 +125,49 @@ pub async fn verify_2fa(
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    if let Err(e) = release_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+    if let Err(e) = clear_failed_logins(&email, &state).await {
+        return (jar, Err(e));
+    }
+
+    let session = Session::new(user.id, client.user_agent, client.ip_address);
+
+    let (auth_cookie, refresh_cookie) = match start_session(session, &state).await {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     (updated_jar, Ok(()))
 }
 
+// Wrong codes allowed per login before its code is burned
+const MAX_TWO_FA_ATTEMPTS: u32 = 5;
+
+// What the user typed into the 2FA prompt
+enum SecondFactor {
+    // An emailed or authenticator-app code
+    Code(TotpCode),
+    RecoveryCode(RecoveryCode),
+}
+
+fn verify_totp_code(
+    email: &Email,
+    totp_secret: Option<EncryptedTotpSecret>,
+    code: &TotpCode,
+) -> Result<bool, AuthAPIError> {
+    totp_secret
+        .ok_or(AuthAPIError::UnexpectedError(eyre!(
+            "TOTP user has no TOTP secret"
+        )))?
+        .decrypt()
+        .and_then(|secret| secret.verify_code(email, code))
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Verify2FARequest {
     pub email: SecretString,
@@ -0,0 +1,86 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, EmailVerificationToken};
+use crate::utils::constants::AUTH_SERVICE_URL;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+#[tracing::instrument(name = "Verify email", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = EmailVerificationToken::parse(request.token)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let mut email_verification_token_store = state.email_verification_token_store.write().await;
+
+    let email = email_verification_token_store
+        .get_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    email_verification_token_store
+        .remove_token(&token)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Generate a new verification token for `email` and send the verification link to it
+#[tracing::instrument(name = "Send verification email", skip_all)]
+pub(crate) async fn send_verification_email(email: &Email, state: &AppState) -> Result<()> {
+    let token = EmailVerificationToken::default();
+
+    state
+        .email_verification_token_store
+        .write()
+        .await
+        .add_token(token.clone(), email.clone())
+        .await?;
+
+    let verification_link = format!(
+        "{}/?verification_token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.as_ref().expose_secret()
+    );
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Verify your email address",
+            &format!(
+                "Use the following link to verify your email address: <a href=\"{0}\">{0}</a>",
+                verification_link
+            ),
+        )
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: SecretString,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
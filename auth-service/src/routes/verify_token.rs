@@ -1,23 +1,150 @@
+use super::api_keys::validate_api_key;
+use super::sessions::{subject_user, token_user};
 use crate::app_state::AppState;
-use crate::domain::AuthAPIError;
+use crate::domain::{ApiKey, ApiKeySecret, AuthAPIError, User};
 use crate::utils::auth;
-use auth::validate_token;
+use auth::{validate_access_token, validate_client_token, validate_token};
+use auth::{AccessTokenClaims, Claims, ClientClaims};
 use axum::extract::State;
-use axum::http::StatusCode;
 use axum::Json;
-use secrecy::SecretString;
-use serde::Deserialize;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
 
 pub async fn verify_token(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
-) -> Result<StatusCode, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
-        Ok(_) => Ok(StatusCode::OK),
+) -> Result<Json<VerifyTokenResponse>, AuthAPIError> {
+    // Tell the caller who the token belongs to and what they may do,
+    // so it can make its own authorization decisions
+    let response = match check_token(&request.token, &state).await? {
+        VerifiedToken::User(claims, user) => VerifyTokenResponse {
+            kind: TokenKind::User,
+            id: claims.sub,
+            email: Some(user.email.as_ref().expose_secret().to_owned()),
+            roles: claims.roles,
+            scopes: vec![],
+            client_id: None,
+        },
+        // A client the user allowed may only do what the user granted it, whatever the user's roles
+        VerifiedToken::Delegated(claims, user) => VerifyTokenResponse {
+            kind: TokenKind::Delegated,
+            scopes: claims.scopes(),
+            id: claims.sub,
+            email: Some(user.email.as_ref().expose_secret().to_owned()),
+            roles: vec![],
+            client_id: Some(claims.client_id),
+        },
+        VerifiedToken::Client(claims) => VerifyTokenResponse {
+            kind: TokenKind::Client,
+            scopes: claims.scopes(),
+            id: claims.client_id,
+            email: None,
+            roles: vec![],
+            client_id: None,
+        },
+        // API keys act for the user who created them, but only as far as their scopes allow.
+        // Reporting the owner's roles would let any key, however narrow, do what an admin can.
+        VerifiedToken::ApiKey(key, user) => VerifyTokenResponse {
+            kind: TokenKind::ApiKey,
+            id: user.id.to_string(),
+            email: Some(user.email.as_ref().expose_secret().to_owned()),
+            roles: vec![],
+            scopes: key.scopes,
+            client_id: None,
+        },
+    };
+
+    Ok(Json(response))
+}
+
+// A token we vouch for, along with what it says about whoever it was issued to
+pub(crate) enum VerifiedToken {
+    User(Claims, User),
+    Delegated(AccessTokenClaims, User),
+    Client(ClientClaims),
+    ApiKey(ApiKey, User),
+}
+
+// Check `token` against every kind of token we hand out to be presented to other services.
+// Fails with `InvalidToken` if it isn't one of ours, or is no longer good.
+#[tracing::instrument(name = "Check token", skip_all)]
+pub(crate) async fn check_token(
+    token: &SecretString,
+    state: &AppState,
+) -> Result<VerifiedToken, AuthAPIError> {
+    // API keys are told apart by their prefix
+    if let Ok(secret) = ApiKeySecret::parse(token.clone()) {
+        let (user, key) = validate_api_key(&secret, state).await?;
+        return Ok(VerifiedToken::ApiKey(key, user));
+    }
+
+    if let Ok(claims) = validate_token(
+        token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.signing_key_store.clone(),
+    )
+    .await
+    {
+        let user = token_user(&claims, state).await?;
+        return Ok(VerifiedToken::User(claims, user));
+    }
+
+    if let Ok(claims) = validate_access_token(
+        token,
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.signing_key_store.clone(),
+    )
+    .await
+    {
+        let user = subject_user(&claims.sub, state).await?;
+        return Ok(VerifiedToken::Delegated(claims, user));
+    }
+
+    // Otherwise it may be a token a machine client got for itself
+    match validate_client_token(
+        token,
+        state.banned_token_store.clone(),
+        state.signing_key_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => Ok(VerifiedToken::Client(claims)),
         Err(_) => Err(AuthAPIError::InvalidToken),
     }
 }
+
 #[derive(Deserialize, Debug)]
 pub struct VerifyTokenRequest {
     pub token: SecretString,
 }
+
+// Who a token was issued to: a user, a machine client acting for itself, a client the user
+// allowed to act for them, or a user's script holding one of their API keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    User,
+    Client,
+    Delegated,
+    ApiKey,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyTokenResponse {
+    pub kind: TokenKind,
+    // The id of the user, or the client id of a machine client
+    pub id: String,
+    // Only for users and their API keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub roles: Vec<String>,
+    // For machine clients and clients acting for a user, which are granted scopes rather than
+    // roles, and API keys limited to some scopes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    // The client a user allowed to act for them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
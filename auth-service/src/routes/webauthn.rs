@@ -0,0 +1,519 @@
+use super::login::{
+    clear_failed_logins, login_attempt_keys, release_login_attempt, reserve_login_attempt,
+};
+use super::sessions::{authenticate, start_session};
+use crate::app_state::AppState;
+use crate::domain::{
+    verify_assertion, verify_attestation, verify_client_data, AuthAPIError, Email, LoginAttemptId,
+    Passkey, PasskeyId, PasskeyStoreError, Session, UserId, WebAuthnCeremony, WebAuthnChallenge,
+    COSE_ALG_ES256, WEBAUTHN_CHALLENGE_TTL_SECONDS,
+};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{
+    WEBAUTHN_ENABLED, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID, WEBAUTHN_RP_NAME,
+};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+// Longest name a user can give a passkey
+const MAX_PASSKEY_NAME_LEN: usize = 64;
+
+// Hand the logged-in user's browser the options for `navigator.credentials.create()`
+#[tracing::instrument(name = "Start passkey registration", skip_all)]
+pub async fn start_passkey_registration(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    check_passkeys_enabled()?;
+    let (user, _) = authenticate(&jar, &state).await?;
+    let email = &user.email;
+
+    // Stop the browser from registering a second passkey on an authenticator that has one
+    let exclude_credentials = user_passkeys(&user.id, &state).await?;
+
+    let challenge = WebAuthnChallenge::default();
+    state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), WebAuthnCeremony::Registration(user.id))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(PasskeyRegistrationOptions {
+        challenge: challenge.as_ref().to_owned(),
+        rp: RelyingParty {
+            id: WEBAUTHN_RP_ID.clone(),
+            name: WEBAUTHN_RP_NAME.to_owned(),
+        },
+        user: PasskeyUser {
+            id: URL_SAFE_NO_PAD.encode(user.id.as_ref().as_bytes()),
+            name: email.as_ref().expose_secret().to_owned(),
+            display_name: email.as_ref().expose_secret().to_owned(),
+        },
+        pub_key_cred_params: vec![CredentialParameters {
+            credential_type: "public-key".to_owned(),
+            alg: COSE_ALG_ES256,
+        }],
+        exclude_credentials,
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "preferred".to_owned(),
+            user_verification: "preferred".to_owned(),
+        },
+        attestation: "none".to_owned(),
+        timeout: WEBAUTHN_CHALLENGE_TTL_SECONDS * 1000,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Save the passkey the browser created for the logged-in user
+#[tracing::instrument(name = "Finish passkey registration", skip_all)]
+pub async fn finish_passkey_registration(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<PasskeyRegistrationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    check_passkeys_enabled()?;
+    let (user, _) = authenticate(&jar, &state).await?;
+
+    let name = request.name.unwrap_or_default().trim().to_owned();
+    let name = if name.is_empty() {
+        "Passkey".to_owned()
+    } else {
+        name
+    };
+    if name.chars().count() > MAX_PASSKEY_NAME_LEN {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let client_data_json = decode(&request.response.client_data_json)?;
+    let attestation_object = decode(&request.response.attestation_object)?;
+
+    let challenge = verify_client_data(&client_data_json, "webauthn.create", &WEBAUTHN_ORIGIN)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    // The challenge must have been handed out to this user for registration
+    match take_ceremony(&challenge, &state).await? {
+        WebAuthnCeremony::Registration(registering) if registering == user.id => {}
+        _ => return Err(AuthAPIError::IncorrectCredentials),
+    }
+
+    let credential = verify_attestation(&attestation_object, &WEBAUTHN_RP_ID)
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    if credential.id.as_ref() != request.id {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    let passkey = Passkey {
+        id: credential.id,
+        user_id: user.id,
+        name,
+        public_key: credential.public_key,
+        sign_count: credential.sign_count,
+        created_at: Utc::now(),
+    };
+
+    match state.passkey_store.write().await.add_passkey(passkey).await {
+        Ok(()) => {}
+        Err(PasskeyStoreError::PasskeyAlreadyExists) => {
+            return Err(AuthAPIError::PasskeyAlreadyRegistered)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(PasskeyRegistrationResponse {
+        message: "Passkey registered".to_owned(),
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+// Hand the browser the options for `navigator.credentials.get()`. An empty body starts a
+// passwordless login; with the email and login attempt id from `/login` it starts the second
+// step of a 2FA login instead of the usual code.
+#[tracing::instrument(name = "Start passkey login", skip_all)]
+pub async fn start_passkey_login(
+    State(state): State<AppState>,
+    Json(request): Json<PasskeyLoginOptionsRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    check_passkeys_enabled()?;
+    let (ceremony, allow_credentials, user_verification) =
+        match (request.email, request.login_attempt_id) {
+            (Some(email), Some(login_attempt_id)) => {
+                let email = Email::parse(email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+                let login_attempt_id = LoginAttemptId::parse(login_attempt_id)
+                    .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+                if !is_pending_login(&email, &login_attempt_id, &state).await {
+                    return Err(AuthAPIError::IncorrectCredentials);
+                }
+
+                let user = state
+                    .user_store
+                    .read()
+                    .await
+                    .get_user(&email)
+                    .await
+                    .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+                let allow_credentials = user_passkeys(&user.id, &state).await?;
+                if allow_credentials.is_empty() {
+                    return Err(AuthAPIError::IncorrectCredentials);
+                }
+
+                (
+                    WebAuthnCeremony::SecondFactor(email, login_attempt_id),
+                    allow_credentials,
+                    // The password was the first factor, so the passkey only needs to be present
+                    "discouraged",
+                )
+            }
+            // The browser lets the user pick any of their passkeys for this site
+            (None, None) => (WebAuthnCeremony::Login, vec![], "required"),
+            _ => return Err(AuthAPIError::InvalidCredentials),
+        };
+
+    let challenge = WebAuthnChallenge::default();
+    state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge.clone(), ceremony)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(PasskeyLoginOptions {
+        challenge: challenge.as_ref().to_owned(),
+        rp_id: WEBAUTHN_RP_ID.clone(),
+        allow_credentials,
+        user_verification: user_verification.to_owned(),
+        timeout: WEBAUTHN_CHALLENGE_TTL_SECONDS * 1000,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Log the user in with the passkey assertion the browser returned, setting the same cookies as
+// `/login`
+#[tracing::instrument(name = "Finish passkey login", skip_all)]
+pub async fn finish_passkey_login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(request): Json<PasskeyLoginRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    if let Err(e) = check_passkeys_enabled() {
+        return (jar, Err(e));
+    }
+
+    let decoded = (
+        PasskeyId::parse(request.id),
+        decode(&request.response.client_data_json),
+        decode(&request.response.authenticator_data),
+        decode(&request.response.signature),
+    );
+    let (passkey_id, client_data_json, authenticator_data, signature) = match decoded {
+        (Ok(id), Ok(client_data_json), Ok(authenticator_data), Ok(signature)) => {
+            (id, client_data_json, authenticator_data, signature)
+        }
+        _ => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let challenge = match verify_client_data(&client_data_json, "webauthn.get", &WEBAUTHN_ORIGIN) {
+        Ok(challenge) => challenge,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    // Each challenge can only be answered once, whether or not the answer is any good
+    let ceremony = match take_ceremony(&challenge, &state).await {
+        Ok(ceremony) => ceremony,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    let passkey = match state
+        .passkey_store
+        .read()
+        .await
+        .get_passkey(&passkey_id)
+        .await
+    {
+        Ok(passkey) => passkey,
+        Err(PasskeyStoreError::PasskeyNotFound) => {
+            return (jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let user = match state
+        .user_store
+        .read()
+        .await
+        .get_user_by_id(&passkey.user_id)
+        .await
+    {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    let second_factor = match ceremony {
+        WebAuthnCeremony::Login => None,
+        WebAuthnCeremony::SecondFactor(email, login_attempt_id) if email == user.email => {
+            if !is_pending_login(&email, &login_attempt_id, &state).await {
+                return (jar, Err(AuthAPIError::IncorrectCredentials));
+            }
+            Some(email)
+        }
+        _ => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    // Passkey logins are throttled and locked out together with password logins
+    let attempt_keys = login_attempt_keys(&user.email, &client);
+    if let Err(e) = reserve_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+
+    // A passkey that checks the user's PIN or biometrics is two factors on its own
+    let sign_count = match verify_assertion(
+        &passkey,
+        &authenticator_data,
+        &client_data_json,
+        &signature,
+        &WEBAUTHN_RP_ID,
+        second_factor.is_none(),
+    ) {
+        Ok(sign_count) => sign_count,
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    if let Err(e) = release_login_attempt(&attempt_keys, &state).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = state
+        .passkey_store
+        .write()
+        .await
+        .update_sign_count(&passkey.id, sign_count)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    if !user.verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
+    // The 2FA login is finished, so its code can't be used as well
+    if let Some(email) = second_factor {
+        if let Err(e) = state
+            .two_fa_code_store
+            .write()
+            .await
+            .remove_code(&email)
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+    }
+
+    if let Err(e) = clear_failed_logins(&user.email, &state).await {
+        return (jar, Err(e));
+    }
+
+    let session = Session::new(user.id, client.user_agent, client.ip_address);
+
+    let (auth_cookie, refresh_cookie) = match start_session(session, &state).await {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+// Passkeys are turned off while the relying party settings can't work in a browser
+fn check_passkeys_enabled() -> Result<(), AuthAPIError> {
+    if *WEBAUTHN_ENABLED {
+        Ok(())
+    } else {
+        Err(AuthAPIError::PasskeysUnavailable)
+    }
+}
+
+// The user's passkeys, in the form the browser wants them
+async fn user_passkeys(
+    user_id: &UserId,
+    state: &AppState,
+) -> Result<Vec<CredentialDescriptor>, AuthAPIError> {
+    let passkeys = state
+        .passkey_store
+        .read()
+        .await
+        .get_passkeys(user_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(passkeys
+        .into_iter()
+        .map(|passkey| CredentialDescriptor {
+            credential_type: "public-key".to_owned(),
+            id: passkey.id.as_ref().to_owned(),
+        })
+        .collect())
+}
+
+// Look up what a challenge was issued for and make sure it can't be answered again
+async fn take_ceremony(
+    challenge: &WebAuthnChallenge,
+    state: &AppState,
+) -> Result<WebAuthnCeremony, AuthAPIError> {
+    let mut webauthn_challenge_store = state.webauthn_challenge_store.write().await;
+
+    let ceremony = webauthn_challenge_store
+        .get_ceremony(challenge)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    webauthn_challenge_store
+        .remove_challenge(challenge)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(ceremony)
+}
+
+// Whether `/login` started this 2FA login and it is still waiting for its second factor
+async fn is_pending_login(
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+    state: &AppState,
+) -> bool {
+    match state.two_fa_code_store.read().await.get_code(email).await {
+        Ok((pending_id, _)) => pending_id == *login_attempt_id,
+        Err(_) => false,
+    }
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, AuthAPIError> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyRegistrationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: PasskeyUser,
+    #[serde(rename = "pubKeyCredParams")]
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    #[serde(rename = "excludeCredentials")]
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    #[serde(rename = "authenticatorSelection")]
+    pub authenticator_selection: AuthenticatorSelection,
+    pub attestation: String,
+    // Milliseconds
+    pub timeout: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyUser {
+    // The user's id, base64url encoded
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthenticatorSelection {
+    #[serde(rename = "residentKey")]
+    pub resident_key: String,
+    #[serde(rename = "userVerification")]
+    pub user_verification: String,
+}
+
+// The credential `navigator.credentials.create()` returned, with binary fields base64url encoded
+#[derive(Deserialize)]
+pub struct PasskeyRegistrationRequest {
+    pub name: Option<String>,
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PasskeyRegistrationResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyLoginOptionsRequest {
+    pub email: Option<SecretString>,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Option<SecretString>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyLoginOptions {
+    pub challenge: String,
+    #[serde(rename = "rpId")]
+    pub rp_id: String,
+    #[serde(rename = "allowCredentials")]
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    #[serde(rename = "userVerification")]
+    pub user_verification: String,
+    // Milliseconds
+    pub timeout: u64,
+}
+
+// The assertion `navigator.credentials.get()` returned, with binary fields base64url encoded
+#[derive(Deserialize)]
+pub struct PasskeyLoginRequest {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, ApiKeyId, HashedApiKey, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapApiKeyStore {
+    keys: HashMap<ApiKeyId, ApiKey>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for HashmapApiKeyStore {
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        self.keys.insert(key.id.clone(), key);
+        Ok(())
+    }
+
+    async fn get_keys(&self, user_id: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut keys: Vec<ApiKey> = self
+            .keys
+            .values()
+            .filter(|key| &key.user_id == user_id)
+            .cloned()
+            .collect();
+        keys.sort_by_key(|key| key.created_at);
+        Ok(keys)
+    }
+
+    async fn get_key_by_hash(&self, key_hash: &HashedApiKey) -> Result<ApiKey, ApiKeyStoreError> {
+        self.keys
+            .values()
+            .find(|key| &key.key_hash == key_hash)
+            .cloned()
+            .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+
+    async fn delete_key(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+    ) -> Result<(), ApiKeyStoreError> {
+        match self.keys.get(id) {
+            Some(key) if &key.user_id == user_id => {
+                self.keys.remove(id);
+                Ok(())
+            }
+            _ => Err(ApiKeyStoreError::KeyNotFound),
+        }
+    }
+
+    async fn touch_key(&mut self, id: &ApiKeyId) -> Result<(), ApiKeyStoreError> {
+        let key = self.keys.get_mut(id).ok_or(ApiKeyStoreError::KeyNotFound)?;
+        key.last_used_at = Some(Utc::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_keys() {
+        let mut store = HashmapApiKeyStore::default();
+        let owner = UserId::default();
+        let (key, secret) = ApiKey::new(owner, "CI".to_owned(), vec![], None).unwrap();
+
+        store.add_key(key.clone()).await.unwrap();
+
+        assert_eq!(store.get_keys(&owner).await, Ok(vec![key.clone()]));
+        assert_eq!(store.get_keys(&UserId::default()).await, Ok(vec![]));
+        assert_eq!(store.get_key_by_hash(&secret.hash()).await, Ok(key.clone()));
+
+        store.touch_key(&key.id).await.unwrap();
+        let touched = store.get_key_by_hash(&secret.hash()).await.unwrap();
+        assert!(touched.last_used_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delete_key() {
+        let mut store = HashmapApiKeyStore::default();
+        let owner = UserId::default();
+        let (key, secret) = ApiKey::new(owner, "CI".to_owned(), vec![], None).unwrap();
+        store.add_key(key.clone()).await.unwrap();
+
+        // Only the owner can delete a key
+        let result = store.delete_key(&UserId::default(), &key.id).await;
+        assert_eq!(result, Err(ApiKeyStoreError::KeyNotFound));
+
+        store.delete_key(&owner, &key.id).await.unwrap();
+        let result = store.get_key_by_hash(&secret.hash()).await;
+        assert_eq!(result, Err(ApiKeyStoreError::KeyNotFound));
+        let result = store.delete_key(&owner, &key.id).await;
+        assert_eq!(result, Err(ApiKeyStoreError::KeyNotFound));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::domain::{
+    data_stores::{AuditLogStore, AuditLogStoreError},
+    AuditEvent, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapAuditLogStore {
+    events: Vec<AuditEvent>,
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for HashmapAuditLogStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    async fn get_events(&self, user_id: &UserId) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        Ok(self
+            .events
+            .iter()
+            .filter(|event| &event.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuditAction, Email};
+    use secrecy::SecretString;
+
+    #[tokio::test]
+    async fn test_add_and_get_events() {
+        let mut store = HashmapAuditLogStore::default();
+        let user_id = UserId::default();
+        let email = "test@example.com".to_owned().into_boxed_str();
+        let event = AuditEvent::new(
+            AuditAction::AccountDeleted,
+            user_id,
+            Email::parse(SecretString::new(email)).unwrap(),
+            Some("Firefox".to_owned()),
+            Some("127.0.0.1".to_owned()),
+        );
+
+        let result = store.add_event(event.clone()).await;
+        assert!(result.is_ok());
+
+        let result = store.get_events(&user_id).await;
+        assert_eq!(result.unwrap(), vec![event]);
+
+        // Other users' events are not returned
+        let result = store.get_events(&UserId::default()).await;
+        assert!(result.unwrap().is_empty());
+    }
+}
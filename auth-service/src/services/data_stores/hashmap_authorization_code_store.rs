@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+    AuthorizationCode, AuthorizationGrant,
+};
+
+#[derive(Default)]
+pub struct HashmapAuthorizationCodeStore {
+    codes: HashMap<String, AuthorizationGrant>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        self.codes
+            .insert(code.as_ref().expose_secret().to_owned(), grant);
+        Ok(())
+    }
+
+    async fn remove_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        match self.codes.remove(code.as_ref().expose_secret()) {
+            Some(_) => Ok(()),
+            None => Err(AuthorizationCodeStoreError::CodeNotFound),
+        }
+    }
+
+    async fn get_grant(
+        &self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        self.codes
+            .get(code.as_ref().expose_secret())
+            .cloned()
+            .ok_or(AuthorizationCodeStoreError::CodeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{CodeChallenge, OAuthClientId, UserId};
+
+    fn grant() -> AuthorizationGrant {
+        AuthorizationGrant {
+            client_id: OAuthClientId::parse("8c5f1e55-9c2b-4d5e-9a57-3f1f3b8f6f3e".to_owned())
+                .unwrap(),
+            user_id: UserId::parse("3f7c2d4e-1b6a-4c8e-9f2d-5a6b7c8d9e0f".to_owned()).unwrap(),
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            code_challenge: CodeChallenge::parse(
+                "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM".to_owned(),
+            )
+            .unwrap(),
+            scopes: vec!["openid".to_owned()],
+            nonce: Some("n-0S6_WzA2Mj".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_grant() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+
+        let result = store.add_code(code.clone(), grant()).await;
+        assert!(result.is_ok());
+
+        let result = store.get_grant(&code).await;
+        assert_eq!(result, Ok(grant()));
+    }
+
+    #[tokio::test]
+    async fn test_remove_code() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        let code = AuthorizationCode::default();
+        store.add_code(code.clone(), grant()).await.unwrap();
+
+        let result = store.remove_code(&code).await;
+        assert!(result.is_ok());
+
+        // Each code can be used once
+        let result = store.get_grant(&code).await;
+        assert_eq!(result, Err(AuthorizationCodeStoreError::CodeNotFound));
+        let result = store.remove_code(&code).await;
+        assert_eq!(result, Err(AuthorizationCodeStoreError::CodeNotFound));
+    }
+}
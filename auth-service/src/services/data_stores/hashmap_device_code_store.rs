@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    data_stores::{DeviceCodeStore, DeviceCodeStoreError},
+    DeviceAuthorization, DeviceCode, UserCode,
+};
+
+#[derive(Default)]
+pub struct HashmapDeviceCodeStore {
+    codes: HashMap<String, (DeviceCode, DeviceAuthorization)>,
+}
+
+#[async_trait::async_trait]
+impl DeviceCodeStore for HashmapDeviceCodeStore {
+    async fn add_code(
+        &mut self,
+        code: DeviceCode,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceCodeStoreError> {
+        self.codes.insert(
+            code.as_ref().expose_secret().to_owned(),
+            (code, authorization),
+        );
+        Ok(())
+    }
+
+    async fn get_authorization(
+        &self,
+        code: &DeviceCode,
+    ) -> Result<DeviceAuthorization, DeviceCodeStoreError> {
+        self.codes
+            .get(code.as_ref().expose_secret())
+            .map(|(_, authorization)| authorization.clone())
+            .ok_or(DeviceCodeStoreError::CodeNotFound)
+    }
+
+    async fn get_code(&self, user_code: &UserCode) -> Result<DeviceCode, DeviceCodeStoreError> {
+        self.codes
+            .values()
+            .find(|(_, authorization)| &authorization.user_code == user_code)
+            .map(|(code, _)| code.clone())
+            .ok_or(DeviceCodeStoreError::CodeNotFound)
+    }
+
+    async fn update_authorization(
+        &mut self,
+        code: &DeviceCode,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceCodeStoreError> {
+        match self.codes.get_mut(code.as_ref().expose_secret()) {
+            Some((_, stored)) => {
+                *stored = authorization;
+                Ok(())
+            }
+            None => Err(DeviceCodeStoreError::CodeNotFound),
+        }
+    }
+
+    async fn remove_code(&mut self, code: &DeviceCode) -> Result<(), DeviceCodeStoreError> {
+        match self.codes.remove(code.as_ref().expose_secret()) {
+            Some(_) => Ok(()),
+            None => Err(DeviceCodeStoreError::CodeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{DeviceAuthorizationStatus, OAuthClientId, UserId};
+
+    fn authorization() -> DeviceAuthorization {
+        let client_id =
+            OAuthClientId::parse("8c5f1e55-9c2b-4d5e-9a57-3f1f3b8f6f3e".to_owned()).unwrap();
+        DeviceAuthorization::new(client_id, vec!["openid".to_owned()])
+    }
+
+    #[tokio::test]
+    async fn test_add_and_approve_code() {
+        let mut store = HashmapDeviceCodeStore::default();
+        let code = DeviceCode::default();
+        let mut authorization = authorization();
+
+        let result = store.add_code(code.clone(), authorization.clone()).await;
+        assert!(result.is_ok());
+
+        // The verification page finds the request by the code the user typed in
+        let result = store.get_code(&authorization.user_code).await;
+        assert_eq!(result, Ok(code.clone()));
+
+        authorization.status = DeviceAuthorizationStatus::Approved(UserId::default());
+        let result = store
+            .update_authorization(&code, authorization.clone())
+            .await;
+        assert!(result.is_ok());
+
+        let result = store.get_authorization(&code).await;
+        assert_eq!(result, Ok(authorization));
+    }
+
+    #[tokio::test]
+    async fn test_remove_code() {
+        let mut store = HashmapDeviceCodeStore::default();
+        let code = DeviceCode::default();
+        let authorization = authorization();
+        store
+            .add_code(code.clone(), authorization.clone())
+            .await
+            .unwrap();
+
+        let result = store.remove_code(&code).await;
+        assert!(result.is_ok());
+
+        // Each code can be used once
+        let result = store.get_authorization(&code).await;
+        assert_eq!(result, Err(DeviceCodeStoreError::CodeNotFound));
+        let result = store.get_code(&authorization.user_code).await;
+        assert_eq!(result, Err(DeviceCodeStoreError::CodeNotFound));
+        let result = store.remove_code(&code).await;
+        assert_eq!(result, Err(DeviceCodeStoreError::CodeNotFound));
+    }
+}
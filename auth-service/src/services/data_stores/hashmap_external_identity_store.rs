@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{ExternalIdentityStore, ExternalIdentityStoreError},
+    ExternalIdentity, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapExternalIdentityStore {
+    // Keyed by provider and subject
+    identities: HashMap<(String, String), ExternalIdentity>,
+}
+
+#[async_trait::async_trait]
+impl ExternalIdentityStore for HashmapExternalIdentityStore {
+    async fn add_identity(
+        &mut self,
+        identity: ExternalIdentity,
+    ) -> Result<(), ExternalIdentityStoreError> {
+        let key = (identity.provider.clone(), identity.subject.clone());
+        if self.identities.contains_key(&key) {
+            return Err(ExternalIdentityStoreError::IdentityAlreadyExists);
+        }
+        self.identities.insert(key, identity);
+        Ok(())
+    }
+
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<ExternalIdentity, ExternalIdentityStoreError> {
+        self.identities
+            .get(&(provider.to_owned(), subject.to_owned()))
+            .cloned()
+            .ok_or(ExternalIdentityStoreError::IdentityNotFound)
+    }
+
+    async fn get_identities(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<ExternalIdentity>, ExternalIdentityStoreError> {
+        let mut identities: Vec<ExternalIdentity> = self
+            .identities
+            .values()
+            .filter(|identity| &identity.user_id == user_id)
+            .cloned()
+            .collect();
+        identities.sort_by_key(|identity| identity.created_at);
+        Ok(identities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(provider: &str, user_id: UserId) -> ExternalIdentity {
+        ExternalIdentity::new(provider.to_owned(), "1234567890".to_owned(), user_id)
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_identity() {
+        let mut store = HashmapExternalIdentityStore::default();
+        let identity = identity("google", UserId::default());
+
+        let result = store.add_identity(identity.clone()).await;
+        assert!(result.is_ok());
+
+        let result = store.get_identity("google", "1234567890").await;
+        assert_eq!(result, Ok(identity));
+
+        // Subjects are only unique within a provider
+        let result = store.get_identity("github", "1234567890").await;
+        assert_eq!(result, Err(ExternalIdentityStoreError::IdentityNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_add_existing_identity() {
+        let mut store = HashmapExternalIdentityStore::default();
+        let user_id = UserId::default();
+        store
+            .add_identity(identity("google", user_id))
+            .await
+            .unwrap();
+
+        let result = store.add_identity(identity("google", user_id)).await;
+        assert_eq!(
+            result,
+            Err(ExternalIdentityStoreError::IdentityAlreadyExists)
+        );
+
+        let result = store.add_identity(identity("github", user_id)).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_get_identities() {
+        let mut store = HashmapExternalIdentityStore::default();
+        let user_id = UserId::default();
+        let google = identity("google", user_id);
+        store.add_identity(google.clone()).await.unwrap();
+        let mut github = identity("github", user_id);
+        github.created_at = google.created_at + chrono::Duration::seconds(1);
+        store.add_identity(github.clone()).await.unwrap();
+
+        let result = store.get_identities(&user_id).await;
+        assert_eq!(result, Ok(vec![google, github]));
+
+        let result = store.get_identities(&UserId::default()).await;
+        assert_eq!(result, Ok(vec![]));
+    }
+}
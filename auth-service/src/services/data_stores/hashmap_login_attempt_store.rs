@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{LoginAttemptStore, LoginAttemptStoreError},
+    FailedAttempts, LoginAttemptKey,
+};
+
+// Counts live in this instance's memory only
+#[derive(Default)]
+pub struct HashmapLoginAttemptStore {
+    failures: HashMap<String, FailedAttempts>,
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for HashmapLoginAttemptStore {
+    async fn get_failures(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<Option<FailedAttempts>, LoginAttemptStoreError> {
+        Ok(self
+            .failures
+            .get(&key.as_key())
+            .filter(|failures| !failures.is_expired())
+            .cloned())
+    }
+
+    async fn record_failure(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<FailedAttempts, LoginAttemptStoreError> {
+        self.failures.retain(|_, failures| !failures.is_expired());
+
+        let failures = self
+            .failures
+            .entry(key.as_key())
+            .and_modify(|failures| failures.count += 1)
+            .or_insert(FailedAttempts {
+                count: 1,
+                last_failed_at: Utc::now(),
+            });
+        failures.last_failed_at = Utc::now();
+
+        Ok(failures.clone())
+    }
+
+    async fn reserve_attempt(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<Option<FailedAttempts>, LoginAttemptStoreError> {
+        let previous = self.get_failures(key).await?;
+        self.record_failure(key).await?;
+        Ok(previous)
+    }
+
+    async fn release_attempt(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<(), LoginAttemptStoreError> {
+        let key = key.as_key();
+        if let Some(failures) = self.failures.get_mut(&key) {
+            failures.count = failures.count.saturating_sub(1);
+            if failures.count == 0 {
+                self.failures.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    async fn clear_failures(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<(), LoginAttemptStoreError> {
+        self.failures.remove(&key.as_key());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, FAILED_ATTEMPTS_TTL_SECONDS};
+    use secrecy::SecretString;
+
+    fn email_key() -> LoginAttemptKey {
+        LoginAttemptKey::Email(
+            Email::parse(SecretString::new(
+                "test@example.com".to_owned().into_boxed_str(),
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_record_failure() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let key = email_key();
+
+        assert_eq!(store.get_failures(&key).await.unwrap(), None);
+
+        store.record_failure(&key).await.unwrap();
+        let failures = store.record_failure(&key).await.unwrap();
+
+        assert_eq!(failures.count, 2);
+        assert_eq!(store.get_failures(&key).await.unwrap(), Some(failures));
+
+        // Other keys are counted separately
+        let ip_key = LoginAttemptKey::IpAddress("127.0.0.1".to_owned());
+        assert_eq!(store.get_failures(&ip_key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_clear_failures() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let key = email_key();
+
+        store.record_failure(&key).await.unwrap();
+        store.clear_failures(&key).await.unwrap();
+
+        assert_eq!(store.get_failures(&key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_and_release_attempt() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let key = email_key();
+
+        assert_eq!(store.reserve_attempt(&key).await.unwrap(), None);
+        let previous = store.reserve_attempt(&key).await.unwrap();
+        assert_eq!(previous.map(|failures| failures.count), Some(1));
+
+        // A reserved attempt counts as a failure until it is released
+        store.release_attempt(&key).await.unwrap();
+        let failures = store.get_failures(&key).await.unwrap().unwrap();
+        assert_eq!(failures.count, 1);
+
+        store.release_attempt(&key).await.unwrap();
+        assert_eq!(store.get_failures(&key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_expired_failures_are_forgotten() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let key = email_key();
+
+        store.failures.insert(
+            key.as_key(),
+            FailedAttempts {
+                count: 5,
+                last_failed_at: Utc::now() - chrono::Duration::seconds(FAILED_ATTEMPTS_TTL_SECONDS),
+            },
+        );
+
+        assert_eq!(store.get_failures(&key).await.unwrap(), None);
+        assert_eq!(store.record_failure(&key).await.unwrap().count, 1);
+    }
+}
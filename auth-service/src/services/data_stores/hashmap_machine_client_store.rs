@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{MachineClientStore, MachineClientStoreError},
+    MachineClient, OAuthClientId,
+};
+
+#[derive(Default)]
+pub struct HashmapMachineClientStore {
+    clients: HashMap<OAuthClientId, MachineClient>,
+}
+
+#[async_trait::async_trait]
+impl MachineClientStore for HashmapMachineClientStore {
+    async fn add_client(&mut self, client: MachineClient) -> Result<(), MachineClientStoreError> {
+        self.clients.insert(client.id.clone(), client);
+        Ok(())
+    }
+
+    async fn get_client(
+        &self,
+        id: &OAuthClientId,
+    ) -> Result<MachineClient, MachineClientStoreError> {
+        self.clients
+            .get(id)
+            .cloned()
+            .ok_or(MachineClientStoreError::ClientNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapMachineClientStore::default();
+        let (client, _) =
+            MachineClient::new("Billing".to_owned(), vec!["orders:read".to_owned()]).unwrap();
+
+        let result = store.add_client(client.clone()).await;
+        assert!(result.is_ok());
+
+        assert_eq!(store.get_client(&client.id).await, Ok(client));
+    }
+
+    #[tokio::test]
+    async fn test_get_unknown_client() {
+        let store = HashmapMachineClientStore::default();
+
+        let result = store.get_client(&OAuthClientId::default()).await;
+        assert_eq!(result, Err(MachineClientStoreError::ClientNotFound));
+    }
+}
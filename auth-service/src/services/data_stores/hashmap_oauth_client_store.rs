@@ -0,0 +1,158 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{OAuthClientStore, OAuthClientStoreError},
+    OAuthClient, OAuthClientId, OAuthConsent, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapOAuthClientStore {
+    clients: HashMap<OAuthClientId, OAuthClient>,
+    // (user, client) -> what the user allowed the client, and when
+    consents: HashMap<(UserId, OAuthClientId), OAuthConsent>,
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for HashmapOAuthClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError> {
+        self.clients.insert(client.id.clone(), client);
+        Ok(())
+    }
+
+    async fn get_client(&self, id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError> {
+        self.clients
+            .get(id)
+            .cloned()
+            .ok_or(OAuthClientStoreError::ClientNotFound)
+    }
+
+    async fn add_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scopes: &[String],
+    ) -> Result<(), OAuthClientStoreError> {
+        if !self.clients.contains_key(client_id) {
+            return Err(OAuthClientStoreError::ClientNotFound);
+        }
+        let consent = self
+            .consents
+            .entry((*user_id, client_id.clone()))
+            .or_insert_with(|| OAuthConsent {
+                client_id: client_id.clone(),
+                scopes: vec![],
+                granted_at: Utc::now(),
+            });
+        for scope in scopes {
+            if !consent.scopes.contains(scope) {
+                consent.scopes.push(scope.clone());
+            }
+        }
+        consent.scopes.sort();
+        Ok(())
+    }
+
+    async fn has_consent(
+        &self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scopes: &[String],
+    ) -> Result<bool, OAuthClientStoreError> {
+        Ok(self
+            .consents
+            .get(&(*user_id, client_id.clone()))
+            .is_some_and(|consent| consent.covers(scopes)))
+    }
+
+    async fn get_consents(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<OAuthConsent>, OAuthClientStoreError> {
+        let mut consents: Vec<OAuthConsent> = self
+            .consents
+            .iter()
+            .filter(|((user, _), _)| user == user_id)
+            .map(|(_, consent)| consent.clone())
+            .collect();
+        consents.sort_by_key(|consent| consent.granted_at);
+        Ok(consents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> OAuthClient {
+        OAuthClient::new(
+            "App".to_owned(),
+            vec!["https://app.example.com/callback".to_owned()],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapOAuthClientStore::default();
+        let client = client();
+
+        let result = store.add_client(client.clone()).await;
+        assert!(result.is_ok());
+
+        assert_eq!(store.get_client(&client.id).await, Ok(client));
+        let result = store.get_client(&OAuthClientId::default()).await;
+        assert_eq!(result, Err(OAuthClientStoreError::ClientNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_add_consent() {
+        let mut store = HashmapOAuthClientStore::default();
+        let client = client();
+        store.add_client(client.clone()).await.unwrap();
+
+        let openid = vec!["openid".to_owned()];
+        let openid_email = vec!["openid".to_owned(), "email".to_owned()];
+        let user = UserId::default();
+        assert_eq!(store.has_consent(&user, &client.id, &[]).await, Ok(false));
+
+        store.add_consent(&user, &client.id, &openid).await.unwrap();
+        store.add_consent(&user, &client.id, &openid).await.unwrap();
+
+        assert_eq!(
+            store.has_consent(&user, &client.id, &openid).await,
+            Ok(true)
+        );
+        assert_eq!(store.has_consent(&user, &client.id, &[]).await, Ok(true));
+        // Asking for more than was allowed needs the user's consent again
+        assert_eq!(
+            store.has_consent(&user, &client.id, &openid_email).await,
+            Ok(false)
+        );
+        let other = UserId::default();
+        assert_eq!(
+            store.has_consent(&other, &client.id, &openid).await,
+            Ok(false)
+        );
+
+        store
+            .add_consent(&user, &client.id, &openid_email)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.has_consent(&user, &client.id, &openid_email).await,
+            Ok(true)
+        );
+
+        let consents = store.get_consents(&user).await.unwrap();
+        assert_eq!(consents.len(), 1);
+        assert_eq!(consents[0].client_id, client.id);
+        assert_eq!(consents[0].scopes, vec!["email", "openid"]);
+        assert_eq!(store.get_consents(&other).await, Ok(vec![]));
+
+        let result = store
+            .add_consent(&user, &OAuthClientId::default(), &openid)
+            .await;
+        assert_eq!(result, Err(OAuthClientStoreError::ClientNotFound));
+    }
+}
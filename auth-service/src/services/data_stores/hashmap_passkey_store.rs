@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{PasskeyStore, PasskeyStoreError},
+    Passkey, PasskeyId, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapPasskeyStore {
+    passkeys: HashMap<PasskeyId, Passkey>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for HashmapPasskeyStore {
+    async fn add_passkey(&mut self, passkey: Passkey) -> Result<(), PasskeyStoreError> {
+        if self.passkeys.contains_key(&passkey.id) {
+            return Err(PasskeyStoreError::PasskeyAlreadyExists);
+        }
+        self.passkeys.insert(passkey.id.clone(), passkey);
+        Ok(())
+    }
+
+    async fn get_passkey(&self, id: &PasskeyId) -> Result<Passkey, PasskeyStoreError> {
+        self.passkeys
+            .get(id)
+            .cloned()
+            .ok_or(PasskeyStoreError::PasskeyNotFound)
+    }
+
+    async fn get_passkeys(&self, user_id: &UserId) -> Result<Vec<Passkey>, PasskeyStoreError> {
+        let mut passkeys: Vec<Passkey> = self
+            .passkeys
+            .values()
+            .filter(|passkey| &passkey.user_id == user_id)
+            .cloned()
+            .collect();
+        passkeys.sort_by_key(|passkey| passkey.created_at);
+        Ok(passkeys)
+    }
+
+    async fn update_sign_count(
+        &mut self,
+        id: &PasskeyId,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let passkey = self
+            .passkeys
+            .get_mut(id)
+            .ok_or(PasskeyStoreError::PasskeyNotFound)?;
+        passkey.sign_count = sign_count;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn passkey(id: &str, user_id: UserId) -> Passkey {
+        Passkey {
+            id: PasskeyId::parse(id.to_owned()).unwrap(),
+            user_id,
+            name: "Laptop".to_owned(),
+            public_key: vec![4; 65],
+            sign_count: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_passkeys() {
+        let mut store = HashmapPasskeyStore::default();
+        let user_id = UserId::default();
+        let first = passkey("AQID", user_id);
+        let mut second = passkey("BAUG", user_id);
+        second.created_at = first.created_at + chrono::Duration::seconds(1);
+        let other = passkey("BwgJ", UserId::default());
+
+        for passkey in [first.clone(), second.clone(), other] {
+            store.add_passkey(passkey).await.unwrap();
+        }
+
+        let result = store.get_passkeys(&user_id).await;
+        assert_eq!(result, Ok(vec![first.clone(), second]));
+        assert_eq!(store.get_passkey(&first.id).await, Ok(first.clone()));
+
+        // Credential ids are unique across users
+        let result = store.add_passkey(first).await;
+        assert_eq!(result, Err(PasskeyStoreError::PasskeyAlreadyExists));
+    }
+
+    #[tokio::test]
+    async fn test_update_sign_count() {
+        let mut store = HashmapPasskeyStore::default();
+        let passkey = passkey("AQID", UserId::default());
+        store.add_passkey(passkey.clone()).await.unwrap();
+
+        let result = store.update_sign_count(&passkey.id, 5).await;
+        assert!(result.is_ok());
+        assert_eq!(store.get_passkey(&passkey.id).await.unwrap().sign_count, 5);
+
+        let unknown = PasskeyId::parse("BAUG".to_owned()).unwrap();
+        let result = store.update_sign_count(&unknown, 5).await;
+        assert_eq!(result, Err(PasskeyStoreError::PasskeyNotFound));
+    }
+}
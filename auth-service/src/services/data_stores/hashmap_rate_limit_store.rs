@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::domain::{
+    data_stores::{RateLimitStore, RateLimitStoreError},
+    RateLimit, TokenBucket,
+};
+
+// Buckets live in this instance's memory only
+#[derive(Default)]
+pub struct HashmapRateLimitStore {
+    // Each bucket, and when it will be full again if no more tokens are taken
+    buckets: HashMap<String, (TokenBucket, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn take_token(
+        &mut self,
+        key: &str,
+        limit: &RateLimit,
+    ) -> Result<(), RateLimitStoreError> {
+        let now = Utc::now();
+        // A full bucket is the same as one that was never taken from, so it can be forgotten.
+        // Otherwise every client address ever seen would be kept around.
+        self.buckets.retain(|_, (_, full_at)| *full_at > now);
+
+        let bucket = limit
+            .take(self.buckets.get(key).map(|(bucket, _)| bucket), now)
+            .map_err(RateLimitStoreError::RateLimitExceeded)?;
+        let full_at = now + Duration::milliseconds(limit.period_milliseconds() as i64);
+        self.buckets.insert(key.to_owned(), (bucket, full_at));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_token() {
+        let mut store = HashmapRateLimitStore::default();
+        let limit = RateLimit::per_minute(2);
+
+        assert!(store.take_token("ip:127.0.0.1", &limit).await.is_ok());
+        assert!(store.take_token("ip:127.0.0.1", &limit).await.is_ok());
+
+        let result = store.take_token("ip:127.0.0.1", &limit).await;
+        assert!(matches!(
+            result,
+            Err(RateLimitStoreError::RateLimitExceeded(retry_after))
+                if retry_after > 29_000 && retry_after <= 30_000
+        ));
+
+        // Other keys have their own bucket
+        assert!(store.take_token("ip:127.0.0.2", &limit).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_take_token_forgets_full_buckets() {
+        let mut store = HashmapRateLimitStore::default();
+        let limit = RateLimit::per_minute(2);
+        let updated_at = Utc::now() - Duration::minutes(2);
+        store.buckets.insert(
+            "ip:127.0.0.1".to_owned(),
+            (
+                TokenBucket {
+                    tokens: 0.0,
+                    updated_at,
+                },
+                updated_at + Duration::minutes(1),
+            ),
+        );
+
+        assert!(store.take_token("ip:127.0.0.2", &limit).await.is_ok());
+
+        assert!(!store.buckets.contains_key("ip:127.0.0.1"));
+        assert!(store.buckets.contains_key("ip:127.0.0.2"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::{
+    data_stores::{RecoveryCodeStore, RecoveryCodeStoreError},
+    HashedRecoveryCode, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapRecoveryCodeStore {
+    codes: HashMap<UserId, HashSet<HashedRecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for HashmapRecoveryCodeStore {
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<HashedRecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        self.codes.insert(*user_id, codes.into_iter().collect());
+        Ok(())
+    }
+
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &HashedRecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let removed = self
+            .codes
+            .get_mut(user_id)
+            .is_some_and(|codes| codes.remove(code));
+
+        match removed {
+            true => Ok(()),
+            false => Err(RecoveryCodeStoreError::CodeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::RecoveryCode;
+
+    fn hashes(codes: &[RecoveryCode]) -> Vec<HashedRecoveryCode> {
+        codes.iter().map(|code| code.hash()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_replace_codes() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let user_id = UserId::default();
+        let codes = RecoveryCode::generate_set();
+
+        let result = store.replace_codes(&user_id, hashes(&codes)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(
+            store.codes.get(&user_id).map(|c| c.len()),
+            Some(codes.len())
+        );
+
+        // Replacing invalidates the previous codes
+        let new_codes = RecoveryCode::generate_set();
+        store
+            .replace_codes(&user_id, hashes(&new_codes))
+            .await
+            .unwrap();
+
+        let result = store.use_code(&user_id, &codes[0].hash()).await;
+        assert_eq!(result, Err(RecoveryCodeStoreError::CodeNotFound));
+
+        let result = store.use_code(&user_id, &new_codes[0].hash()).await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_use_code() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let user_id = UserId::default();
+        let codes = RecoveryCode::generate_set();
+        store.replace_codes(&user_id, hashes(&codes)).await.unwrap();
+
+        let result = store.use_code(&user_id, &codes[0].hash()).await;
+        assert_eq!(result, Ok(()));
+
+        // Each code works exactly once
+        let result = store.use_code(&user_id, &codes[0].hash()).await;
+        assert_eq!(result, Err(RecoveryCodeStoreError::CodeNotFound));
+
+        // Other codes are unaffected
+        let result = store.use_code(&user_id, &codes[1].hash()).await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_use_code_not_found() {
+        let mut store = HashmapRecoveryCodeStore::default();
+        let user_id = UserId::default();
+        let code = RecoveryCode::default();
+
+        let result = store.use_code(&user_id, &code.hash()).await;
+
+        assert_eq!(result, Err(RecoveryCodeStoreError::CodeNotFound));
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    data_stores::{RefreshToken, RefreshTokenFamily, RefreshTokenStore, RefreshTokenStoreError},
+    SessionId,
+};
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    // token -> (family, whether the token has already been used)
+    tokens: HashMap<String, (RefreshTokenFamily, bool)>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        family: RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), (family, false));
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError> {
+        let (family, used) = self
+            .tokens
+            .get_mut(token.as_ref().expose_secret())
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+        if *used {
+            return Err(RefreshTokenStoreError::TokenReused(family.clone()));
+        }
+        *used = true;
+
+        Ok(family.clone())
+    }
+
+    async fn get_family(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError> {
+        match self.tokens.get(token.as_ref().expose_secret()) {
+            Some((family, false)) => Ok(family.clone()),
+            Some((family, true)) => Err(RefreshTokenStoreError::TokenReused(family.clone())),
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn revoke_family(&mut self, id: &SessionId) -> Result<(), RefreshTokenStoreError> {
+        self.tokens.retain(|_, (family, _)| &family.id != id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::UserId;
+
+    fn family() -> RefreshTokenFamily {
+        RefreshTokenFamily::new(SessionId::default(), UserId::default())
+    }
+
+    #[tokio::test]
+    async fn test_add_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let family = family();
+
+        let result = store.add_token(token.clone(), family.clone()).await;
+
+        assert!(result.is_ok());
+        assert_eq!(store.get_family(&token).await, Ok(family));
+    }
+
+    #[tokio::test]
+    async fn test_consume_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        let family = family();
+        store
+            .add_token(token.clone(), family.clone())
+            .await
+            .unwrap();
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(result, Ok(family.clone()));
+
+        // A token can only be consumed once
+        let result = store.consume_token(&token).await;
+        assert_eq!(
+            result,
+            Err(RefreshTokenStoreError::TokenReused(family.clone()))
+        );
+        assert_eq!(
+            store.get_family(&token).await,
+            Err(RefreshTokenStoreError::TokenReused(family))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consume_token_not_found() {
+        let mut store = HashmapRefreshTokenStore::default();
+
+        let result = store.consume_token(&RefreshToken::default()).await;
+
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let family = family();
+        let other_family = RefreshTokenFamily::new(SessionId::default(), family.user_id);
+
+        let first = RefreshToken::default();
+        let second = RefreshToken::default();
+        let other = RefreshToken::default();
+        store
+            .add_token(first.clone(), family.clone())
+            .await
+            .unwrap();
+        store
+            .add_token(second.clone(), family.clone())
+            .await
+            .unwrap();
+        store
+            .add_token(other.clone(), other_family.clone())
+            .await
+            .unwrap();
+
+        let result = store.revoke_family(&family.id).await;
+        assert!(result.is_ok());
+
+        assert_eq!(
+            store.get_family(&first).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert_eq!(
+            store.get_family(&second).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+
+        // Other logins of the same user are unaffected
+        assert_eq!(store.get_family(&other).await, Ok(other_family));
+    }
+}
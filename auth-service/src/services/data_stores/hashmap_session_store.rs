@@ -0,0 +1,159 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{SessionStore, SessionStoreError},
+    Session, SessionId, UserId,
+};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<SessionId, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| &session.user_id == user_id)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| Reverse(session.last_seen_at));
+        Ok(sessions)
+    }
+
+    async fn touch_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen_at = Utc::now();
+        Ok(())
+    }
+
+    async fn delete_session(
+        &mut self,
+        user_id: &UserId,
+        id: &SessionId,
+    ) -> Result<(), SessionStoreError> {
+        // Users may only delete their own sessions
+        match self.sessions.get(id) {
+            Some(session) if &session.user_id == user_id => {
+                self.sessions.remove(id);
+                Ok(())
+            }
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn delete_sessions(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<Vec<SessionId>, SessionStoreError> {
+        let ids: Vec<SessionId> = self
+            .sessions
+            .values()
+            .filter(|session| &session.user_id == user_id)
+            .map(|session| session.id.clone())
+            .collect();
+        for id in &ids {
+            self.sessions.remove(id);
+        }
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(user_id: UserId) -> Session {
+        Session::new(
+            user_id,
+            Some("Mozilla/5.0".to_owned()),
+            Some("127.0.0.1".to_owned()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let user_id = UserId::default();
+        let first = session(user_id);
+        let second = session(user_id);
+        let other = session(UserId::default());
+
+        store.add_session(first.clone()).await.unwrap();
+        store.add_session(second.clone()).await.unwrap();
+        store.add_session(other).await.unwrap();
+
+        let sessions = store.get_sessions(&user_id).await.unwrap();
+
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.contains(&first));
+        assert!(sessions.contains(&second));
+    }
+
+    #[tokio::test]
+    async fn test_touch_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = session(UserId::default());
+        store.add_session(session.clone()).await.unwrap();
+
+        let result = store.touch_session(&session.id).await;
+
+        assert_eq!(result, Ok(()));
+        let sessions = store.get_sessions(&session.user_id).await.unwrap();
+        assert!(sessions[0].last_seen_at >= session.last_seen_at);
+
+        let result = store.touch_session(&SessionId::default()).await;
+        assert_eq!(result, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = session(UserId::default());
+        store.add_session(session.clone()).await.unwrap();
+
+        // Another user can't delete the session
+        let result = store.delete_session(&UserId::default(), &session.id).await;
+        assert_eq!(result, Err(SessionStoreError::SessionNotFound));
+
+        let result = store.delete_session(&session.user_id, &session.id).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.touch_session(&session.id).await;
+        assert_eq!(result, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_delete_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let user_id = UserId::default();
+        let first = session(user_id);
+        let second = session(user_id);
+        let other = session(UserId::default());
+
+        store.add_session(first.clone()).await.unwrap();
+        store.add_session(second.clone()).await.unwrap();
+        store.add_session(other.clone()).await.unwrap();
+
+        let ids = store.delete_sessions(&user_id).await.unwrap();
+
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&first.id));
+        assert!(ids.contains(&second.id));
+        assert!(store.get_sessions(&user_id).await.unwrap().is_empty());
+        assert_eq!(store.touch_session(&other.id).await, Ok(()));
+    }
+}
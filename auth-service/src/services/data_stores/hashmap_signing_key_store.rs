@@ -0,0 +1,151 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::{
+    data_stores::{SigningKeyStore, SigningKeyStoreError},
+    SigningKey,
+};
+
+#[derive(Default)]
+pub struct HashmapSigningKeyStore {
+    keys: HashMap<String, SigningKey>,
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for HashmapSigningKeyStore {
+    async fn get_active_key(&self) -> Result<SigningKey, SigningKeyStoreError> {
+        self.keys
+            .values()
+            .find(|key| key.expires_at.is_none())
+            .cloned()
+            .ok_or(SigningKeyStoreError::SigningKeyNotFound)
+    }
+
+    async fn get_key(&self, kid: &str) -> Result<SigningKey, SigningKeyStoreError> {
+        self.keys
+            .get(kid)
+            .filter(|key| !key.is_expired())
+            .cloned()
+            .ok_or(SigningKeyStoreError::SigningKeyNotFound)
+    }
+
+    async fn get_keys(&self) -> Result<Vec<SigningKey>, SigningKeyStoreError> {
+        let mut keys: Vec<SigningKey> = self
+            .keys
+            .values()
+            .filter(|key| !key.is_expired())
+            .cloned()
+            .collect();
+        keys.sort_by_key(|key| Reverse(key.created_at));
+        Ok(keys)
+    }
+
+    async fn promote_key(
+        &mut self,
+        key: SigningKey,
+        retire_until: DateTime<Utc>,
+    ) -> Result<(), SigningKeyStoreError> {
+        self.keys.retain(|_, key| !key.is_expired());
+        for active_key in self
+            .keys
+            .values_mut()
+            .filter(|key| key.expires_at.is_none())
+        {
+            active_key.expires_at = Some(retire_until);
+        }
+        self.keys.insert(key.kid.clone(), key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+
+    fn signing_key(secret: &str) -> SigningKey {
+        SigningKey::from_secret(SecretString::new(secret.to_owned().into_boxed_str()))
+    }
+
+    fn in_minutes(minutes: i64) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::minutes(minutes)
+    }
+
+    #[tokio::test]
+    async fn test_get_active_key() {
+        let mut store = HashmapSigningKeyStore::default();
+
+        let result = store.get_active_key().await;
+        assert_eq!(result, Err(SigningKeyStoreError::SigningKeyNotFound));
+
+        let key = signing_key("first");
+        store
+            .promote_key(key.clone(), in_minutes(10))
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_active_key().await, Ok(key));
+    }
+
+    #[tokio::test]
+    async fn test_promote_key_retires_the_active_key() {
+        let mut store = HashmapSigningKeyStore::default();
+        let first = signing_key("first");
+        let second = signing_key("second");
+        let retire_until = in_minutes(10);
+
+        store
+            .promote_key(first.clone(), in_minutes(10))
+            .await
+            .unwrap();
+        store
+            .promote_key(second.clone(), retire_until)
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_active_key().await, Ok(second.clone()));
+
+        // Tokens signed with the retired key can still be verified
+        let retired = store.get_key(&first.kid).await.unwrap();
+        assert_eq!(retired.expires_at, Some(retire_until));
+
+        let kids: Vec<String> = store
+            .get_keys()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|key| key.kid)
+            .collect();
+        assert_eq!(kids, vec![second.kid, first.kid]);
+    }
+
+    #[tokio::test]
+    async fn test_expired_keys_are_not_returned() {
+        let mut store = HashmapSigningKeyStore::default();
+        let first = signing_key("first");
+        let second = signing_key("second");
+
+        store
+            .promote_key(first.clone(), in_minutes(10))
+            .await
+            .unwrap();
+        store
+            .promote_key(second.clone(), in_minutes(-1))
+            .await
+            .unwrap();
+
+        let result = store.get_key(&first.kid).await;
+        assert_eq!(result, Err(SigningKeyStoreError::SigningKeyNotFound));
+        assert_eq!(store.get_keys().await.unwrap(), vec![second.clone()]);
+
+        // Expired keys are cleaned up on the next rotation
+        store
+            .promote_key(signing_key("third"), in_minutes(10))
+            .await
+            .unwrap();
+        assert!(!store.keys.contains_key(&first.kid));
+        assert!(store.keys.contains_key(&second.kid));
+    }
+}
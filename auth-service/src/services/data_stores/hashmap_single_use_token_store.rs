@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::domain::data_stores::{SingleUseTokenStore, SingleUseTokenStoreError, TokenStoreEntry};
+
+pub struct HashmapSingleUseTokenStore<K, V> {
+    tokens: HashMap<String, V>,
+    _key: PhantomData<fn(K)>,
+}
+
+impl<K, V> Default for HashmapSingleUseTokenStore<K, V> {
+    fn default() -> Self {
+        Self {
+            tokens: HashMap::new(),
+            _key: PhantomData,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<K, V> SingleUseTokenStore<K, V> for HashmapSingleUseTokenStore<K, V>
+where
+    K: TokenStoreEntry + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    async fn add_token(&mut self, key: K, value: V) -> Result<(), SingleUseTokenStoreError> {
+        self.tokens.insert(key.encode(), value);
+        Ok(())
+    }
+
+    async fn remove_token(&mut self, key: &K) -> Result<(), SingleUseTokenStoreError> {
+        match self.tokens.remove(&key.encode()) {
+            Some(_) => Ok(()),
+            None => Err(SingleUseTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn get_token(&self, key: &K) -> Result<V, SingleUseTokenStoreError> {
+        self.tokens
+            .get(&key.encode())
+            .cloned()
+            .ok_or(SingleUseTokenStoreError::TokenNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, MagicLinkToken, PasswordResetToken};
+    use secrecy::SecretString;
+
+    fn email() -> Email {
+        Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_token() {
+        let mut store = HashmapSingleUseTokenStore::<PasswordResetToken, Email>::default();
+        let token = PasswordResetToken::default();
+
+        let result = store.add_token(token.clone(), email()).await;
+        assert!(result.is_ok());
+
+        assert_eq!(store.get_token(&token).await, Ok(email()));
+        assert_eq!(
+            store.get_token(&PasswordResetToken::default()).await,
+            Err(SingleUseTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_token_replaces_previous_value() {
+        let mut store = HashmapSingleUseTokenStore::<Email, MagicLinkToken>::default();
+        let first = MagicLinkToken::default();
+        let second = MagicLinkToken::default();
+
+        store.add_token(email(), first).await.unwrap();
+        store.add_token(email(), second.clone()).await.unwrap();
+
+        assert_eq!(store.get_token(&email()).await, Ok(second));
+    }
+
+    #[tokio::test]
+    async fn test_remove_token() {
+        let mut store = HashmapSingleUseTokenStore::<PasswordResetToken, Email>::default();
+        let token = PasswordResetToken::default();
+        store.add_token(token.clone(), email()).await.unwrap();
+
+        let result = store.remove_token(&token).await;
+        assert!(result.is_ok());
+
+        // Each token works exactly once
+        assert_eq!(
+            store.get_token(&token).await,
+            Err(SingleUseTokenStoreError::TokenNotFound)
+        );
+        assert_eq!(
+            store.remove_token(&token).await,
+            Err(SingleUseTokenStoreError::TokenNotFound)
+        );
+    }
+}
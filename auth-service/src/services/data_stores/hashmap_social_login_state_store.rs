@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{SocialLoginStateStore, SocialLoginStateStoreError},
+    SocialLoginRequest, SocialLoginState,
+};
+
+#[derive(Default)]
+pub struct HashmapSocialLoginStateStore {
+    requests: HashMap<String, SocialLoginRequest>,
+}
+
+#[async_trait::async_trait]
+impl SocialLoginStateStore for HashmapSocialLoginStateStore {
+    async fn add_state(
+        &mut self,
+        state: SocialLoginState,
+        request: SocialLoginRequest,
+    ) -> Result<(), SocialLoginStateStoreError> {
+        self.requests.insert(state.as_ref().to_owned(), request);
+        Ok(())
+    }
+
+    async fn remove_state(
+        &mut self,
+        state: &SocialLoginState,
+    ) -> Result<(), SocialLoginStateStoreError> {
+        match self.requests.remove(state.as_ref()) {
+            Some(_) => Ok(()),
+            None => Err(SocialLoginStateStoreError::StateNotFound),
+        }
+    }
+
+    async fn get_request(
+        &self,
+        state: &SocialLoginState,
+    ) -> Result<SocialLoginRequest, SocialLoginStateStoreError> {
+        self.requests
+            .get(state.as_ref())
+            .cloned()
+            .ok_or(SocialLoginStateStoreError::StateNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_request() {
+        let mut store = HashmapSocialLoginStateStore::default();
+        let state = SocialLoginState::default();
+        let request = SocialLoginRequest::new("google".to_owned());
+
+        let result = store.add_state(state.clone(), request.clone()).await;
+        assert!(result.is_ok());
+
+        let result = store.get_request(&state).await;
+        assert_eq!(result, Ok(request));
+    }
+
+    #[tokio::test]
+    async fn test_remove_state() {
+        let mut store = HashmapSocialLoginStateStore::default();
+        let state = SocialLoginState::default();
+        store
+            .add_state(state.clone(), SocialLoginRequest::new("google".to_owned()))
+            .await
+            .unwrap();
+
+        let result = store.remove_state(&state).await;
+        assert!(result.is_ok());
+
+        // Each state can be used once
+        let result = store.get_request(&state).await;
+        assert_eq!(result, Err(SocialLoginStateStoreError::StateNotFound));
+        let result = store.remove_state(&state).await;
+        assert_eq!(result, Err(SocialLoginStateStoreError::StateNotFound));
+    }
+}
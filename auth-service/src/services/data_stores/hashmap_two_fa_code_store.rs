@@ -50,7 +50,7 @@ mod tests {
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
 
@@ -68,7 +68,7 @@ mod tests {
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
 
@@ -88,7 +88,7 @@ mod tests {
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
         store
@@ -107,7 +107,7 @@ mod tests {
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
 
         let result = store.get_code(&email).await;
 
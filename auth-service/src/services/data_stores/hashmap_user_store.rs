@@ -1,4 +1,7 @@
-use crate::domain::{Email, User, UserStore, UserStoreError};
+use crate::domain::{
+    Email, EncryptedTotpSecret, HashedPassword, Role, TwoFAMethod, User, UserId, UserStore,
+    UserStoreError,
+};
 use secrecy::SecretString;
 use std::collections::HashMap;
 
@@ -26,6 +29,14 @@ impl UserStore for HashmapUserStore {
         //self.users.get(email).ok_or(UserStoreError::UserNotFound).cloned()
     }
 
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        self.users
+            .values()
+            .find(|user| &user.id == id)
+            .cloned()
+            .ok_or(UserStoreError::UserNotFound)
+    }
+
     async fn validate_user(
         &self,
         email: &Email,
@@ -38,12 +49,113 @@ impl UserStore for HashmapUserStore {
             .await
             .map_err(|_| UserStoreError::InvalidCredentials)
     }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.password = password;
+        Ok(())
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.verified = true;
+        Ok(())
+    }
+
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: EncryptedTotpSecret,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.totp_secret = Some(secret);
+        Ok(())
+    }
+
+    async fn set_two_fa_method(
+        &mut self,
+        email: &Email,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.two_fa_method = method;
+        Ok(())
+    }
+
+    async fn add_role(&mut self, email: &Email, role: Role) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        if !user.roles.contains(&role) {
+            user.roles.push(role);
+            user.roles.sort();
+        }
+        Ok(())
+    }
+
+    async fn remove_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.roles.retain(|r| r != role);
+        Ok(())
+    }
+
+    async fn update_email(
+        &mut self,
+        user_id: &UserId,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), UserStoreError> {
+        if !self
+            .users
+            .get(email)
+            .is_some_and(|user| &user.id == user_id)
+        {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if self.users.contains_key(new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self
+            .users
+            .remove(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.email = new_email.clone();
+        self.users.insert(new_email.clone(), user);
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        self.users
+            .remove(email)
+            .map(|_| ())
+            .ok_or(UserStoreError::UserNotFound)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::HashedPassword;
+    use crate::domain::TotpSecret;
 
     #[tokio::test]
     async fn test_add_user() {
@@ -53,12 +165,16 @@ mod tests {
                 .await
                 .unwrap();
         let user = User {
+            id: UserId::default(),
             email: Email::parse(SecretString::new(
                 "test@example.com".to_owned().into_boxed_str(),
             ))
-                .unwrap(),
+            .unwrap(),
             password,
-            requires_2fa: false,
+            two_fa_method: TwoFAMethod::None,
+            totp_secret: None,
+            verified: false,
+            roles: vec![],
         };
 
         // Test adding a new user
@@ -76,16 +192,20 @@ mod tests {
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
 
         let password =
             HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
                 .await
                 .unwrap();
         let user = User {
+            id: UserId::default(),
             email: email.clone(),
             password,
-            requires_2fa: false,
+            two_fa_method: TwoFAMethod::None,
+            totp_secret: None,
+            verified: false,
+            roles: vec![],
         };
 
         // Test getting a user that exists
@@ -99,29 +219,56 @@ mod tests {
                 &Email::parse(SecretString::new(
                     "nonexistent@example.com".to_owned().into_boxed_str(),
                 ))
-                    .unwrap(),
+                .unwrap(),
             )
             .await;
 
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
 
+    #[tokio::test]
+    async fn test_get_user_by_id() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let password =
+            HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
+                .await
+                .unwrap();
+        let user = User::new(email, password, TwoFAMethod::None);
+        user_store.add_user(user.clone()).await.unwrap();
+
+        // Test getting a user that exists
+        let result = user_store.get_user_by_id(&user.id).await;
+        assert_eq!(result, Ok(user));
+
+        // Test getting a user that doesn't exist
+        let result = user_store.get_user_by_id(&UserId::default()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
     #[tokio::test]
     async fn test_validate_user() {
         let mut user_store = HashmapUserStore::default();
         let email = Email::parse(SecretString::new(
             "test@example.com".to_owned().into_boxed_str(),
         ))
-            .unwrap();
+        .unwrap();
         let password =
             HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
                 .await
                 .unwrap();
 
         let user = User {
+            id: UserId::default(),
             email: email.clone(),
             password: password.clone(),
-            requires_2fa: false,
+            two_fa_method: TwoFAMethod::None,
+            totp_secret: None,
+            verified: false,
+            roles: vec![],
         };
 
         // Test validating a user that exists with correct password
@@ -145,11 +292,307 @@ mod tests {
                 &Email::parse(SecretString::new(
                     "nonexistent@example.com".to_owned().into_boxed_str(),
                 ))
-                    .unwrap(),
+                .unwrap(),
+                &SecretString::new("password".to_owned().into_boxed_str()),
+            )
+            .await;
+
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let password =
+            HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
+                .await
+                .unwrap();
+
+        let user = User {
+            id: UserId::default(),
+            email: email.clone(),
+            password,
+            two_fa_method: TwoFAMethod::None,
+            totp_secret: None,
+            verified: false,
+            roles: vec![],
+        };
+        user_store.users.insert(email.clone(), user);
+
+        // Test updating the password of a user that exists
+        let new_password = HashedPassword::parse(SecretString::new(
+            "new_password".to_owned().into_boxed_str(),
+        ))
+        .await
+        .unwrap();
+        let result = user_store.update_password(&email, new_password).await;
+        assert_eq!(result, Ok(()));
+
+        let result = user_store
+            .validate_user(
+                &email,
+                &SecretString::new("new_password".to_owned().into_boxed_str()),
+            )
+            .await;
+        assert_eq!(result, Ok(()));
+
+        let result = user_store
+            .validate_user(
+                &email,
                 &SecretString::new("password".to_owned().into_boxed_str()),
             )
             .await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+
+        // Test updating the password of a user that doesn't exist
+        let new_password = HashedPassword::parse(SecretString::new(
+            "new_password".to_owned().into_boxed_str(),
+        ))
+        .await
+        .unwrap();
+        let result = user_store
+            .update_password(
+                &Email::parse(SecretString::new(
+                    "nonexistent@example.com".to_owned().into_boxed_str(),
+                ))
+                .unwrap(),
+                new_password,
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let password =
+            HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
+                .await
+                .unwrap();
+
+        user_store
+            .add_user(User::new(email.clone(), password, TwoFAMethod::None))
+            .await
+            .unwrap();
+        assert!(!user_store.get_user(&email).await.unwrap().verified);
+
+        // Test verifying a user that exists
+        let result = user_store.mark_email_verified(&email).await;
+        assert_eq!(result, Ok(()));
+        assert!(user_store.get_user(&email).await.unwrap().verified);
+
+        // Test verifying a user that doesn't exist
+        let result = user_store
+            .mark_email_verified(
+                &Email::parse(SecretString::new(
+                    "nonexistent@example.com".to_owned().into_boxed_str(),
+                ))
+                .unwrap(),
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_totp_secret_and_two_fa_method() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let password =
+            HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
+                .await
+                .unwrap();
+
+        user_store
+            .add_user(User::new(email.clone(), password, TwoFAMethod::None))
+            .await
+            .unwrap();
+
+        // Test storing a secret for a user that exists
+        let secret = EncryptedTotpSecret::encrypt(&TotpSecret::default()).unwrap();
+        let result = user_store.set_totp_secret(&email, secret.clone()).await;
+        assert_eq!(result, Ok(()));
+
+        let user = user_store.get_user(&email).await.unwrap();
+        assert_eq!(user.totp_secret, Some(secret.clone()));
+        assert_eq!(user.two_fa_method, TwoFAMethod::None);
+
+        // Test switching the 2FA method of a user that exists
+        let result = user_store
+            .set_two_fa_method(&email, TwoFAMethod::Totp)
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.get_user(&email).await.unwrap().two_fa_method,
+            TwoFAMethod::Totp
+        );
+
+        // Test updating a user that doesn't exist
+        let nonexistent = Email::parse(SecretString::new(
+            "nonexistent@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let result = user_store.set_totp_secret(&nonexistent, secret).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+        let result = user_store
+            .set_two_fa_method(&nonexistent, TwoFAMethod::Totp)
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_role() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let password =
+            HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
+                .await
+                .unwrap();
+
+        user_store
+            .add_user(User::new(email.clone(), password, TwoFAMethod::None))
+            .await
+            .unwrap();
+        assert!(user_store.get_user(&email).await.unwrap().roles.is_empty());
+
+        let support = Role::parse("support".to_owned()).unwrap();
+
+        // Granting a role twice only grants it once
+        for role in [support.clone(), Role::admin(), Role::admin()] {
+            let result = user_store.add_role(&email, role).await;
+            assert_eq!(result, Ok(()));
+        }
+        assert_eq!(
+            user_store.get_user(&email).await.unwrap().roles,
+            vec![Role::admin(), support.clone()]
+        );
+
+        let result = user_store.remove_role(&email, &Role::admin()).await;
+        assert_eq!(result, Ok(()));
+        let result = user_store.remove_role(&email, &Role::admin()).await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.get_user(&email).await.unwrap().roles,
+            vec![support]
+        );
+
+        // Test updating a user that doesn't exist
+        let nonexistent = Email::parse(SecretString::new(
+            "nonexistent@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let result = user_store.add_role(&nonexistent, Role::admin()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+        let result = user_store.remove_role(&nonexistent, &Role::admin()).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_email() {
+        let mut user_store = HashmapUserStore::default();
+        let email = |email: &str| {
+            Email::parse(SecretString::new(email.to_owned().into_boxed_str())).unwrap()
+        };
+        let password =
+            HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
+                .await
+                .unwrap();
+
+        for address in ["old@example.com", "taken@example.com"] {
+            user_store
+                .add_user(User::new(
+                    email(address),
+                    password.clone(),
+                    TwoFAMethod::None,
+                ))
+                .await
+                .unwrap();
+        }
+
+        // Test moving a user to an address nobody has. They keep their id.
+        let id = user_store
+            .get_user(&email("old@example.com"))
+            .await
+            .unwrap()
+            .id;
+        let result = user_store
+            .update_email(&id, &email("old@example.com"), &email("new@example.com"))
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.get_user(&email("old@example.com")).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        let user = user_store
+            .get_user(&email("new@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(user.email, email("new@example.com"));
+        assert_eq!(user.id, id);
+
+        // Test moving a user to an address that belongs to someone else
+        let result = user_store
+            .update_email(&id, &email("new@example.com"), &email("taken@example.com"))
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
+
+        // Test moving a user from an address they no longer have
+        let result = user_store
+            .update_email(&id, &email("old@example.com"), &email("other@example.com"))
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+
+        // Test moving someone else's account
+        let result = user_store
+            .update_email(
+                &id,
+                &email("taken@example.com"),
+                &email("other@example.com"),
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
 
+    #[tokio::test]
+    async fn test_delete_user() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap();
+        let password =
+            HashedPassword::parse(SecretString::new("password".to_owned().into_boxed_str()))
+                .await
+                .unwrap();
+
+        user_store
+            .add_user(User::new(email.clone(), password, TwoFAMethod::None))
+            .await
+            .unwrap();
+
+        // Test deleting a user that exists
+        let result = user_store.delete_user(&email).await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        // Test deleting a user that doesn't exist
+        let result = user_store.delete_user(&email).await;
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{WebAuthnChallengeStore, WebAuthnChallengeStoreError},
+    WebAuthnCeremony, WebAuthnChallenge,
+};
+
+#[derive(Default)]
+pub struct HashmapWebAuthnChallengeStore {
+    challenges: HashMap<String, WebAuthnCeremony>,
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for HashmapWebAuthnChallengeStore {
+    async fn add_challenge(
+        &mut self,
+        challenge: WebAuthnChallenge,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<(), WebAuthnChallengeStoreError> {
+        self.challenges
+            .insert(challenge.as_ref().to_owned(), ceremony);
+        Ok(())
+    }
+
+    async fn remove_challenge(
+        &mut self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<(), WebAuthnChallengeStoreError> {
+        match self.challenges.remove(challenge.as_ref()) {
+            Some(_) => Ok(()),
+            None => Err(WebAuthnChallengeStoreError::ChallengeNotFound),
+        }
+    }
+
+    async fn get_ceremony(
+        &self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<WebAuthnCeremony, WebAuthnChallengeStoreError> {
+        self.challenges
+            .get(challenge.as_ref())
+            .cloned()
+            .ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::UserId;
+
+    fn ceremony() -> WebAuthnCeremony {
+        WebAuthnCeremony::Registration(
+            UserId::parse("8c5f1e55-9c2b-4d5e-9a57-3f1f3b8f6f3e".to_owned()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_ceremony() {
+        let mut store = HashmapWebAuthnChallengeStore::default();
+        let challenge = WebAuthnChallenge::default();
+
+        let result = store.add_challenge(challenge.clone(), ceremony()).await;
+        assert!(result.is_ok());
+
+        let result = store.get_ceremony(&challenge).await;
+        assert_eq!(result, Ok(ceremony()));
+    }
+
+    #[tokio::test]
+    async fn test_remove_challenge() {
+        let mut store = HashmapWebAuthnChallengeStore::default();
+        let challenge = WebAuthnChallenge::default();
+        store
+            .add_challenge(challenge.clone(), WebAuthnCeremony::Login)
+            .await
+            .unwrap();
+
+        let result = store.remove_challenge(&challenge).await;
+        assert!(result.is_ok());
+
+        // Each challenge can be answered once
+        let result = store.get_ceremony(&challenge).await;
+        assert_eq!(result, Err(WebAuthnChallengeStoreError::ChallengeNotFound));
+        let result = store.remove_challenge(&challenge).await;
+        assert_eq!(result, Err(WebAuthnChallengeStoreError::ChallengeNotFound));
+    }
+}
@@ -1,21 +1,20 @@
 use crate::domain::{BannedTokenStore, BannedTokenStoreError};
-use secrecy::{ExposeSecret, SecretString};
 use std::collections::HashSet;
 
 #[derive(Default)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    token_ids: HashSet<String>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError> {
-        self.tokens.insert(token.expose_secret().to_owned());
+    async fn add_token_id(&mut self, jti: &str) -> Result<(), BannedTokenStoreError> {
+        self.token_ids.insert(jti.to_owned());
         Ok(())
     }
 
-    async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.tokens.contains(token.expose_secret()))
+    async fn contains_token_id(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        Ok(self.token_ids.contains(jti))
     }
 }
 
@@ -24,24 +23,23 @@ mod tests {
     use super::*;
 
     #[tokio::test]
-    async fn test_add_token() {
+    async fn test_add_token_id() {
         let mut store = HashsetBannedTokenStore::default();
-        let token = SecretString::new("test_token".to_owned().into_boxed_str());
+        let jti = "b1d7e7a4-1f0e-4a6b-9a47-3c1c1f0e2d5a";
 
-        let result = store.add_token(token.to_owned()).await;
+        let result = store.add_token_id(jti).await;
 
         assert!(result.is_ok());
-        assert!(store.contains_token(&token).await.unwrap());
+        assert!(store.contains_token_id(jti).await.unwrap());
     }
 
     #[tokio::test]
-    async fn test_contains_token() {
+    async fn test_contains_token_id() {
         let mut store = HashsetBannedTokenStore::default();
-        let token = SecretString::new("test_token".to_owned().into_boxed_str());
-        store.tokens.insert(token.expose_secret().to_owned());
+        let jti = "b1d7e7a4-1f0e-4a6b-9a47-3c1c1f0e2d5a";
+        store.token_ids.insert(jti.to_owned());
 
-        let result = store.contains_token(&token).await;
-
-        assert!(result.unwrap());
+        assert!(store.contains_token_id(jti).await.unwrap());
+        assert!(!store.contains_token_id("other").await.unwrap());
     }
 }
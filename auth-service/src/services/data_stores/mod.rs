@@ -1,13 +1,81 @@
+mod hashmap_api_key_store;
+mod hashmap_audit_log_store;
+mod hashmap_authorization_code_store;
+mod hashmap_device_code_store;
+mod hashmap_external_identity_store;
+mod hashmap_login_attempt_store;
+mod hashmap_machine_client_store;
+mod hashmap_oauth_client_store;
+mod hashmap_passkey_store;
+mod hashmap_rate_limit_store;
+mod hashmap_recovery_code_store;
+mod hashmap_refresh_token_store;
+mod hashmap_session_store;
+mod hashmap_signing_key_store;
+mod hashmap_single_use_token_store;
+mod hashmap_social_login_state_store;
 mod hashmap_two_fa_code_store;
 mod hashmap_user_store;
+mod hashmap_webauthn_challenge_store;
 mod hashset_banned_token_store;
+mod postgres_api_key_store;
+mod postgres_audit_log_store;
+mod postgres_external_identity_store;
+mod postgres_machine_client_store;
+mod postgres_oauth_client_store;
+mod postgres_passkey_store;
+mod postgres_recovery_code_store;
+mod postgres_session_store;
+mod postgres_signing_key_store;
 mod postgres_user_store;
+mod redis_authorization_code_store;
 mod redis_banned_token_store;
+mod redis_device_code_store;
+mod redis_login_attempt_store;
+mod redis_rate_limit_store;
+mod redis_refresh_token_store;
+mod redis_single_use_token_store;
+mod redis_social_login_state_store;
 mod redis_two_fa_code_store;
+mod redis_webauthn_challenge_store;
 
+pub use hashmap_api_key_store::*;
+pub use hashmap_audit_log_store::*;
+pub use hashmap_authorization_code_store::*;
+pub use hashmap_device_code_store::*;
+pub use hashmap_external_identity_store::*;
+pub use hashmap_login_attempt_store::*;
+pub use hashmap_machine_client_store::*;
+pub use hashmap_oauth_client_store::*;
+pub use hashmap_passkey_store::*;
+pub use hashmap_rate_limit_store::*;
+pub use hashmap_recovery_code_store::*;
+pub use hashmap_refresh_token_store::*;
+pub use hashmap_session_store::*;
+pub use hashmap_signing_key_store::*;
+pub use hashmap_single_use_token_store::*;
+pub use hashmap_social_login_state_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
+pub use hashmap_webauthn_challenge_store::*;
 pub use hashset_banned_token_store::*;
+pub use postgres_api_key_store::*;
+pub use postgres_audit_log_store::*;
+pub use postgres_external_identity_store::*;
+pub use postgres_machine_client_store::*;
+pub use postgres_oauth_client_store::*;
+pub use postgres_passkey_store::*;
+pub use postgres_recovery_code_store::*;
+pub use postgres_session_store::*;
+pub use postgres_signing_key_store::*;
 pub use postgres_user_store::*;
+pub use redis_authorization_code_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_device_code_store::*;
+pub use redis_login_attempt_store::*;
+pub use redis_rate_limit_store::*;
+pub use redis_refresh_token_store::*;
+pub use redis_single_use_token_store::*;
+pub use redis_social_login_state_store::*;
 pub use redis_two_fa_code_store::*;
+pub use redis_webauthn_challenge_store::*;
@@ -0,0 +1,168 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, ApiKeyId, HashedApiKey, UserId,
+};
+
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to PostgresSQL", skip_all)]
+    async fn add_key(&mut self, key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        let id = parse_uuid(&key.id)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO api_keys
+                (id, user_id, name, key_hash, scopes, created_at, expires_at, last_used_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            id,
+            key.user_id.as_ref(),
+            key.name,
+            key.key_hash.as_ref(),
+            &key.scopes,
+            key.created_at,
+            key.expires_at,
+            key.last_used_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving API keys from PostgresSQL", skip_all)]
+    async fn get_keys(&self, user_id: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            SELECT id, user_id, name, key_hash, scopes, created_at, expires_at, last_used_at
+            FROM api_keys
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(ApiKey::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Retrieving API key by hash from PostgresSQL", skip_all)]
+    async fn get_key_by_hash(&self, key_hash: &HashedApiKey) -> Result<ApiKey, ApiKeyStoreError> {
+        sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            SELECT id, user_id, name, key_hash, scopes, created_at, expires_at, last_used_at
+            FROM api_keys
+            WHERE key_hash = $1
+            "#,
+            key_hash.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(ApiKeyStoreError::KeyNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Deleting API key from PostgresSQL", skip_all)]
+    async fn delete_key(
+        &mut self,
+        user_id: &UserId,
+        id: &ApiKeyId,
+    ) -> Result<(), ApiKeyStoreError> {
+        let id = parse_uuid(id)?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM api_keys
+            WHERE id = $1 AND user_id = $2
+            "#,
+            id,
+            user_id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Touching API key in PostgresSQL", skip_all)]
+    async fn touch_key(&mut self, id: &ApiKeyId) -> Result<(), ApiKeyStoreError> {
+        let id = parse_uuid(id)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = NOW()
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_uuid(id: &ApiKeyId) -> Result<uuid::Uuid, ApiKeyStoreError> {
+    uuid::Uuid::parse_str(id.as_ref()).map_err(|e| ApiKeyStoreError::UnexpectedError(eyre!(e)))
+}
+
+// A row of `api_keys`
+struct ApiKeyRow {
+    id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    name: String,
+    key_hash: String,
+    scopes: Vec<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl TryFrom<ApiKeyRow> for ApiKey {
+    type Error = ApiKeyStoreError;
+
+    fn try_from(row: ApiKeyRow) -> Result<Self, Self::Error> {
+        Ok(ApiKey {
+            id: ApiKeyId::parse(row.id.to_string()).map_err(ApiKeyStoreError::UnexpectedError)?,
+            user_id: UserId::from(row.user_id),
+            name: row.name,
+            key_hash: HashedApiKey::parse(row.key_hash)
+                .map_err(ApiKeyStoreError::UnexpectedError)?,
+            scopes: row.scopes,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            last_used_at: row.last_used_at,
+        })
+    }
+}
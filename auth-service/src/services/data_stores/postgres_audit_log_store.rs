@@ -0,0 +1,72 @@
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{AuditLogStore, AuditLogStoreError},
+    AuditAction, AuditEvent, Email, UserId,
+};
+
+pub struct PostgresAuditLogStore {
+    pool: PgPool,
+}
+
+impl PostgresAuditLogStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for PostgresAuditLogStore {
+    #[tracing::instrument(name = "Adding audit event to PostgresSQL", skip_all)]
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_log (action, user_id, email, user_agent, ip_address, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            event.action.as_str(),
+            event.user_id.as_ref(),
+            event.email.as_ref().expose_secret(),
+            event.user_agent,
+            event.ip_address,
+            event.occurred_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving audit events from PostgresSQL", skip_all)]
+    async fn get_events(&self, user_id: &UserId) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT action, user_id AS "user_id!", email, user_agent, ip_address, occurred_at
+            FROM audit_log
+            WHERE user_id = $1
+            ORDER BY id
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(AuditEvent {
+                action: AuditAction::parse(&row.action)
+                    .map_err(AuditLogStoreError::UnexpectedError)?,
+                user_id: UserId::from(row.user_id),
+                email: Email::parse(SecretString::new(row.email.into_boxed_str()))
+                    .map_err(|e| AuditLogStoreError::UnexpectedError(eyre!(e)))?,
+                user_agent: row.user_agent,
+                ip_address: row.ip_address,
+                occurred_at: row.occurred_at,
+            })
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,109 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{ExternalIdentityStore, ExternalIdentityStoreError},
+    ExternalIdentity, UserId,
+};
+
+pub struct PostgresExternalIdentityStore {
+    pool: PgPool,
+}
+
+impl PostgresExternalIdentityStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExternalIdentityStore for PostgresExternalIdentityStore {
+    #[tracing::instrument(name = "Adding external identity to PostgresSQL", skip_all)]
+    async fn add_identity(
+        &mut self,
+        identity: ExternalIdentity,
+    ) -> Result<(), ExternalIdentityStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO external_identities (provider, subject, user_id, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            identity.provider,
+            identity.subject,
+            identity.user_id.as_ref(),
+            identity.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                ExternalIdentityStoreError::IdentityAlreadyExists
+            }
+            e => ExternalIdentityStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving external identity from PostgresSQL", skip_all)]
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<ExternalIdentity, ExternalIdentityStoreError> {
+        sqlx::query_as!(
+            ExternalIdentityRow,
+            r#"
+            SELECT provider, subject, user_id, created_at
+            FROM external_identities
+            WHERE provider = $1 AND subject = $2
+            "#,
+            provider,
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ExternalIdentityStoreError::UnexpectedError(e.into()))?
+        .map(ExternalIdentity::from)
+        .ok_or(ExternalIdentityStoreError::IdentityNotFound)
+    }
+
+    #[tracing::instrument(name = "Retrieving external identities from PostgresSQL", skip_all)]
+    async fn get_identities(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<ExternalIdentity>, ExternalIdentityStoreError> {
+        sqlx::query_as!(
+            ExternalIdentityRow,
+            r#"
+            SELECT provider, subject, user_id, created_at
+            FROM external_identities
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|rows| rows.into_iter().map(ExternalIdentity::from).collect())
+        .map_err(|e| ExternalIdentityStoreError::UnexpectedError(e.into()))
+    }
+}
+
+// A row of `external_identities`
+struct ExternalIdentityRow {
+    provider: String,
+    subject: String,
+    user_id: uuid::Uuid,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<ExternalIdentityRow> for ExternalIdentity {
+    fn from(row: ExternalIdentityRow) -> Self {
+        ExternalIdentity {
+            provider: row.provider,
+            subject: row.subject,
+            user_id: UserId::from(row.user_id),
+            created_at: row.created_at,
+        }
+    }
+}
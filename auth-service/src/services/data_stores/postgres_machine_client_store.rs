@@ -0,0 +1,78 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{MachineClientStore, MachineClientStoreError},
+    HashedClientSecret, MachineClient, OAuthClientId,
+};
+
+pub struct PostgresMachineClientStore {
+    pool: PgPool,
+}
+
+impl PostgresMachineClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl MachineClientStore for PostgresMachineClientStore {
+    #[tracing::instrument(name = "Adding machine client to PostgresSQL", skip_all)]
+    async fn add_client(&mut self, client: MachineClient) -> Result<(), MachineClientStoreError> {
+        let id = parse_uuid(&client.id)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO machine_clients (id, name, secret_hash, scopes, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            id,
+            client.name,
+            client.secret_hash.as_ref(),
+            &client.scopes,
+            client.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| MachineClientStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving machine client from PostgresSQL", skip_all)]
+    async fn get_client(
+        &self,
+        id: &OAuthClientId,
+    ) -> Result<MachineClient, MachineClientStoreError> {
+        let id = parse_uuid(id)?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT id, name, secret_hash, scopes, created_at
+            FROM machine_clients
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| MachineClientStoreError::UnexpectedError(e.into()))?
+        .ok_or(MachineClientStoreError::ClientNotFound)?;
+
+        Ok(MachineClient {
+            id: OAuthClientId::parse(row.id.to_string())
+                .map_err(MachineClientStoreError::UnexpectedError)?,
+            name: row.name,
+            secret_hash: HashedClientSecret::parse(row.secret_hash)
+                .map_err(MachineClientStoreError::UnexpectedError)?,
+            scopes: row.scopes,
+            created_at: row.created_at,
+        })
+    }
+}
+
+fn parse_uuid(id: &OAuthClientId) -> Result<uuid::Uuid, MachineClientStoreError> {
+    uuid::Uuid::parse_str(id.as_ref())
+        .map_err(|e| MachineClientStoreError::UnexpectedError(eyre!(e)))
+}
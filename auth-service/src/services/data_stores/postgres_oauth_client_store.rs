@@ -0,0 +1,160 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{OAuthClientStore, OAuthClientStoreError},
+    OAuthClient, OAuthClientId, OAuthConsent, UserId,
+};
+
+pub struct PostgresOAuthClientStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for PostgresOAuthClientStore {
+    #[tracing::instrument(name = "Adding OAuth client to PostgresSQL", skip_all)]
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), OAuthClientStoreError> {
+        let id = parse_uuid(&client.id)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_clients (id, name, redirect_uris, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            id,
+            client.name,
+            &client.redirect_uris,
+            client.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth client from PostgresSQL", skip_all)]
+    async fn get_client(&self, id: &OAuthClientId) -> Result<OAuthClient, OAuthClientStoreError> {
+        let id = parse_uuid(id)?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT id, name, redirect_uris, created_at
+            FROM oauth_clients
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?
+        .ok_or(OAuthClientStoreError::ClientNotFound)?;
+
+        Ok(OAuthClient {
+            id: OAuthClientId::parse(row.id.to_string())
+                .map_err(OAuthClientStoreError::UnexpectedError)?,
+            name: row.name,
+            redirect_uris: row.redirect_uris,
+            created_at: row.created_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Adding OAuth consent to PostgresSQL", skip_all)]
+    async fn add_consent(
+        &mut self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scopes: &[String],
+    ) -> Result<(), OAuthClientStoreError> {
+        let client_id = parse_uuid(client_id)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_consents (user_id, client_id, scopes, granted_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (user_id, client_id) DO UPDATE
+            SET scopes = ARRAY(
+                SELECT DISTINCT unnest(oauth_consents.scopes || EXCLUDED.scopes) ORDER BY 1
+            )
+            "#,
+            user_id.as_ref(),
+            client_id,
+            scopes
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                OAuthClientStoreError::ClientNotFound
+            }
+            e => OAuthClientStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking OAuth consent in PostgresSQL", skip_all)]
+    async fn has_consent(
+        &self,
+        user_id: &UserId,
+        client_id: &OAuthClientId,
+        scopes: &[String],
+    ) -> Result<bool, OAuthClientStoreError> {
+        let client_id = parse_uuid(client_id)?;
+
+        sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM oauth_consents
+                WHERE user_id = $1 AND client_id = $2 AND scopes @> $3
+            ) AS "exists!"
+            "#,
+            user_id.as_ref(),
+            client_id,
+            scopes
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving OAuth consents from PostgresSQL", skip_all)]
+    async fn get_consents(
+        &self,
+        user_id: &UserId,
+    ) -> Result<Vec<OAuthConsent>, OAuthClientStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT client_id, scopes, granted_at
+            FROM oauth_consents
+            WHERE user_id = $1
+            ORDER BY granted_at
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(OAuthConsent {
+                    client_id: OAuthClientId::parse(row.client_id.to_string())
+                        .map_err(OAuthClientStoreError::UnexpectedError)?,
+                    scopes: row.scopes,
+                    granted_at: row.granted_at,
+                })
+            })
+            .collect()
+    }
+}
+
+fn parse_uuid(id: &OAuthClientId) -> Result<uuid::Uuid, OAuthClientStoreError> {
+    uuid::Uuid::parse_str(id.as_ref()).map_err(|e| OAuthClientStoreError::UnexpectedError(eyre!(e)))
+}
@@ -0,0 +1,136 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{PasskeyStore, PasskeyStoreError},
+    Passkey, PasskeyId, UserId,
+};
+
+pub struct PostgresPasskeyStore {
+    pool: PgPool,
+}
+
+impl PostgresPasskeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for PostgresPasskeyStore {
+    #[tracing::instrument(name = "Adding passkey to PostgresSQL", skip_all)]
+    async fn add_passkey(&mut self, passkey: Passkey) -> Result<(), PasskeyStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO passkeys (id, user_id, name, public_key, sign_count, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            passkey.id.as_ref(),
+            passkey.user_id.as_ref(),
+            passkey.name,
+            passkey.public_key,
+            i64::from(passkey.sign_count),
+            passkey.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                PasskeyStoreError::PasskeyAlreadyExists
+            }
+            e => PasskeyStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving passkey from PostgresSQL", skip_all)]
+    async fn get_passkey(&self, id: &PasskeyId) -> Result<Passkey, PasskeyStoreError> {
+        sqlx::query_as!(
+            PasskeyRow,
+            r#"
+            SELECT id, user_id, name, public_key, sign_count, created_at
+            FROM passkeys
+            WHERE id = $1
+            "#,
+            id.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(PasskeyStoreError::PasskeyNotFound)?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Retrieving passkeys from PostgresSQL", skip_all)]
+    async fn get_passkeys(&self, user_id: &UserId) -> Result<Vec<Passkey>, PasskeyStoreError> {
+        sqlx::query_as!(
+            PasskeyRow,
+            r#"
+            SELECT id, user_id, name, public_key, sign_count, created_at
+            FROM passkeys
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(Passkey::try_from)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Updating passkey sign count in PostgresSQL", skip_all)]
+    async fn update_sign_count(
+        &mut self,
+        id: &PasskeyId,
+        sign_count: u32,
+    ) -> Result<(), PasskeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE passkeys
+            SET sign_count = $2
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+            i64::from(sign_count)
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| PasskeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(PasskeyStoreError::PasskeyNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+// A row of `passkeys`
+struct PasskeyRow {
+    id: String,
+    user_id: uuid::Uuid,
+    name: String,
+    public_key: Vec<u8>,
+    sign_count: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TryFrom<PasskeyRow> for Passkey {
+    type Error = PasskeyStoreError;
+
+    fn try_from(row: PasskeyRow) -> Result<Self, Self::Error> {
+        Ok(Passkey {
+            id: PasskeyId::parse(row.id).map_err(PasskeyStoreError::UnexpectedError)?,
+            user_id: UserId::from(row.user_id),
+            name: row.name,
+            public_key: row.public_key,
+            sign_count: u32::try_from(row.sign_count)
+                .map_err(|e| PasskeyStoreError::UnexpectedError(eyre!(e)))?,
+            created_at: row.created_at,
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{RecoveryCodeStore, RecoveryCodeStoreError},
+    HashedRecoveryCode, UserId,
+};
+
+pub struct PostgresRecoveryCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+    #[tracing::instrument(name = "Replacing recovery codes in PostgresSQL", skip_all)]
+    async fn replace_codes(
+        &mut self,
+        user_id: &UserId,
+        codes: Vec<HashedRecoveryCode>,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        let codes: Vec<String> = codes
+            .into_iter()
+            .map(|code| code.as_ref().to_owned())
+            .collect();
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE user_id = $1
+            "#,
+            user_id.as_ref()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (user_id, code_hash)
+            SELECT $1, code_hash FROM UNNEST($2::TEXT[]) AS code_hash
+            "#,
+            user_id.as_ref(),
+            &codes
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Using recovery code in PostgresSQL", skip_all)]
+    async fn use_code(
+        &mut self,
+        user_id: &UserId,
+        code: &HashedRecoveryCode,
+    ) -> Result<(), RecoveryCodeStoreError> {
+        // Deleting the row is what consumes the code, so two concurrent requests can't both use it
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE user_id = $1 AND code_hash = $2
+            "#,
+            user_id.as_ref(),
+            code.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RecoveryCodeStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(RecoveryCodeStoreError::CodeNotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{SessionStore, SessionStoreError},
+    Session, SessionId, UserId,
+};
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    #[tracing::instrument(name = "Adding session to PostgresSQL", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, user_id, user_agent, ip_address, created_at, last_seen_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            session.id.as_ref(),
+            session.user_id.as_ref(),
+            session.user_agent,
+            session.ip_address,
+            session.created_at,
+            session.last_seen_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving sessions from PostgresSQL", skip_all)]
+    async fn get_sessions(&self, user_id: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT id, user_id, user_agent, ip_address, created_at, last_seen_at
+            FROM sessions
+            WHERE user_id = $1
+            ORDER BY last_seen_at DESC
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(Session {
+                id: SessionId::parse(row.id).map_err(SessionStoreError::UnexpectedError)?,
+                user_id: UserId::from(row.user_id),
+                user_agent: row.user_agent,
+                ip_address: row.ip_address,
+                created_at: row.created_at,
+                last_seen_at: row.last_seen_at,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Touching session in PostgresSQL", skip_all)]
+    async fn touch_session(&mut self, id: &SessionId) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET last_seen_at = now()
+            WHERE id = $1
+            "#,
+            id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting session from PostgresSQL", skip_all)]
+    async fn delete_session(
+        &mut self,
+        user_id: &UserId,
+        id: &SessionId,
+    ) -> Result<(), SessionStoreError> {
+        // Matching on the user too means users can only delete their own sessions
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE id = $1 AND user_id = $2
+            "#,
+            id.as_ref(),
+            user_id.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting all sessions from PostgresSQL", skip_all)]
+    async fn delete_sessions(
+        &mut self,
+        user_id: &UserId,
+    ) -> Result<Vec<SessionId>, SessionStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE user_id = $1
+            RETURNING id
+            "#,
+            user_id.as_ref()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| SessionId::parse(row.id).map_err(SessionStoreError::UnexpectedError))
+        .collect()
+    }
+}
@@ -0,0 +1,193 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context, Result};
+use jsonwebtoken::Algorithm;
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{SigningKeyStore, SigningKeyStoreError},
+    SigningKey,
+};
+use crate::utils::{constants::SIGNING_KEY_ENCRYPTION_KEY, encryption};
+
+pub struct PostgresSigningKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresSigningKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for PostgresSigningKeyStore {
+    #[tracing::instrument(name = "Retrieving active signing key from PostgresSQL", skip_all)]
+    async fn get_active_key(&self) -> Result<SigningKey, SigningKeyStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT kid, algorithm, private_key, public_key, created_at, expires_at
+            FROM signing_keys
+            WHERE expires_at IS NULL
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(SigningKeyStoreError::SigningKeyNotFound)?;
+
+        signing_key(
+            row.kid,
+            row.algorithm,
+            row.private_key,
+            row.public_key,
+            row.created_at,
+            row.expires_at,
+        )
+        .map_err(SigningKeyStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Retrieving signing key from PostgresSQL", skip_all)]
+    async fn get_key(&self, kid: &str) -> Result<SigningKey, SigningKeyStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT kid, algorithm, private_key, public_key, created_at, expires_at
+            FROM signing_keys
+            WHERE kid = $1 AND (expires_at IS NULL OR expires_at > now())
+            "#,
+            kid
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(SigningKeyStoreError::SigningKeyNotFound)?;
+
+        signing_key(
+            row.kid,
+            row.algorithm,
+            row.private_key,
+            row.public_key,
+            row.created_at,
+            row.expires_at,
+        )
+        .map_err(SigningKeyStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Retrieving signing keys from PostgresSQL", skip_all)]
+    async fn get_keys(&self) -> Result<Vec<SigningKey>, SigningKeyStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT kid, algorithm, private_key, public_key, created_at, expires_at
+            FROM signing_keys
+            WHERE expires_at IS NULL OR expires_at > now()
+            ORDER BY created_at DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            signing_key(
+                row.kid,
+                row.algorithm,
+                row.private_key,
+                row.public_key,
+                row.created_at,
+                row.expires_at,
+            )
+            .map_err(SigningKeyStoreError::UnexpectedError)
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Promoting signing key in PostgresSQL", skip_all)]
+    async fn promote_key(
+        &mut self,
+        key: SigningKey,
+        retire_until: DateTime<Utc>,
+    ) -> Result<(), SigningKeyStoreError> {
+        // Private key material is encrypted at rest, with a key of its own
+        let private_key = encryption::encrypt(
+            &SIGNING_KEY_ENCRYPTION_KEY,
+            key.private_key.expose_secret().as_bytes(),
+        )
+        .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM signing_keys
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE signing_keys
+            SET expires_at = $1
+            WHERE expires_at IS NULL
+            "#,
+            retire_until
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO signing_keys (kid, algorithm, private_key, public_key, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            key.kid,
+            // `Algorithm` parses back from its variant name
+            format!("{:?}", key.algorithm),
+            private_key,
+            key.public_key,
+            key.created_at,
+            key.expires_at
+        )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))
+    }
+}
+
+fn signing_key(
+    kid: String,
+    algorithm: String,
+    private_key: String,
+    public_key: Option<String>,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<SigningKey> {
+    let algorithm = Algorithm::from_str(&algorithm)
+        .map_err(|_| eyre!("unsupported JWT algorithm: {}", algorithm))?;
+    let private_key = encryption::decrypt(&SIGNING_KEY_ENCRYPTION_KEY, &private_key)
+        .and_then(|key| String::from_utf8(key).wrap_err("decrypted signing key is not UTF-8"))
+        .wrap_err(format!("failed to decrypt signing key {}", kid))?;
+
+    Ok(SigningKey {
+        kid,
+        algorithm,
+        private_key: SecretString::new(private_key.into_boxed_str()),
+        public_key,
+        created_at,
+        expires_at,
+    })
+}
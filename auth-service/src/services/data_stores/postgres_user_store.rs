@@ -3,9 +3,10 @@ use secrecy::{ExposeSecret, SecretString};
 
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
-    Email, HashedPassword, User,
+    Email, EncryptedTotpSecret, HashedPassword, Role, TwoFAMethod, User, UserId,
 };
 use sqlx::PgPool;
+use uuid::Uuid;
 
 pub struct PostgresUserStore {
     pool: PgPool,
@@ -20,27 +21,61 @@ impl PostgresUserStore {
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgresSQL", skip_all)]
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
         sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (id, email, password_hash, two_fa_method, totp_secret, verified)
+            VALUES ($1, $2, $3, $4, $5, $6)
             "#,
+            user.id.as_ref(),
             user.email.as_ref().expose_secret(),
             &user.password.as_ref().expose_secret(),
-            user.requires_2fa
+            user.two_fa_method.as_str(),
+            user.totp_secret
+                .as_ref()
+                .map(|secret| secret.as_ref().expose_secret()),
+            user.verified
         )
-            .execute(&self.pool)
-            .await
-            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-        Ok(())
+        let roles: Vec<String> = user
+            .roles
+            .iter()
+            .map(|role| role.as_ref().to_owned())
+            .collect();
+        sqlx::query!(
+            r#"
+            INSERT INTO user_roles (user_id, role)
+            SELECT $1, unnest($2::TEXT[])
+            "#,
+            user.id.as_ref(),
+            &roles
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
     }
 
     #[tracing::instrument(name = "Retrieving user from PostgresSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        sqlx::query!(
+        sqlx::query_as!(
+            UserRow,
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT id, email, password_hash, two_fa_method, totp_secret, verified,
+                   ARRAY(SELECT role FROM user_roles WHERE user_roles.user_id = users.id ORDER BY role)
+                       AS "roles!"
             FROM users
             WHERE email = $1
             "#,
@@ -49,18 +84,28 @@ impl UserStore for PostgresUserStore {
             .fetch_optional(&self.pool)
             .await
             .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-            .map(|row| {
-                Ok(User {
-                    email: Email::parse(SecretString::new(row.email.into_boxed_str()))
-                        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                    password: HashedPassword::parse_password_hash(SecretString::new(
-                        row.password_hash.into_boxed_str(),
-                    ))
-                        .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                    requires_2fa: row.requires_2fa,
-                })
-            })
             .ok_or(UserStoreError::UserNotFound)?
+            .try_into()
+    }
+
+    #[tracing::instrument(name = "Retrieving user by id from PostgresSQL", skip_all)]
+    async fn get_user_by_id(&self, id: &UserId) -> Result<User, UserStoreError> {
+        sqlx::query_as!(
+            UserRow,
+            r#"
+            SELECT id, email, password_hash, two_fa_method, totp_secret, verified,
+                   ARRAY(SELECT role FROM user_roles WHERE user_roles.user_id = users.id ORDER BY role)
+                       AS "roles!"
+            FROM users
+            WHERE id = $1
+            "#,
+            id.as_ref()
+        )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+            .ok_or(UserStoreError::UserNotFound)?
+            .try_into()
     }
 
     #[tracing::instrument(name = "Validating user credentials in PostgresSQL", skip_all)]
@@ -75,4 +120,241 @@ impl UserStore for PostgresUserStore {
             .await
             .map_err(|_| UserStoreError::InvalidCredentials)
     }
+
+    #[tracing::instrument(name = "Updating user password in PostgresSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: HashedPassword,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $1
+            WHERE email = $2
+            "#,
+            password.as_ref().expose_secret(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking user email as verified in PostgresSQL", skip_all)]
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET verified = TRUE
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Storing user TOTP secret in PostgresSQL", skip_all)]
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: EncryptedTotpSecret,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET totp_secret = $1
+            WHERE email = $2
+            "#,
+            secret.as_ref().expose_secret(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user 2FA method in PostgresSQL", skip_all)]
+    async fn set_two_fa_method(
+        &mut self,
+        email: &Email,
+        method: TwoFAMethod,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET two_fa_method = $1
+            WHERE email = $2
+            "#,
+            method.as_str(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Granting user role in PostgresSQL", skip_all)]
+    async fn add_role(&mut self, email: &Email, role: Role) -> Result<(), UserStoreError> {
+        // Make sure the user exists, so granting a role to nobody isn't mistaken for success
+        let user = self.get_user(email).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_roles (user_id, role)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+            user.id.as_ref(),
+            role.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking user role in PostgresSQL", skip_all)]
+    async fn remove_role(&mut self, email: &Email, role: &Role) -> Result<(), UserStoreError> {
+        let user = self.get_user(email).await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM user_roles
+            WHERE user_id = $1 AND role = $2
+            "#,
+            user.id.as_ref(),
+            role.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user email in PostgresSQL", skip_all)]
+    async fn update_email(
+        &mut self,
+        user_id: &UserId,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), UserStoreError> {
+        // Everything else refers to the user by id, so only the user's own row changes
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email = $1
+            WHERE id = $2 AND email = $3
+            "#,
+            new_email.as_ref().expose_secret(),
+            user_id.as_ref(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgresSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        // Everything stored for the user goes with them through `ON DELETE CASCADE`
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+// A row of `users`, along with the user's roles
+struct UserRow {
+    id: Uuid,
+    email: String,
+    password_hash: String,
+    two_fa_method: String,
+    totp_secret: Option<String>,
+    verified: bool,
+    roles: Vec<String>,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        Ok(User {
+            id: UserId::from(row.id),
+            email: Email::parse(SecretString::new(row.email.into_boxed_str()))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: HashedPassword::parse_password_hash(SecretString::new(
+                row.password_hash.into_boxed_str(),
+            ))
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            two_fa_method: TwoFAMethod::parse(&row.two_fa_method)
+                .map_err(UserStoreError::UnexpectedError)?,
+            totp_secret: row
+                .totp_secret
+                .map(|secret| {
+                    EncryptedTotpSecret::parse(SecretString::new(secret.into_boxed_str()))
+                })
+                .transpose()
+                .map_err(UserStoreError::UnexpectedError)?,
+            verified: row.verified,
+            roles: row
+                .roles
+                .into_iter()
+                .map(Role::parse)
+                .collect::<Result<_>>()
+                .map_err(UserStoreError::UnexpectedError)?,
+        })
+    }
 }
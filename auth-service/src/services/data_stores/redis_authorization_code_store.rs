@@ -0,0 +1,122 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+    AuthorizationCode, AuthorizationGrant, CodeChallenge, OAuthClientId, UserId,
+    AUTHORIZATION_CODE_TTL_SECONDS,
+};
+
+pub struct RedisAuthorizationCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for RedisAuthorizationCodeStore {
+    #[tracing::instrument(name = "Storing authorization code in Redis", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+        grant: AuthorizationGrant,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let key = get_key(&code);
+        let data = GrantData {
+            client_id: grant.client_id.as_ref().to_owned(),
+            user_id: grant.user_id.to_string(),
+            redirect_uri: grant.redirect_uri,
+            code_challenge: grant.code_challenge.as_ref().to_owned(),
+            scopes: grant.scopes,
+            nonce: grant.nonce,
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize authorization grant")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(key, serialized_data, AUTHORIZATION_CODE_TTL_SECONDS)
+            .wrap_err("failed to set authorization code in Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing authorization code from Redis", skip_all)]
+    async fn remove_code(
+        &mut self,
+        code: &AuthorizationCode,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let key = get_key(code);
+        let removed: u64 = self
+            .conn
+            .write()
+            .await
+            .del(key)
+            .wrap_err("failed to delete authorization code from Redis")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        if removed == 0 {
+            return Err(AuthorizationCodeStoreError::CodeNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving authorization code from Redis", skip_all)]
+    async fn get_grant(
+        &self,
+        code: &AuthorizationCode,
+    ) -> Result<AuthorizationGrant, AuthorizationCodeStoreError> {
+        let key = get_key(code);
+
+        let data = match self.conn.write().await.get::<_, String>(&key) {
+            Ok(data) => data,
+            Err(_) => return Err(AuthorizationCodeStoreError::CodeNotFound),
+        };
+        let data: GrantData = serde_json::from_str(&data)
+            .wrap_err("failed to deserialize authorization grant")
+            .map_err(AuthorizationCodeStoreError::UnexpectedError)?;
+
+        Ok(AuthorizationGrant {
+            client_id: OAuthClientId::parse(data.client_id)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            user_id: UserId::parse(data.user_id)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            redirect_uri: data.redirect_uri,
+            code_challenge: CodeChallenge::parse(data.code_challenge)
+                .map_err(AuthorizationCodeStoreError::UnexpectedError)?,
+            scopes: data.scopes,
+            nonce: data.nonce,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GrantData {
+    client_id: String,
+    user_id: String,
+    redirect_uri: String,
+    code_challenge: String,
+    scopes: Vec<String>,
+    nonce: Option<String>,
+}
+
+const AUTHORIZATION_CODE_PREFIX: &str = "authorization_code:";
+
+fn get_key(code: &AuthorizationCode) -> String {
+    format!(
+        "{}{}",
+        AUTHORIZATION_CODE_PREFIX,
+        code.as_ref().expose_secret()
+    )
+}
@@ -1,6 +1,5 @@
 use color_eyre::eyre::Context;
 use redis::{Commands, Connection};
-use secrecy::{ExposeSecret, SecretString};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -20,7 +19,7 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Storing banned JWT in Redis", skip_all)]
-    async fn add_token(&mut self, token: SecretString) -> Result<(), BannedTokenStoreError> {
+    async fn add_token_id(&mut self, jti: &str) -> Result<(), BannedTokenStoreError> {
         // TODO:
         // 1. Create a new key using the get_key helper function.
         // 2. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL).
@@ -28,7 +27,7 @@ impl BannedTokenStore for RedisBannedTokenStore {
         // The expiration time should be set to TOKEN_TTL_SECONDS.
         // NOTE: The TTL is expected to be a u64 so you will have to cast TOKEN_TTL_SECONDS to a u64.
         // Return BannedTokenStoreError::UnexpectedError if casting fails or the call to set_ex fails.
-        let token_key = get_key(token.expose_secret());
+        let token_key = get_key(jti);
 
         let value = true;
 
@@ -47,8 +46,8 @@ impl BannedTokenStore for RedisBannedTokenStore {
         Ok(())
     }
     #[tracing::instrument(name = "Checking for banned JWT in Redis", skip_all)]
-    async fn contains_token(&self, token: &SecretString) -> Result<bool, BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
+    async fn contains_token_id(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        let token_key = get_key(jti);
         let exists = self
             .conn
             .write()
@@ -61,8 +60,8 @@ impl BannedTokenStore for RedisBannedTokenStore {
 }
 
 // We are using a key prefix to prevent collisions and organize data!
-const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token_id:";
 
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn get_key(jti: &str) -> String {
+    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, jti)
 }
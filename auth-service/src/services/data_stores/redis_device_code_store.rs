@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{DeviceCodeStore, DeviceCodeStoreError},
+    DeviceAuthorization, DeviceAuthorizationStatus, DeviceCode, OAuthClientId, UserCode, UserId,
+    DEVICE_CODE_TTL_SECONDS,
+};
+
+pub struct RedisDeviceCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisDeviceCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceCodeStore for RedisDeviceCodeStore {
+    #[tracing::instrument(name = "Storing device code in Redis", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: DeviceCode,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceCodeStoreError> {
+        let code_key = get_code_key(&code);
+        let user_code_key = get_user_code_key(&authorization.user_code);
+        let serialized_data = serialize(&authorization)?;
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .set_ex(&code_key, serialized_data, DEVICE_CODE_TTL_SECONDS)
+            .wrap_err("failed to set device code in Redis")
+            .map_err(DeviceCodeStoreError::UnexpectedError)?;
+        // The verification page only knows the user code, so it needs a way back to the device code
+        let _: () = conn
+            .set_ex(
+                &user_code_key,
+                code.as_ref().expose_secret(),
+                DEVICE_CODE_TTL_SECONDS,
+            )
+            .wrap_err("failed to set user code in Redis")
+            .map_err(DeviceCodeStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving device code from Redis", skip_all)]
+    async fn get_authorization(
+        &self,
+        code: &DeviceCode,
+    ) -> Result<DeviceAuthorization, DeviceCodeStoreError> {
+        let key = get_code_key(code);
+        let mut conn = self.conn.write().await;
+
+        get_data(&mut conn, &key)?.into_authorization()
+    }
+
+    #[tracing::instrument(name = "Retrieving device code by user code from Redis", skip_all)]
+    async fn get_code(&self, user_code: &UserCode) -> Result<DeviceCode, DeviceCodeStoreError> {
+        let key = get_user_code_key(user_code);
+
+        let code = match self.conn.write().await.get::<_, String>(&key) {
+            Ok(code) => code,
+            Err(_) => return Err(DeviceCodeStoreError::CodeNotFound),
+        };
+        DeviceCode::parse(SecretString::new(code.into_boxed_str()))
+            .map_err(DeviceCodeStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Updating device code in Redis", skip_all)]
+    async fn update_authorization(
+        &mut self,
+        code: &DeviceCode,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceCodeStoreError> {
+        let key = get_code_key(code);
+        let serialized_data = serialize(&authorization)?;
+        let mut conn = self.conn.write().await;
+
+        // Keep the code's expiry, so approving it or polling doesn't make it last longer
+        let remaining_ttl: i64 = conn
+            .ttl(&key)
+            .wrap_err("failed to get device code TTL from Redis")
+            .map_err(DeviceCodeStoreError::UnexpectedError)?;
+        if remaining_ttl <= 0 {
+            return Err(DeviceCodeStoreError::CodeNotFound);
+        }
+
+        let _: () = conn
+            .set_ex(&key, serialized_data, remaining_ttl as u64)
+            .wrap_err("failed to update device code in Redis")
+            .map_err(DeviceCodeStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing device code from Redis", skip_all)]
+    async fn remove_code(&mut self, code: &DeviceCode) -> Result<(), DeviceCodeStoreError> {
+        let key = get_code_key(code);
+        let mut conn = self.conn.write().await;
+
+        let data = get_data(&mut conn, &key)?;
+        let removed: u64 = conn
+            .del(&key)
+            .wrap_err("failed to delete device code from Redis")
+            .map_err(DeviceCodeStoreError::UnexpectedError)?;
+        if removed == 0 {
+            return Err(DeviceCodeStoreError::CodeNotFound);
+        }
+
+        let user_code_key = format!("{}{}", USER_CODE_PREFIX, data.user_code);
+        let _: () = conn
+            .del(user_code_key)
+            .wrap_err("failed to delete user code from Redis")
+            .map_err(DeviceCodeStoreError::UnexpectedError)?;
+        Ok(())
+    }
+}
+
+fn get_data(
+    conn: &mut Connection,
+    key: &str,
+) -> Result<DeviceAuthorizationData, DeviceCodeStoreError> {
+    let data = match conn.get::<_, String>(key) {
+        Ok(data) => data,
+        Err(_) => return Err(DeviceCodeStoreError::CodeNotFound),
+    };
+    serde_json::from_str(&data)
+        .wrap_err("failed to deserialize device authorization")
+        .map_err(DeviceCodeStoreError::UnexpectedError)
+}
+
+fn serialize(authorization: &DeviceAuthorization) -> Result<String, DeviceCodeStoreError> {
+    let status = match &authorization.status {
+        DeviceAuthorizationStatus::Pending => StatusData::Pending,
+        DeviceAuthorizationStatus::Approved(user_id) => StatusData::Approved(user_id.to_string()),
+        DeviceAuthorizationStatus::Denied => StatusData::Denied,
+    };
+    let data = DeviceAuthorizationData {
+        client_id: authorization.client_id.as_ref().to_owned(),
+        user_code: authorization.user_code.as_ref().to_owned(),
+        scopes: authorization.scopes.clone(),
+        status,
+        last_polled_at: authorization
+            .last_polled_at
+            .map(|last_polled_at| last_polled_at.timestamp_millis()),
+        interval: authorization.interval,
+    };
+    serde_json::to_string(&data)
+        .wrap_err("failed to serialize device authorization")
+        .map_err(DeviceCodeStoreError::UnexpectedError)
+}
+
+#[derive(Serialize, Deserialize)]
+enum StatusData {
+    Pending,
+    // Holds the id of the user who approved the device
+    Approved(String),
+    Denied,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeviceAuthorizationData {
+    client_id: String,
+    user_code: String,
+    scopes: Vec<String>,
+    status: StatusData,
+    // Unix timestamp in milliseconds
+    last_polled_at: Option<i64>,
+    // In seconds
+    interval: u64,
+}
+
+impl DeviceAuthorizationData {
+    fn into_authorization(self) -> Result<DeviceAuthorization, DeviceCodeStoreError> {
+        let status = match self.status {
+            StatusData::Pending => DeviceAuthorizationStatus::Pending,
+            StatusData::Approved(user_id) => DeviceAuthorizationStatus::Approved(
+                UserId::parse(user_id).map_err(DeviceCodeStoreError::UnexpectedError)?,
+            ),
+            StatusData::Denied => DeviceAuthorizationStatus::Denied,
+        };
+
+        let last_polled_at = match self.last_polled_at {
+            Some(millis) => Some(
+                DateTime::<Utc>::from_timestamp_millis(millis)
+                    .ok_or(eyre!("invalid device code poll timestamp"))
+                    .map_err(DeviceCodeStoreError::UnexpectedError)?,
+            ),
+            None => None,
+        };
+
+        Ok(DeviceAuthorization {
+            client_id: OAuthClientId::parse(self.client_id)
+                .map_err(DeviceCodeStoreError::UnexpectedError)?,
+            user_code: UserCode::parse(&self.user_code)
+                .map_err(DeviceCodeStoreError::UnexpectedError)?,
+            scopes: self.scopes,
+            status,
+            last_polled_at,
+            interval: self.interval,
+        })
+    }
+}
+
+const DEVICE_CODE_PREFIX: &str = "device_code:";
+const USER_CODE_PREFIX: &str = "device_user_code:";
+
+fn get_code_key(code: &DeviceCode) -> String {
+    format!("{}{}", DEVICE_CODE_PREFIX, code.as_ref().expose_secret())
+}
+
+fn get_user_code_key(user_code: &UserCode) -> String {
+    format!("{}{}", USER_CODE_PREFIX, user_code.as_ref())
+}
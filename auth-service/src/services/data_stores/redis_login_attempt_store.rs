@@ -0,0 +1,162 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection, Script};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{LoginAttemptStore, LoginAttemptStoreError},
+    FailedAttempts, LoginAttemptKey, FAILED_ATTEMPTS_TTL_SECONDS,
+};
+
+// Counts are shared by every instance of the service, so attackers can't spread their
+// attempts across them. They are changed in single scripts, so concurrent failures can't
+// overwrite each other.
+pub struct RedisLoginAttemptStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisLoginAttemptStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    // Count a failure, returning the failures before and after it
+    async fn count_failure(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<(Option<FailedAttempts>, FailedAttempts), LoginAttemptStoreError> {
+        let now = Utc::now();
+        let (previous_count, previous_last_failed_at, count): (u32, i64, u32) =
+            Script::new(RECORD_FAILURE_SCRIPT)
+                .key(get_key(key))
+                .arg(now.timestamp_millis())
+                .arg(FAILED_ATTEMPTS_TTL_SECONDS)
+                .invoke(&mut *self.conn.write().await)
+                .wrap_err("failed to record failed login attempt in Redis")
+                .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+        let previous = match previous_count {
+            0 => None,
+            count => Some(failed_attempts(count, previous_last_failed_at)?),
+        };
+        let failures = FailedAttempts {
+            count,
+            last_failed_at: now,
+        };
+        Ok((previous, failures))
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for RedisLoginAttemptStore {
+    #[tracing::instrument(name = "Retrieving failed login attempts from Redis", skip_all)]
+    async fn get_failures(
+        &self,
+        key: &LoginAttemptKey,
+    ) -> Result<Option<FailedAttempts>, LoginAttemptStoreError> {
+        let (count, last_failed_at): (Option<u32>, Option<i64>) = self
+            .conn
+            .write()
+            .await
+            .hget(get_key(key), &[COUNT_FIELD, LAST_FAILED_AT_FIELD])
+            .wrap_err("failed to get failed login attempts from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+        match (count, last_failed_at) {
+            (Some(count), Some(last_failed_at)) if count > 0 => {
+                Ok(Some(failed_attempts(count, last_failed_at)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(name = "Recording failed login attempt in Redis", skip_all)]
+    async fn record_failure(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<FailedAttempts, LoginAttemptStoreError> {
+        let (_, failures) = self.count_failure(key).await?;
+        Ok(failures)
+    }
+
+    #[tracing::instrument(name = "Reserving login attempt in Redis", skip_all)]
+    async fn reserve_attempt(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<Option<FailedAttempts>, LoginAttemptStoreError> {
+        let (previous, _) = self.count_failure(key).await?;
+        Ok(previous)
+    }
+
+    #[tracing::instrument(name = "Releasing login attempt in Redis", skip_all)]
+    async fn release_attempt(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<(), LoginAttemptStoreError> {
+        let _: () = Script::new(RELEASE_ATTEMPT_SCRIPT)
+            .key(get_key(key))
+            .invoke(&mut *self.conn.write().await)
+            .wrap_err("failed to release login attempt in Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Clearing failed login attempts from Redis", skip_all)]
+    async fn clear_failures(
+        &mut self,
+        key: &LoginAttemptKey,
+    ) -> Result<(), LoginAttemptStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(get_key(key))
+            .wrap_err("failed to delete failed login attempts from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+        Ok(())
+    }
+}
+
+fn failed_attempts(
+    count: u32,
+    last_failed_at: i64,
+) -> Result<FailedAttempts, LoginAttemptStoreError> {
+    let last_failed_at = DateTime::from_timestamp_millis(last_failed_at)
+        .ok_or(eyre!("invalid failed login attempt timestamp"))
+        .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+    Ok(FailedAttempts {
+        count,
+        last_failed_at,
+    })
+}
+
+// Counts a failure at ARGV[1], a Unix timestamp in milliseconds, and pushes back when they are
+// all forgotten. Returns the count and time of the last failure before it (0 if there were
+// none), then the new count.
+const RECORD_FAILURE_SCRIPT: &str = r#"
+local previous = redis.call('HMGET', KEYS[1], 'count', 'last_failed_at')
+local count = redis.call('HINCRBY', KEYS[1], 'count', 1)
+redis.call('HSET', KEYS[1], 'last_failed_at', ARGV[1])
+redis.call('EXPIRE', KEYS[1], ARGV[2])
+return {tonumber(previous[1]) or 0, tonumber(previous[2]) or 0, count}
+"#;
+
+// Takes one failure back without touching when the last one happened. Counts that have been
+// forgotten in the meantime are left alone.
+const RELEASE_ATTEMPT_SCRIPT: &str = r#"
+if redis.call('HEXISTS', KEYS[1], 'count') == 1 then
+    if redis.call('HINCRBY', KEYS[1], 'count', -1) <= 0 then
+        redis.call('DEL', KEYS[1])
+    end
+end
+"#;
+
+const COUNT_FIELD: &str = "count";
+const LAST_FAILED_AT_FIELD: &str = "last_failed_at";
+const LOGIN_ATTEMPT_PREFIX: &str = "login_attempts:";
+
+fn get_key(key: &LoginAttemptKey) -> String {
+    format!("{}{}", LOGIN_ATTEMPT_PREFIX, key.as_key())
+}
@@ -0,0 +1,77 @@
+use color_eyre::eyre::Context;
+use redis::{Connection, Script};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{RateLimitStore, RateLimitStoreError},
+    RateLimit,
+};
+
+// Buckets are shared by every instance of the service. They are refilled and taken from in a
+// single script, so concurrent requests can't both take the last token.
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(name = "Taking rate limit token in Redis", skip_all)]
+    async fn take_token(
+        &mut self,
+        key: &str,
+        limit: &RateLimit,
+    ) -> Result<(), RateLimitStoreError> {
+        let retry_after: u64 = Script::new(TAKE_TOKEN_SCRIPT)
+            .key(get_key(key))
+            .arg(limit.capacity)
+            .arg(limit.period_milliseconds())
+            .invoke(&mut *self.conn.write().await)
+            .wrap_err("failed to take rate limit token in Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        match retry_after {
+            0 => Ok(()),
+            retry_after => Err(RateLimitStoreError::RateLimitExceeded(retry_after)),
+        }
+    }
+}
+
+// The same token bucket as `RateLimit::take`, timed by the Redis server's clock so every
+// instance agrees. Returns 0 if a token was taken, or the milliseconds until one is available.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local period = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1])
+if tokens == nil then
+    tokens = capacity
+else
+    local elapsed = math.max(0, now - tonumber(bucket[2]))
+    tokens = math.min(capacity, tokens + elapsed * capacity / period)
+end
+
+if tokens < 1 then
+    return math.ceil((1 - tokens) * period / capacity)
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens - 1), 'updated_at', now)
+-- A bucket left alone for a whole period is full again, so it can be forgotten
+redis.call('PEXPIRE', KEYS[1], period)
+return 0
+"#;
+
+const RATE_LIMIT_PREFIX: &str = "rate_limit:";
+
+fn get_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_PREFIX, key)
+}
@@ -0,0 +1,219 @@
+use color_eyre::eyre::{eyre, Context};
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{RefreshToken, RefreshTokenFamily, RefreshTokenStore, RefreshTokenStoreError},
+    OAuthClientId, SessionId, UserId,
+};
+use crate::utils::auth::REFRESH_TOKEN_TTL_SECONDS;
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Storing refresh token in Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        family: RefreshTokenFamily,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let token_key = get_token_key(token.as_ref().expose_secret());
+        let family_key = get_family_key(family.id.as_ref());
+        let data = RefreshTokenData {
+            family_id: family.id.as_ref().to_owned(),
+            user_id: family.user_id.to_string(),
+            client_id: family.client_id.map(|id| id.as_ref().to_owned()),
+            scopes: family.scopes,
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize refresh token data")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let ttl = ttl()?;
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .set_ex(&token_key, serialized_data, ttl)
+            .wrap_err("failed to set refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        // Track the family's tokens so they can all be revoked together.
+        // The family lives as long as its newest token.
+        let _: () = conn
+            .sadd(&family_key, &token_key)
+            .wrap_err("failed to add refresh token to its family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let _: () = conn
+            .expire(&family_key, ttl as i64)
+            .wrap_err("failed to set refresh token family TTL in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming refresh token in Redis", skip_all)]
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError> {
+        let token_key = get_token_key(token.as_ref().expose_secret());
+        let used_key = get_used_key(token.as_ref().expose_secret());
+        let mut conn = self.conn.write().await;
+
+        let data = get_data(&mut conn, &token_key)?;
+        let family = data.to_family()?;
+
+        // Keep the marker around until the token would have expired anyway, so reuse can be
+        // detected
+        let remaining_ttl: i64 = conn
+            .ttl(&token_key)
+            .wrap_err("failed to get refresh token TTL from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        if remaining_ttl <= 0 {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        }
+
+        // Other instances share this Redis, so the token is marked as used with a single SET NX.
+        // Only the first of several concurrent refreshes gets to set it.
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(remaining_ttl as u64));
+        let marked: Option<String> = conn
+            .set_options(&used_key, true, options)
+            .wrap_err("failed to mark refresh token as used in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        match marked {
+            Some(_) => Ok(family),
+            None => Err(RefreshTokenStoreError::TokenReused(family)),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving refresh token family from Redis", skip_all)]
+    async fn get_family(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenFamily, RefreshTokenStoreError> {
+        let token_key = get_token_key(token.as_ref().expose_secret());
+        let mut conn = self.conn.write().await;
+
+        let data = get_data(&mut conn, &token_key)?;
+        let family = data.to_family()?;
+
+        let used: bool = conn
+            .exists(get_used_key(token.as_ref().expose_secret()))
+            .wrap_err("failed to check if refresh token was used in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        match used {
+            true => Err(RefreshTokenStoreError::TokenReused(family)),
+            false => Ok(family),
+        }
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in Redis", skip_all)]
+    async fn revoke_family(&mut self, id: &SessionId) -> Result<(), RefreshTokenStoreError> {
+        let family_key = get_family_key(id.as_ref());
+        let mut conn = self.conn.write().await;
+
+        let mut keys: Vec<String> = conn
+            .smembers(&family_key)
+            .wrap_err("failed to get refresh token family from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let used_keys: Vec<String> = keys
+            .iter()
+            .filter_map(|key| key.strip_prefix(REFRESH_TOKEN_KEY_PREFIX))
+            .map(get_used_key)
+            .collect();
+        keys.extend(used_keys);
+        keys.push(family_key);
+
+        let _: () = conn
+            .del(keys)
+            .wrap_err("failed to delete refresh token family from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenData {
+    family_id: String,
+    user_id: String,
+    client_id: Option<String>,
+    scopes: Vec<String>,
+}
+
+impl RefreshTokenData {
+    fn to_family(&self) -> Result<RefreshTokenFamily, RefreshTokenStoreError> {
+        let id = SessionId::parse(self.family_id.clone())
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let user_id =
+            UserId::parse(self.user_id.clone()).map_err(RefreshTokenStoreError::UnexpectedError)?;
+        match &self.client_id {
+            Some(client_id) => {
+                let client_id = OAuthClientId::parse(client_id.clone())
+                    .map_err(RefreshTokenStoreError::UnexpectedError)?;
+                Ok(RefreshTokenFamily::for_client(
+                    id,
+                    user_id,
+                    client_id,
+                    self.scopes.clone(),
+                ))
+            }
+            None => Ok(RefreshTokenFamily::new(id, user_id)),
+        }
+    }
+}
+
+fn get_data(
+    conn: &mut Connection,
+    token_key: &str,
+) -> Result<RefreshTokenData, RefreshTokenStoreError> {
+    let data: Option<String> = conn
+        .get(token_key)
+        .wrap_err("failed to get refresh token from Redis")
+        .map_err(RefreshTokenStoreError::UnexpectedError)?;
+    let data = data.ok_or(RefreshTokenStoreError::TokenNotFound)?;
+
+    serde_json::from_str(&data)
+        .wrap_err("failed to deserialize refresh token data")
+        .map_err(RefreshTokenStoreError::UnexpectedError)
+}
+
+fn ttl() -> Result<u64, RefreshTokenStoreError> {
+    REFRESH_TOKEN_TTL_SECONDS
+        .try_into()
+        .map_err(|_| eyre!("failed to cast REFRESH_TOKEN_TTL_SECONDS to u64"))
+        .map_err(RefreshTokenStoreError::UnexpectedError)
+}
+
+// We are using key prefixes to prevent collisions and organize data!
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token:";
+const REFRESH_TOKEN_FAMILY_KEY_PREFIX: &str = "refresh_token_family:";
+const USED_REFRESH_TOKEN_KEY_PREFIX: &str = "used_refresh_token:";
+
+fn get_token_key(token: &str) -> String {
+    format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token)
+}
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REFRESH_TOKEN_FAMILY_KEY_PREFIX, family_id)
+}
+
+fn get_used_key(token: &str) -> String {
+    format!("{}{}", USED_REFRESH_TOKEN_KEY_PREFIX, token)
+}
@@ -0,0 +1,100 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::{
+    SingleUseTokenStore, SingleUseTokenStoreError, TokenPurpose, TokenStoreEntry,
+};
+
+pub struct RedisSingleUseTokenStore<K, V> {
+    conn: Arc<RwLock<Connection>>,
+    purpose: TokenPurpose,
+    _entries: PhantomData<fn(K) -> V>,
+}
+
+impl<K, V> RedisSingleUseTokenStore<K, V> {
+    pub fn new(conn: Arc<RwLock<Connection>>, purpose: TokenPurpose) -> Self {
+        Self {
+            conn,
+            purpose,
+            _entries: PhantomData,
+        }
+    }
+
+    fn get_key(&self, key: &K) -> String
+    where
+        K: TokenStoreEntry,
+    {
+        format!("{}{}", key_prefix(self.purpose), key.encode())
+    }
+}
+
+#[async_trait::async_trait]
+impl<K, V> SingleUseTokenStore<K, V> for RedisSingleUseTokenStore<K, V>
+where
+    K: TokenStoreEntry + Send + Sync,
+    V: TokenStoreEntry + Send,
+{
+    #[tracing::instrument(name = "Storing single-use token in Redis", skip_all)]
+    async fn add_token(&mut self, key: K, value: V) -> Result<(), SingleUseTokenStoreError> {
+        let key = self.get_key(&key);
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(key, value.encode(), ttl_seconds(self.purpose))
+            .wrap_err("failed to set single-use token in Redis")
+            .map_err(SingleUseTokenStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing single-use token from Redis", skip_all)]
+    async fn remove_token(&mut self, key: &K) -> Result<(), SingleUseTokenStoreError> {
+        let key = self.get_key(key);
+        let removed: u64 = self
+            .conn
+            .write()
+            .await
+            .del(key)
+            .wrap_err("failed to delete single-use token from Redis")
+            .map_err(SingleUseTokenStoreError::UnexpectedError)?;
+
+        // A token that was already removed must not be usable twice
+        if removed == 0 {
+            return Err(SingleUseTokenStoreError::TokenNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving single-use token from Redis", skip_all)]
+    async fn get_token(&self, key: &K) -> Result<V, SingleUseTokenStoreError> {
+        let key = self.get_key(key);
+
+        match self.conn.write().await.get::<_, String>(&key) {
+            Ok(value) => V::decode(value).map_err(SingleUseTokenStoreError::UnexpectedError),
+            Err(_) => Err(SingleUseTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+fn key_prefix(purpose: TokenPurpose) -> &'static str {
+    match purpose {
+        TokenPurpose::PasswordReset => "password_reset_token:",
+        TokenPurpose::EmailVerification => "email_verification_token:",
+        TokenPurpose::EmailChange => "email_change_token:",
+        TokenPurpose::MagicLink => "magic_link_token:",
+    }
+}
+
+fn ttl_seconds(purpose: TokenPurpose) -> u64 {
+    match purpose {
+        // Reset links stay valid for 15 minutes
+        TokenPurpose::PasswordReset => 900,
+        // Verification and email change links stay valid for 24 hours
+        TokenPurpose::EmailVerification | TokenPurpose::EmailChange => 86_400,
+        // Magic links are good for as long as 2FA codes
+        TokenPurpose::MagicLink => 600,
+    }
+}
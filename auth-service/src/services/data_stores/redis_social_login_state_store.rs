@@ -0,0 +1,105 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{SocialLoginStateStore, SocialLoginStateStoreError},
+    SocialLoginRequest, SocialLoginState, SOCIAL_LOGIN_STATE_TTL_SECONDS,
+};
+
+pub struct RedisSocialLoginStateStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSocialLoginStateStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SocialLoginStateStore for RedisSocialLoginStateStore {
+    #[tracing::instrument(name = "Storing social login state in Redis", skip_all)]
+    async fn add_state(
+        &mut self,
+        state: SocialLoginState,
+        request: SocialLoginRequest,
+    ) -> Result<(), SocialLoginStateStoreError> {
+        let key = get_key(&state);
+        let data = SocialLoginRequestData {
+            provider: request.provider,
+            nonce: request.nonce,
+            code_verifier: request.code_verifier.expose_secret().to_owned(),
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize social login request")
+            .map_err(SocialLoginStateStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(key, serialized_data, SOCIAL_LOGIN_STATE_TTL_SECONDS)
+            .wrap_err("failed to set social login state in Redis")
+            .map_err(SocialLoginStateStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing social login state from Redis", skip_all)]
+    async fn remove_state(
+        &mut self,
+        state: &SocialLoginState,
+    ) -> Result<(), SocialLoginStateStoreError> {
+        let key = get_key(state);
+        let removed: u64 = self
+            .conn
+            .write()
+            .await
+            .del(key)
+            .wrap_err("failed to delete social login state from Redis")
+            .map_err(SocialLoginStateStoreError::UnexpectedError)?;
+
+        if removed == 0 {
+            return Err(SocialLoginStateStoreError::StateNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving social login state from Redis", skip_all)]
+    async fn get_request(
+        &self,
+        state: &SocialLoginState,
+    ) -> Result<SocialLoginRequest, SocialLoginStateStoreError> {
+        let key = get_key(state);
+
+        let data = match self.conn.write().await.get::<_, String>(&key) {
+            Ok(data) => data,
+            Err(_) => return Err(SocialLoginStateStoreError::StateNotFound),
+        };
+        let data: SocialLoginRequestData = serde_json::from_str(&data)
+            .wrap_err("failed to deserialize social login request")
+            .map_err(SocialLoginStateStoreError::UnexpectedError)?;
+
+        Ok(SocialLoginRequest {
+            provider: data.provider,
+            nonce: data.nonce,
+            code_verifier: SecretString::new(data.code_verifier.into_boxed_str()),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SocialLoginRequestData {
+    provider: String,
+    nonce: String,
+    code_verifier: String,
+}
+
+const SOCIAL_LOGIN_STATE_PREFIX: &str = "social_login_state:";
+
+fn get_key(state: &SocialLoginState) -> String {
+    format!("{}{}", SOCIAL_LOGIN_STATE_PREFIX, state.as_ref())
+}
@@ -0,0 +1,130 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{WebAuthnChallengeStore, WebAuthnChallengeStoreError},
+    Email, LoginAttemptId, UserId, WebAuthnCeremony, WebAuthnChallenge,
+    WEBAUTHN_CHALLENGE_TTL_SECONDS,
+};
+
+pub struct RedisWebAuthnChallengeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisWebAuthnChallengeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for RedisWebAuthnChallengeStore {
+    #[tracing::instrument(name = "Storing WebAuthn challenge in Redis", skip_all)]
+    async fn add_challenge(
+        &mut self,
+        challenge: WebAuthnChallenge,
+        ceremony: WebAuthnCeremony,
+    ) -> Result<(), WebAuthnChallengeStoreError> {
+        let key = get_key(&challenge);
+        let data = match ceremony {
+            WebAuthnCeremony::Registration(user_id) => CeremonyData::Registration {
+                user_id: user_id.to_string(),
+            },
+            WebAuthnCeremony::Login => CeremonyData::Login,
+            WebAuthnCeremony::SecondFactor(email, login_attempt_id) => CeremonyData::SecondFactor {
+                email: email.as_ref().expose_secret().to_owned(),
+                login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+            },
+        };
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize WebAuthn ceremony")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(key, serialized_data, WEBAUTHN_CHALLENGE_TTL_SECONDS)
+            .wrap_err("failed to set WebAuthn challenge in Redis")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing WebAuthn challenge from Redis", skip_all)]
+    async fn remove_challenge(
+        &mut self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<(), WebAuthnChallengeStoreError> {
+        let key = get_key(challenge);
+        let removed: u64 = self
+            .conn
+            .write()
+            .await
+            .del(key)
+            .wrap_err("failed to delete WebAuthn challenge from Redis")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        if removed == 0 {
+            return Err(WebAuthnChallengeStoreError::ChallengeNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving WebAuthn challenge from Redis", skip_all)]
+    async fn get_ceremony(
+        &self,
+        challenge: &WebAuthnChallenge,
+    ) -> Result<WebAuthnCeremony, WebAuthnChallengeStoreError> {
+        let key = get_key(challenge);
+
+        let data = match self.conn.write().await.get::<_, String>(&key) {
+            Ok(data) => data,
+            Err(_) => return Err(WebAuthnChallengeStoreError::ChallengeNotFound),
+        };
+        let data: CeremonyData = serde_json::from_str(&data)
+            .wrap_err("failed to deserialize WebAuthn ceremony")
+            .map_err(WebAuthnChallengeStoreError::UnexpectedError)?;
+
+        let parse_email = |email: String| {
+            Email::parse(SecretString::new(email.into_boxed_str()))
+                .map_err(WebAuthnChallengeStoreError::UnexpectedError)
+        };
+        Ok(match data {
+            CeremonyData::Registration { user_id } => WebAuthnCeremony::Registration(
+                UserId::parse(user_id).map_err(WebAuthnChallengeStoreError::UnexpectedError)?,
+            ),
+            CeremonyData::Login => WebAuthnCeremony::Login,
+            CeremonyData::SecondFactor {
+                email,
+                login_attempt_id,
+            } => WebAuthnCeremony::SecondFactor(
+                parse_email(email)?,
+                LoginAttemptId::parse(SecretString::new(login_attempt_id.into_boxed_str()))
+                    .map_err(WebAuthnChallengeStoreError::UnexpectedError)?,
+            ),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "ceremony", rename_all = "snake_case")]
+enum CeremonyData {
+    Registration {
+        user_id: String,
+    },
+    Login,
+    SecondFactor {
+        email: String,
+        login_attempt_id: String,
+    },
+}
+
+const WEBAUTHN_CHALLENGE_PREFIX: &str = "webauthn_challenge:";
+
+fn get_key(challenge: &WebAuthnChallenge) -> String {
+    format!("{}{}", WEBAUTHN_CHALLENGE_PREFIX, challenge.as_ref())
+}
@@ -0,0 +1,176 @@
+use color_eyre::eyre::{eyre, ContextCompat, Result};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use crate::domain::{IdentityProvider, SocialLoginRequest, SocialLoginState};
+
+// Logs users in with external OpenID Connect identity providers, as a relying party. Each
+// provider's endpoints and keys are discovered from its issuer URL every time they are needed.
+pub struct IdentityProviderClient {
+    http_client: Client,
+    providers: Vec<IdentityProvider>,
+}
+
+impl IdentityProviderClient {
+    pub fn new(providers: Vec<IdentityProvider>, http_client: Client) -> Self {
+        Self {
+            http_client,
+            providers,
+        }
+    }
+
+    pub fn get_provider(&self, name: &str) -> Option<&IdentityProvider> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+
+    pub fn provider_names(&self) -> Vec<String> {
+        self.providers
+            .iter()
+            .map(|provider| provider.name.clone())
+            .collect()
+    }
+
+    // Where to send the user to log in with the provider. They come back to `redirect_uri`.
+    #[tracing::instrument(name = "Building identity provider authorization URL", skip_all)]
+    pub async fn authorization_url(
+        &self,
+        provider: &IdentityProvider,
+        redirect_uri: &str,
+        state: &SocialLoginState,
+        request: &SocialLoginRequest,
+    ) -> Result<Url> {
+        let configuration = self.discover(provider).await?;
+
+        let mut url = Url::parse(&configuration.authorization_endpoint)?;
+        url.query_pairs_mut()
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", "openid email")
+            .append_pair("state", state.as_ref())
+            .append_pair("nonce", &request.nonce)
+            .append_pair("code_challenge", &request.code_challenge())
+            .append_pair("code_challenge_method", "S256");
+        Ok(url)
+    }
+
+    // Swap the code the provider sent the user back with for an ID token, and check the token
+    // was issued by the provider, to us, for this login
+    #[tracing::instrument(name = "Logging in with identity provider", skip_all)]
+    pub async fn log_in(
+        &self,
+        provider: &IdentityProvider,
+        redirect_uri: &str,
+        code: &SecretString,
+        request: &SocialLoginRequest,
+    ) -> Result<ExternalIdTokenClaims> {
+        let configuration = self.discover(provider).await?;
+
+        let tokens = self
+            .http_client
+            .post(&configuration.token_endpoint)
+            .basic_auth(
+                &provider.client_id,
+                Some(provider.client_secret.expose_secret()),
+            )
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code.expose_secret()),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", request.code_verifier.expose_secret()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?;
+
+        let header = decode_header(&tokens.id_token)?;
+        // Only the provider's published keys may vouch for a user. An HMAC-signed token could
+        // have been made by anyone holding the client secret.
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(eyre!("ID token is not signed with a public key"));
+        }
+
+        let jwks = self
+            .http_client
+            .get(&configuration.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+        // Providers with a single key may leave out the key id
+        let jwk = match header.kid.as_deref() {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        }
+        .wrap_err("ID token key is not published")?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[configuration.issuer.as_str()]);
+        validation.set_audience(&[provider.client_id.as_str()]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<ExternalIdTokenClaims>(
+            &tokens.id_token,
+            &DecodingKey::from_jwk(jwk)?,
+            &validation,
+        )?
+        .claims;
+
+        if claims.nonce.as_deref() != Some(request.nonce.as_str()) {
+            return Err(eyre!("ID token was not issued for this login"));
+        }
+        Ok(claims)
+    }
+
+    async fn discover(&self, provider: &IdentityProvider) -> Result<ProviderConfiguration> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            provider.issuer.trim_end_matches('/')
+        );
+        let configuration = self
+            .http_client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<ProviderConfiguration>()
+            .await?;
+
+        // OpenID Connect Discovery section 4.3: a provider can only speak for its own issuer
+        if configuration.issuer != provider.issuer {
+            return Err(eyre!("Identity provider issuer mismatch"));
+        }
+        Ok(configuration)
+    }
+}
+
+// The claims we use from an identity provider's ID token
+#[derive(Debug, Deserialize)]
+pub struct ExternalIdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub nonce: Option<String>,
+}
+
+// The parts of an identity provider's OpenID configuration we use
+#[derive(Deserialize)]
+struct ProviderConfiguration {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
@@ -17,4 +17,4 @@ impl EmailClient for MockEmailClient {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,4 @@
-pub mod mock_email_client;
 pub mod data_stores;
-pub mod resend_email_client;
\ No newline at end of file
+pub mod identity_provider_client;
+pub mod mock_email_client;
+pub mod resend_email_client;
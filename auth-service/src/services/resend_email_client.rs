@@ -89,7 +89,7 @@ mod tests {
         Email::parse(SecretString::new(
             SafeEmail().fake::<String>().into_boxed_str(),
         ))
-            .unwrap()
+        .unwrap()
     }
 
     fn email_client(base_url: String) -> ResendEmailClient {
@@ -179,4 +179,4 @@ mod tests {
 
         assert!(outcome.is_err());
     }
-}
\ No newline at end of file
+}
@@ -1,17 +1,27 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
-use crate::app_state::BannedTokenStoreType;
-use crate::domain::email::Email;
+use super::constants::{JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, REFRESH_COOKIE_NAME};
+use crate::app_state::{BannedTokenStoreType, SessionStoreType, SigningKeyStoreType};
+use crate::domain::{
+    AuthAPIError, OAuthClientId, RefreshToken, Role, SessionId, SigningKey, SigningKeyStoreError,
+    User, UserId,
+};
 use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::CookieJar;
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, Header, Validation};
 use secrecy::{ExposeSecret, SecretString};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use uuid::Uuid;
 
-// Create a cookie with a new JWT auth token
+// Create a cookie with a new JWT auth token for the login identified by `session_id`
 #[tracing::instrument(name = "Generate auth cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub async fn generate_auth_cookie(
+    user_id: &UserId,
+    session_id: &SessionId,
+    roles: &[Role],
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user_id, session_id, roles, signing_key_store_type).await?;
     Ok(create_auth_cookie(token))
 }
 
@@ -29,90 +39,556 @@ fn create_auth_cookie(token: SecretString) -> Cookie<'static> {
 // This value determines how long the JWT auth token is valid for
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
+// This value determines how long a refresh token can be swapped for a new JWT auth token
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 2_592_000; // 30 days
+
+// Create a cookie holding a refresh token
+#[tracing::instrument(name = "Create refresh cookie", skip_all)]
+pub fn create_refresh_cookie(token: &RefreshToken) -> Cookie<'static> {
+    Cookie::build((
+        REFRESH_COOKIE_NAME,
+        token.as_ref().expose_secret().to_owned(),
+    ))
+    .path("/")
+    .http_only(true)
+    .same_site(SameSite::Strict) // only ever needed by our own `/refresh` and `/logout` calls
+    .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+    .build()
+}
+
 // Create a JWT auth token
 #[tracing::instrument(name = "Generate auth token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<SecretString> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .wrap_err("failed to create 10 minute time delta")?;
+pub async fn generate_auth_token(
+    user_id: &UserId,
+    session_id: &SessionId,
+    roles: &[Role],
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<SecretString> {
+    let claims = Claims::new(user_id, session_id, roles)?;
+
+    let signing_key = signing_key_store_type
+        .read()
+        .await
+        .get_active_key()
+        .await
+        .wrap_err("failed to get the active signing key")?;
+
+    create_token(&claims, &signing_key)
+}
+
+// Create an OpenID Connect ID token, telling `client_id` which user logged in
+#[tracing::instrument(name = "Generate ID token", skip_all)]
+pub async fn generate_id_token(
+    user: &User,
+    client_id: &OAuthClientId,
+    scopes: &[String],
+    nonce: Option<String>,
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<SecretString> {
+    let iat = Utc::now().timestamp();
+    let (email, email_verified) = match scopes.iter().any(|scope| scope == "email") {
+        true => (
+            Some(user.email.as_ref().expose_secret().to_owned()),
+            Some(user.verified),
+        ),
+        false => (None, None),
+    };
+    let claims = IdTokenClaims {
+        iss: JWT_ISSUER.to_owned(),
+        aud: client_id.as_ref().to_owned(),
+        sub: user.id.to_string(),
+        iat: iat
+            .try_into()
+            .wrap_err("failed to cast iat time to usize")?,
+        exp: (iat + TOKEN_TTL_SECONDS)
+            .try_into()
+            .wrap_err("failed to cast exp time to usize")?,
+        nonce,
+        email,
+        email_verified,
+    };
+
+    let signing_key = signing_key_store_type
+        .read()
+        .await
+        .get_active_key()
+        .await
+        .wrap_err("failed to get the active signing key")?;
 
-    // Create JWT expiration time
-    let exp = Utc::now()
-        .checked_add_signed(delta)
-        .ok_or(eyre!("failed to add 10 minutes to current time"))?
-        .timestamp();
+    create_token(&claims, &signing_key)
+}
+
+// Create an access token for `client_id`, acting for the user with the scopes they granted it in
+// the login `session_id`
+#[tracing::instrument(name = "Generate access token", skip_all)]
+pub async fn generate_access_token(
+    user_id: &UserId,
+    session_id: &SessionId,
+    client_id: &OAuthClientId,
+    scopes: &[String],
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<SecretString> {
+    let iat: usize = Utc::now()
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast iat time to usize")?;
+    let claims = AccessTokenClaims {
+        iss: JWT_ISSUER.to_owned(),
+        aud: client_id.as_ref().to_owned(),
+        sub: user_id.to_string(),
+        client_id: client_id.as_ref().to_owned(),
+        sid: session_id.as_ref().to_owned(),
+        scope: scopes.join(" "),
+        iat,
+        nbf: iat,
+        exp: iat + TOKEN_TTL_SECONDS as usize,
+        jti: Uuid::new_v4().to_string(),
+    };
 
-    // Cast exp to the usize, which is what Claims expects
-    let exp: usize = exp.try_into().wrap_err(format!(
-        "failed to cast exp time to usize. exp time: {}",
-        exp
-    ))?;
+    let signing_key = signing_key_store_type
+        .read()
+        .await
+        .get_active_key()
+        .await
+        .wrap_err("failed to get the active signing key")?;
+
+    create_token(&claims, &signing_key)
+}
 
-    let sub = email.as_ref().expose_secret().to_owned();
+// Create a token for a machine client, good for `scopes` rather than on behalf of any user
+#[tracing::instrument(name = "Generate client token", skip_all)]
+pub async fn generate_client_token(
+    client_id: &OAuthClientId,
+    scopes: &[String],
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<SecretString> {
+    let iat: usize = Utc::now()
+        .timestamp()
+        .try_into()
+        .wrap_err("failed to cast iat time to usize")?;
+    let claims = ClientClaims {
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.to_owned(),
+        sub: client_id.as_ref().to_owned(),
+        client_id: client_id.as_ref().to_owned(),
+        scope: scopes.join(" "),
+        iat,
+        nbf: iat,
+        exp: iat + TOKEN_TTL_SECONDS as usize,
+        jti: Uuid::new_v4().to_string(),
+    };
 
-    let claims = Claims { sub, exp };
+    let signing_key = signing_key_store_type
+        .read()
+        .await
+        .get_active_key()
+        .await
+        .wrap_err("failed to get the active signing key")?;
 
-    create_token(&claims)
+    create_token(&claims, &signing_key)
 }
 
-// Check if a JWT auth token is valid by decoding it using the JWT secret
+// Check if a JWT auth token is valid by decoding it using the key named in its `kid` header,
+// and that the login it belongs to hasn't been revoked
 #[tracing::instrument(name = "Validate token", skip_all)]
 pub async fn validate_token(
     token: &SecretString,
     banned_token_store_type: BannedTokenStoreType,
+    session_store_type: SessionStoreType,
+    signing_key_store_type: SigningKeyStoreType,
 ) -> Result<Claims> {
-    match banned_token_store_type
+    let claims: Claims = decode_token(token, signing_key_store_type, validation).await?;
+    check_not_banned(&claims.jti, banned_token_store_type).await?;
+    check_session(&claims.sid, session_store_type).await?;
+
+    Ok(claims)
+}
+
+// Check if an access token issued to an OAuth client is valid, and that the login the user
+// allowed it in hasn't been revoked. Their audience is the client rather than us, so they are
+// never mistaken for auth tokens, and vice versa.
+#[tracing::instrument(name = "Validate access token", skip_all)]
+pub async fn validate_access_token(
+    token: &SecretString,
+    banned_token_store_type: BannedTokenStoreType,
+    session_store_type: SessionStoreType,
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<AccessTokenClaims> {
+    let claims: AccessTokenClaims =
+        decode_token(token, signing_key_store_type, access_token_validation).await?;
+    if claims.aud != claims.client_id {
+        return Err(eyre!("token was not issued for the client it names"));
+    }
+    check_not_banned(&claims.jti, banned_token_store_type).await?;
+    check_session(&claims.sid, session_store_type).await?;
+
+    Ok(claims)
+}
+
+// Check if a token issued to a machine client with the client credentials grant is valid. These
+// belong to no login, so there is no session to check. User tokens are rejected, and vice versa,
+// since each lacks claims the other requires.
+#[tracing::instrument(name = "Validate client token", skip_all)]
+pub async fn validate_client_token(
+    token: &SecretString,
+    banned_token_store_type: BannedTokenStoreType,
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<ClientClaims> {
+    let claims: ClientClaims = decode_token(token, signing_key_store_type, validation).await?;
+    check_not_banned(&claims.jti, banned_token_store_type).await?;
+
+    Ok(claims)
+}
+
+// Decode a token using the key named in its `kid` header
+async fn decode_token<T: DeserializeOwned>(
+    token: &SecretString,
+    signing_key_store_type: SigningKeyStoreType,
+    validation: fn(Algorithm) -> Validation,
+) -> Result<T> {
+    // Retired keys still verify the tokens they signed until those have expired
+    let kid = decode_header(token.expose_secret())
+        .wrap_err("failed to decode token header")?
+        .kid
+        .ok_or(eyre!("token has no key id"))?;
+    let signing_key = signing_key_store_type
         .read()
         .await
-        .contains_token(token)
+        .get_key(&kid)
+        .await
+        .wrap_err("token was not signed with a current key")?;
+
+    decode::<T>(
+        token.expose_secret(),
+        &signing_key.decoding_key()?,
+        &validation(signing_key.algorithm),
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode token")
+}
+
+// Touching the session also records it as recently active
+async fn check_session(sid: &str, session_store_type: SessionStoreType) -> Result<()> {
+    let session_id = SessionId::parse(sid.to_owned())?;
+    session_store_type
+        .write()
+        .await
+        .touch_session(&session_id)
+        .await
+        .wrap_err("session is no longer active")
+}
+
+async fn check_not_banned(jti: &str, banned_token_store_type: BannedTokenStoreType) -> Result<()> {
+    if banned_token_store_type
+        .read()
         .await
+        .contains_token_id(jti)
+        .await?
     {
-        Ok(value) => {
-            if value {
-                return Err(eyre!("token is banned"));
-            }
-        }
-        Err(e) => return Err(e.into()),
+        return Err(eyre!("token is banned"));
     }
+    Ok(())
+}
 
-    decode::<Claims>(
-        token.expose_secret(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
+// Authenticate a request from the JWT cookie in `jar`, for routes that act on the logged-in user
+#[tracing::instrument(name = "Validate auth cookie", skip_all)]
+pub async fn validate_auth_cookie(
+    jar: &CookieJar,
+    banned_token_store_type: BannedTokenStoreType,
+    session_store_type: SessionStoreType,
+    signing_key_store_type: SigningKeyStoreType,
+) -> Result<Claims, AuthAPIError> {
+    let cookie = jar.get(JWT_COOKIE_NAME).ok_or(AuthAPIError::MissingToken)?;
+    let token = SecretString::new(cookie.value().to_owned().into_boxed_str());
+
+    validate_token(
+        &token,
+        banned_token_store_type,
+        session_store_type,
+        signing_key_store_type,
     )
-        .map(|data| data.claims)
-        .wrap_err("failed to decode token")
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)
 }
 
-// Create a JWT auth token by encoding claims using the JWT secret
+// Create a JWT by encoding claims using `signing_key`, named in the `kid` header
 #[tracing::instrument(name = "Create token", skip_all)]
-fn create_token(claims: &Claims) -> Result<SecretString> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
+fn create_token<T: Serialize>(claims: &T, signing_key: &SigningKey) -> Result<SecretString> {
+    let mut header = Header::new(signing_key.algorithm);
+    header.kid = Some(signing_key.kid.clone());
+
+    encode(&header, &claims, &signing_key.encoding_key()?)
         .map(|value: String| SecretString::new(value.into_boxed_str()))
         .wrap_err("failed to create token")
 }
 
+// Seed the signing key store with the configured key the first time the service starts.
+// Afterwards the configured key must be the active one: a changed key is only put to use by
+// `rotate_signing_key`, and silently signing with another key than configured would be confusing.
+#[tracing::instrument(name = "Init signing key", skip_all)]
+pub async fn init_signing_key(
+    signing_key_store_type: SigningKeyStoreType,
+    signing_key: SigningKey,
+) -> Result<()> {
+    match signing_key_store_type.read().await.get_active_key().await {
+        Ok(active_key) if active_key.same_key_as(&signing_key) => return Ok(()),
+        Ok(active_key) => {
+            return Err(eyre!(
+                "the configured {:?} signing key is not the active key {} ({:?}), \
+                run `auth-service rotate-signing-key` to start using it",
+                signing_key.algorithm,
+                active_key.kid,
+                active_key.algorithm
+            ));
+        }
+        Err(SigningKeyStoreError::SigningKeyNotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    promote_signing_key(signing_key_store_type, signing_key).await
+}
+
+// Start signing new tokens with `signing_key`. The previously active key is retired: it keeps
+// verifying the tokens it already signed until they have expired, and is then dropped.
+#[tracing::instrument(name = "Rotate signing key", skip_all)]
+pub async fn rotate_signing_key(
+    signing_key_store_type: SigningKeyStoreType,
+    signing_key: SigningKey,
+) -> Result<()> {
+    match signing_key_store_type.read().await.get_active_key().await {
+        Ok(active_key) if active_key.same_key_as(&signing_key) => {
+            return Err(eyre!("signing key {} is already active", active_key.kid));
+        }
+        Ok(_) | Err(SigningKeyStoreError::SigningKeyNotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    promote_signing_key(signing_key_store_type, signing_key).await
+}
+
+async fn promote_signing_key(
+    signing_key_store_type: SigningKeyStoreType,
+    signing_key: SigningKey,
+) -> Result<()> {
+    // Tokens are accepted for a little while past `exp` to allow for clock skew
+    let retire_for = TOKEN_TTL_SECONDS + Validation::default().leeway as i64;
+    let retire_until = Utc::now()
+        + chrono::Duration::try_seconds(retire_for).wrap_err("failed to create time delta")?;
+
+    signing_key_store_type
+        .write()
+        .await
+        .promote_key(signing_key, retire_until)
+        .await
+        .wrap_err("failed to promote signing key")
+}
+
+// Tokens are only accepted if they were issued by us, for our audience, and are currently valid
+fn validation(algorithm: Algorithm) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+    validation.set_required_spec_claims(&["iss", "aud", "sub", "nbf", "exp"]);
+    validation.validate_nbf = true;
+    validation
+}
+
+// Access tokens are for whichever client they were issued to, which the caller checks against the
+// token's own `client_id` claim
+fn access_token_validation(algorithm: Algorithm) -> Validation {
+    let mut validation = validation(algorithm);
+    validation.validate_aud = false;
+    validation
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
+    pub iss: String,
+    pub aud: String,
+    // The id of the user the token was issued to
+    pub sub: String,
+    // The id of the session this token was issued for
+    pub sid: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+    // Unique to every token, so a single token can be banned
+    pub jti: String,
+    // What the user is authorized to do, as of when the token was issued
+    pub roles: Vec<String>,
+}
+
+impl Claims {
+    fn new(user_id: &UserId, session_id: &SessionId, roles: &[Role]) -> Result<Self> {
+        let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
+            .wrap_err("failed to create 10 minute time delta")?;
+
+        let now = Utc::now();
+
+        // Create JWT expiration time
+        let exp = now
+            .checked_add_signed(delta)
+            .ok_or(eyre!("failed to add 10 minutes to current time"))?
+            .timestamp();
+
+        // Cast exp to the usize, which is what Claims expects
+        let exp: usize = exp.try_into().wrap_err(format!(
+            "failed to cast exp time to usize. exp time: {}",
+            exp
+        ))?;
+
+        let iat: usize = now
+            .timestamp()
+            .try_into()
+            .wrap_err("failed to cast iat time to usize")?;
+
+        Ok(Self {
+            iss: JWT_ISSUER.to_owned(),
+            aud: JWT_AUDIENCE.to_owned(),
+            sub: user_id.to_string(),
+            sid: session_id.as_ref().to_owned(),
+            iat,
+            nbf: iat,
+            exp,
+            jti: Uuid::new_v4().to_string(),
+            roles: roles.iter().map(|role| role.as_ref().to_owned()).collect(),
+        })
+    }
+
+    pub fn has_role(&self, role: &Role) -> bool {
+        self.roles.iter().any(|r| r == role.as_ref())
+    }
+}
+
+// The claims of an access token issued to an OAuth client the user allowed, as in RFC 9068. They
+// carry the scopes the user granted the client, but none of the user's roles.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    pub iss: String,
+    // The client the token was issued to, so no other service accepts it
+    pub aud: String,
+    // The id of the user the client acts for
+    pub sub: String,
+    pub client_id: String,
+    // The id of the session the user allowed the client in
+    pub sid: String,
+    // What the user allowed the client to do, space separated
+    pub scope: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+    pub jti: String,
+}
+
+impl AccessTokenClaims {
+    pub fn scopes(&self) -> Vec<String> {
+        self.scope
+            .split_whitespace()
+            .map(|s| s.to_owned())
+            .collect()
+    }
+}
+
+// The claims of a token issued to a machine client with the client credentials grant, as in
+// RFC 9068. There is no user or login behind them, so no `sid` or `roles`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientClaims {
+    pub iss: String,
+    pub aud: String,
+    // The id of the client, as in `client_id`
+    pub sub: String,
+    pub client_id: String,
+    // What the client may do, space separated
+    pub scope: String,
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+    pub jti: String,
+}
+
+impl ClientClaims {
+    pub fn scopes(&self) -> Vec<String> {
+        self.scope
+            .split_whitespace()
+            .map(|s| s.to_owned())
+            .collect()
+    }
+}
+
+// The claims of an OpenID Connect ID token. Unlike auth tokens they are meant for the client, so
+// their audience is its client id and they can't be used to call us.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub aud: String,
+    // The id of the user who logged in
     pub sub: String,
+    pub iat: usize,
     pub exp: usize,
+    // The nonce the client sent to `/authorize`, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    // Only for clients granted the `email` scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::BannedTokenStore;
-    use crate::services::data_stores::HashsetBannedTokenStore;
+    use crate::domain::{
+        BannedTokenStore, Email, HashedPassword, Session, SessionStore, TwoFAMethod,
+    };
+    use crate::services::data_stores::{
+        HashmapSessionStore, HashmapSigningKeyStore, HashsetBannedTokenStore,
+    };
     use std::sync::Arc;
     use tokio::sync::RwLock;
 
+    const USER_ID: &str = "0b8f6a3e-8a4e-4f4b-9a51-3c2d1e0f9a7b";
+
+    fn user_id() -> UserId {
+        UserId::parse(USER_ID.to_owned()).unwrap()
+    }
+
+    fn email() -> Email {
+        Email::parse(SecretString::new(
+            "test@example.com".to_owned().into_boxed_str(),
+        ))
+        .unwrap()
+    }
+
+    // A session store that knows about a single active session
+    async fn session_store() -> (SessionId, SessionStoreType) {
+        let session = Session::new(user_id(), None, None);
+        let id = session.id.clone();
+        let mut store = HashmapSessionStore::default();
+        store.add_session(session).await.unwrap();
+        (id, Arc::new(RwLock::new(store)))
+    }
+
+    fn signing_key(secret: &str) -> SigningKey {
+        SigningKey::from_secret(SecretString::new(secret.to_owned().into_boxed_str()))
+    }
+
+    // A signing key store with a single active key
+    async fn signing_key_store() -> SigningKeyStoreType {
+        let store: SigningKeyStoreType = Arc::new(RwLock::new(HashmapSigningKeyStore::default()));
+        init_signing_key(store.clone(), signing_key("secret"))
+            .await
+            .unwrap();
+        store
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
-        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let signing_key_store = signing_key_store().await;
+        let cookie =
+            generate_auth_cookie(&user_id(), &SessionId::default(), &[], signing_key_store)
+                .await
+                .unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -133,20 +609,42 @@ mod tests {
 
     #[tokio::test]
     async fn test_generate_auth_token() {
-        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let signing_key_store = signing_key_store().await;
+        let result = generate_auth_token(
+            &user_id(),
+            &SessionId::default(),
+            &[],
+            signing_key_store.clone(),
+        )
+        .await
+        .unwrap();
         assert_eq!(result.expose_secret().split('.').count(), 3);
+
+        let active_key = signing_key_store
+            .read()
+            .await
+            .get_active_key()
+            .await
+            .unwrap();
+        let header = decode_header(result.expose_secret()).unwrap();
+        assert_eq!(header.kid, Some(active_key.kid));
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let token = generate_auth_token(&user_id(), &session_id, &[], signing_key_store.clone())
+            .await
+            .unwrap();
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
 
-        let result = validate_token(&token, banned_token_store).await.unwrap();
+        let result = validate_token(&token, banned_token_store, session_store, signing_key_store)
+            .await
+            .unwrap();
 
-        assert_eq!(result.sub, "test@example.com");
+        assert_eq!(result.sub, USER_ID);
+        assert_eq!(result.sid, session_id.as_ref());
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -160,19 +658,393 @@ mod tests {
     async fn test_validate_token_with_invalid_token() {
         let token = SecretString::new("invalid_token".to_owned().into_boxed_str());
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let (_, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let result =
+            validate_token(&token, banned_token_store, session_store, signing_key_store).await;
         // assert that the token is not valid and compare the error
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
-        let email = Email::parse(SecretString::new("test@example.com".to_owned().into_boxed_str())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let token = generate_auth_token(&user_id(), &session_id, &[], signing_key_store.clone())
+            .await
+            .unwrap();
         let mut hs = HashsetBannedTokenStore::default();
-        hs.add_token(token.to_owned()).await.unwrap();
+        let jti = jsonwebtoken::dangerous::insecure_decode::<Claims>(token.expose_secret())
+            .unwrap()
+            .claims
+            .jti;
+        hs.add_token_id(&jti).await.unwrap();
         let banned_token_store = Arc::new(RwLock::new(hs));
-        let result = validate_token(&token, banned_token_store).await;
+        let result =
+            validate_token(&token, banned_token_store, session_store, signing_key_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_revoked_session() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let token = generate_auth_token(&user_id(), &session_id, &[], signing_key_store.clone())
+            .await
+            .unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        session_store
+            .write()
+            .await
+            .delete_session(&user_id(), &session_id)
+            .await
+            .unwrap();
+
+        let result =
+            validate_token(&token, banned_token_store, session_store, signing_key_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_after_key_rotation() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let old_token =
+            generate_auth_token(&user_id(), &session_id, &[], signing_key_store.clone())
+                .await
+                .unwrap();
+
+        rotate_signing_key(signing_key_store.clone(), signing_key("new secret"))
+            .await
+            .unwrap();
+        let new_token =
+            generate_auth_token(&user_id(), &session_id, &[], signing_key_store.clone())
+                .await
+                .unwrap();
+
+        assert_ne!(
+            decode_header(old_token.expose_secret()).unwrap().kid,
+            decode_header(new_token.expose_secret()).unwrap().kid
+        );
+
+        // Tokens signed with the retired key stay valid until they expire
+        for token in [&old_token, &new_token] {
+            let result = validate_token(
+                token,
+                banned_token_store.clone(),
+                session_store.clone(),
+                signing_key_store.clone(),
+            )
+            .await;
+            assert!(result.is_ok());
+        }
+
+        // Once a retired key has expired, its tokens are rejected
+        signing_key_store
+            .write()
+            .await
+            .promote_key(signing_key("newest secret"), Utc::now())
+            .await
+            .unwrap();
+
+        let result = validate_token(
+            &new_token,
+            banned_token_store,
+            session_store,
+            signing_key_store,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_without_key_id() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        // Signed with the active key, but not saying which key that is
+        let claims = Claims::new(&user_id(), &session_id, &[]).unwrap();
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &signing_key("secret").encoding_key().unwrap(),
+        )
+        .unwrap();
+        let token = SecretString::new(token.into_boxed_str());
+
+        let result =
+            validate_token(&token, banned_token_store, session_store, signing_key_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_init_signing_key_accepts_the_active_key() {
+        let signing_key_store = signing_key_store().await;
+
+        let result = init_signing_key(signing_key_store.clone(), signing_key("secret")).await;
+        assert!(result.is_ok());
+
+        let keys = signing_key_store.read().await.get_keys().await.unwrap();
+        assert_eq!(keys.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_init_signing_key_rejects_another_key_and_keeps_the_active_key() {
+        let signing_key_store = signing_key_store().await;
+        let active_key = signing_key_store
+            .read()
+            .await
+            .get_active_key()
+            .await
+            .unwrap();
+
+        let result = init_signing_key(signing_key_store.clone(), signing_key("new secret")).await;
+        assert!(result.is_err());
+
+        let result = signing_key_store.read().await.get_active_key().await;
+        assert_eq!(result, Ok(active_key));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_signing_key_rejects_the_active_key() {
+        let signing_key_store = signing_key_store().await;
+
+        let result = rotate_signing_key(signing_key_store.clone(), signing_key("secret")).await;
+        assert!(result.is_err());
+
+        let keys = signing_key_store.read().await.get_keys().await.unwrap();
+        assert_eq!(keys.len(), 1);
+    }
+
+    // Sign `claims` with the active key of `signing_key_store`, as we would for a real token
+    async fn sign(claims: &Claims, signing_key_store: &SigningKeyStoreType) -> SecretString {
+        let signing_key = signing_key_store
+            .read()
+            .await
+            .get_active_key()
+            .await
+            .unwrap();
+        create_token(claims, &signing_key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_token_claims() {
+        let session_id = SessionId::default();
+        let first = Claims::new(&user_id(), &session_id, &[]).unwrap();
+        let second = Claims::new(&user_id(), &session_id, &[]).unwrap();
+
+        assert_eq!(first.iss, *JWT_ISSUER);
+        assert_eq!(first.aud, *JWT_AUDIENCE);
+        assert_eq!(first.nbf, first.iat);
+        assert_eq!(first.exp, first.iat + TOKEN_TTL_SECONDS as usize);
+        // Every token can be told apart, even for the same session
+        assert_ne!(first.jti, second.jti);
+    }
+
+    #[tokio::test]
+    async fn test_generate_id_token() {
+        let signing_key_store = signing_key_store().await;
+        let password = HashedPassword::parse(SecretString::new("password123".into()))
+            .await
+            .unwrap();
+        let user = User::new(email(), password, TwoFAMethod::None);
+        let client_id = OAuthClientId::default();
+
+        let token = generate_id_token(
+            &user,
+            &client_id,
+            &["openid".to_owned(), "email".to_owned()],
+            Some("n-0S6_WzA2Mj".to_owned()),
+            signing_key_store,
+        )
+        .await
+        .unwrap();
+
+        let mut id_token_validation = Validation::new(Algorithm::HS256);
+        id_token_validation.set_audience(&[client_id.as_ref()]);
+        let claims = decode::<IdTokenClaims>(
+            token.expose_secret(),
+            &signing_key("secret").decoding_key().unwrap(),
+            &id_token_validation,
+        )
+        .unwrap()
+        .claims;
+
+        assert_eq!(claims.iss, *JWT_ISSUER);
+        assert_eq!(claims.sub, user.id.to_string());
+        assert_eq!(claims.nonce, Some("n-0S6_WzA2Mj".to_owned()));
+        assert_eq!(claims.email, Some("test@example.com".to_owned()));
+        assert_eq!(claims.email_verified, Some(false));
+        // ID tokens are for the client, not for calling us
+        let result = decode::<Claims>(
+            token.expose_secret(),
+            &signing_key("secret").decoding_key().unwrap(),
+            &validation(Algorithm::HS256),
+        );
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_returns_roles() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let support = Role::parse("support".to_owned()).unwrap();
+
+        let token = generate_auth_token(
+            &user_id(),
+            &session_id,
+            &[Role::admin(), support.clone()],
+            signing_key_store.clone(),
+        )
+        .await
+        .unwrap();
+
+        let claims = validate_token(&token, banned_token_store, session_store, signing_key_store)
+            .await
+            .unwrap();
+
+        assert_eq!(claims.roles, vec!["admin", "support"]);
+        assert!(claims.has_role(&Role::admin()));
+        assert!(claims.has_role(&support));
+        assert!(!claims.has_role(&Role::parse("billing".to_owned()).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_wrong_issuer_or_audience() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let mut wrong_issuer = Claims::new(&user_id(), &session_id, &[]).unwrap();
+        wrong_issuer.iss = "https://other-issuer.example.com".to_owned();
+        let mut wrong_audience = Claims::new(&user_id(), &session_id, &[]).unwrap();
+        wrong_audience.aud = "other-service".to_owned();
+
+        for claims in [wrong_issuer, wrong_audience] {
+            let token = sign(&claims, &signing_key_store).await;
+            let result = validate_token(
+                &token,
+                banned_token_store.clone(),
+                session_store.clone(),
+                signing_key_store.clone(),
+            )
+            .await;
+            assert!(result.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_not_yet_valid() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let mut claims = Claims::new(&user_id(), &session_id, &[]).unwrap();
+        claims.nbf += 3600;
+        let token = sign(&claims, &signing_key_store).await;
+
+        let result =
+            validate_token(&token, banned_token_store, session_store, signing_key_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_access_token() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let client_id = OAuthClientId::default();
+        let scopes = vec!["openid".to_owned(), "email".to_owned()];
+
+        let access_token = generate_access_token(
+            &user_id(),
+            &session_id,
+            &client_id,
+            &scopes,
+            signing_key_store.clone(),
+        )
+        .await
+        .unwrap();
+        let claims = validate_access_token(
+            &access_token,
+            banned_token_store.clone(),
+            session_store.clone(),
+            signing_key_store.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.sub, USER_ID);
+        assert_eq!(claims.aud, client_id.as_ref());
+        assert_eq!(claims.sid, session_id.as_ref());
+        assert_eq!(claims.scopes(), scopes);
+
+        // Access tokens can't stand in for auth tokens, or the other way around
+        let result = validate_token(
+            &access_token,
+            banned_token_store.clone(),
+            session_store.clone(),
+            signing_key_store.clone(),
+        )
+        .await;
+        assert!(result.is_err());
+        let user_token =
+            generate_auth_token(&user_id(), &session_id, &[], signing_key_store.clone())
+                .await
+                .unwrap();
+        let result = validate_access_token(
+            &user_token,
+            banned_token_store,
+            session_store,
+            signing_key_store,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_client_token() {
+        let (session_id, session_store) = session_store().await;
+        let signing_key_store = signing_key_store().await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let client_id = OAuthClientId::default();
+        let scopes = vec!["orders:read".to_owned(), "orders:write".to_owned()];
+
+        let client_token = generate_client_token(&client_id, &scopes, signing_key_store.clone())
+            .await
+            .unwrap();
+        let claims = validate_client_token(
+            &client_token,
+            banned_token_store.clone(),
+            signing_key_store.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.client_id, client_id.as_ref());
+        assert_eq!(claims.scopes(), scopes);
+
+        // Neither kind of token passes for the other
+        let user_token =
+            generate_auth_token(&user_id(), &session_id, &[], signing_key_store.clone())
+                .await
+                .unwrap();
+        let result = validate_client_token(
+            &user_token,
+            banned_token_store.clone(),
+            signing_key_store.clone(),
+        )
+        .await;
+        assert!(result.is_err());
+        let result = validate_token(
+            &client_token,
+            banned_token_store,
+            session_store,
+            signing_key_store,
+        )
+        .await;
         assert!(result.is_err());
     }
 }
@@ -0,0 +1,38 @@
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::{header::USER_AGENT, request::Parts};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+// Describes the device a request came from, so it can be shown on the user's session list
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        // The service is exposed directly rather than behind a proxy,
+        // so the peer address is the client's address
+        let ip_address = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        Ok(Self {
+            user_agent,
+            ip_address,
+        })
+    }
+}
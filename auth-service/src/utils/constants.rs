@@ -1,20 +1,64 @@
+use crate::domain::{IdentityProvider, RateLimit, SigningKey};
+use crate::utils::rate_limit::{RateLimitKey, RouteRateLimit};
 use dotenvy::dotenv;
+use jsonwebtoken::Algorithm;
 use lazy_static::lazy_static;
 use secrecy::SecretString;
 use std::env as std_env;
 
 // Define lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
 lazy_static! {
-    pub static ref JWT_SECRET: SecretString = set_token();
+    pub static ref JWT_SIGNING_KEY: SigningKey = set_jwt_signing_key();
     pub static ref DATABASE_URL: SecretString = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
     pub static ref RESEND_API_KEY: SecretString = set_resend_auth_token();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref WEBAUTHN_ORIGIN: String = set_webauthn_origin();
+    pub static ref WEBAUTHN_RP_ID: String = set_webauthn_rp_id();
+    pub static ref WEBAUTHN_ENABLED: bool = set_webauthn_enabled();
+    pub static ref JWT_ISSUER: String = set_jwt_issuer();
+    pub static ref JWT_AUDIENCE: String = set_jwt_audience();
+    pub static ref TOTP_ENCRYPTION_KEY: SecretString = set_totp_encryption_key();
+    pub static ref SIGNING_KEY_ENCRYPTION_KEY: SecretString = set_signing_key_encryption_key();
+    pub static ref INTROSPECTION_CLIENT_ID: String = set_introspection_client_id();
+    pub static ref INTROSPECTION_CLIENT_SECRET: Option<SecretString> =
+        set_introspection_client_secret();
+    pub static ref IDENTITY_PROVIDERS: Vec<IdentityProvider> = set_identity_providers();
 }
 
-fn set_token() -> SecretString {
+// HS256 with `JWT_SECRET` unless `JWT_ALGORITHM` asks for RS256 or EdDSA,
+// in which case the key pair is read from the PEM files at `JWT_PRIVATE_KEY_PATH` and `JWT_PUBLIC_KEY_PATH`.
+// Tokens are signed with the active key in the signing key store. The configured key seeds it
+// on first start, and replaces it when an admin rotates keys.
+fn set_jwt_signing_key() -> SigningKey {
     dotenv().ok();
-    let secret =
-        std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET environment variable must be set");
+    let algorithm = std_env::var(env::JWT_ALGORITHM_ENV_VAR).unwrap_or_default();
+    let algorithm = match algorithm.as_str() {
+        "" | "HS256" => return SigningKey::from_secret(read_jwt_secret()),
+        "RS256" => Algorithm::RS256,
+        "EdDSA" => Algorithm::EdDSA,
+        _ => panic!("JWT_ALGORITHM must be one of HS256, RS256 or EdDSA"),
+    };
+
+    let read_key = |env_var: &str| {
+        let path = std_env::var(env_var).unwrap_or_else(|_| {
+            panic!(
+                "{} must be set when JWT_ALGORITHM is {:?}",
+                env_var, algorithm
+            )
+        });
+        std::fs::read(&path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e))
+    };
+    let private_key = read_key(env::JWT_PRIVATE_KEY_PATH_ENV_VAR);
+    let public_key = read_key(env::JWT_PUBLIC_KEY_PATH_ENV_VAR);
+
+    SigningKey::from_pem(algorithm, &private_key, &public_key).expect("Failed to load JWT keys")
+}
+
+// Only HS256 signs with a shared secret, so only it needs `JWT_SECRET`
+fn read_jwt_secret() -> SecretString {
+    let secret = std_env::var(env::JWT_SECRET_ENV_VAR)
+        .expect("JWT_SECRET must be set when JWT_ALGORITHM is HS256");
     if secret.is_empty() {
         panic!("JWT_SECRET must not be empty");
     }
@@ -43,16 +87,333 @@ fn set_resend_auth_token() -> SecretString {
     )
 }
 
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+}
+
+// Browsers only hand passkeys to the page they were registered on, so WebAuthn ceremonies are
+// tied to an origin and a host name. Both default to those of `AUTH_SERVICE_URL`.
+fn set_webauthn_origin() -> String {
+    dotenv().ok();
+    match std_env::var(env::WEBAUTHN_ORIGIN_ENV_VAR) {
+        // Left as is if it doesn't parse, for `WEBAUTHN_ENABLED` to report
+        Ok(origin) if !origin.is_empty() => reqwest::Url::parse(&origin)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or(origin),
+        _ => reqwest::Url::parse(&AUTH_SERVICE_URL)
+            .expect("AUTH_SERVICE_URL must be a valid URL")
+            .origin()
+            .ascii_serialization(),
+    }
+}
+
+fn set_webauthn_rp_id() -> String {
+    dotenv().ok();
+    match std_env::var(env::WEBAUTHN_RP_ID_ENV_VAR) {
+        Ok(rp_id) if !rp_id.is_empty() => rp_id,
+        _ => reqwest::Url::parse(&WEBAUTHN_ORIGIN)
+            .ok()
+            .and_then(|origin| origin.host_str().map(str::to_owned))
+            .unwrap_or_default(),
+    }
+}
+
+// Passkeys are turned off, rather than the service refusing to start, when the relying party
+// can't work in a browser. Everything else keeps running on e.g. a plain HTTP IP address.
+fn set_webauthn_enabled() -> bool {
+    let result = reqwest::Url::parse(&WEBAUTHN_ORIGIN)
+        .map_err(|e| format!("{} is not a valid URL: {}", *WEBAUTHN_ORIGIN, e))
+        .and_then(|origin| check_webauthn_relying_party(&origin, &WEBAUTHN_RP_ID));
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::error!(
+                "Passkeys are disabled, set WEBAUTHN_ORIGIN and WEBAUTHN_RP_ID to enable them: {}",
+                e
+            );
+            false
+        }
+    }
+}
+
+// Browsers refuse WebAuthn unless the page is a secure context, i.e. served over HTTPS or from
+// localhost, and the RP ID is a domain name the page's host is, or is under
+fn check_webauthn_relying_party(origin: &reqwest::Url, rp_id: &str) -> Result<(), String> {
+    let host = origin
+        .domain()
+        .ok_or_else(|| format!("{} doesn't have a domain name", origin))?;
+    if rp_id.parse::<std::net::IpAddr>().is_ok() {
+        return Err(format!("{} is an IP address, not a domain name", rp_id));
+    }
+    if origin.scheme() != "https" && host != "localhost" {
+        return Err(format!("{} must use https", origin));
+    }
+    if host != rp_id && !host.ends_with(&format!(".{}", rp_id)) {
+        return Err(format!(
+            "{} is not {} or one of its subdomains",
+            host, rp_id
+        ));
+    }
+    Ok(())
+}
+
+// The `iss` claim of our JWT auth tokens. Defaults to the service's own URL.
+fn set_jwt_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_ISSUER_ENV_VAR).unwrap_or(AUTH_SERVICE_URL.to_owned())
+}
+
+// The `aud` claim of our JWT auth tokens: tokens minted for another audience are rejected
+fn set_jwt_audience() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_AUDIENCE_ENV_VAR).unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned())
+}
+
+// Encrypts users' TOTP secrets at rest
+fn set_totp_encryption_key() -> SecretString {
+    set_encryption_key(env::TOTP_ENCRYPTION_KEY_ENV_VAR)
+}
+
+// Encrypts the private keys tokens are signed with at rest
+fn set_signing_key_encryption_key() -> SecretString {
+    set_encryption_key(env::SIGNING_KEY_ENCRYPTION_KEY_ENV_VAR)
+}
+
+fn set_encryption_key(env_var: &str) -> SecretString {
+    dotenv().ok();
+    let key = std_env::var(env_var)
+        .unwrap_or_else(|_| panic!("{} environment variable must be set", env_var));
+    if key.is_empty() {
+        panic!("{} must not be empty", env_var);
+    }
+    SecretString::new(key.into_boxed_str())
+}
+
+// The resource server allowed to call `/introspect`
+fn set_introspection_client_id() -> String {
+    dotenv().ok();
+    std_env::var(env::INTROSPECTION_CLIENT_ID_ENV_VAR)
+        .unwrap_or(DEFAULT_INTROSPECTION_CLIENT_ID.to_owned())
+}
+
+// `/introspect` rejects every client while this is unset
+fn set_introspection_client_secret() -> Option<SecretString> {
+    dotenv().ok();
+    std_env::var(env::INTROSPECTION_CLIENT_SECRET_ENV_VAR)
+        .ok()
+        .filter(|secret| !secret.is_empty())
+        .map(|secret| SecretString::new(secret.into_boxed_str()))
+}
+
+// The identity providers users can log in with, as a JSON list of
+// `{"name", "issuer", "client_id", "client_secret"}` objects. Social login is off while unset.
+fn set_identity_providers() -> Vec<IdentityProvider> {
+    dotenv().ok();
+    match std_env::var(env::IDENTITY_PROVIDERS_ENV_VAR) {
+        Ok(providers) if !providers.is_empty() => serde_json::from_str(&providers)
+            .unwrap_or_else(|e| panic!("IDENTITY_PROVIDERS must be a valid JSON list: {}", e)),
+        _ => vec![],
+    }
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_ALGORITHM_ENV_VAR: &str = "JWT_ALGORITHM";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_PUBLIC_KEY_PATH_ENV_VAR: &str = "JWT_PUBLIC_KEY_PATH";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const RESEND_AUTH_TOKEN_ENV_VAR: &str = "RESEND_API_KEY";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const SIGNING_KEY_ENCRYPTION_KEY_ENV_VAR: &str = "SIGNING_KEY_ENCRYPTION_KEY";
+    pub const INTROSPECTION_CLIENT_ID_ENV_VAR: &str = "INTROSPECTION_CLIENT_ID";
+    pub const INTROSPECTION_CLIENT_SECRET_ENV_VAR: &str = "INTROSPECTION_CLIENT_SECRET";
+    pub const IDENTITY_PROVIDERS_ENV_VAR: &str = "IDENTITY_PROVIDERS";
 }
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_COOKIE_NAME: &str = "refresh_token";
+pub const SOCIAL_LOGIN_COOKIE_NAME: &str = "social_login_state";
 
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1"; // New!
 
+// Base URL used to build the links we send out by email
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+
+// Who our JWT auth tokens are meant for
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+pub const DEFAULT_INTROSPECTION_CLIENT_ID: &str = "app-service";
+
+// Name shown next to the account in authenticator apps
+pub const TOTP_ISSUER: &str = "Auth";
+
+// Name shown by browsers when creating or using a passkey
+pub const WEBAUTHN_RP_NAME: &str = "Auth";
+
+// Requests allowed to each route per client address and, for routes that send email, per
+// email address. Routes that aren't listed are not rate limited. Requests to routes that check
+// credentials are refused while the limits can't be checked; the rest are let through.
+pub const ROUTE_RATE_LIMITS: &[RouteRateLimit] = &[
+    fail_open_rate_limit("/signup", RateLimitKey::IpAddress, RateLimit::per_hour(20)),
+    fail_open_rate_limit("/signup", RateLimitKey::Email, RateLimit::per_hour(3)),
+    rate_limit("/login", RateLimitKey::IpAddress, RateLimit::per_minute(30)),
+    rate_limit(
+        "/login/magic-link",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/login/magic-link",
+        RateLimitKey::Email,
+        RateLimit::per_hour(3),
+    ),
+    rate_limit(
+        "/login/magic-link/callback",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/login/social/{provider}",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/login/social/{provider}/callback",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/verify-2fa",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/webauthn/login/start",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/webauthn/login/finish",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit("/token", RateLimitKey::IpAddress, RateLimit::per_minute(30)),
+    rate_limit(
+        "/device/code",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/device/verify",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/device/approve",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/forgot-password",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/forgot-password",
+        RateLimitKey::Email,
+        RateLimit::per_hour(3),
+    ),
+    rate_limit(
+        "/reset-password",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/verify-email",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/resend-verification",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/resend-verification",
+        RateLimitKey::Email,
+        RateLimit::per_hour(3),
+    ),
+    rate_limit(
+        "/change-email",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/confirm-email-change",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/change-password",
+        RateLimitKey::IpAddress,
+        RateLimit::per_hour(20),
+    ),
+    rate_limit(
+        "/refresh",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(60),
+    ),
+    rate_limit(
+        "/api-keys",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    rate_limit(
+        "/api-keys/{id}",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(30),
+    ),
+    // Other services check the tokens of every request they get, so these allow far more, and
+    // keep working without the rate limit store. Our tokens are too long to guess anyway.
+    fail_open_rate_limit(
+        "/verify-token",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(600),
+    ),
+    fail_open_rate_limit(
+        "/introspect",
+        RateLimitKey::IpAddress,
+        RateLimit::per_minute(600),
+    ),
+];
+
+const fn rate_limit(path: &'static str, key: RateLimitKey, limit: RateLimit) -> RouteRateLimit {
+    RouteRateLimit {
+        path,
+        key,
+        limit,
+        fail_open: false,
+    }
+}
+
+const fn fail_open_rate_limit(
+    path: &'static str,
+    key: RateLimitKey,
+    limit: RateLimit,
+) -> RouteRateLimit {
+    RouteRateLimit {
+        path,
+        key,
+        limit,
+        fail_open: true,
+    }
+}
+
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
     pub mod email_client {
@@ -62,6 +423,11 @@ pub mod prod {
         pub const SENDER_RESEND: &str = "onboarding@resend.dev";
         pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
     }
+    pub mod identity_provider_client {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
 }
 
 pub mod test {
@@ -72,4 +438,31 @@ pub mod test {
         pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
-}
\ No newline at end of file
+    pub mod identity_provider_client {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(500);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(origin: &str, rp_id: &str) -> Result<(), String> {
+        check_webauthn_relying_party(&reqwest::Url::parse(origin).unwrap(), rp_id)
+    }
+
+    #[test]
+    fn webauthn_relying_party_must_be_a_secure_domain() {
+        assert!(check("https://auth.example.com", "auth.example.com").is_ok());
+        assert!(check("https://auth.example.com", "example.com").is_ok());
+        assert!(check("http://localhost:3000", "localhost").is_ok());
+
+        assert!(check("http://auth.example.com", "auth.example.com").is_err());
+        assert!(check("http://203.0.113.7:3000", "203.0.113.7").is_err());
+        assert!(check("https://auth.example.com", "203.0.113.7").is_err());
+        assert!(check("https://auth.example.com", "other.com").is_err());
+        assert!(check("https://auth.example.com", "ample.com").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+
+const NONCE_LENGTH: usize = 12;
+
+// Encrypt a secret we need to keep at rest with AES-256-GCM, as base64(nonce || ciphertext).
+// Each kind of secret has its own `key`, so leaking one key doesn't expose the others.
+pub fn encrypt(key: &SecretString, plaintext: &[u8]) -> Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| eyre!("failed to encrypt secret"))?;
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+
+    Ok(STANDARD.encode(data))
+}
+
+pub fn decrypt(key: &SecretString, encrypted: &str) -> Result<Vec<u8>> {
+    let data = decode(encrypted)?;
+    let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+    let nonce: [u8; NONCE_LENGTH] = nonce.try_into()?;
+
+    cipher(key)
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| eyre!("failed to decrypt secret"))
+}
+
+// Check `encrypted` looks like something `encrypt` produced, without decrypting it
+pub fn check_encrypted(encrypted: &str) -> Result<()> {
+    decode(encrypted).map(|_| ())
+}
+
+fn decode(encrypted: &str) -> Result<Vec<u8>> {
+    let data = STANDARD
+        .decode(encrypted)
+        .wrap_err("encrypted secret is not valid base64")?;
+    if data.len() <= NONCE_LENGTH {
+        return Err(eyre!("encrypted secret is too short"));
+    }
+    Ok(data)
+}
+
+// Derive a 256-bit AES key from a configured encryption key
+fn cipher(key: &SecretString) -> Aes256Gcm {
+    let key: Key<Aes256Gcm> = Sha256::digest(key.expose_secret().as_bytes());
+    Aes256Gcm::new(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> SecretString {
+        SecretString::new(key.into())
+    }
+
+    #[test]
+    fn encrypted_secret_round_trips() {
+        let encrypted = encrypt(&key("key"), b"secret").unwrap();
+
+        assert!(!encrypted.contains("secret"));
+        assert!(check_encrypted(&encrypted).is_ok());
+        assert_eq!(decrypt(&key("key"), &encrypted).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn secret_fails_to_decrypt_with_another_key() {
+        let encrypted = encrypt(&key("key"), b"secret").unwrap();
+
+        assert!(decrypt(&key("other key"), &encrypted).is_err());
+    }
+
+    #[test]
+    fn malformed_secret_is_rejected() {
+        assert!(check_encrypted("not base64!").is_err());
+        assert!(check_encrypted(&STANDARD.encode([0u8; NONCE_LENGTH])).is_err());
+    }
+
+    #[test]
+    fn tampered_secret_fails_to_decrypt() {
+        let encrypted = encrypt(&key("key"), b"secret").unwrap();
+
+        let mut data = STANDARD.decode(&encrypted).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+
+        assert!(decrypt(&key("key"), &STANDARD.encode(data)).is_err());
+    }
+}
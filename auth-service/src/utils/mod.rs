@@ -1,3 +1,6 @@
-pub mod constants;
 pub mod auth;
-pub mod tracing;
\ No newline at end of file
+pub mod client_info;
+pub mod constants;
+pub mod encryption;
+pub mod rate_limit;
+pub mod tracing;
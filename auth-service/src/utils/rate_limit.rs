@@ -0,0 +1,111 @@
+use crate::app_state::AppState;
+use crate::domain::{AuthAPIError, Email, RateLimit, RateLimitStoreError};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::ROUTE_RATE_LIMITS;
+use axum::body::{to_bytes, Body};
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+// The largest request body we read to find the email address to rate limit by
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+// What a route's requests are counted against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitKey {
+    IpAddress,
+    // The `email` field of the JSON request body
+    Email,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RouteRateLimit {
+    pub path: &'static str,
+    pub key: RateLimitKey,
+    pub limit: RateLimit,
+    // Whether requests are let through while the rate limit store is down. Only for routes where
+    // nothing can be guessed, as anywhere else that would allow unlimited guesses.
+    pub fail_open: bool,
+}
+
+// Middleware applying the limits in `ROUTE_RATE_LIMITS` to each request,
+// answering with 429 and `Retry-After` once any of them is exhausted,
+// and with 503 if they can't be checked
+#[tracing::instrument(name = "Rate limit", skip_all)]
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    matched_path: Option<MatchedPath>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(path) = matched_path else {
+        return next.run(request).await;
+    };
+    let limits: Vec<&RouteRateLimit> = ROUTE_RATE_LIMITS
+        .iter()
+        .filter(|limit| limit.path == path.as_str())
+        .collect();
+    if limits.is_empty() {
+        return next.run(request).await;
+    }
+
+    // Only read the body when a limit needs the email address in it
+    let (request, email) = if limits.iter().any(|limit| limit.key == RateLimitKey::Email) {
+        let (parts, body) = request.into_parts();
+        let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+        };
+        // Requests without a valid email are left for the route to reject
+        let email = serde_json::from_slice::<EmailField>(&bytes)
+            .ok()
+            .and_then(|body| Email::parse(body.email).ok());
+        (Request::from_parts(parts, Body::from(bytes)), email)
+    } else {
+        (request, None)
+    };
+
+    for limit in limits {
+        let key = match limit.key {
+            RateLimitKey::IpAddress => client.ip_address.as_ref().map(|ip| format!("ip:{}", ip)),
+            RateLimitKey::Email => email
+                .as_ref()
+                .map(|email| format!("email:{}", email.as_ref().expose_secret())),
+        };
+        let Some(key) = key else {
+            continue;
+        };
+
+        let result = state
+            .rate_limit_store
+            .write()
+            .await
+            .take_token(&format!("{}:{}", limit.path, key), &limit.limit)
+            .await;
+        match result {
+            Ok(()) => {}
+            Err(RateLimitStoreError::RateLimitExceeded(retry_after)) => {
+                return AuthAPIError::RateLimited(retry_after.div_ceil(1000)).into_response();
+            }
+            // Routes that don't check credentials carry on without the rate limit store, rather
+            // than go down with it. The rest are refused until it is back.
+            Err(e) => {
+                tracing::error!("failed to apply rate limit: {:?}", e);
+                if !limit.fail_open {
+                    return AuthAPIError::RateLimitUnavailable.into_response();
+                }
+            }
+        }
+    }
+
+    next.run(request).await
+}
+
+#[derive(Deserialize)]
+struct EmailField {
+    email: SecretString,
+}
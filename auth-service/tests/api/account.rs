@@ -0,0 +1,223 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{AuditAction, AuditEvent, Email, TwoFAMethod, UserId};
+use auth_service::routes::{AccountExportResponse, DeleteAccountResponse};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::ErrorResponse;
+use secrecy::SecretString;
+use test_helpers::api_test;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+fn get_auth_token(response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[api_test]
+async fn should_return_200_and_delete_account() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let user_id = UserId::parse(app.get_user_id(&random_email).await).unwrap();
+
+    // Log in again, as if from another device
+    let other_device = get_auth_token(&login(&app, &random_email).await);
+    let this_device = get_auth_token(&login(&app, &random_email).await);
+
+    // The owner is told their account is gone
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<DeleteAccountResponse>()
+            .await
+            .expect("Could not deserialize response body to DeleteAccountResponse")
+            .message,
+        "Account deleted successfully!".to_owned()
+    );
+
+    // Every device is logged out...
+    assert_eq!(verify_token(&app, &other_device).await, 401);
+    assert_eq!(verify_token(&app, &this_device).await, 401);
+
+    // ...and the account can't be logged into again
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The deletion is on record
+    let events = app
+        .audit_log_store
+        .read()
+        .await
+        .get_events(&user_id)
+        .await
+        .expect("Failed to get audit events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, AuditAction::AccountDeleted);
+}
+
+#[api_test]
+async fn should_delete_account_even_if_notice_fails() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The session cookie is cleared
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME && cookie.value().is_empty()));
+
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_password_incorrect() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "wrong-password" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    // The account is untouched
+    let response = app.get_account_export().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let test_cases = [
+        serde_json::json!({}),
+        serde_json::json!({ "password": 1234 }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.delete_account(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_200_and_export_account() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    app.grant_role(&random_email, "support").await;
+    let user_id = UserId::parse(app.get_user_id(&random_email).await).unwrap();
+    let email = Email::parse(SecretString::new(random_email.clone().into_boxed_str())).unwrap();
+    let event = AuditEvent::new(
+        AuditAction::EmailChanged,
+        user_id,
+        email,
+        Some("test-agent".to_owned()),
+        None,
+    );
+    app.audit_log_store
+        .write()
+        .await
+        .add_event(event)
+        .await
+        .unwrap();
+
+    let response = app.get_account_export().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-disposition")
+            .expect("No Content-Disposition header"),
+        "attachment; filename=\"account-export.json\""
+    );
+
+    let export = response
+        .json::<AccountExportResponse>()
+        .await
+        .expect("Could not deserialize response body to AccountExportResponse");
+
+    assert_eq!(export.id, app.get_user_id(&random_email).await);
+    assert_eq!(export.email, random_email);
+    assert!(export.verified);
+    assert_eq!(export.two_fa_method, TwoFAMethod::None);
+    assert!(!export.totp_enrolled);
+    assert_eq!(export.roles, vec!["support".to_owned()]);
+    assert_eq!(export.sessions.len(), 1);
+    assert!(export.sessions[0].current);
+    assert!(export.passkeys.is_empty());
+    assert!(export.oauth_consents.is_empty());
+    assert!(export.external_identities.is_empty());
+    assert_eq!(export.audit_events.len(), 1);
+    assert_eq!(export.audit_events[0].action, "email_changed");
+    assert_eq!(
+        export.audit_events[0].user_agent.as_deref(),
+        Some("test-agent")
+    );
+}
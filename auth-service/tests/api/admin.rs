@@ -0,0 +1,215 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{RolesResponse, VerifyTokenResponse};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::ErrorResponse;
+use test_helpers::api_test;
+
+// Log in as a new user with the admin role
+async fn login_as_admin(app: &TestApp) -> String {
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.mark_email_verified(&email).await;
+    app.grant_role(&email, "admin").await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    email
+}
+
+async fn login(app: &TestApp, email: &str) -> String {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    app.post_login(&login_body)
+        .await
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+async fn get_token_roles(app: &TestApp, token: &str) -> Vec<String> {
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse")
+        .roles
+}
+
+#[api_test]
+async fn should_grant_and_revoke_roles() {
+    // Sign the user up first, since signing up doesn't need a login
+    let user_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": user_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.mark_email_verified(&user_email).await;
+
+    login_as_admin(&app).await;
+
+    let response = app
+        .post_grant_role(&serde_json::json!({ "email": user_email, "role": "support" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let roles = response
+        .json::<RolesResponse>()
+        .await
+        .expect("Could not deserialize response body to RolesResponse")
+        .roles;
+    assert_eq!(roles, vec!["support"]);
+
+    // The role is in the user's tokens from their next login
+    let token = login(&app, &user_email).await;
+    assert_eq!(get_token_roles(&app, &token).await, vec!["support"]);
+
+    login_as_admin(&app).await;
+
+    let response = app
+        .post_revoke_role(&serde_json::json!({ "email": user_email, "role": "support" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let roles = response
+        .json::<RolesResponse>()
+        .await
+        .expect("Could not deserialize response body to RolesResponse")
+        .roles;
+    assert!(roles.is_empty());
+
+    let token = login(&app, &user_email).await;
+    assert!(get_token_roles(&app, &token).await.is_empty());
+}
+
+#[api_test]
+async fn should_return_403_if_not_an_admin() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let body = serde_json::json!({ "email": random_email, "role": "admin" });
+    for response in [
+        app.post_grant_role(&body).await,
+        app.post_revoke_role(&body).await,
+    ] {
+        assert_eq!(response.status().as_u16(), 403);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Forbidden".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let body = serde_json::json!({ "email": get_random_email(), "role": "support" });
+    for response in [
+        app.post_grant_role(&body).await,
+        app.post_revoke_role(&body).await,
+    ] {
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    login_as_admin(&app).await;
+
+    let test_cases = [
+        (
+            serde_json::json!({ "email": "invalid", "role": "support" }),
+            "Invalid credentials",
+        ),
+        (
+            serde_json::json!({ "email": get_random_email(), "role": "" }),
+            "Invalid role",
+        ),
+        (
+            serde_json::json!({ "email": get_random_email(), "role": "Not A Role" }),
+            "Invalid role",
+        ),
+    ];
+
+    for (body, error) in test_cases {
+        let response = app.post_grant_role(&body).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            body
+        );
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            error.to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_404_if_user_not_found() {
+    login_as_admin(&app).await;
+
+    let body = serde_json::json!({ "email": get_random_email(), "role": "support" });
+    for response in [
+        app.post_grant_role(&body).await,
+        app.post_revoke_role(&body).await,
+    ] {
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "User not found".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    login_as_admin(&app).await;
+
+    let test_cases = [
+        serde_json::json!({ "email": get_random_email() }),
+        serde_json::json!({ "role": "support" }),
+        serde_json::json!({ "email": get_random_email(), "role": 1 }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_grant_role(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
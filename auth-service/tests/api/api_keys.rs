@@ -0,0 +1,172 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{ApiKeysResponse, CreateApiKeyResponse, TokenKind, VerifyTokenResponse};
+use test_helpers::api_test;
+use uuid::Uuid;
+
+async fn create_api_key(app: &TestApp, body: serde_json::Value) -> CreateApiKeyResponse {
+    let response = app.post_api_key(&body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse")
+}
+
+async fn get_api_keys(app: &TestApp) -> ApiKeysResponse {
+    let response = app.get_api_keys().await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<ApiKeysResponse>()
+        .await
+        .expect("Could not deserialize response body to ApiKeysResponse")
+}
+
+async fn verify_token_status(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[api_test]
+async fn should_create_list_and_revoke_api_keys() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let created = create_api_key(
+        &app,
+        serde_json::json!({ "name": "CI", "scopes": ["deploy"], "expiresInDays": 30 }),
+    )
+    .await;
+    assert!(created.key.starts_with("ak_"));
+    assert_eq!(created.api_key.name, "CI");
+    assert!(created.api_key.expires_at.is_some());
+    assert!(created.api_key.last_used_at.is_none());
+
+    // The key stands in for the user, limited to its scopes
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": created.key }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.kind, TokenKind::ApiKey);
+    assert_eq!(body.id, app.get_user_id(&random_email).await);
+    assert_eq!(body.email.as_deref(), Some(random_email.as_str()));
+    assert_eq!(body.scopes, vec!["deploy"]);
+
+    let api_keys = get_api_keys(&app).await.api_keys;
+    assert_eq!(api_keys.len(), 1);
+    assert_eq!(api_keys[0].id, created.api_key.id);
+    assert!(api_keys[0].last_used_at.is_some());
+
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(verify_token_status(&app, &created.key).await, 401);
+    assert!(get_api_keys(&app).await.api_keys.is_empty());
+}
+
+#[api_test]
+async fn should_return_401_if_unknown_api_key() {
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let created = create_api_key(&app, serde_json::json!({ "name": "CI" })).await;
+
+    // Right shape, but never issued
+    let replacement = if created.key.as_bytes()[5] == b'A' {
+        "B"
+    } else {
+        "A"
+    };
+    let mut unknown_key = created.key.clone();
+    unknown_key.replace_range(5..6, replacement);
+
+    assert_eq!(verify_token_status(&app, &unknown_key).await, 401);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_api_key_request() {
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let test_cases = [
+        serde_json::json!({ "name": "" }),
+        serde_json::json!({ "name": "CI", "expiresInDays": 0 }),
+        serde_json::json!({ "name": "CI", "expiresInDays": 366 }),
+        serde_json::json!({ "name": "CI", "scopes": ["two scopes"] }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_api_key(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for: {:?}",
+            test_case
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    assert_eq!(
+        app.post_api_key(&serde_json::json!({ "name": "CI" }))
+            .await
+            .status()
+            .as_u16(),
+        400
+    );
+    assert_eq!(app.get_api_keys().await.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_404_if_key_belongs_to_someone_else() {
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let created = create_api_key(&app, serde_json::json!({ "name": "CI" })).await;
+    app.post_logout().await;
+
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let unknown_id = Uuid::new_v4().to_string();
+    for id in [
+        created.api_key.id.as_str(),
+        unknown_id.as_str(),
+        "not-a-uuid",
+    ] {
+        let response = app.delete_api_key(id).await;
+        assert_eq!(response.status().as_u16(), 404, "Failed for: {}", id);
+    }
+
+    // The owner's key still works
+    assert_eq!(verify_token_status(&app, &created.key).await, 200);
+}
+
+#[api_test]
+async fn should_not_report_owner_roles_for_api_keys() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    app.grant_role(&random_email, "admin").await;
+
+    let created = create_api_key(
+        &app,
+        serde_json::json!({ "name": "Metrics", "scopes": ["metrics:read"] }),
+    )
+    .await;
+
+    // An admin's key can only do what its scopes say
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": created.key }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.kind, TokenKind::ApiKey);
+    assert!(body.roles.is_empty());
+    assert_eq!(body.scopes, vec!["metrics:read"]);
+}
@@ -0,0 +1,357 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{AuditAction, UserId};
+use auth_service::routes::{ChangeEmailResponse, ConfirmEmailChangeResponse, VerifyTokenResponse};
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::ErrorResponse;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+fn get_auth_token(response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+async fn mock_emails(app: &TestApp) {
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn change_email(app: &TestApp, new_email: &str) -> reqwest::Response {
+    app.post_change_email(&serde_json::json!({
+        "newEmail": new_email,
+        "password": "password123",
+    }))
+    .await
+}
+
+#[api_test]
+async fn should_return_200_and_email_both_addresses() {
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+
+    // A confirmation link goes to the new address, and a notice to the old one
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = change_email(&app, &new_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<ChangeEmailResponse>()
+            .await
+            .expect("Could not deserialize response body to ChangeEmailResponse")
+            .message,
+        "Confirmation email sent!".to_owned()
+    );
+
+    let recipients: Vec<String> = app
+        .email_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled")
+        .iter()
+        .rev()
+        .take(2)
+        .map(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            body["to"][0].as_str().unwrap().to_owned()
+        })
+        .collect();
+    assert_eq!(recipients, vec![old_email.clone(), new_email]);
+
+    // Nothing changes until the new address is confirmed
+    let response = login(&app, &old_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_change_email_once_confirmed() {
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+    app.grant_role(&old_email, "support").await;
+    let user_id = app.get_user_id(&old_email).await;
+    let old_token = get_auth_token(&login(&app, &old_email).await);
+    mock_emails(&app).await;
+
+    let response = change_email(&app, &new_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = app.get_token_from_last_email("email_change_token").await;
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<ConfirmEmailChangeResponse>()
+            .await
+            .expect("Could not deserialize response body to ConfirmEmailChangeResponse")
+            .message,
+        "Email changed successfully!".to_owned()
+    );
+
+    // Logins from before the change carry on, now under the new address...
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": old_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.email.as_deref(), Some(new_email.as_str()));
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // ...and the account, id and roles and all, now lives at the new address
+    let response = login(&app, &old_email).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let new_token = get_auth_token(&login(&app, &new_email).await);
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.id, user_id);
+    assert_eq!(body.email.as_deref(), Some(new_email.as_str()));
+    assert_eq!(body.roles, vec!["support".to_owned()]);
+
+    // The change is on record, along with the address it was made from
+    let user_id = UserId::parse(user_id).unwrap();
+    let events = app
+        .audit_log_store
+        .read()
+        .await
+        .get_events(&user_id)
+        .await
+        .expect("Failed to get audit events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, AuditAction::EmailChanged);
+    assert_eq!(events[0].email.as_ref().expose_secret(), old_email);
+}
+
+#[api_test]
+async fn should_return_401_if_confirmation_token_reused() {
+    let old_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+    mock_emails(&app).await;
+
+    let response = change_email(&app, &get_random_email()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = app.get_token_from_last_email("email_change_token").await;
+    let body = serde_json::json!({ "token": token });
+
+    let response = app.post_confirm_email_change(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_confirm_email_change(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_409_if_new_email_taken() {
+    let old_email = get_random_email();
+    let taken_email = get_random_email();
+    app.signup_and_login(&taken_email, "password123").await;
+    app.signup_and_login(&old_email, "password123").await;
+
+    let response = change_email(&app, &taken_email).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "User already exists".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_409_if_new_email_taken_before_confirmation() {
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+    mock_emails(&app).await;
+
+    let response = change_email(&app, &new_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = app.get_token_from_last_email("email_change_token").await;
+
+    // Someone else signs up with the address in the meantime
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": new_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    // A change that didn't go through leaves the user logged in
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_old_email_reused_before_confirmation() {
+    let old_email = get_random_email();
+    let first_email = get_random_email();
+    let second_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+    mock_emails(&app).await;
+
+    let response = change_email(&app, &first_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let first_token = app.get_token_from_last_email("email_change_token").await;
+
+    let response = change_email(&app, &second_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let second_token = app.get_token_from_last_email("email_change_token").await;
+
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": second_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Someone else signs up with the address the user left behind
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": old_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let other_id = app.get_user_id(&old_email).await;
+
+    // The older link must not move the newcomer's account
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": first_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(app.get_user_id(&old_email).await, other_id);
+    let response = login(&app, &first_email).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = login(&app, &second_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_password_incorrect() {
+    let old_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": get_random_email(),
+            "password": "wrong-password",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_new_email() {
+    let old_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+
+    for new_email in ["", "not-an-email", old_email.as_str()] {
+        let response = change_email(&app, new_email).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            new_email
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = change_email(&app, &get_random_email()).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_reject_invalid_confirmation_tokens() {
+    // Not a token at all
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": "invalid" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // A token we never issued
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": Uuid::new_v4().to_string() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let old_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+
+    let test_cases = [
+        serde_json::json!({ "newEmail": get_random_email() }),
+        serde_json::json!({ "password": "password123" }),
+        serde_json::json!({ "new_email": get_random_email(), "password": "password123" }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_change_email(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    let response = app.post_confirm_email_change(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 422);
+}
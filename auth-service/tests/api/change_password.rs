@@ -0,0 +1,191 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::ChangePasswordResponse;
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::ErrorResponse;
+use test_helpers::api_test;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+fn get_auth_token(response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[api_test]
+async fn should_return_200_and_change_password() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    // The owner is told their password changed
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<ChangePasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to ChangePasswordResponse")
+            .message,
+        "Password changed successfully!".to_owned()
+    );
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &random_email, "new-password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_log_out_other_sessions() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    // Log in again, as if from another device, keeping the first device's token
+    let other_device = get_auth_token(&login(&app, &random_email, "password123").await);
+    let this_device = get_auth_token(&login(&app, &random_email, "password123").await);
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(verify_token(&app, &other_device).await, 401);
+    assert_eq!(verify_token(&app, &this_device).await, 200);
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_current_password_incorrect() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "wrong-password",
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    // The password is unchanged
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_new_password() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "short",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "new-password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let test_cases = [
+        serde_json::json!({ "currentPassword": "password123" }),
+        serde_json::json!({ "newPassword": "new-password123" }),
+        serde_json::json!({ "current_password": "password123", "new_password": "password" }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_change_password(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
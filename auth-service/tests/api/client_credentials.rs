@@ -0,0 +1,193 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{TokenKind, TokenResponse, VerifyTokenResponse};
+use auth_service::ErrorResponse;
+use reqwest::header::{CACHE_CONTROL, WWW_AUTHENTICATE};
+use test_helpers::api_test;
+use uuid::Uuid;
+
+const SCOPES: [&str; 2] = ["orders:read", "orders:write"];
+
+async fn assert_token_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> VerifyTokenResponse {
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse")
+}
+
+#[api_test]
+async fn should_issue_token_for_client_credentials() {
+    let client = app.register_machine_client(&SCOPES).await;
+
+    let response = app
+        .post_token_with_basic_auth(
+            &client.client_id,
+            &client.client_secret,
+            &[("grant_type", "client_credentials")],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.expires_in, 600);
+    assert_eq!(tokens.scope.as_deref(), Some("orders:read orders:write"));
+    // The client just authenticates again once the token expires
+    assert!(tokens.refresh_token.is_none());
+
+    let body = verify_token(&app, &tokens.access_token).await;
+    assert_eq!(body.kind, TokenKind::Client);
+    assert_eq!(body.id, client.client_id);
+    assert_eq!(body.scopes, SCOPES);
+    assert!(body.email.is_none());
+    assert!(body.roles.is_empty());
+
+    // There is no user behind the token
+    let response = app.get_userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_issue_token_with_requested_scopes() {
+    let client = app.register_machine_client(&SCOPES).await;
+
+    // Credentials can also be sent in the form body
+    let response = app
+        .post_token(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", &client.client_id),
+            ("client_secret", &client.client_secret),
+            ("scope", "orders:read"),
+        ])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(tokens.scope.as_deref(), Some("orders:read"));
+
+    let body = verify_token(&app, &tokens.access_token).await;
+    assert_eq!(body.scopes, vec!["orders:read"]);
+}
+
+#[api_test]
+async fn should_return_invalid_scope_if_scope_not_allowed() {
+    let client = app.register_machine_client(&SCOPES).await;
+
+    let response = app
+        .post_token_with_basic_auth(
+            &client.client_id,
+            &client.client_secret,
+            &[
+                ("grant_type", "client_credentials"),
+                ("scope", "orders:read admin"),
+            ],
+        )
+        .await;
+
+    assert_token_error(response, 400, "invalid_scope").await;
+}
+
+#[api_test]
+async fn should_return_invalid_client_if_bad_credentials() {
+    let client = app.register_machine_client(&SCOPES).await;
+    let other_client = app.register_machine_client(&SCOPES).await;
+    let unknown_client_id = Uuid::new_v4().to_string();
+
+    let test_cases = [
+        (client.client_id.as_str(), "wrong-secret"),
+        (
+            client.client_id.as_str(),
+            other_client.client_secret.as_str(),
+        ),
+        (unknown_client_id.as_str(), client.client_secret.as_str()),
+        ("not-a-uuid", client.client_secret.as_str()),
+    ];
+
+    for (client_id, client_secret) in test_cases {
+        let response = app
+            .post_token_with_basic_auth(
+                client_id,
+                client_secret,
+                &[("grant_type", "client_credentials")],
+            )
+            .await;
+
+        assert_eq!(
+            response.headers().get(WWW_AUTHENTICATE).unwrap(),
+            "Basic",
+            "Failed for: {}",
+            client_id
+        );
+        assert_token_error(response, 401, "invalid_client").await;
+    }
+
+    let response = app
+        .post_token(&[("grant_type", "client_credentials")])
+        .await;
+    assert_token_error(response, 401, "invalid_client").await;
+}
+
+#[api_test]
+async fn should_only_let_admins_register_machine_clients() {
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app
+        .post_register_machine_client(&serde_json::json!({
+            "name": "Billing Service",
+            "scopes": SCOPES,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_machine_client_registration() {
+    let admin_email = get_random_email();
+    app.signup_and_login(&admin_email, "password123").await;
+    app.grant_role(&admin_email, "admin").await;
+    app.post_login(&serde_json::json!({
+        "email": admin_email,
+        "password": "password123",
+    }))
+    .await;
+
+    let test_cases = [
+        serde_json::json!({ "name": "", "scopes": SCOPES }),
+        serde_json::json!({ "name": "Billing Service", "scopes": ["orders read"] }),
+        serde_json::json!({ "name": "Billing Service", "scopes": ["\"quoted\""] }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_register_machine_client(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for: {:?}",
+            test_case
+        );
+    }
+}
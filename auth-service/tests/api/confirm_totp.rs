@@ -0,0 +1,157 @@
+use crate::helpers::{generate_totp_code, get_random_email, TestApp};
+use auth_service::{
+    domain::{Email, TwoFAMethod, RECOVERY_CODE_COUNT},
+    routes::{ConfirmTotpResponse, EnrollTotpResponse},
+    ErrorResponse,
+};
+use secrecy::SecretString;
+use test_helpers::api_test;
+
+#[api_test]
+async fn should_return_200_and_enable_totp_if_code_is_correct() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let enrollment = app
+        .post_enroll_totp()
+        .await
+        .json::<EnrollTotpResponse>()
+        .await
+        .unwrap();
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({
+            "code": generate_totp_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response_body = response
+        .json::<ConfirmTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to ConfirmTotpResponse");
+
+    assert_eq!(
+        response_body.message,
+        "TOTP enabled successfully!".to_owned()
+    );
+    assert_eq!(
+        response_body.recovery_codes.map(|codes| codes.len()),
+        Some(RECOVERY_CODE_COUNT)
+    );
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(SecretString::new(random_email.into_boxed_str())).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(user.two_fa_method, TwoFAMethod::Totp);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    app.post_enroll_totp().await;
+
+    for code in ["", "12345", "1234567", "abcdef"] {
+        let request_body = serde_json::json!({ "code": code });
+
+        let response = app.post_confirm_totp(&request_body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            request_body
+        );
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid credentials".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_not_enrolled() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": "123456" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "TOTP not enrolled".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_incorrect_code() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let enrollment = app
+        .post_enroll_totp()
+        .await
+        .json::<EnrollTotpResponse>()
+        .await
+        .unwrap();
+
+    let code = generate_totp_code(&enrollment.otpauth_uri);
+    let wrong_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": wrong_code }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(SecretString::new(random_email.into_boxed_str())).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(user.two_fa_method, TwoFAMethod::None);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "code": "123456" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({ "totp": "123456" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
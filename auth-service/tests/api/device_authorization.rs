@@ -0,0 +1,260 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::DeviceCode;
+use auth_service::routes::{
+    DeviceAuthorizationResponse, TokenResponse, UserCodeResponse, VerifyTokenResponse,
+};
+use auth_service::utils::constants::AUTH_SERVICE_URL;
+use auth_service::ErrorResponse;
+use chrono::Duration;
+use reqwest::header::CACHE_CONTROL;
+use secrecy::SecretString;
+use test_helpers::api_test;
+use uuid::Uuid;
+
+const REDIRECT_URI: &str = "http://localhost:8000/callback";
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+async fn start_device_authorization(
+    app: &TestApp,
+    client_id: &str,
+    scope: &str,
+) -> DeviceAuthorizationResponse {
+    let response = app
+        .post_device_code(&[("client_id", client_id), ("scope", scope)])
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+    response
+        .json::<DeviceAuthorizationResponse>()
+        .await
+        .expect("Could not deserialize response body to DeviceAuthorizationResponse")
+}
+
+async fn poll(app: &TestApp, client_id: &str, device_code: &str) -> reqwest::Response {
+    app.post_token(&[
+        ("grant_type", DEVICE_CODE_GRANT_TYPE),
+        ("client_id", client_id),
+        ("device_code", device_code),
+    ])
+    .await
+}
+
+// Make the device's last poll look `seconds` older than it was, rather than wait that long
+async fn rewind_last_poll(app: &TestApp, device_code: &str, seconds: i64) {
+    let code =
+        DeviceCode::parse(SecretString::new(device_code.to_owned().into_boxed_str())).unwrap();
+    let mut device_code_store = app.device_code_store.write().await;
+    let mut authorization = device_code_store
+        .get_authorization(&code)
+        .await
+        .expect("Failed to get device authorization");
+    authorization.last_polled_at = authorization
+        .last_polled_at
+        .map(|last_polled_at| last_polled_at - Duration::seconds(seconds));
+    device_code_store
+        .update_authorization(&code, authorization)
+        .await
+        .expect("Failed to update device authorization");
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+#[api_test]
+async fn should_log_device_in_once_user_approves() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let device = start_device_authorization(&app, &client_id, "openid").await;
+    assert_eq!(
+        device.verification_uri,
+        format!("{}/device", AUTH_SERVICE_URL.as_str())
+    );
+    assert!(device
+        .verification_uri_complete
+        .ends_with(&format!("?user_code={}", device.user_code)));
+    assert_eq!(device.expires_in, 600);
+    assert_eq!(device.interval, 5);
+
+    // The user hasn't answered yet, and the device is polling too often
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "authorization_pending").await;
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "slow_down").await;
+
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    // The user types the code in on the verification page, however they like
+    let typed_user_code = device.user_code.replace('-', " ").to_lowercase();
+    let response = app
+        .post_device_verify(&serde_json::json!({ "userCode": typed_user_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let request = response
+        .json::<UserCodeResponse>()
+        .await
+        .expect("Could not deserialize response body to UserCodeResponse");
+    assert_eq!(request.client_name, "Example App");
+    assert_eq!(request.scopes, vec!["openid"]);
+
+    let response = app
+        .post_device_approve(&serde_json::json!({
+            "userCode": device.user_code,
+            "decision": "approve",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert!(tokens.refresh_token.is_some());
+    assert!(tokens.id_token.is_some());
+    assert_eq!(tokens.scope.as_deref(), Some("openid"));
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.id, app.get_user_id(&random_email).await);
+
+    // Both codes only work once
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "expired_token").await;
+    let response = app
+        .post_device_verify(&serde_json::json!({ "userCode": device.user_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_make_device_wait_longer_after_slow_down() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let device = start_device_authorization(&app, &client_id, "openid").await;
+
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "authorization_pending").await;
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "slow_down").await;
+
+    // Waiting the interval it was first given isn't enough any more...
+    rewind_last_poll(&app, &device.device_code, 6).await;
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "slow_down").await;
+
+    // ...and polling too fast again made it longer still
+    rewind_last_poll(&app, &device.device_code, 11).await;
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "slow_down").await;
+
+    rewind_last_poll(&app, &device.device_code, 20).await;
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "authorization_pending").await;
+}
+
+#[api_test]
+async fn should_return_access_denied_if_user_denies() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let device = start_device_authorization(&app, &client_id, "").await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app
+        .post_device_approve(&serde_json::json!({
+            "userCode": device.user_code,
+            "decision": "deny",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "access_denied").await;
+
+    // The device has to start again
+    let response = poll(&app, &client_id, &device.device_code).await;
+    assert_error(response, 400, "expired_token").await;
+}
+
+#[api_test]
+async fn should_return_invalid_client_if_unknown_client() {
+    let unknown_client_id = Uuid::new_v4().to_string();
+
+    for client_id in [unknown_client_id.as_str(), "not-a-uuid"] {
+        let response = app.post_device_code(&[("client_id", client_id)]).await;
+        assert_error(response, 401, "invalid_client").await;
+    }
+
+    let response = app.post_device_code(&[("scope", "openid")]).await;
+    assert_error(response, 400, "invalid_request").await;
+}
+
+#[api_test]
+async fn should_return_invalid_grant_if_device_code_not_for_client() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let other_client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let device = start_device_authorization(&app, &client_id, "").await;
+
+    let response = poll(&app, &other_client_id, &device.device_code).await;
+    assert_error(response, 400, "invalid_grant").await;
+
+    let response = poll(&app, &client_id, "not-a-device-code").await;
+    assert_error(response, 400, "invalid_grant").await;
+
+    let response = app
+        .post_token(&[
+            ("grant_type", DEVICE_CODE_GRANT_TYPE),
+            ("client_id", &client_id),
+        ])
+        .await;
+    assert_error(response, 400, "invalid_request").await;
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_user_code() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let device = start_device_authorization(&app, &client_id, "").await;
+
+    // The user has to be logged in to answer
+    app.post_logout().await;
+    let approval = serde_json::json!({ "userCode": device.user_code, "decision": "approve" });
+    let response = app.post_device_approve(&approval).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    for user_code in ["", "not-a-code", "BCDF-GHJK"] {
+        let response = app
+            .post_device_verify(&serde_json::json!({ "userCode": user_code }))
+            .await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for: {}", user_code);
+    }
+
+    // Each request can only be answered once
+    let response = app.post_device_approve(&approval).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_device_approve(&approval).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_serve_verification_page() {
+    let response = app.get_device_page().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/html");
+}
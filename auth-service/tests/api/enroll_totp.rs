@@ -0,0 +1,143 @@
+use crate::helpers::{generate_totp_code, get_random_email, TestApp};
+use auth_service::{
+    domain::{Email, TwoFAMethod},
+    routes::EnrollTotpResponse,
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use reqwest::Url;
+use secrecy::{ExposeSecret, SecretString};
+use test_helpers::api_test;
+
+#[api_test]
+async fn should_return_200_with_secret_and_otpauth_uri() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = app.post_enroll_totp().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response_body = response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse");
+
+    assert!(!response_body.secret.is_empty());
+    assert!(response_body.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(response_body
+        .otpauth_uri
+        .contains(&format!("secret={}", response_body.secret)));
+
+    // The secret is stored encrypted, but 2FA stays unchanged until the first code is confirmed
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::parse(SecretString::new(random_email.into_boxed_str())).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(user.two_fa_method, TwoFAMethod::None);
+    let encrypted_secret = user.totp_secret.expect("No TOTP secret stored");
+    assert_eq!(
+        encrypted_secret.decrypt().unwrap().as_ref().expose_secret(),
+        response_body.secret
+    );
+}
+
+#[api_test]
+async fn should_replace_unconfirmed_secret_when_enrolling_again() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let first = app
+        .post_enroll_totp()
+        .await
+        .json::<EnrollTotpResponse>()
+        .await
+        .unwrap();
+
+    let second = app
+        .post_enroll_totp()
+        .await
+        .json::<EnrollTotpResponse>()
+        .await
+        .unwrap();
+
+    assert_ne!(first.secret, second.secret);
+
+    // Only the newest secret can be confirmed
+    let response = app
+        .post_confirm_totp(&serde_json::json!({
+            "code": generate_totp_code(&second.otpauth_uri)
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_enroll_totp().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_token() {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
+            JWT_COOKIE_NAME
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_enroll_totp().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_409_if_totp_already_enabled() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let enrollment = app
+        .post_enroll_totp()
+        .await
+        .json::<EnrollTotpResponse>()
+        .await
+        .unwrap();
+
+    let response = app
+        .post_confirm_totp(&serde_json::json!({
+            "code": generate_totp_code(&enrollment.otpauth_uri)
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_enroll_totp().await;
+
+    assert_eq!(response.status().as_u16(), 409);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "TOTP already enabled".to_owned()
+    );
+}
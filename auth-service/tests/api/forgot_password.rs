@@ -0,0 +1,154 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::ForgotPasswordResponse;
+use auth_service::ErrorResponse;
+use test_helpers::api_test;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[api_test]
+async fn should_return_200_and_send_email_if_user_exists() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_forgot_password(&serde_json::json!({
+            "email": random_email,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = response
+        .json::<ForgotPasswordResponse>()
+        .await
+        .expect("Could not deserialize response body to ForgotPasswordResponse");
+
+    assert_eq!(
+        json_body.message,
+        "If an account exists for this email, a password reset link has been sent".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_200_without_sending_email_if_user_does_not_exist() {
+    let random_email = get_random_email();
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_forgot_password(&serde_json::json!({
+            "email": random_email,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let response = app
+        .post_forgot_password(&serde_json::json!({
+            "email": "invalid_email",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_forgot_password(&serde_json::json!({
+            "mail": get_random_email(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
+
+#[api_test]
+async fn should_return_429_if_too_many_emails_requested() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Only the requests within the limit send an email
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({ "email": random_email });
+    for _ in 0..3 {
+        let response = app.post_forgot_password(&body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app.post_forgot_password(&body).await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    // One request is allowed again every 20 minutes
+    let retry_after: u64 = response
+        .headers()
+        .get("retry-after")
+        .expect("No Retry-After header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 1190 && retry_after <= 1200);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+
+    // Other email addresses have their own limit
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -1,28 +1,66 @@
-use auth_service::app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType};
-use auth_service::domain::Email;
+use auth_service::app_state::{
+    AppState, AuditLogStoreType, BannedTokenStoreType, DeviceCodeStoreType, LoginAttemptStoreType,
+    SessionStoreType, SigningKeyStoreType, TwoFACodeStoreType, UserStoreType,
+};
+use auth_service::domain::{Email, IdentityProvider, Role, TokenPurpose};
+use auth_service::routes::{
+    ConfirmTotpResponse, EnrollTotpResponse, MachineClientResponse, OAuthClientResponse,
+};
+use auth_service::services::data_stores::HashmapLoginAttemptStore;
+use auth_service::services::data_stores::HashmapRateLimitStore;
+use auth_service::services::data_stores::PostgresApiKeyStore;
+use auth_service::services::data_stores::PostgresAuditLogStore;
+use auth_service::services::data_stores::PostgresExternalIdentityStore;
+use auth_service::services::data_stores::PostgresMachineClientStore;
+use auth_service::services::data_stores::PostgresOAuthClientStore;
+use auth_service::services::data_stores::PostgresPasskeyStore;
+use auth_service::services::data_stores::PostgresRecoveryCodeStore;
+use auth_service::services::data_stores::PostgresSessionStore;
+use auth_service::services::data_stores::PostgresSigningKeyStore;
 use auth_service::services::data_stores::PostgresUserStore;
+use auth_service::services::data_stores::RedisAuthorizationCodeStore;
 use auth_service::services::data_stores::RedisBannedTokenStore;
+use auth_service::services::data_stores::RedisDeviceCodeStore;
+use auth_service::services::data_stores::RedisRefreshTokenStore;
+use auth_service::services::data_stores::RedisSingleUseTokenStore;
+use auth_service::services::data_stores::RedisSocialLoginStateStore;
 use auth_service::services::data_stores::RedisTwoFACodeStore;
+use auth_service::services::data_stores::RedisWebAuthnChallengeStore;
+use auth_service::services::identity_provider_client::IdentityProviderClient;
 use auth_service::services::resend_email_client::ResendEmailClient;
-use auth_service::utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME};
+use auth_service::utils::auth::init_signing_key;
+use auth_service::utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME, JWT_SIGNING_KEY};
 use auth_service::{get_postgres_pool, get_redis_client, Application};
-use reqwest::{cookie::Jar, Client};
+use reqwest::{cookie::Jar, redirect, Client};
 use secrecy::{ExposeSecret, SecretString};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use totp_rs::TOTP;
 use uuid::Uuid;
 use wiremock::MockServer;
 
+// How the test app is registered with the mock identity provider
+pub const IDENTITY_PROVIDER_NAME: &str = "mock";
+pub const IDENTITY_PROVIDER_CLIENT_ID: &str = "auth-service";
+pub const IDENTITY_PROVIDER_CLIENT_SECRET: &str = "identity-provider-secret";
+
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
+    pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub session_store: SessionStoreType,
+    pub signing_key_store: SigningKeyStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
+    pub audit_log_store: AuditLogStoreType,
+    pub device_code_store: DeviceCodeStoreType,
     pub http_client: Client,
     pub email_server: MockServer,
+    pub identity_provider_server: MockServer,
     pub db_name: String,
     pub clean_up_called: bool,
 }
@@ -33,22 +71,103 @@ impl TestApp {
         let pg_pool = configure_postgresql(&db_name).await;
         let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
         let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
             redis_connection.clone(),
         )));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(
+            redis_connection.clone(),
+        )));
+        let password_reset_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+            redis_connection.clone(),
+            TokenPurpose::PasswordReset,
+        )));
+        let email_verification_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+            redis_connection.clone(),
+            TokenPurpose::EmailVerification,
+        )));
+        let email_change_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+            redis_connection.clone(),
+            TokenPurpose::EmailChange,
+        )));
+        let magic_link_token_store = Arc::new(RwLock::new(RedisSingleUseTokenStore::new(
+            redis_connection.clone(),
+            TokenPurpose::MagicLink,
+        )));
+        let webauthn_challenge_store = Arc::new(RwLock::new(RedisWebAuthnChallengeStore::new(
+            redis_connection.clone(),
+        )));
+        let authorization_code_store = Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(
+            redis_connection.clone(),
+        )));
+        let device_code_store = Arc::new(RwLock::new(RedisDeviceCodeStore::new(
+            redis_connection.clone(),
+        )));
+        let social_login_state_store = Arc::new(RwLock::new(RedisSocialLoginStateStore::new(
+            redis_connection.clone(),
+        )));
+        let refresh_token_store =
+            Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_connection)));
+
+        let recovery_code_store =
+            Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(pg_pool.clone())));
+        let passkey_store = Arc::new(RwLock::new(PostgresPasskeyStore::new(pg_pool.clone())));
+        let external_identity_store = Arc::new(RwLock::new(PostgresExternalIdentityStore::new(
+            pg_pool.clone(),
+        )));
+        let api_key_store = Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool.clone())));
+        let oauth_client_store =
+            Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())));
+        let machine_client_store = Arc::new(RwLock::new(PostgresMachineClientStore::new(
+            pg_pool.clone(),
+        )));
+        let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+        let audit_log_store = Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool.clone())));
+        let signing_key_store = Arc::new(RwLock::new(PostgresSigningKeyStore::new(pg_pool)));
+        init_signing_key(signing_key_store.clone(), JWT_SIGNING_KEY.clone())
+            .await
+            .expect("Failed to initialize signing key");
+        // Every test calls in from 127.0.0.1, so failed attempts and rate limits are counted
+        // per test app rather than in the shared Redis
+        let login_attempt_store = Arc::new(RwLock::new(HashmapLoginAttemptStore::default()));
+        let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
 
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
         let email_client = Arc::new(configure_resend_email_client(base_url));
 
-        let app_state = AppState::new(
-            user_store,
-            banned_token_store.clone(),
-            two_fa_code_store.clone(),
+        let identity_provider_server = MockServer::start().await;
+        let identity_provider_client = Arc::new(configure_identity_provider_client(
+            identity_provider_server.uri(),
+        ));
+
+        let app_state = AppState {
+            user_store: user_store.clone(),
+            banned_token_store: banned_token_store.clone(),
+            two_fa_code_store: two_fa_code_store.clone(),
+            password_reset_token_store,
+            email_verification_token_store,
+            email_change_token_store,
+            magic_link_token_store,
+            webauthn_challenge_store,
+            authorization_code_store,
+            device_code_store: device_code_store.clone(),
+            social_login_state_store,
+            recovery_code_store,
+            passkey_store,
+            external_identity_store,
+            api_key_store,
+            oauth_client_store,
+            machine_client_store,
+            refresh_token_store,
+            session_store: session_store.clone(),
+            signing_key_store: signing_key_store.clone(),
+            login_attempt_store: login_attempt_store.clone(),
+            rate_limit_store,
+            audit_log_store: audit_log_store.clone(),
             email_client,
-        );
+            identity_provider_client,
+        };
 
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -63,8 +182,10 @@ impl TestApp {
 
         let cookie_jar = Arc::new(Jar::default());
 
+        // Redirects are left to the tests, so they can check where a route sends the browser
         let http_client = Client::builder()
             .cookie_provider(cookie_jar.clone())
+            .redirect(redirect::Policy::none())
             .build()
             .unwrap();
 
@@ -72,10 +193,17 @@ impl TestApp {
         Self {
             address,
             cookie_jar,
+            user_store,
             banned_token_store,
             two_fa_code_store,
+            session_store,
+            signing_key_store,
+            login_attempt_store,
+            audit_log_store,
+            device_code_store,
             http_client,
             email_server,
+            identity_provider_server,
             db_name,
             clean_up_called: false,
         }
@@ -83,7 +211,15 @@ impl TestApp {
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -94,7 +230,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -106,15 +242,173 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn post_magic_link<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/magic-link", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_magic_link_callback(&self, id: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login/magic-link/callback", &self.address))
+            .json(&serde_json::json!({ "id": id, "token": token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_identity_providers(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/social", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_social_login(&self, provider: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/social/{}", &self.address, provider))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_social_login_callback(
+        &self,
+        provider: &str,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/login/social/{}/callback",
+                &self.address, provider
+            ))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_key<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/api-keys", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_keys(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api-keys", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_api_key(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/api-keys/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_email_change<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/confirm-email-change", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .delete(format!("{}/account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_account_export(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/account/export", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -144,6 +438,488 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-verification", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enroll_totp(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/enroll-totp", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/confirm-totp", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Sign up a verified user without 2FA and log them in, leaving the auth cookie in `cookie_jar`
+    pub async fn signup_and_login(&self, email: &str, password: &str) {
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": password,
+            "requires2FA": false
+        });
+        let response = self.post_signup(&signup_body).await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        self.mark_email_verified(email).await;
+
+        let login_body = serde_json::json!({
+            "email": email,
+            "password": password,
+        });
+        let response = self.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    pub async fn post_regenerate_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/regenerate-recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_start(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/webauthn/register/start", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/webauthn/register/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_start<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/webauthn/login/start", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_finish<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/webauthn/login/finish", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Introspect a token, authenticating as the resource server with HTTP Basic auth
+    pub async fn post_introspect<Body>(
+        &self,
+        body: &Body,
+        client_id: &str,
+        client_secret: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/introspect", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_introspect_unauthenticated<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/introspect", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_grant_role<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/grant-role", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_revoke_role<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/revoke-role", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_register_oauth_client<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/oauth-clients", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_register_machine_client<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/machine-clients", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/.well-known/openid-configuration",
+                &self.address
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_userinfo(&self, access_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/userinfo", &self.address))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_authorize(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_authorize(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/authorize", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_token(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/token", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_device_code(&self, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/device/code", &self.address))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // The verification page devices send the user to
+    pub async fn get_device_page(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/device", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_device_verify<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/device/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_device_approve<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/device/approve", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Call `/token` as a machine client authenticating with HTTP Basic auth
+    pub async fn post_token_with_basic_auth(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        form: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/token", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Enroll and confirm an authenticator app for the logged-in user,
+    // returning its otpauth URI and the recovery codes issued with it
+    pub async fn enable_totp(&self) -> (String, Vec<String>) {
+        let enrollment = self
+            .post_enroll_totp()
+            .await
+            .json::<EnrollTotpResponse>()
+            .await
+            .expect("Could not deserialize response body to EnrollTotpResponse");
+
+        let confirm_body = serde_json::json!({
+            "code": generate_totp_code(&enrollment.otpauth_uri)
+        });
+        let response = self.post_confirm_totp(&confirm_body).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let recovery_codes = response
+            .json::<ConfirmTotpResponse>()
+            .await
+            .expect("Could not deserialize response body to ConfirmTotpResponse")
+            .recovery_codes
+            .expect("No recovery codes returned");
+
+        (enrollment.otpauth_uri, recovery_codes)
+    }
+
+    // Skip the emailed verification link for tests that only need a verified account
+    pub async fn mark_email_verified(&self, email: &str) {
+        let email = Email::parse(SecretString::new(email.to_owned().into_boxed_str())).unwrap();
+        self.user_store
+            .write()
+            .await
+            .mark_email_verified(&email)
+            .await
+            .expect("Failed to mark email as verified");
+    }
+
+    // Look up the id tokens name the user by
+    pub async fn get_user_id(&self, email: &str) -> String {
+        let email = Email::parse(SecretString::new(email.to_owned().into_boxed_str())).unwrap();
+        self.user_store
+            .read()
+            .await
+            .get_user(&email)
+            .await
+            .expect("Failed to get user")
+            .id
+            .to_string()
+    }
+
+    // Grant a role directly in the store, e.g. to bootstrap an admin.
+    // It shows up in auth tokens from the next login.
+    pub async fn grant_role(&self, email: &str, role: &str) {
+        let email = Email::parse(SecretString::new(email.to_owned().into_boxed_str())).unwrap();
+        self.user_store
+            .write()
+            .await
+            .add_role(&email, Role::parse(role.to_owned()).unwrap())
+            .await
+            .expect("Failed to grant role");
+    }
+
+    // Log in as a new admin, for the calls that need one
+    async fn login_as_admin(&self) {
+        let admin_email = get_random_email();
+        self.signup_and_login(&admin_email, "password123").await;
+        self.grant_role(&admin_email, "admin").await;
+        // Roles are picked up at login
+        let login_body = serde_json::json!({
+            "email": admin_email,
+            "password": "password123",
+        });
+        self.post_login(&login_body).await;
+    }
+
+    // Register an OAuth client as a new admin, then log the admin out again,
+    // returning the client id
+    pub async fn register_oauth_client(&self, redirect_uri: &str) -> String {
+        self.login_as_admin().await;
+
+        let response = self
+            .post_register_oauth_client(&serde_json::json!({
+                "name": "Example App",
+                "redirectUris": [redirect_uri],
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+        let client = response
+            .json::<OAuthClientResponse>()
+            .await
+            .expect("Could not deserialize response body to OAuthClientResponse");
+
+        self.post_logout().await;
+        client.client_id
+    }
+
+    // Register a machine client allowed `scopes` as a new admin, then log the admin out again
+    pub async fn register_machine_client(&self, scopes: &[&str]) -> MachineClientResponse {
+        self.login_as_admin().await;
+
+        let response = self
+            .post_register_machine_client(&serde_json::json!({
+                "name": "Billing Service",
+                "scopes": scopes,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+        let client = response
+            .json::<MachineClientResponse>()
+            .await
+            .expect("Could not deserialize response body to MachineClientResponse");
+
+        self.post_logout().await;
+        client
+    }
+
+    // Pull the value of `query_param` out of the link in the most recently sent email that has
+    // one, skipping any notices sent after it
+    pub async fn get_token_from_last_email(&self, query_param: &str) -> String {
+        let requests = self
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled");
+
+        let prefix = format!("{}=", query_param);
+        let html = requests
+            .iter()
+            .rev()
+            .map(|request| {
+                let body: serde_json::Value =
+                    serde_json::from_slice(&request.body).expect("Email body is not valid JSON");
+                body["html"]
+                    .as_str()
+                    .expect("Email has no html content")
+                    .to_owned()
+            })
+            .find(|html| html.contains(&prefix))
+            .expect("No email contains a token");
+        let start = html.find(&prefix).unwrap() + prefix.len();
+        html[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect()
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
@@ -165,7 +941,7 @@ impl Drop for TestApp {
 async fn configure_postgresql(db_name: &str) -> PgPool {
     let postgresql_conn_url = DATABASE_URL.to_owned();
 
-    configure_database(&postgresql_conn_url, &db_name).await;
+    configure_database(&postgresql_conn_url, db_name).await;
 
     let postgresql_conn_url_with_db = SecretString::new(
         format!("{}/{}", postgresql_conn_url.expose_secret(), db_name).into_boxed_str(),
@@ -208,7 +984,7 @@ async fn configure_database(db_conn_string: &SecretString, db_name: &str) {
 async fn delete_database(db_name: &str) {
     let postgresql_conn_url = DATABASE_URL.to_owned();
 
-    let connection_options = PgConnectOptions::from_str(&postgresql_conn_url.expose_secret())
+    let connection_options = PgConnectOptions::from_str(postgresql_conn_url.expose_secret())
         .expect("Failed to parse PostgresSQL connection string");
 
     let mut connection = PgConnection::connect_with(&connection_options)
@@ -227,7 +1003,7 @@ async fn delete_database(db_name: &str) {
         "#,
                 db_name
             )
-                .as_str(),
+            .as_str(),
         )
         .await
         .expect("Failed to drop the database.");
@@ -239,7 +1015,7 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
+pub fn configure_redis() -> redis::Connection {
     let redis_hostname = DEFAULT_REDIS_HOSTNAME.to_owned();
 
     get_redis_client(redis_hostname)
@@ -251,13 +1027,21 @@ pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
+// Generate the code an authenticator app would currently show for `otpauth_uri`
+pub fn generate_totp_code(otpauth_uri: &str) -> String {
+    TOTP::from_url(otpauth_uri)
+        .expect("Invalid otpauth URI")
+        .generate_current()
+        .expect("Failed to generate TOTP code")
+}
+
 fn configure_resend_email_client(base_url: String) -> ResendEmailClient {
     let resend_auth_token = SecretString::new("resend_auth_token".to_owned().into_boxed_str());
 
     let sender = Email::parse(SecretString::new(
         test::email_client::SENDER.to_owned().into_boxed_str(),
     ))
-        .unwrap();
+    .unwrap();
 
     let http_client = Client::builder()
         .timeout(test::email_client::TIMEOUT)
@@ -266,3 +1050,21 @@ fn configure_resend_email_client(base_url: String) -> ResendEmailClient {
 
     ResendEmailClient::new(base_url, sender, resend_auth_token, http_client)
 }
+
+fn configure_identity_provider_client(issuer: String) -> IdentityProviderClient {
+    let provider = IdentityProvider {
+        name: IDENTITY_PROVIDER_NAME.to_owned(),
+        issuer,
+        client_id: IDENTITY_PROVIDER_CLIENT_ID.to_owned(),
+        client_secret: SecretString::new(
+            IDENTITY_PROVIDER_CLIENT_SECRET.to_owned().into_boxed_str(),
+        ),
+    };
+
+    let http_client = Client::builder()
+        .timeout(test::identity_provider_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    IdentityProviderClient::new(vec![provider], http_client)
+}
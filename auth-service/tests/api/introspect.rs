@@ -0,0 +1,322 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{CreateApiKeyResponse, IntrospectResponse, TokenResponse};
+use auth_service::utils::constants::{
+    INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET, JWT_AUDIENCE, JWT_COOKIE_NAME,
+    JWT_ISSUER, REFRESH_COOKIE_NAME,
+};
+use auth_service::ErrorResponse;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+
+// Log a new user in and return their JWT auth token
+async fn login(app: &TestApp, email: &str) -> String {
+    login_tokens(app, email).await.0
+}
+
+// Log a new user in and return their JWT auth token and refresh token
+async fn login_tokens(app: &TestApp, email: &str) -> (String, String) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.mark_email_verified(email).await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    let cookie = |name: &str| {
+        response
+            .cookies()
+            .find(|cookie| cookie.name() == name)
+            .expect("No cookie found")
+            .value()
+            .to_owned()
+    };
+    (cookie(JWT_COOKIE_NAME), cookie(REFRESH_COOKIE_NAME))
+}
+
+fn client_secret() -> &'static str {
+    INTROSPECTION_CLIENT_SECRET
+        .as_ref()
+        .expect("INTROSPECTION_CLIENT_SECRET must be set to run the introspection tests")
+        .expose_secret()
+}
+
+async fn introspect(app: &TestApp, token: &str) -> IntrospectResponse {
+    introspect_with_hint(app, token, "access_token").await
+}
+
+async fn introspect_with_hint(app: &TestApp, token: &str, hint: &str) -> IntrospectResponse {
+    let response = app
+        .post_introspect(
+            &[("token", token), ("token_type_hint", hint)],
+            &INTROSPECTION_CLIENT_ID,
+            client_secret(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse")
+}
+
+#[api_test]
+async fn should_return_active_token_details() {
+    let random_email = get_random_email();
+    let token = login(&app, &random_email).await;
+    app.grant_role(&random_email, "support").await;
+
+    let body = introspect(&app, &token).await;
+
+    assert!(body.active);
+    let user_id = app.get_user_id(&random_email).await;
+    assert_eq!(body.sub.as_deref(), Some(user_id.as_str()));
+    assert_eq!(body.username.as_deref(), Some(random_email.as_str()));
+    assert_eq!(body.iss.as_deref(), Some(JWT_ISSUER.as_str()));
+    assert_eq!(body.aud.as_deref(), Some(JWT_AUDIENCE.as_str()));
+    assert_eq!(body.token_type.as_deref(), Some("Bearer"));
+    // Roles granted after login only show up in the next token
+    assert_eq!(body.scope.as_deref(), Some(""));
+    assert!(body.jti.is_some());
+
+    let exp = body.exp.expect("No exp in response");
+    let iat = body.iat.expect("No iat in response");
+    assert!(exp > iat);
+
+    // The session id matches the one listed for the user's login
+    let sessions = app.get_sessions().await.text().await.unwrap();
+    assert!(sessions.contains(&body.sid.expect("No sid in response")));
+}
+
+#[api_test]
+async fn should_return_roles_as_scope() {
+    let random_email = get_random_email();
+    login(&app, &random_email).await;
+    app.grant_role(&random_email, "support").await;
+    app.grant_role(&random_email, "admin").await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let body = introspect(&app, &token).await;
+
+    assert!(body.active);
+    assert_eq!(body.scope.as_deref(), Some("admin support"));
+}
+
+#[api_test]
+async fn should_return_client_credentials_token_details() {
+    let client = app
+        .register_machine_client(&["orders:read", "orders:write"])
+        .await;
+    let response = app
+        .post_token_with_basic_auth(
+            &client.client_id,
+            &client.client_secret,
+            &[("grant_type", "client_credentials")],
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+
+    let body = introspect(&app, &tokens.access_token).await;
+
+    assert!(body.active);
+    assert_eq!(body.client_id.as_deref(), Some(client.client_id.as_str()));
+    assert_eq!(body.sub.as_deref(), Some(client.client_id.as_str()));
+    assert_eq!(body.scope.as_deref(), Some("orders:read orders:write"));
+    assert_eq!(body.token_type.as_deref(), Some("Bearer"));
+    // There is no user behind the token
+    assert!(body.username.is_none());
+    assert!(body.sid.is_none());
+}
+
+#[api_test]
+async fn should_return_api_key_details() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let response = app
+        .post_api_key(&serde_json::json!({
+            "name": "CI",
+            "scopes": ["deploy", "logs:read"],
+            "expiresInDays": 30
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let created = response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse");
+
+    let body = introspect(&app, &created.key).await;
+
+    assert!(body.active);
+    let user_id = app.get_user_id(&random_email).await;
+    assert_eq!(body.sub.as_deref(), Some(user_id.as_str()));
+    assert_eq!(body.username.as_deref(), Some(random_email.as_str()));
+    assert_eq!(body.scope.as_deref(), Some("deploy logs:read"));
+    assert!(body.client_id.is_none());
+    assert!(body.exp.expect("No exp in response") > body.iat.expect("No iat in response"));
+
+    // Revoked keys are inactive
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!introspect(&app, &created.key).await.active);
+}
+
+#[api_test]
+async fn should_return_refresh_token_details_without_using_it() {
+    let random_email = get_random_email();
+    let (access_token, refresh_token) = login_tokens(&app, &random_email).await;
+
+    let body = introspect_with_hint(&app, &refresh_token, "refresh_token").await;
+
+    assert!(body.active);
+    let user_id = app.get_user_id(&random_email).await;
+    assert_eq!(body.sub.as_deref(), Some(user_id.as_str()));
+    assert_eq!(body.username.as_deref(), Some(random_email.as_str()));
+    assert!(body.sid.is_some());
+    assert!(body.client_id.is_none());
+    assert!(body.token_type.is_none());
+
+    // Tokens are found whatever the hint says
+    assert!(introspect(&app, &refresh_token).await.active);
+    assert!(
+        introspect_with_hint(&app, &access_token, "refresh_token")
+            .await
+            .active
+    );
+
+    // Looking at the token didn't use it up, but swapping it does
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        !introspect_with_hint(&app, &refresh_token, "refresh_token")
+            .await
+            .active
+    );
+
+    // Introspecting a used token doesn't end the login, as reusing it would
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_inactive_for_invalid_token() {
+    for token in ["", "invalid_token"] {
+        let response = app
+            .post_introspect(
+                &[("token", token)],
+                &INTROSPECTION_CLIENT_ID,
+                client_secret(),
+            )
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+
+        // Nothing but `active` is disclosed
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body, serde_json::json!({ "active": false }));
+    }
+}
+
+#[api_test]
+async fn should_return_inactive_after_logout() {
+    let token = login(&app, &get_random_email()).await;
+    assert!(introspect(&app, &token).await.active);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert!(!introspect(&app, &token).await.active);
+}
+
+#[api_test]
+async fn should_accept_client_credentials_in_body() {
+    let token = login(&app, &get_random_email()).await;
+
+    let body = [
+        ("token", token.as_str()),
+        ("token_type_hint", "access_token"),
+        ("client_id", INTROSPECTION_CLIENT_ID.as_str()),
+        ("client_secret", client_secret()),
+    ];
+    let response = app.post_introspect_unauthenticated(&body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse");
+    assert!(body.active);
+}
+
+#[api_test]
+async fn should_return_401_if_client_not_authenticated() {
+    let token = login(&app, &get_random_email()).await;
+
+    let responses = [
+        app.post_introspect_unauthenticated(&[("token", token.as_str())])
+            .await,
+        app.post_introspect(
+            &[("token", token.as_str())],
+            &INTROSPECTION_CLIENT_ID,
+            "wrong",
+        )
+        .await,
+        app.post_introspect(
+            &[("token", token.as_str())],
+            "other-client",
+            client_secret(),
+        )
+        .await,
+        app.post_introspect_unauthenticated(&[
+            ("token", token.as_str()),
+            ("client_id", INTROSPECTION_CLIENT_ID.as_str()),
+            ("client_secret", "wrong"),
+        ])
+        .await,
+    ];
+
+    for response in responses {
+        assert_eq!(response.status().as_u16(), 401);
+        assert!(response.headers().contains_key("www-authenticate"));
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid client".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_introspect(
+            &[("token_type_hint", "access_token")],
+            &INTROSPECTION_CLIENT_ID,
+            client_secret(),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
@@ -0,0 +1,116 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::SigningKey;
+use auth_service::utils::{
+    auth::rotate_signing_key,
+    constants::{JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER},
+};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use test_helpers::api_test;
+
+const RS256_PRIVATE_KEY: &[u8] = include_bytes!("../fixtures/jwt_rs256_private.pem");
+const RS256_PUBLIC_KEY: &[u8] = include_bytes!("../fixtures/jwt_rs256_public.pem");
+const EDDSA_PRIVATE_KEY: &[u8] = include_bytes!("../fixtures/jwt_eddsa_private.pem");
+const EDDSA_PUBLIC_KEY: &[u8] = include_bytes!("../fixtures/jwt_eddsa_public.pem");
+
+async fn login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.mark_email_verified(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    app.post_login(&login_body)
+        .await
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+// Verify `token` the way another service would, with the published key its `kid` names
+fn verify_with_jwks(jwks: &JwkSet, token: &str) -> bool {
+    let header = decode_header(token).unwrap();
+    let Some(jwk) = header.kid.as_deref().and_then(|kid| jwks.find(kid)) else {
+        return false;
+    };
+    let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[JWT_AUDIENCE.as_str()]);
+
+    decode::<serde_json::Value>(token, &decoding_key, &validation).is_ok()
+}
+
+#[api_test]
+async fn should_return_200_with_cacheable_key_set() {
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+    assert_eq!(
+        response.headers().get("cache-control").unwrap(),
+        "public, max-age=300"
+    );
+
+    response
+        .json::<JwkSet>()
+        .await
+        .expect("Could not deserialize response body to JwkSet");
+}
+
+#[api_test]
+async fn should_publish_keys_that_verify_auth_tokens() {
+    let token = login(&app).await;
+    let algorithm = decode_header(&token).unwrap().alg;
+
+    let jwks = app.get_jwks().await.json::<JwkSet>().await.unwrap();
+
+    // A shared HS256 secret must never be published
+    if algorithm == Algorithm::HS256 {
+        assert!(jwks.keys.is_empty());
+    } else {
+        assert!(verify_with_jwks(&jwks, &token));
+    }
+}
+
+#[api_test]
+async fn should_publish_retired_keys_alongside_the_active_key() {
+    let old_token = login(&app).await;
+    let old_algorithm = decode_header(&old_token).unwrap().alg;
+
+    // Rotate to a key that differs from whichever one is configured
+    let signing_key = match old_algorithm {
+        Algorithm::EdDSA => {
+            SigningKey::from_pem(Algorithm::RS256, RS256_PRIVATE_KEY, RS256_PUBLIC_KEY)
+        }
+        _ => SigningKey::from_pem(Algorithm::EdDSA, EDDSA_PRIVATE_KEY, EDDSA_PUBLIC_KEY),
+    }
+    .expect("Failed to load signing key");
+    rotate_signing_key(app.signing_key_store.clone(), signing_key)
+        .await
+        .expect("Failed to rotate signing key");
+
+    let new_token = login(&app).await;
+
+    let jwks = app.get_jwks().await.json::<JwkSet>().await.unwrap();
+
+    assert!(verify_with_jwks(&jwks, &new_token));
+    if old_algorithm == Algorithm::HS256 {
+        assert_eq!(jwks.keys.len(), 1);
+    } else {
+        assert_eq!(jwks.keys.len(), 2);
+        assert!(verify_with_jwks(&jwks, &old_token));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::domain::Email;
+use auth_service::domain::{Email, LoginAttemptKey, TwoFAMethod};
 use auth_service::routes::TwoFactorAuthResponse;
 use auth_service::utils::constants::JWT_COOKIE_NAME;
 use auth_service::ErrorResponse;
@@ -22,6 +22,8 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -41,7 +43,6 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
 #[api_test]
 async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
-
     let random_email = get_random_email();
 
     let signup_body = serde_json::json!({
@@ -54,6 +55,8 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     Mock::given(path("/emails"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -75,6 +78,7 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
         .expect("Could not deserialize response body to TwoFactorAuthResponse");
 
     assert_eq!(json_body.message, "2FA required".to_owned());
+    assert_eq!(json_body.two_fa_method, TwoFAMethod::Email);
 
     // assert that `json_body.login_attempt_id` is stored inside `app.two_fa_code_store`
     let two_fa_code_store = app.two_fa_code_store.read().await;
@@ -91,8 +95,39 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 }
 
 #[api_test]
-async fn should_return_422_if_malformed_credentials() {
+async fn should_return_206_without_sending_email_if_totp_enabled() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    app.enable_totp().await;
+
+    // The code comes from the user's authenticator app, so nothing is emailed
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
 
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    assert_eq!(json_body.message, "2FA required".to_owned());
+    assert_eq!(json_body.two_fa_method, TwoFAMethod::Totp);
+    assert!(!json_body.login_attempt_id.is_empty());
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_credentials() {
     let random_email = get_random_email();
 
     let test_cases = [
@@ -165,6 +200,39 @@ async fn should_return_400_if_invalid_input() {
     assert_eq!(response.status().as_u16(), 400);
 }
 
+#[api_test]
+async fn should_return_403_if_email_not_verified() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email not verified".to_owned()
+    );
+}
+
 #[api_test]
 async fn should_return_401_if_incorrect_credentials() {
     // Call the log-in route with incorrect credentials and assert
@@ -182,6 +250,8 @@ async fn should_return_401_if_incorrect_credentials() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     let response = app
         .post_login(&serde_json::json!({
             "email": random_email,
@@ -191,3 +261,191 @@ async fn should_return_401_if_incorrect_credentials() {
 
     assert_eq!(response.status().as_u16(), 401);
 }
+
+#[api_test]
+async fn should_return_429_after_repeated_failures() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrong password",
+    });
+
+    for _ in 0..3 {
+        let response = app.post_login(&wrong_login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // Even the right password has to wait until the delay has passed
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(
+        response
+            .headers()
+            .get("retry-after")
+            .expect("No Retry-After header"),
+        "1"
+    );
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many attempts".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_throttle_parallel_failures() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrong password",
+    });
+
+    // Attempts made at once are counted before any of them is checked
+    let responses = tokio::join!(
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+    );
+    let mut statuses = [
+        responses.0.status().as_u16(),
+        responses.1.status().as_u16(),
+        responses.2.status().as_u16(),
+        responses.3.status().as_u16(),
+        responses.4.status().as_u16(),
+        responses.5.status().as_u16(),
+    ];
+    statuses.sort();
+
+    assert_eq!(statuses, [401, 401, 401, 429, 429, 429]);
+}
+
+#[api_test]
+async fn should_return_423_if_account_locked() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let key = LoginAttemptKey::Email(
+        Email::parse(SecretString::new(random_email.clone().into())).unwrap(),
+    );
+    for _ in 0..10 {
+        app.login_attempt_store
+            .write()
+            .await
+            .record_failure(&key)
+            .await
+            .unwrap();
+    }
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+    let retry_after: u64 = response
+        .headers()
+        .get("retry-after")
+        .expect("No Retry-After header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 890 && retry_after <= 900);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Account locked".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_423_if_ip_address_locked() {
+    // Failures spread over many accounts from one address add up
+    let key = LoginAttemptKey::IpAddress("127.0.0.1".to_owned());
+    for _ in 0..100 {
+        app.login_attempt_store
+            .write()
+            .await
+            .record_failure(&key)
+            .await
+            .unwrap();
+    }
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+}
+
+#[api_test]
+async fn should_clear_failures_after_successful_login() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    for _ in 0..2 {
+        let response = app
+            .post_login(&serde_json::json!({
+                "email": random_email,
+                "password": "wrong password",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let key = LoginAttemptKey::Email(
+        Email::parse(SecretString::new(random_email.clone().into())).unwrap(),
+    );
+    let failures = app
+        .login_attempt_store
+        .read()
+        .await
+        .get_failures(&key)
+        .await
+        .unwrap();
+    assert_eq!(failures, None);
+
+    // The address's failures are kept
+    let key = LoginAttemptKey::IpAddress("127.0.0.1".to_owned());
+    let failures = app
+        .login_attempt_store
+        .read()
+        .await
+        .get_failures(&key)
+        .await
+        .unwrap()
+        .expect("No failures recorded for address");
+    assert_eq!(failures.count, 2);
+}
@@ -1,12 +1,14 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+use jsonwebtoken::dangerous::insecure_decode;
 use reqwest::Url;
-use secrecy::SecretString;
 use test_helpers::api_test;
 
 #[api_test]
 async fn should_return_200_if_valid_jwt_cookie() {
-
     let random_email = get_random_email();
 
     let signup_body = serde_json::json!({
@@ -19,6 +21,8 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -35,7 +39,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(!auth_cookie.value().is_empty());
 
-    let token = SecretString::new(auth_cookie.value().to_owned().into());
+    let token = auth_cookie.value().to_owned();
 
     let response = app.post_logout().await;
 
@@ -48,10 +52,16 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(auth_cookie.value().is_empty());
 
+    // The token is banned by its ID rather than stored whole
+    let jti = insecure_decode::<Claims>(&token)
+        .expect("Failed to decode token")
+        .claims
+        .jti;
+
     let banned_token_store = app.banned_token_store.read().await;
 
     let contains_token = banned_token_store
-        .contains_token(&token)
+        .contains_token_id(&jti)
         .await
         .expect("Failed to check if token is banned");
 
@@ -72,6 +82,8 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -135,7 +147,6 @@ async fn should_return_400_if_jwt_cookie_missing() {
 
 #[api_test]
 async fn should_return_401_if_invalid_token() {
-
     // add invalid cookie
     app.cookie_jar.add_cookie_str(
         &format!(
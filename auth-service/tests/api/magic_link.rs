@@ -0,0 +1,232 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{MagicLinkResponse, VerifyTokenResponse};
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use auth_service::ErrorResponse;
+use test_helpers::api_test;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": requires_2fa
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.mark_email_verified(email).await;
+}
+
+async fn mock_emails(app: &TestApp, expected: u64) {
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected)
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn request_link(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_magic_link(&serde_json::json!({ "email": email }))
+        .await
+}
+
+// The user id and token from the most recently sent link
+async fn get_link_params(app: &TestApp) -> (String, String) {
+    (
+        app.get_token_from_last_email("magic_link_id").await,
+        app.get_token_from_last_email("magic_link_token").await,
+    )
+}
+
+#[api_test]
+async fn should_log_in_from_link() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    mock_emails(&app, 1).await;
+
+    let response = request_link(&app, &random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<MagicLinkResponse>()
+            .await
+            .expect("Could not deserialize response body to MagicLinkResponse")
+            .message,
+        "If this email belongs to an account that can use magic links, one has been sent"
+            .to_owned()
+    );
+
+    let (id, token) = get_link_params(&app).await;
+    assert_eq!(id, app.get_user_id(&random_email).await);
+
+    let response = app.post_magic_link_callback(&id, &token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == REFRESH_COOKIE_NAME));
+
+    // The cookie holds the same kind of token `/login` issues
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_cookie.value() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.email.as_deref(), Some(random_email.as_str()));
+}
+
+#[api_test]
+async fn should_return_401_if_link_used_twice() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    mock_emails(&app, 1).await;
+
+    request_link(&app, &random_email).await;
+    let (id, token) = get_link_params(&app).await;
+
+    let response = app.post_magic_link_callback(&id, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_magic_link_callback(&id, &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_not_spend_link_when_it_is_fetched() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    mock_emails(&app, 1).await;
+
+    request_link(&app, &random_email).await;
+    let (id, token) = get_link_params(&app).await;
+
+    // Mail scanners fetch links without confirming anything
+    let response = app
+        .http_client
+        .get(format!("{}/login/magic-link/callback", &app.address))
+        .query(&[("id", id.as_str()), ("token", token.as_str())])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 405);
+
+    let response = app.post_magic_link_callback(&id, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_link_replaced() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    mock_emails(&app, 2).await;
+
+    request_link(&app, &random_email).await;
+    let (id, old_token) = get_link_params(&app).await;
+    request_link(&app, &random_email).await;
+    let (_, new_token) = get_link_params(&app).await;
+
+    let response = app.post_magic_link_callback(&id, &old_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_magic_link_callback(&id, &new_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_token_belongs_to_another_user() {
+    let first_email = get_random_email();
+    let second_email = get_random_email();
+    signup(&app, &first_email, false).await;
+    signup(&app, &second_email, false).await;
+    mock_emails(&app, 1).await;
+
+    request_link(&app, &first_email).await;
+    let (_, token) = get_link_params(&app).await;
+    let other_id = app.get_user_id(&second_email).await;
+
+    let response = app.post_magic_link_callback(&other_id, &token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_not_send_link_to_unknown_or_2fa_accounts() {
+    let two_fa_email = get_random_email();
+    signup(&app, &two_fa_email, true).await;
+
+    // Both get the same answer as an account that can use links
+    mock_emails(&app, 0).await;
+
+    for email in [get_random_email(), two_fa_email] {
+        let response = request_link(&app, &email).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let id = app.get_user_id(&random_email).await;
+    let token = Uuid::new_v4().to_string();
+
+    let response = request_link(&app, "not-an-email").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let test_cases = [("not-a-uuid", token.as_str()), (id.as_str(), "not-a-uuid")];
+    for (id, token) in test_cases {
+        let response = app.post_magic_link_callback(id, token).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for id: {}, token: {}",
+            id,
+            token
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_401_if_unknown_user_or_token() {
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let token = Uuid::new_v4().to_string();
+
+    // No link has been requested
+    let id = app.get_user_id(&random_email).await;
+    let response = app.post_magic_link_callback(&id, &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let unknown_id = Uuid::new_v4().to_string();
+    let response = app.post_magic_link_callback(&unknown_id, &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_magic_link(&serde_json::json!({ "address": get_random_email() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
@@ -1,7 +1,30 @@
+mod account;
+mod admin;
+mod api_keys;
+mod change_email;
+mod change_password;
+mod client_credentials;
+mod confirm_totp;
+mod device_authorization;
+mod enroll_totp;
+mod forgot_password;
 mod helpers;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod refresh;
+mod regenerate_recovery_codes;
+mod resend_verification;
+mod reset_password;
 mod root;
+mod sessions;
 mod signup;
+mod social_login;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webauthn;
@@ -0,0 +1,616 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{
+    AccountExportResponse, IntrospectResponse, TokenKind, TokenResponse, VerifyTokenResponse,
+};
+use auth_service::utils::constants::{
+    INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET, JWT_COOKIE_NAME, REFRESH_COOKIE_NAME,
+};
+use auth_service::ErrorResponse;
+use reqwest::header::{CACHE_CONTROL, LOCATION, X_FRAME_OPTIONS};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const REDIRECT_URI: &str = "https://app.example.com/callback";
+const STATE: &str = "af0ifjsldkj";
+
+// The example from RFC 7636 appendix B
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+fn authorize_query(client_id: &str) -> Vec<(&str, &str)> {
+    vec![
+        ("client_id", client_id),
+        ("redirect_uri", REDIRECT_URI),
+        ("response_type", "code"),
+        ("state", STATE),
+        ("code_challenge", CODE_CHALLENGE),
+        ("code_challenge_method", "S256"),
+    ]
+}
+
+// Approve the client on the consent page and return the code it is sent
+async fn approve(app: &TestApp, client_id: &str) -> String {
+    let mut form = authorize_query(client_id);
+    form.push(("decision", "approve"));
+    let response = app.post_authorize(&form).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let location = redirect_location(&response);
+    assert_eq!(query_param(&location, "state"), Some(STATE.to_owned()));
+    query_param(&location, "code").expect("No code in redirect")
+}
+
+async fn exchange_code(
+    app: &TestApp,
+    client_id: &str,
+    code: &str,
+    code_verifier: &str,
+) -> reqwest::Response {
+    app.post_token(&[
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", REDIRECT_URI),
+        ("client_id", client_id),
+        ("code_verifier", code_verifier),
+    ])
+    .await
+}
+
+fn redirect_location(response: &reqwest::Response) -> Url {
+    let location = response
+        .headers()
+        .get(LOCATION)
+        .expect("No redirect location")
+        .to_str()
+        .unwrap();
+    Url::parse(location).expect("Redirect location is not a URL")
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+async fn assert_token_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+async fn assert_token_belongs_to(app: &TestApp, access_token: &str, email: &str) {
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.kind, TokenKind::Delegated);
+    assert_eq!(body.email.as_deref(), Some(email));
+}
+
+async fn refresh(app: &TestApp, client_id: &str, refresh_token: &str) -> reqwest::Response {
+    app.post_token(&[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", client_id),
+    ])
+    .await
+}
+
+async fn introspect(app: &TestApp, token: &str, hint: &str) -> IntrospectResponse {
+    let client_secret = INTROSPECTION_CLIENT_SECRET
+        .as_ref()
+        .expect("INTROSPECTION_CLIENT_SECRET must be set to run the introspection tests")
+        .expose_secret();
+    let response = app
+        .post_introspect(
+            &[("token", token), ("token_type_hint", hint)],
+            &INTROSPECTION_CLIENT_ID,
+            client_secret,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectResponse")
+}
+
+// Log a new user in to the client and return its tokens
+async fn log_in_to_client(app: &TestApp, client_id: &str, email: &str) -> TokenResponse {
+    app.signup_and_login(email, "password123").await;
+    let code = approve(app, client_id).await;
+    let response = exchange_code(app, client_id, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+}
+
+#[api_test]
+async fn should_issue_tokens_for_authorization_code() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let query = authorize_query(&client_id);
+
+    // Logged out users are sent to log in first, and back to `/authorize` after
+    let response = app.get_authorize(&query).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let next = query_param(&redirect_location(&response), "next").expect("No next parameter");
+    assert!(next.starts_with("/authorize?"));
+
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = app.get_authorize(&query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get(X_FRAME_OPTIONS).unwrap(), "DENY");
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Example App"));
+    assert!(page.contains(&random_email));
+
+    let code = approve(&app, &client_id).await;
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-store");
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.expires_in, 600);
+    assert_token_belongs_to(&app, &tokens.access_token, &random_email).await;
+
+    let refresh_token = tokens
+        .refresh_token
+        .clone()
+        .expect("No refresh token issued");
+    let response = refresh(&app, &client_id, &refresh_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let refreshed = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_ne!(refreshed.refresh_token, tokens.refresh_token);
+    assert_token_belongs_to(&app, &refreshed.access_token, &random_email).await;
+}
+
+#[api_test]
+async fn should_issue_access_tokens_for_the_client_only() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let random_email = get_random_email();
+    let mut query = authorize_query(&client_id);
+    query.push(("scope", "openid email"));
+    app.signup_and_login(&random_email, "password123").await;
+    app.grant_role(&random_email, "support").await;
+
+    let mut form = query.clone();
+    form.push(("decision", "approve"));
+    let response = app.post_authorize(&form).await;
+    let code = query_param(&redirect_location(&response), "code").expect("No code in redirect");
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_eq!(tokens.scope.as_deref(), Some("openid email"));
+
+    // The token carries what the user granted the client, and none of the user's roles
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.kind, TokenKind::Delegated);
+    assert_eq!(body.id, app.get_user_id(&random_email).await);
+    assert_eq!(body.client_id.as_deref(), Some(client_id.as_str()));
+    assert_eq!(body.scopes, vec!["openid".to_owned(), "email".to_owned()]);
+    assert!(body.roles.is_empty());
+
+    // Nor does it work as the user's own auth cookie
+    app.post_logout().await;
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Path=/",
+            JWT_COOKIE_NAME, tokens.access_token
+        ),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_introspect_tokens_issued_to_the_client() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let random_email = get_random_email();
+    let mut query = authorize_query(&client_id);
+    query.push(("scope", "openid email"));
+    app.signup_and_login(&random_email, "password123").await;
+
+    query.push(("decision", "approve"));
+    let response = app.post_authorize(&query).await;
+    let code = query_param(&redirect_location(&response), "code").expect("No code in redirect");
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    let refresh_token = tokens.refresh_token.expect("No refresh token issued");
+
+    let user_id = app.get_user_id(&random_email).await;
+    for (token, hint) in [
+        (tokens.access_token.as_str(), "access_token"),
+        (refresh_token.as_str(), "refresh_token"),
+    ] {
+        let body = introspect(&app, token, hint).await;
+        assert!(body.active, "Failed for: {}", hint);
+        assert_eq!(body.client_id.as_deref(), Some(client_id.as_str()));
+        assert_eq!(body.sub.as_deref(), Some(user_id.as_str()));
+        assert_eq!(body.username.as_deref(), Some(random_email.as_str()));
+        assert_eq!(body.scope.as_deref(), Some("openid email"));
+    }
+
+    // The access token is only meant for the client
+    let body = introspect(&app, &tokens.access_token, "access_token").await;
+    assert_eq!(body.aud.as_deref(), Some(client_id.as_str()));
+}
+
+#[api_test]
+async fn should_only_swap_refresh_tokens_for_the_client_they_were_issued_to() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let other_client_id = app.register_oauth_client(REDIRECT_URI).await;
+
+    // Another client can't use the token, and it is spent all the same
+    let tokens = log_in_to_client(&app, &client_id, &get_random_email()).await;
+    let refresh_token = tokens.refresh_token.expect("No refresh token issued");
+    let response = refresh(&app, &other_client_id, &refresh_token).await;
+    assert_token_error(response, 400, "invalid_grant").await;
+    let response = refresh(&app, &client_id, &refresh_token).await;
+    assert_token_error(response, 400, "invalid_grant").await;
+
+    // Clients have to say who they are
+    let tokens = log_in_to_client(&app, &client_id, &get_random_email()).await;
+    let refresh_token = tokens.refresh_token.expect("No refresh token issued");
+    let response = app
+        .post_token(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+        ])
+        .await;
+    assert_token_error(response, 400, "invalid_request").await;
+
+    // Nor can the client swap its token for the user's own cookies
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Strict; Path=/",
+            REFRESH_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_not_swap_the_users_own_refresh_tokens() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    let response = refresh(&app, &client_id, &refresh_token).await;
+
+    assert_token_error(response, 400, "invalid_grant").await;
+}
+
+#[api_test]
+async fn should_issue_tokens_after_the_user_changes_email() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let old_email = get_random_email();
+    let new_email = get_random_email();
+    app.signup_and_login(&old_email, "password123").await;
+    let code = approve(&app, &client_id).await;
+
+    // The user moves to a new address before the client swaps the code
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_change_email(&serde_json::json!({
+            "newEmail": new_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = app.get_token_from_last_email("email_change_token").await;
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let tokens = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse");
+    assert_token_belongs_to(&app, &tokens.access_token, &new_email).await;
+}
+
+#[api_test]
+async fn should_remember_consent() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    approve(&app, &client_id).await;
+
+    let response = app.get_authorize(&authorize_query(&client_id)).await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    let location = redirect_location(&response);
+    assert_eq!(location.path(), "/callback");
+    assert!(query_param(&location, "code").is_some());
+    assert_eq!(query_param(&location, "state"), Some(STATE.to_owned()));
+
+    // The user can see which clients they allowed
+    let export = app
+        .get_account_export()
+        .await
+        .json::<AccountExportResponse>()
+        .await
+        .expect("Could not deserialize response body to AccountExportResponse");
+    assert_eq!(export.oauth_consents.len(), 1);
+    assert_eq!(export.oauth_consents[0].client_id, client_id);
+    assert_eq!(export.oauth_consents[0].client_name, "Example App");
+}
+
+#[api_test]
+async fn should_ask_again_for_more_scopes_than_allowed() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let mut query = authorize_query(&client_id);
+    query.push(("scope", "openid"));
+    let mut form = query.clone();
+    form.push(("decision", "approve"));
+    assert_eq!(app.post_authorize(&form).await.status().as_u16(), 303);
+
+    // The same scopes go straight through...
+    assert_eq!(app.get_authorize(&query).await.status().as_u16(), 303);
+
+    // ...but asking for the email too shows the consent page again, listing what is asked for
+    let mut query = authorize_query(&client_id);
+    query.push(("scope", "openid email"));
+    let response = app.get_authorize(&query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("<strong>openid</strong>"));
+    assert!(page.contains("<strong>email</strong>"));
+
+    let mut form = query.clone();
+    form.push(("decision", "approve"));
+    assert_eq!(app.post_authorize(&form).await.status().as_u16(), 303);
+    assert_eq!(app.get_authorize(&query).await.status().as_u16(), 303);
+
+    let export = app
+        .get_account_export()
+        .await
+        .json::<AccountExportResponse>()
+        .await
+        .expect("Could not deserialize response body to AccountExportResponse");
+    assert_eq!(export.oauth_consents.len(), 1);
+    assert_eq!(export.oauth_consents[0].scopes, vec!["email", "openid"]);
+}
+
+#[api_test]
+async fn should_redirect_with_access_denied_if_user_denies() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let mut form = authorize_query(&client_id);
+    form.push(("decision", "deny"));
+    let response = app.post_authorize(&form).await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    let location = redirect_location(&response);
+    assert_eq!(
+        query_param(&location, "error"),
+        Some("access_denied".to_owned())
+    );
+    assert_eq!(query_param(&location, "code"), None);
+
+    // Nothing was remembered, so the user is asked again
+    let response = app.get_authorize(&authorize_query(&client_id)).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_unknown_client_or_redirect_uri() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let unknown_client_id = Uuid::new_v4().to_string();
+
+    let test_cases = [
+        (unknown_client_id.as_str(), REDIRECT_URI, "Unknown client"),
+        ("not-a-uuid", REDIRECT_URI, "Unknown client"),
+        (
+            client_id.as_str(),
+            "https://evil.example.com/callback",
+            "Invalid redirect URI",
+        ),
+        (
+            client_id.as_str(),
+            "https://app.example.com/callback/",
+            "Invalid redirect URI",
+        ),
+    ];
+
+    for (client_id, redirect_uri, error) in test_cases {
+        let mut query = authorize_query(client_id);
+        query[1] = ("redirect_uri", redirect_uri);
+        let response = app.get_authorize(&query).await;
+
+        // Never redirect to a URI the client didn't register
+        assert_eq!(response.status().as_u16(), 400, "Failed for: {:?}", query);
+        assert!(response.headers().get(LOCATION).is_none());
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            error.to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_redirect_with_error_if_invalid_request() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let test_cases = [
+        ("code_challenge", "", "invalid_request"),
+        ("code_challenge", "not-a-challenge", "invalid_request"),
+        ("code_challenge_method", "plain", "invalid_request"),
+        ("response_type", "token", "unsupported_response_type"),
+    ];
+
+    for (name, value, error) in test_cases {
+        let query: Vec<_> = authorize_query(&client_id)
+            .into_iter()
+            .map(|(key, current)| (key, if key == name { value } else { current }))
+            .collect();
+        let response = app.get_authorize(&query).await;
+
+        assert_eq!(response.status().as_u16(), 303, "Failed for: {:?}", query);
+        let location = redirect_location(&response);
+        assert_eq!(query_param(&location, "error"), Some(error.to_owned()));
+        assert_eq!(query_param(&location, "state"), Some(STATE.to_owned()));
+    }
+}
+
+#[api_test]
+async fn should_return_invalid_grant_if_bad_verifier_or_reused_code() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    // A code sent with the wrong verifier is used up
+    let code = approve(&app, &client_id).await;
+    let wrong_verifier = CODE_VERIFIER.replace('d', "e");
+    let response = exchange_code(&app, &client_id, &code, &wrong_verifier).await;
+    assert_token_error(response, 400, "invalid_grant").await;
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    assert_token_error(response, 400, "invalid_grant").await;
+
+    // As is a code that has been swapped for tokens
+    let code = approve(&app, &client_id).await;
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = exchange_code(&app, &client_id, &code, CODE_VERIFIER).await;
+    assert_token_error(response, 400, "invalid_grant").await;
+
+    let unknown_code = Uuid::new_v4().to_string();
+    let response = exchange_code(&app, &client_id, &unknown_code, CODE_VERIFIER).await;
+    assert_token_error(response, 400, "invalid_grant").await;
+}
+
+#[api_test]
+async fn should_return_error_if_invalid_token_request() {
+    let response = app.post_token(&[("grant_type", "password")]).await;
+    assert_token_error(response, 400, "unsupported_grant_type").await;
+
+    let response = app.post_token(&[("code", "some-code")]).await;
+    assert_token_error(response, 400, "invalid_request").await;
+
+    let response = app
+        .post_token(&[("grant_type", "authorization_code")])
+        .await;
+    assert_token_error(response, 400, "invalid_request").await;
+
+    let unknown_token = Uuid::new_v4().to_string();
+    let client_id = Uuid::new_v4().to_string();
+    let response = refresh(&app, &client_id, &unknown_token).await;
+    assert_token_error(response, 400, "invalid_grant").await;
+}
+
+#[api_test]
+async fn should_only_let_admins_register_clients() {
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app
+        .post_register_oauth_client(&serde_json::json!({
+            "name": "Example App",
+            "redirectUris": [REDIRECT_URI],
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_client_registration() {
+    let admin_email = get_random_email();
+    app.signup_and_login(&admin_email, "password123").await;
+    app.grant_role(&admin_email, "admin").await;
+    app.post_login(&serde_json::json!({
+        "email": admin_email,
+        "password": "password123",
+    }))
+    .await;
+
+    let test_cases = [
+        serde_json::json!({ "name": "Example App", "redirectUris": [] }),
+        serde_json::json!({ "name": "Example App", "redirectUris": ["http://app.example.com"] }),
+        serde_json::json!({ "name": "", "redirectUris": [REDIRECT_URI] }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_register_oauth_client(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for: {:?}",
+            test_case
+        );
+    }
+}
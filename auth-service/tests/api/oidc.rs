@@ -0,0 +1,326 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::SigningKey;
+use auth_service::routes::{OpenIdConfiguration, TokenResponse, UserInfoResponse};
+use auth_service::utils::auth::{rotate_signing_key, IdTokenClaims};
+use auth_service::utils::constants::{AUTH_SERVICE_URL, JWT_COOKIE_NAME, JWT_ISSUER};
+use auth_service::ErrorResponse;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::header::{CACHE_CONTROL, LOCATION, WWW_AUTHENTICATE};
+use reqwest::Url;
+use test_helpers::api_test;
+
+const RS256_PRIVATE_KEY: &[u8] = include_bytes!("../fixtures/jwt_rs256_private.pem");
+const RS256_PUBLIC_KEY: &[u8] = include_bytes!("../fixtures/jwt_rs256_public.pem");
+
+const REDIRECT_URI: &str = "https://rp.example.com/callback";
+const NONCE: &str = "n-0S6_WzA2Mj";
+
+// The example from RFC 7636 appendix B
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+// A minimal OpenID Connect relying party that only knows the issuer, the way an off-the-shelf
+// client library would log a user in: discover the endpoints, send the user to authorize, swap
+// the code for tokens and check the ID token against the published keys
+struct RelyingParty<'a> {
+    app: &'a TestApp,
+    client_id: String,
+    configuration: OpenIdConfiguration,
+}
+
+impl<'a> RelyingParty<'a> {
+    async fn discover(app: &'a TestApp, client_id: String) -> Self {
+        let configuration = app
+            .get_openid_configuration()
+            .await
+            .json::<OpenIdConfiguration>()
+            .await
+            .expect("Could not deserialize response body to OpenIdConfiguration");
+
+        Self {
+            app,
+            client_id,
+            configuration,
+        }
+    }
+
+    // Endpoints are published under `AUTH_SERVICE_URL`, but the test app listens elsewhere
+    fn endpoint(&self, url: &str) -> String {
+        let path = url
+            .strip_prefix(AUTH_SERVICE_URL.as_str())
+            .expect("Endpoint is not on the auth service");
+        format!("{}{}", self.app.address, path)
+    }
+
+    // Authorize as the logged-in user, approving the client, and swap the code for tokens
+    async fn log_in(&self, scope: &str) -> TokenResponse {
+        let query = [
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", REDIRECT_URI),
+            ("response_type", "code"),
+            ("scope", scope),
+            ("nonce", NONCE),
+            ("code_challenge", CODE_CHALLENGE),
+            ("code_challenge_method", "S256"),
+        ];
+        let response = self
+            .app
+            .http_client
+            .get(self.endpoint(&self.configuration.authorization_endpoint))
+            .query(&query)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+
+        // The user clicks "Allow" on the consent page
+        let mut form = query.to_vec();
+        form.push(("decision", "approve"));
+        let response = self.app.post_authorize(&form).await;
+        assert_eq!(response.status().as_u16(), 303);
+        let location = response.headers().get(LOCATION).unwrap().to_str().unwrap();
+        let code = Url::parse(location)
+            .unwrap()
+            .query_pairs()
+            .find(|(key, _)| key == "code")
+            .map(|(_, value)| value.into_owned())
+            .expect("No code in redirect");
+
+        let response = self
+            .app
+            .http_client
+            .post(self.endpoint(&self.configuration.token_endpoint))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", REDIRECT_URI),
+                ("client_id", self.client_id.as_str()),
+                ("code_verifier", CODE_VERIFIER),
+            ])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        response
+            .json::<TokenResponse>()
+            .await
+            .expect("Could not deserialize response body to TokenResponse")
+    }
+
+    // Check the ID token was issued by the issuer, for this client, in answer to our request
+    async fn verify_id_token(&self, id_token: &str) -> IdTokenClaims {
+        let header = decode_header(id_token).unwrap();
+        assert!(self
+            .configuration
+            .id_token_signing_alg_values_supported
+            .contains(&header.alg));
+
+        let jwks = self
+            .app
+            .http_client
+            .get(self.endpoint(&self.configuration.jwks_uri))
+            .send()
+            .await
+            .unwrap()
+            .json::<JwkSet>()
+            .await
+            .unwrap();
+        let jwk = jwks
+            .find(header.kid.as_deref().expect("No kid in ID token"))
+            .expect("ID token key is not published");
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[self.configuration.issuer.as_str()]);
+        validation.set_audience(&[self.client_id.as_str()]);
+        let claims =
+            decode::<IdTokenClaims>(id_token, &DecodingKey::from_jwk(jwk).unwrap(), &validation)
+                .expect("ID token is not valid")
+                .claims;
+
+        assert_eq!(claims.nonce.as_deref(), Some(NONCE));
+        claims
+    }
+
+    async fn userinfo(&self, access_token: &str) -> UserInfoResponse {
+        let response = self
+            .app
+            .http_client
+            .get(self.endpoint(&self.configuration.userinfo_endpoint))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        response
+            .json::<UserInfoResponse>()
+            .await
+            .expect("Could not deserialize response body to UserInfoResponse")
+    }
+}
+
+// ID tokens are checked against the published keys, so sign them with a key that is published
+async fn use_rs256_signing_key(app: &TestApp) {
+    let signing_key = SigningKey::from_pem(Algorithm::RS256, RS256_PRIVATE_KEY, RS256_PUBLIC_KEY)
+        .expect("Failed to load signing key");
+    rotate_signing_key(app.signing_key_store.clone(), signing_key)
+        .await
+        .expect("Failed to rotate signing key");
+}
+
+#[api_test]
+async fn should_log_in_an_openid_connect_client() {
+    use_rs256_signing_key(&app).await;
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let user_id = app.get_user_id(&random_email).await;
+
+    let relying_party = RelyingParty::discover(&app, client_id).await;
+    let tokens = relying_party.log_in("openid email").await;
+
+    assert_eq!(tokens.scope.as_deref(), Some("openid email"));
+    let id_token = tokens.id_token.expect("No ID token issued");
+    let claims = relying_party.verify_id_token(&id_token).await;
+    assert_eq!(claims.sub, user_id);
+    assert_eq!(claims.email, Some(random_email.clone()));
+    assert_eq!(claims.email_verified, Some(true));
+
+    let userinfo = relying_party.userinfo(&tokens.access_token).await;
+    assert_eq!(userinfo.sub, user_id);
+    assert_eq!(userinfo.email, Some(random_email));
+    assert_eq!(userinfo.email_verified, Some(true));
+}
+
+#[api_test]
+async fn should_only_share_email_for_email_scope() {
+    use_rs256_signing_key(&app).await;
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let relying_party = RelyingParty::discover(&app, client_id).await;
+    let tokens = relying_party.log_in("openid").await;
+
+    let id_token = tokens.id_token.expect("No ID token issued");
+    let claims = relying_party.verify_id_token(&id_token).await;
+    assert_eq!(claims.sub, app.get_user_id(&random_email).await);
+    assert_eq!(claims.email, None);
+    assert_eq!(claims.email_verified, None);
+
+    let userinfo = relying_party.userinfo(&tokens.access_token).await;
+    assert_eq!(userinfo.sub, app.get_user_id(&random_email).await);
+    assert_eq!(userinfo.email, None);
+    assert_eq!(userinfo.email_verified, None);
+}
+
+#[api_test]
+async fn should_return_403_from_userinfo_without_openid_scope() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let relying_party = RelyingParty::discover(&app, client_id).await;
+    let tokens = relying_party.log_in("email").await;
+
+    let response = app.get_userinfo(&tokens.access_token).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response.headers().get(WWW_AUTHENTICATE).unwrap(),
+        "Bearer error=\"insufficient_scope\""
+    );
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "insufficient_scope".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_only_issue_id_token_for_openid_scope() {
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let relying_party = RelyingParty::discover(&app, client_id).await;
+    let tokens = relying_party.log_in("profile").await;
+
+    assert!(tokens.id_token.is_none());
+    assert!(tokens.scope.is_none());
+}
+
+#[api_test]
+async fn should_publish_openid_configuration() {
+    use_rs256_signing_key(&app).await;
+
+    let response = app.get_openid_configuration().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get(CACHE_CONTROL).unwrap(),
+        "public, max-age=300"
+    );
+    let configuration = response
+        .json::<OpenIdConfiguration>()
+        .await
+        .expect("Could not deserialize response body to OpenIdConfiguration");
+    assert_eq!(configuration.issuer, *JWT_ISSUER);
+    assert_eq!(
+        configuration.userinfo_endpoint,
+        format!("{}/userinfo", AUTH_SERVICE_URL.as_str())
+    );
+    assert_eq!(
+        configuration.id_token_signing_alg_values_supported,
+        vec![Algorithm::RS256]
+    );
+    assert!(configuration
+        .scopes_supported
+        .contains(&"openid".to_owned()));
+}
+
+#[api_test]
+async fn should_return_401_from_userinfo_if_invalid_token() {
+    use_rs256_signing_key(&app).await;
+    let client_id = app.register_oauth_client(REDIRECT_URI).await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let relying_party = RelyingParty::discover(&app, client_id).await;
+    let tokens = relying_party.log_in("openid").await;
+
+    // ID tokens are for the client, and can't be used in place of an access token.
+    // Neither can the user's own auth token.
+    let id_token = tokens.id_token.expect("No ID token issued");
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    for token in ["not-a-token", id_token.as_str(), auth_token.as_str()] {
+        let response = app.get_userinfo(token).await;
+
+        assert_eq!(response.status().as_u16(), 401, "Failed for: {}", token);
+        assert_eq!(
+            response.headers().get(WWW_AUTHENTICATE).unwrap(),
+            "Bearer error=\"invalid_token\""
+        );
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "invalid_token".to_owned()
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use crate::helpers::{configure_redis, get_random_email, TestApp};
+use auth_service::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenFamily, RefreshTokenStore, RefreshTokenStoreError,
+        },
+        SessionId, UserId,
+    },
+    services::data_stores::RedisRefreshTokenStore,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use std::sync::Arc;
+use test_helpers::api_test;
+use tokio::sync::RwLock;
+
+fn get_cookie(response: &reqwest::Response, name: &str) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == name)
+        .unwrap_or_else(|| panic!("No {} cookie found", name))
+        .value()
+        .to_owned()
+}
+
+// Put `token` back in the client's cookie jar, e.g. to replay an old refresh token
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Strict; Path=/",
+            REFRESH_COOKIE_NAME, token
+        ),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.mark_email_verified(email).await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+}
+
+#[api_test]
+async fn should_return_200_and_rotate_tokens() {
+    let random_email = get_random_email();
+    let response = login(&app, &random_email).await;
+
+    let old_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+    assert!(!old_refresh_token.is_empty());
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = get_cookie(&response, JWT_COOKIE_NAME);
+    let new_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    assert!(!auth_token.is_empty());
+    assert_ne!(new_refresh_token, old_refresh_token);
+
+    // The new JWT is a valid auth token
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // And the new refresh token can be rotated again
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_revoke_family_if_refresh_token_reused() {
+    let random_email = get_random_email();
+    let response = login(&app, &random_email).await;
+    let old_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let new_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    // Replaying the rotated token is treated as theft...
+    set_refresh_cookie(&app, &old_refresh_token);
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+
+    // ...so the newest token in the family stops working as well
+    set_refresh_cookie(&app, &new_refresh_token);
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_not_affect_other_logins_if_refresh_token_reused() {
+    let random_email = get_random_email();
+    let response = login(&app, &random_email).await;
+    let first_login_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    let second_login_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    set_refresh_cookie(&app, &first_login_token);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 200);
+    set_refresh_cookie(&app, &first_login_token);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+
+    set_refresh_cookie(&app, &second_login_token);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 200);
+}
+
+// Each store has its own Redis connection, like two instances of the service would
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn should_only_let_one_of_two_racing_consumes_use_a_refresh_token() {
+    let mut first_store = RedisRefreshTokenStore::new(Arc::new(RwLock::new(configure_redis())));
+    let mut second_store = RedisRefreshTokenStore::new(Arc::new(RwLock::new(configure_redis())));
+
+    for _ in 0..20 {
+        let token = RefreshToken::default();
+        let family = RefreshTokenFamily::new(SessionId::default(), UserId::default());
+        first_store
+            .add_token(token.clone(), family)
+            .await
+            .expect("Failed to add refresh token");
+
+        let first_token = token.clone();
+        let first = tokio::spawn(async move {
+            let result = first_store.consume_token(&first_token).await;
+            (first_store, result)
+        });
+        let second = tokio::spawn(async move {
+            let result = second_store.consume_token(&token).await;
+            (second_store, result)
+        });
+        let (returned_first_store, first_result) = first.await.expect("Task panicked");
+        let (returned_second_store, second_result) = second.await.expect("Task panicked");
+        first_store = returned_first_store;
+        second_store = returned_second_store;
+
+        let results = [first_result, second_result];
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert_eq!(
+            results
+                .iter()
+                .filter(|result| matches!(result, Err(RefreshTokenStoreError::TokenReused(_))))
+                .count(),
+            1
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_refresh_token() {
+    for token in ["invalid", "7f9c3f0e-0000-4000-8000-000000000000"] {
+        set_refresh_cookie(&app, token);
+
+        let response = app.post_refresh().await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for token: {}",
+            token
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_401_after_logout() {
+    let random_email = get_random_email();
+    let response = login(&app, &random_email).await;
+    let refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,113 @@
+use crate::helpers::{generate_totp_code, get_random_email, TestApp};
+use auth_service::{
+    domain::RECOVERY_CODE_COUNT,
+    routes::{RecoveryCodesResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use test_helpers::api_test;
+
+#[api_test]
+async fn should_return_200_and_replace_old_codes() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let (otpauth_uri, old_codes) = app.enable_totp().await;
+
+    let response = app.post_regenerate_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+
+    assert_eq!(new_codes.len(), RECOVERY_CODE_COUNT);
+    assert!(new_codes.iter().all(|code| !old_codes.contains(code)));
+
+    // Old codes stop working as soon as new ones are issued
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    for (code, expected_status) in [(&old_codes[0], 401), (&new_codes[0], 200)] {
+        let response = app.post_login(&login_body).await;
+
+        assert_eq!(response.status().as_u16(), 206);
+
+        let login_attempt_id = response
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFactorAuthResponse")
+            .login_attempt_id;
+
+        let request_body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        });
+
+        let response = app.post_verify_2fa(&request_body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            expected_status,
+            "Failed for input: {:?}",
+            request_body
+        );
+    }
+
+    // The authenticator app keeps working as before
+    let response = app.post_login(&login_body).await;
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .unwrap()
+        .login_attempt_id;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": generate_totp_code(&otpauth_uri)
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_2fa_not_enabled() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = app.post_regenerate_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "2FA not enabled".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_regenerate_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+}
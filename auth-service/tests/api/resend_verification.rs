@@ -0,0 +1,118 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::ResendVerificationResponse;
+use test_helpers::api_test;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[api_test]
+async fn should_return_200_and_send_new_link_if_user_is_unverified() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_resend_verification(&serde_json::json!({
+            "email": random_email,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let json_body = response
+        .json::<ResendVerificationResponse>()
+        .await
+        .expect("Could not deserialize response body to ResendVerificationResponse");
+
+    assert_eq!(
+        json_body.message,
+        "If this email belongs to an unverified account, a verification link has been sent"
+            .to_owned()
+    );
+
+    // The new link should verify the account
+    let token = app.get_token_from_last_email("verification_token").await;
+
+    let response = app
+        .post_verify_email(&serde_json::json!({
+            "token": token,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_200_without_sending_email_if_user_is_verified() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.mark_email_verified(&random_email).await;
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_resend_verification(&serde_json::json!({
+            "email": random_email,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_200_without_sending_email_if_user_does_not_exist() {
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_resend_verification(&serde_json::json!({
+            "email": get_random_email(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let response = app
+        .post_resend_verification(&serde_json::json!({
+            "email": "invalid_email",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,237 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::PasswordResetToken;
+use auth_service::routes::ResetPasswordResponse;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME};
+use auth_service::ErrorResponse;
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn request_reset_token(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.mark_email_verified(email).await;
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_forgot_password(&serde_json::json!({
+            "email": email,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.get_token_from_last_email("reset_token").await
+}
+
+#[api_test]
+async fn should_return_200_and_update_password_if_valid_token() {
+    let random_email = get_random_email();
+    let token = request_reset_token(&app, &random_email).await;
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "new_password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<ResetPasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to ResetPasswordResponse"),
+        ResetPasswordResponse {
+            message: "Password reset successfully!".to_owned(),
+        }
+    );
+
+    // The old password should no longer work
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "new_password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+fn get_cookie(response: &reqwest::Response, name: &str) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == name)
+        .unwrap_or_else(|| panic!("No {} cookie found", name))
+        .value()
+        .to_owned()
+}
+
+#[api_test]
+async fn should_log_out_every_session() {
+    let random_email = get_random_email();
+    let token = request_reset_token(&app, &random_email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_token = get_cookie(&response, JWT_COOKIE_NAME);
+    let refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "new_password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Strict; Path=/",
+            REFRESH_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+    let response = app.post_refresh().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_token_is_reused() {
+    let random_email = get_random_email();
+    let token = request_reset_token(&app, &random_email).await;
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "new_password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token,
+            "newPassword": "another_password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_unknown_token() {
+    let token = PasswordResetToken::default();
+
+    let response = app
+        .post_reset_password(&serde_json::json!({
+            "token": token.as_ref().expose_secret(),
+            "newPassword": "new_password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let token = PasswordResetToken::default();
+
+    let test_cases = [
+        serde_json::json!({
+            "token": "invalid_token",
+            "newPassword": "new_password123",
+        }),
+        serde_json::json!({
+            "token": token.as_ref().expose_secret(),
+            "newPassword": "1234",
+        }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_reset_password(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid credentials".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let test_cases = [
+        serde_json::json!({
+            "newPassword": "new_password123",
+        }),
+        serde_json::json!({
+            "token": PasswordResetToken::default().as_ref().expose_secret(),
+        }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_reset_password(&test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{SessionId, UserId},
+    routes::{SessionResponse, SessionsResponse},
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_COOKIE_NAME},
+    ErrorResponse,
+};
+use reqwest::Url;
+use test_helpers::api_test;
+
+fn get_cookie(response: &reqwest::Response, name: &str) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == name)
+        .unwrap_or_else(|| panic!("No {} cookie found", name))
+        .value()
+        .to_owned()
+}
+
+fn set_cookie(app: &TestApp, name: &str, value: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; Path=/", name, value),
+        &Url::parse(&app.address).expect("Failed to parse URL"),
+    );
+}
+
+async fn login(app: &TestApp, email: &str, user_agent: &str) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("User-Agent", user_agent)
+        .json(&login_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+}
+
+async fn get_sessions(app: &TestApp) -> Vec<SessionResponse> {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<SessionsResponse>()
+        .await
+        .expect("Could not deserialize response body to SessionsResponse")
+        .sessions
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[api_test]
+async fn should_list_all_sessions_for_the_user() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    login(&app, &random_email, "Laptop browser").await;
+
+    // Another user's session is never listed
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    login(&app, &random_email, "Phone browser").await;
+
+    let sessions = get_sessions(&app).await;
+
+    assert_eq!(sessions.len(), 3);
+
+    // Most recently active first, and that is the one making this request
+    assert!(sessions[0].current);
+    assert_eq!(sessions[0].user_agent.as_deref(), Some("Phone browser"));
+    assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+    assert!(sessions
+        .iter()
+        .any(|session| session.user_agent.as_deref() == Some("Laptop browser")));
+
+    for session in &sessions {
+        assert_eq!(session.ip_address.as_deref(), Some("127.0.0.1"));
+        assert!(SessionId::parse(session.id.clone()).is_ok());
+    }
+
+    let user_id = UserId::parse(app.get_user_id(&random_email).await).unwrap();
+    let stored = app
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user_id)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 3);
+}
+
+#[api_test]
+async fn should_revoke_a_single_session() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = login(&app, &random_email, "Laptop browser").await;
+    let laptop_token = get_cookie(&response, JWT_COOKIE_NAME);
+    let laptop_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = login(&app, &random_email, "Phone browser").await;
+    let phone_token = get_cookie(&response, JWT_COOKIE_NAME);
+
+    let laptop_session = get_sessions(&app)
+        .await
+        .into_iter()
+        .find(|session| session.user_agent.as_deref() == Some("Laptop browser"))
+        .expect("Laptop session not found");
+
+    let response = app.delete_session(&laptop_session.id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The revoked session's tokens stop working, even before the JWT expires...
+    assert_eq!(verify_token(&app, &laptop_token).await, 401);
+
+    set_cookie(&app, REFRESH_COOKIE_NAME, &laptop_refresh_token);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+
+    // ...while the device making the request stays logged in
+    assert_eq!(verify_token(&app, &phone_token).await, 200);
+
+    set_cookie(&app, JWT_COOKIE_NAME, &phone_token);
+    let sessions = get_sessions(&app).await;
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions
+        .iter()
+        .all(|session| session.id != laptop_session.id));
+}
+
+#[api_test]
+async fn should_log_out_when_revoking_the_current_session() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let current_session = get_sessions(&app).await.remove(0);
+    assert!(current_session.current);
+
+    let response = app.delete_session(&current_session.id).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+
+    assert_eq!(app.get_sessions().await.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_revoke_all_sessions() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = login(&app, &random_email, "Laptop browser").await;
+    let laptop_token = get_cookie(&response, JWT_COOKIE_NAME);
+    let laptop_refresh_token = get_cookie(&response, REFRESH_COOKIE_NAME);
+
+    let response = login(&app, &random_email, "Phone browser").await;
+    let phone_token = get_cookie(&response, JWT_COOKIE_NAME);
+
+    // Other users stay logged in
+    let other_email = get_random_email();
+    app.signup_and_login(&other_email, "password123").await;
+    let response = login(&app, &other_email, "Other browser").await;
+    let other_token = get_cookie(&response, JWT_COOKIE_NAME);
+
+    set_cookie(&app, JWT_COOKIE_NAME, &phone_token);
+    let response = app.delete_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(verify_token(&app, &laptop_token).await, 401);
+    assert_eq!(verify_token(&app, &phone_token).await, 401);
+    assert_eq!(verify_token(&app, &other_token).await, 200);
+
+    set_cookie(&app, REFRESH_COOKIE_NAME, &laptop_refresh_token);
+    assert_eq!(app.post_refresh().await.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_404_if_session_not_found() {
+    let other_email = get_random_email();
+    app.signup_and_login(&other_email, "password123").await;
+    let other_session = get_sessions(&app).await.remove(0);
+
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    // Users can't revoke another user's sessions
+    for id in [
+        "invalid".to_owned(),
+        SessionId::default().as_ref().to_owned(),
+        other_session.id,
+    ] {
+        let response = app.delete_session(&id).await;
+
+        assert_eq!(response.status().as_u16(), 404, "Failed for id: {}", id);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Session not found".to_owned()
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    for response in [
+        app.get_sessions().await,
+        app.delete_session(SessionId::default().as_ref()).await,
+        app.delete_sessions().await,
+    ] {
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
+
+#[api_test]
+async fn should_return_401_if_token_is_from_a_logged_out_session() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+
+    let response = login(&app, &random_email, "Laptop browser").await;
+    let token = get_cookie(&response, JWT_COOKIE_NAME);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_cookie(&app, JWT_COOKIE_NAME, &token);
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
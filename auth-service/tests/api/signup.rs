@@ -1,4 +1,5 @@
 use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::RECOVERY_CODE_COUNT;
 use auth_service::routes::SignupResponse;
 use auth_service::ErrorResponse;
 use test_helpers::api_test;
@@ -17,11 +18,43 @@ async fn should_return_201_if_valid_input() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    let response_body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to UserBody");
+
+    // Assert that we are getting the correct response body!
+    assert_eq!(
+        response_body.message,
+        "User created successfully!".to_owned()
+    );
+
+    // Users who sign up with 2FA get their recovery codes straight away
+    let recovery_codes = response_body
+        .recovery_codes
+        .expect("No recovery codes returned");
+    assert_eq!(recovery_codes.len(), RECOVERY_CODE_COUNT);
+}
+
+#[api_test]
+async fn should_not_return_recovery_codes_if_2fa_disabled() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
     let expected_response = SignupResponse {
         message: "User created successfully!".to_owned(),
+        recovery_codes: None,
     };
 
-    // Assert that we are getting the correct response body!
     assert_eq!(
         response
             .json::<SignupResponse>()
@@ -33,7 +66,6 @@ async fn should_return_201_if_valid_input() {
 
 #[api_test]
 async fn should_return_422_if_malformed_input() {
-
     let random_email = get_random_email();
 
     let test_cases = [
@@ -136,3 +168,28 @@ async fn should_return_409_if_email_already_exists() {
         "User already exists".to_owned()
     );
 }
+
+#[api_test]
+async fn should_return_429_if_too_many_signups_from_one_address() {
+    for _ in 0..20 {
+        let response = app
+            .post_signup(&serde_json::json!({
+                "email": get_random_email(),
+                "password": "password123",
+                "requires2FA": false
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+}
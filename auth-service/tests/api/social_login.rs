@@ -0,0 +1,514 @@
+use crate::helpers::{
+    get_random_email, TestApp, IDENTITY_PROVIDER_CLIENT_ID, IDENTITY_PROVIDER_CLIENT_SECRET,
+    IDENTITY_PROVIDER_NAME,
+};
+use auth_service::domain::{Email, SigningKey, TwoFAMethod};
+use auth_service::routes::{AccountExportResponse, IdentityProvidersResponse, VerifyTokenResponse};
+use auth_service::utils::constants::{
+    AUTH_SERVICE_URL, JWT_COOKIE_NAME, REFRESH_COOKIE_NAME, SOCIAL_LOGIN_COOKIE_NAME,
+};
+use auth_service::ErrorResponse;
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use jsonwebtoken::{encode, jwk::JwkSet, Algorithm, EncodingKey, Header};
+use reqwest::header::LOCATION;
+use reqwest::Url;
+use secrecy::SecretString;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use test_helpers::api_test;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, Request, Respond, ResponseTemplate};
+
+const RS256_PRIVATE_KEY: &[u8] = include_bytes!("../fixtures/jwt_rs256_private.pem");
+const RS256_PUBLIC_KEY: &[u8] = include_bytes!("../fixtures/jwt_rs256_public.pem");
+
+const CODE: &str = "mock-authorization-code";
+
+// A minimal OpenID Connect provider on the test app's `identity_provider_server`. It logs in
+// whoever the test says, signing their ID tokens with a key it publishes.
+struct MockIssuer<'a> {
+    app: &'a TestApp,
+    signing_key: SigningKey,
+}
+
+impl<'a> MockIssuer<'a> {
+    async fn start(app: &'a TestApp) -> Self {
+        let signing_key =
+            SigningKey::from_pem(Algorithm::RS256, RS256_PRIVATE_KEY, RS256_PUBLIC_KEY)
+                .expect("Failed to load signing key");
+        let issuer = app.identity_provider_server.uri();
+
+        Mock::given(path("/.well-known/openid-configuration"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "issuer": issuer,
+                "authorization_endpoint": format!("{}/authorize", issuer),
+                "token_endpoint": format!("{}/token", issuer),
+                "jwks_uri": format!("{}/jwks", issuer),
+            })))
+            .mount(&app.identity_provider_server)
+            .await;
+
+        let jwk = signing_key.jwk().unwrap().expect("No public key");
+        Mock::given(path("/jwks"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(JwkSet { keys: vec![jwk] }))
+            .mount(&app.identity_provider_server)
+            .await;
+
+        Self { app, signing_key }
+    }
+
+    fn issuer(&self) -> String {
+        self.app.identity_provider_server.uri()
+    }
+
+    // Start a social login and return the query the test app sent the browser to `/authorize`
+    // with
+    async fn authorize(&self) -> HashMap<String, String> {
+        let response = self.app.get_social_login(IDENTITY_PROVIDER_NAME).await;
+        assert_eq!(response.status().as_u16(), 303);
+
+        let location = response.headers().get(LOCATION).unwrap().to_str().unwrap();
+        assert!(location.starts_with(&format!("{}/authorize?", self.issuer())));
+        Url::parse(location)
+            .unwrap()
+            .query_pairs()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect()
+    }
+
+    // Log the user with `claims` in, with an ID token carrying the nonce the test app asked for
+    async fn log_in(&self, claims: serde_json::Value) -> reqwest::Response {
+        let query = self.authorize().await;
+        let id_token = self.sign(&self.id_token_claims(&query["nonce"], &claims));
+
+        self.log_in_with_id_token(&query, id_token).await
+    }
+
+    // Answer the login started with `query` with any ID token
+    async fn log_in_with_id_token(
+        &self,
+        query: &HashMap<String, String>,
+        id_token: String,
+    ) -> reqwest::Response {
+        Mock::given(path("/token"))
+            .and(method("POST"))
+            .respond_with(TokenEndpoint {
+                code_challenge: query["code_challenge"].clone(),
+                redirect_uri: query["redirect_uri"].clone(),
+                id_token,
+            })
+            .up_to_n_times(1)
+            .mount(&self.app.identity_provider_server)
+            .await;
+
+        self.app
+            .get_social_login_callback(
+                IDENTITY_PROVIDER_NAME,
+                &[("code", CODE), ("state", &query["state"])],
+            )
+            .await
+    }
+
+    // A valid ID token's claims for the login with `nonce`, with `claims` added or replaced
+    fn id_token_claims(&self, nonce: &str, claims: &serde_json::Value) -> serde_json::Value {
+        let now = chrono::Utc::now().timestamp();
+        let mut id_token_claims = serde_json::json!({
+            "iss": self.issuer(),
+            "aud": IDENTITY_PROVIDER_CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": nonce,
+        });
+        id_token_claims
+            .as_object_mut()
+            .unwrap()
+            .extend(claims.as_object().unwrap().clone());
+        id_token_claims
+    }
+
+    fn sign(&self, claims: &serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.signing_key.kid.clone());
+        encode(&header, claims, &self.signing_key.encoding_key().unwrap()).unwrap()
+    }
+}
+
+// The provider's token endpoint. It only hands out the ID token to the test app, for the code it
+// issued, with the PKCE code verifier the login was started with.
+struct TokenEndpoint {
+    code_challenge: String,
+    redirect_uri: String,
+    id_token: String,
+}
+
+impl Respond for TokenEndpoint {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let mut url = Url::parse("http://localhost/").unwrap();
+        url.set_query(std::str::from_utf8(&request.body).ok());
+        let form: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let credentials = STANDARD.encode(format!(
+            "{}:{}",
+            IDENTITY_PROVIDER_CLIENT_ID, IDENTITY_PROVIDER_CLIENT_SECRET
+        ));
+        let authorization = request
+            .headers
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let code_verifier = form.get("code_verifier").map(String::as_str).unwrap_or("");
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        let is_valid = authorization == Some(format!("Basic {}", credentials))
+            && form.get("grant_type").map(String::as_str) == Some("authorization_code")
+            && form.get("code").map(String::as_str) == Some(CODE)
+            && form.get("redirect_uri") == Some(&self.redirect_uri)
+            && code_challenge == self.code_challenge;
+        if !is_valid {
+            return ResponseTemplate::new(400)
+                .set_body_json(serde_json::json!({ "error": "invalid_grant" }));
+        }
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "mock-access-token",
+            "token_type": "Bearer",
+            "id_token": self.id_token,
+        }))
+    }
+}
+
+fn verified_email_claims(subject: &str, email: &str) -> serde_json::Value {
+    serde_json::json!({ "sub": subject, "email": email, "email_verified": true })
+}
+
+// The email address the JWT cookie set by `response` was issued to
+async fn logged_in_email(app: &TestApp, response: &reqwest::Response) -> String {
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get(LOCATION).unwrap(), "/");
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == REFRESH_COOKIE_NAME));
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_cookie.value() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse")
+        .email
+        .expect("No email in token")
+}
+
+async fn assert_error(response: reqwest::Response, status: u16, error: &str) {
+    assert_eq!(response.status().as_u16(), status);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        error.to_owned()
+    );
+}
+
+#[api_test]
+async fn should_list_identity_providers() {
+    let response = app.get_identity_providers().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<IdentityProvidersResponse>()
+        .await
+        .expect("Could not deserialize response body to IdentityProvidersResponse");
+    assert_eq!(body.providers, vec![IDENTITY_PROVIDER_NAME]);
+}
+
+#[api_test]
+async fn should_send_user_to_identity_provider() {
+    let issuer = MockIssuer::start(&app).await;
+
+    let query = issuer.authorize().await;
+
+    assert_eq!(query["client_id"], IDENTITY_PROVIDER_CLIENT_ID);
+    assert_eq!(
+        query["redirect_uri"],
+        format!(
+            "{}/login/social/{}/callback",
+            AUTH_SERVICE_URL.as_str(),
+            IDENTITY_PROVIDER_NAME
+        )
+    );
+    assert_eq!(query["response_type"], "code");
+    assert_eq!(query["scope"], "openid email");
+    assert_eq!(query["code_challenge_method"], "S256");
+    for param in ["state", "nonce", "code_challenge"] {
+        assert!(!query[param].is_empty(), "Missing {}", param);
+    }
+
+    // Every login gets its own state and nonce
+    let other_query = issuer.authorize().await;
+    assert_ne!(query["state"], other_query["state"]);
+    assert_ne!(query["nonce"], other_query["nonce"]);
+
+    // The browser that started the login is remembered in a cookie scripts can't read
+    let response = app.get_social_login(IDENTITY_PROVIDER_NAME).await;
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == SOCIAL_LOGIN_COOKIE_NAME)
+        .expect("No social login cookie found");
+    assert!(cookie.http_only());
+    assert!(cookie.same_site_lax());
+    assert_eq!(cookie.path(), Some("/login/social"));
+    assert!(cookie.max_age().is_some());
+}
+
+#[api_test]
+async fn should_create_user_on_first_social_login() {
+    let issuer = MockIssuer::start(&app).await;
+    let random_email = get_random_email();
+
+    let response = issuer
+        .log_in(verified_email_claims("first-login", &random_email))
+        .await;
+
+    assert_eq!(logged_in_email(&app, &response).await, random_email);
+    let email = Email::parse(SecretString::new(random_email.clone().into_boxed_str())).unwrap();
+    let user = app.user_store.read().await.get_user(&email).await.unwrap();
+    assert!(user.verified);
+    let user_id = user.id.to_string();
+
+    // Logging in again finds the same user
+    let response = issuer
+        .log_in(verified_email_claims("first-login", &random_email))
+        .await;
+    assert_eq!(logged_in_email(&app, &response).await, random_email);
+    assert_eq!(app.get_user_id(&random_email).await, user_id);
+}
+
+#[api_test]
+async fn should_link_existing_account_by_email() {
+    let issuer = MockIssuer::start(&app).await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let user_id = app.get_user_id(&random_email).await;
+    app.post_logout().await;
+
+    let response = issuer
+        .log_in(verified_email_claims("existing-account", &random_email))
+        .await;
+    assert_eq!(logged_in_email(&app, &response).await, random_email);
+    assert_eq!(app.get_user_id(&random_email).await, user_id);
+
+    // Once linked, the provider account logs in as the user whatever its email address becomes
+    let response = issuer
+        .log_in(verified_email_claims(
+            "existing-account",
+            &get_random_email(),
+        ))
+        .await;
+    assert_eq!(logged_in_email(&app, &response).await, random_email);
+
+    // The password still works
+    let response = app
+        .post_login(&serde_json::json!({ "email": random_email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The user can see which provider accounts log in as them
+    let export = app
+        .get_account_export()
+        .await
+        .json::<AccountExportResponse>()
+        .await
+        .expect("Could not deserialize response body to AccountExportResponse");
+    assert_eq!(export.external_identities.len(), 1);
+    assert_eq!(
+        export.external_identities[0].provider,
+        IDENTITY_PROVIDER_NAME
+    );
+    assert_eq!(export.external_identities[0].subject, "existing-account");
+}
+
+#[api_test]
+async fn should_replace_password_of_unverified_account() {
+    let issuer = MockIssuer::start(&app).await;
+    let random_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = issuer
+        .log_in(verified_email_claims("unverified-account", &random_email))
+        .await;
+    assert_eq!(logged_in_email(&app, &response).await, random_email);
+
+    // Whoever signed up with the address can't log in with their password
+    let response = app
+        .post_login(&serde_json::json!({ "email": random_email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_403_if_provider_has_not_verified_email() {
+    let issuer = MockIssuer::start(&app).await;
+
+    for claims in [
+        serde_json::json!({ "sub": "unverified", "email": get_random_email() }),
+        serde_json::json!({
+            "sub": "unverified",
+            "email": get_random_email(),
+            "email_verified": false,
+        }),
+        serde_json::json!({ "sub": "unverified" }),
+    ] {
+        let response = issuer.log_in(claims).await;
+        assert_error(response, 403, "Email not verified").await;
+    }
+}
+
+#[api_test]
+async fn should_return_401_if_id_token_is_invalid() {
+    let issuer = MockIssuer::start(&app).await;
+    let claims = verified_email_claims("invalid-token", &get_random_email());
+
+    // Issued for another login, another client or by another issuer, or already expired
+    let invalid_claims = [
+        serde_json::json!({ "nonce": "another-nonce" }),
+        serde_json::json!({ "aud": "another-client" }),
+        serde_json::json!({ "iss": "https://issuer.example.com" }),
+        serde_json::json!({ "exp": chrono::Utc::now().timestamp() - 3600 }),
+    ];
+    for invalid_claims in invalid_claims {
+        let query = issuer.authorize().await;
+        let mut id_token_claims = issuer.id_token_claims(&query["nonce"], &claims);
+        id_token_claims
+            .as_object_mut()
+            .unwrap()
+            .extend(invalid_claims.as_object().unwrap().clone());
+
+        let response = issuer
+            .log_in_with_id_token(&query, issuer.sign(&id_token_claims))
+            .await;
+        assert_error(response, 401, "Incorrect credentials").await;
+    }
+
+    // Signed with the client secret rather than the provider's key
+    let query = issuer.authorize().await;
+    let id_token = encode(
+        &Header::new(Algorithm::HS256),
+        &issuer.id_token_claims(&query["nonce"], &claims),
+        &EncodingKey::from_secret(IDENTITY_PROVIDER_CLIENT_SECRET.as_bytes()),
+    )
+    .unwrap();
+    let response = issuer.log_in_with_id_token(&query, id_token).await;
+    assert_error(response, 401, "Incorrect credentials").await;
+}
+
+#[api_test]
+async fn should_return_400_if_state_is_invalid() {
+    let issuer = MockIssuer::start(&app).await;
+    let claims = verified_email_claims("invalid-state", &get_random_email());
+
+    let query = issuer.authorize().await;
+    for callback_query in [
+        vec![("code", CODE)],
+        vec![("code", CODE), ("state", "not-a-state")],
+        vec![
+            ("code", CODE),
+            ("state", "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+        ],
+        vec![
+            ("error", "access_denied"),
+            ("state", query["state"].as_str()),
+        ],
+    ] {
+        let response = app
+            .get_social_login_callback(IDENTITY_PROVIDER_NAME, &callback_query)
+            .await;
+        assert_error(response, 400, "Invalid credentials").await;
+    }
+
+    // Each login can only come back once
+    let id_token = issuer.sign(&issuer.id_token_claims(&query["nonce"], &claims));
+    let response = issuer.log_in_with_id_token(&query, id_token.clone()).await;
+    assert_eq!(response.status().as_u16(), 303);
+    let response = issuer.log_in_with_id_token(&query, id_token).await;
+    assert_error(response, 400, "Invalid credentials").await;
+}
+
+#[api_test]
+async fn should_return_400_if_login_was_started_in_another_browser() {
+    let issuer = MockIssuer::start(&app).await;
+    let claims = verified_email_claims("other-browser", &get_random_email());
+    let query = issuer.authorize().await;
+
+    // The browser's cookie now names a login of its own, as if an attacker had sent it the
+    // callback of theirs
+    let other_query = issuer.authorize().await;
+    let response = app
+        .get_social_login_callback(
+            IDENTITY_PROVIDER_NAME,
+            &[("code", CODE), ("state", &query["state"])],
+        )
+        .await;
+    assert_error(response, 400, "Invalid credentials").await;
+
+    // The browser's own login still works
+    let id_token = issuer.sign(&issuer.id_token_claims(&other_query["nonce"], &claims));
+    let response = issuer.log_in_with_id_token(&other_query, id_token).await;
+    assert_eq!(response.status().as_u16(), 303);
+}
+
+#[api_test]
+async fn should_return_404_if_provider_is_unknown() {
+    let response = app.get_social_login("unknown").await;
+    assert_error(response, 404, "Unknown identity provider").await;
+
+    let response = app
+        .get_social_login_callback("unknown", &[("code", CODE), ("state", "state")])
+        .await;
+    assert_error(response, 404, "Unknown identity provider").await;
+}
+
+#[api_test]
+async fn should_return_401_if_account_requires_2fa() {
+    let issuer = MockIssuer::start(&app).await;
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    app.post_logout().await;
+    let email = Email::parse(SecretString::new(random_email.clone().into_boxed_str())).unwrap();
+    app.user_store
+        .write()
+        .await
+        .set_two_fa_method(&email, TwoFAMethod::Email)
+        .await
+        .unwrap();
+
+    let response = issuer
+        .log_in(verified_email_claims("requires-2fa", &random_email))
+        .await;
+
+    assert_error(response, 401, "Incorrect credentials").await;
+
+    // Nor was the provider account linked, so it logs in as whoever has its address next time
+    let other_email = get_random_email();
+    let response = issuer
+        .log_in(verified_email_claims("requires-2fa", &other_email))
+        .await;
+    assert_eq!(logged_in_email(&app, &response).await, other_email);
+}
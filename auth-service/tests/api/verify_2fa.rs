@@ -1,6 +1,6 @@
 use auth_service::{
-    domain::{Email, LoginAttemptId, TwoFACode},
-    routes::TwoFactorAuthResponse,
+    domain::{Email, LoginAttemptId, LoginAttemptKey, TwoFACode},
+    routes::{SignupResponse, TwoFactorAuthResponse},
     utils::constants::JWT_COOKIE_NAME,
     ErrorResponse,
 };
@@ -11,7 +11,7 @@ use wiremock::{
     Mock, ResponseTemplate,
 };
 
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{generate_totp_code, get_random_email, TestApp};
 
 #[api_test]
 async fn should_return_200_if_correct_code() {
@@ -27,6 +27,8 @@ async fn should_return_200_if_correct_code() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     Mock::given(path("/emails"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -147,6 +149,8 @@ async fn should_return_401_if_incorrect_credentials() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     Mock::given(path("/emails"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -250,6 +254,8 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     Mock::given(path("/emails"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -321,6 +327,8 @@ async fn should_return_401_if_same_code_twice() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     Mock::given(path("/emails"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
@@ -379,6 +387,199 @@ async fn should_return_401_if_same_code_twice() {
     assert_eq!(response.status().as_u16(), 401);
 }
 
+#[api_test]
+async fn should_return_200_if_correct_totp_code() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let (otpauth_uri, _) = app.enable_totp().await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let response_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let request_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": generate_totp_code(&otpauth_uri)
+    });
+
+    let response = app.post_verify_2fa(&request_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+}
+
+#[api_test]
+async fn should_return_401_if_incorrect_totp_code() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let (otpauth_uri, _) = app.enable_totp().await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let response_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let code = generate_totp_code(&otpauth_uri);
+    let wrong_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+    // Neither a wrong TOTP code nor the unused code stored for the login attempt should work
+    let stored_code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(SecretString::new(random_email.clone().into())).unwrap())
+        .await
+        .unwrap()
+        .1;
+
+    for code in [wrong_code.as_str(), stored_code.as_ref().expose_secret()] {
+        if code == generate_totp_code(&otpauth_uri) {
+            continue;
+        }
+
+        let request_body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": response_body.login_attempt_id,
+            "2FACode": code
+        });
+
+        let response = app.post_verify_2fa(&request_body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for input: {:?}",
+            request_body
+        );
+    }
+}
+
+#[api_test]
+async fn should_accept_each_recovery_code_once() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes returned");
+
+    app.mark_email_verified(&random_email).await;
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    // Recovery codes are accepted whatever their case or separator
+    let test_cases = [
+        (recovery_codes[0].to_uppercase(), 200),
+        (recovery_codes[0].clone(), 401),
+        (recovery_codes[1].replace('-', ""), 200),
+    ];
+
+    for (code, expected_status) in test_cases {
+        let response = app.post_login(&login_body).await;
+
+        assert_eq!(response.status().as_u16(), 206);
+
+        let response_body = response
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+        let request_body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": response_body.login_attempt_id,
+            "2FACode": code
+        });
+
+        let response = app.post_verify_2fa(&request_body).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            expected_status,
+            "Failed for input: {:?}",
+            request_body
+        );
+    }
+}
+
+#[api_test]
+async fn should_accept_recovery_code_for_totp_user() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let (_, recovery_codes) = app.enable_totp().await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let response_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let request_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": recovery_codes[0]
+    });
+
+    let response = app.post_verify_2fa(&request_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
 #[api_test]
 async fn should_return_422_if_malformed_input() {
     let random_email = get_random_email();
@@ -419,4 +620,137 @@ async fn should_return_422_if_malformed_input() {
             test_case
         );
     }
-}
\ No newline at end of file
+}
+// Log a user with emailed 2FA codes in, returning their login attempt id and code
+async fn login_with_2fa(app: &TestApp, email: &str) -> (String, String) {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let code = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(SecretString::new(email.to_owned().into())).unwrap())
+        .await
+        .unwrap()
+        .1;
+
+    (login_attempt_id, code.as_ref().expose_secret().to_owned())
+}
+
+async fn signup_with_2fa(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.mark_email_verified(&random_email).await;
+
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    random_email
+}
+
+// A code that is always wrong, since codes are 6 digits
+fn wrong_code(code: &str) -> String {
+    format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000)
+}
+
+#[api_test]
+async fn should_return_429_after_repeated_wrong_codes() {
+    let random_email = signup_with_2fa(&app).await;
+    let (login_attempt_id, code) = login_with_2fa(&app, &random_email).await;
+
+    for _ in 0..3 {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code(&code)
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+}
+
+#[api_test]
+async fn should_burn_code_after_too_many_wrong_codes() {
+    let random_email = signup_with_2fa(&app).await;
+    let (login_attempt_id, code) = login_with_2fa(&app, &random_email).await;
+    let email_key = LoginAttemptKey::Email(
+        Email::parse(SecretString::new(random_email.clone().into())).unwrap(),
+    );
+
+    for _ in 0..5 {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": wrong_code(&code)
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+
+        // As if the guesses were spaced out enough not to be delayed
+        app.login_attempt_store
+            .write()
+            .await
+            .clear_failures(&email_key)
+            .await
+            .unwrap();
+    }
+
+    // The right code no longer works for this login
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Logging in again issues a new code
+    let (login_attempt_id, code) = login_with_2fa(&app, &random_email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
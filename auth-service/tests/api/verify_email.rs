@@ -0,0 +1,132 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::EmailVerificationToken;
+use auth_service::routes::VerifyEmailResponse;
+use auth_service::ErrorResponse;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn signup_and_get_verification_token(app: &TestApp, email: &str) -> String {
+    Mock::given(path("/emails"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.get_token_from_last_email("verification_token").await
+}
+
+#[api_test]
+async fn should_return_200_and_allow_login_if_valid_token() {
+    let random_email = get_random_email();
+    let token = signup_and_get_verification_token(&app, &random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_verify_email(&serde_json::json!({
+            "token": token,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<VerifyEmailResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyEmailResponse"),
+        VerifyEmailResponse {
+            message: "Email verified successfully!".to_owned(),
+        }
+    );
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_return_401_if_token_is_reused() {
+    let random_email = get_random_email();
+    let token = signup_and_get_verification_token(&app, &random_email).await;
+
+    let response = app
+        .post_verify_email(&serde_json::json!({
+            "token": token,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_email(&serde_json::json!({
+            "token": token,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_unknown_token() {
+    let token = EmailVerificationToken::default();
+
+    let response = app
+        .post_verify_email(&serde_json::json!({
+            "token": token.as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let response = app
+        .post_verify_email(&serde_json::json!({
+            "token": "invalid_token",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_verify_email(&serde_json::json!({
+            "verificationToken": EmailVerificationToken::default().as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}
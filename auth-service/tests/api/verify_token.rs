@@ -1,8 +1,48 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::utils::constants::JWT_COOKIE_NAME;
+use auth_service::domain::SigningKey;
+use auth_service::routes::{TokenKind, VerifyTokenResponse};
+use auth_service::utils::{auth::rotate_signing_key, constants::JWT_COOKIE_NAME};
 use auth_service::ErrorResponse;
+use chrono::Utc;
+use jsonwebtoken::decode_header;
+use secrecy::SecretString;
 use test_helpers::api_test;
 
+// Log a new user in and return their JWT auth token
+async fn login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    app.post_signup(&signup_body).await;
+    app.mark_email_verified(&random_email).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    app.post_login(&login_body)
+        .await
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+fn signing_key(secret: &str) -> SigningKey {
+    SigningKey::from_secret(SecretString::new(secret.to_owned().into_boxed_str()))
+}
+
+async fn verify_token(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
 #[api_test]
 async fn should_return_200_valid_token() {
     let random_email = get_random_email();
@@ -17,6 +57,8 @@ async fn should_return_200_valid_token() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -42,6 +84,16 @@ async fn should_return_200_valid_token() {
     let response = app.post_verify_token(&verify_token_body).await;
 
     assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+
+    assert_eq!(body.kind, TokenKind::User);
+    assert_eq!(body.id, app.get_user_id(&random_email).await);
+    assert_eq!(body.email.as_deref(), Some(random_email.as_str()));
+    assert!(body.roles.is_empty());
 }
 
 #[api_test]
@@ -69,7 +121,6 @@ async fn should_return_401_if_invalid_token() {
 
 #[api_test]
 async fn should_return_401_if_banned_token() {
-
     let random_email = get_random_email();
 
     let signup_body = serde_json::json!({
@@ -82,6 +133,8 @@ async fn should_return_401_if_banned_token() {
 
     assert_eq!(response.status().as_u16(), 201);
 
+    app.mark_email_verified(&random_email).await;
+
     let login_body = serde_json::json!({
         "email": random_email,
         "password": "password123",
@@ -123,7 +176,6 @@ async fn should_return_401_if_banned_token() {
 }
 #[api_test]
 async fn should_return_422_if_malformed_input() {
-
     let test_cases = [
         serde_json::json!({
                         "token": 1234,
@@ -143,3 +195,39 @@ async fn should_return_422_if_malformed_input() {
         );
     }
 }
+
+#[api_test]
+async fn should_return_200_for_token_signed_with_retired_key() {
+    let old_token = login(&app).await;
+
+    rotate_signing_key(app.signing_key_store.clone(), signing_key("rotated secret"))
+        .await
+        .expect("Failed to rotate signing key");
+
+    let new_token = login(&app).await;
+
+    // New tokens name the new key...
+    let old_kid = decode_header(&old_token).unwrap().kid;
+    let new_kid = decode_header(&new_token).unwrap().kid;
+    assert!(old_kid.is_some());
+    assert_ne!(old_kid, new_kid);
+
+    // ...while tokens signed before the rotation keep working until they expire
+    assert_eq!(verify_token(&app, &old_token).await, 200);
+    assert_eq!(verify_token(&app, &new_token).await, 200);
+}
+
+#[api_test]
+async fn should_return_401_if_signing_key_expired() {
+    let token = login(&app).await;
+
+    // Retire the key the token was signed with as if its grace period were over
+    app.signing_key_store
+        .write()
+        .await
+        .promote_key(signing_key("rotated secret"), Utc::now())
+        .await
+        .expect("Failed to promote signing key");
+
+    assert_eq!(verify_token(&app, &token).await, 401);
+}
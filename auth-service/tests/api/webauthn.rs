@@ -0,0 +1,436 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{LoginAttemptId, COSE_ALG_ES256};
+use auth_service::routes::{
+    PasskeyLoginOptions, PasskeyRegistrationOptions, TwoFactorAuthResponse, VerifyTokenResponse,
+};
+use auth_service::utils::constants::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID};
+use auth_service::ErrorResponse;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use test_helpers::api_test;
+use uuid::Uuid;
+
+const USER_PRESENT: u8 = 0x01;
+const USER_VERIFIED: u8 = 0x04;
+const ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// Plays the part of the browser and a passkey provider, producing the same data they would
+struct SoftwareAuthenticator {
+    key: SigningKey,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    origin: String,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        Self {
+            key: SigningKey::from_slice(&rand::random::<[u8; 32]>()).unwrap(),
+            credential_id: rand::random::<[u8; 16]>().to_vec(),
+            sign_count: 0,
+            origin: WEBAUTHN_ORIGIN.clone(),
+        }
+    }
+
+    fn id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data(&self, ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        serde_json::json!({
+            "type": ceremony_type,
+            "challenge": challenge,
+            "origin": self.origin,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn authenticator_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+        [
+            &Sha256::digest(rp_id.as_bytes())[..],
+            &[flags],
+            &self.sign_count.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    // What `navigator.credentials.create()` would return
+    fn register(&self, options: &PasskeyRegistrationOptions) -> serde_json::Value {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (
+                Value::Integer(3.into()),
+                Value::Integer(COSE_ALG_ES256.into()),
+            ),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (
+                Value::Integer((-2).into()),
+                Value::Bytes(point.x().unwrap().to_vec()),
+            ),
+            (
+                Value::Integer((-3).into()),
+                Value::Bytes(point.y().unwrap().to_vec()),
+            ),
+        ]);
+
+        let mut auth_data = self.authenticator_data(
+            &options.rp.id,
+            USER_PRESENT | USER_VERIFIED | ATTESTED_CREDENTIAL_DATA,
+        );
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            (
+                Value::Text("fmt".to_owned()),
+                Value::Text("none".to_owned()),
+            ),
+            (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+            (Value::Text("authData".to_owned()), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = vec![];
+        ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        serde_json::json!({
+            "name": "Test passkey",
+            "id": self.id(),
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD
+                    .encode(self.client_data("webauthn.create", &options.challenge)),
+                "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+            }
+        })
+    }
+
+    // What `navigator.credentials.get()` would return
+    fn sign_in(&mut self, options: &PasskeyLoginOptions, flags: u8) -> serde_json::Value {
+        self.sign_count += 1;
+        let authenticator_data = self.authenticator_data(&options.rp_id, flags);
+        let client_data_json = self.client_data("webauthn.get", &options.challenge);
+
+        let signed_data = [
+            authenticator_data.as_slice(),
+            &Sha256::digest(&client_data_json)[..],
+        ]
+        .concat();
+        let signature: Signature = self.key.sign(&signed_data);
+
+        serde_json::json!({
+            "id": self.id(),
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data_json),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(authenticator_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.to_der().as_bytes()),
+            }
+        })
+    }
+}
+
+async fn registration_options(app: &TestApp) -> PasskeyRegistrationOptions {
+    let response = app.post_webauthn_register_start().await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<PasskeyRegistrationOptions>()
+        .await
+        .expect("Could not deserialize response body to PasskeyRegistrationOptions")
+}
+
+async fn register_passkey(app: &TestApp, authenticator: &SoftwareAuthenticator) {
+    let options = registration_options(app).await;
+    let response = app
+        .post_webauthn_register_finish(&authenticator.register(&options))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login_options(app: &TestApp, body: &serde_json::Value) -> PasskeyLoginOptions {
+    let response = app.post_webauthn_login_start(body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json::<PasskeyLoginOptions>()
+        .await
+        .expect("Could not deserialize response body to PasskeyLoginOptions")
+}
+
+// Log in with a password as a user with an authenticator app, returning the login attempt id
+async fn start_2fa_login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+#[api_test]
+async fn should_register_passkey_and_log_in_without_password() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let mut authenticator = SoftwareAuthenticator::new();
+
+    let options = registration_options(&app).await;
+
+    assert_eq!(options.rp.id, *WEBAUTHN_RP_ID);
+    assert_eq!(options.user.name, random_email);
+    let user_id = Uuid::parse_str(&app.get_user_id(&random_email).await).unwrap();
+    assert_eq!(options.user.id, URL_SAFE_NO_PAD.encode(user_id.as_bytes()));
+    assert!(options.exclude_credentials.is_empty());
+
+    let response = app
+        .post_webauthn_register_finish(&authenticator.register(&options))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.post_logout().await;
+
+    let options = login_options(&app, &serde_json::json!({})).await;
+    assert!(options.allow_credentials.is_empty());
+    assert_eq!(options.user_verification, "required");
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.sign_in(&options, USER_PRESENT | USER_VERIFIED))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_cookie.value() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response
+        .json::<VerifyTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to VerifyTokenResponse");
+    assert_eq!(body.email.as_deref(), Some(random_email.as_str()));
+}
+
+#[api_test]
+async fn should_finish_2fa_login_with_passkey() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+    app.enable_totp().await;
+    app.post_logout().await;
+
+    let login_attempt_id = start_2fa_login(&app, &random_email).await;
+    let options = login_options(
+        &app,
+        &serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+        }),
+    )
+    .await;
+    assert_eq!(options.allow_credentials.len(), 1);
+    assert_eq!(options.allow_credentials[0].id, authenticator.id());
+
+    // The password already counts as one factor, so the user doesn't have to be verified
+    let response = app
+        .post_webauthn_login_finish(&authenticator.sign_in(&options, USER_PRESENT))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    // The login attempt is finished, so it can't also be completed with a code
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": "123456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_409_if_passkey_registered_twice() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let authenticator = SoftwareAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+
+    let options = registration_options(&app).await;
+    assert_eq!(options.exclude_credentials.len(), 1);
+    assert_eq!(options.exclude_credentials[0].id, authenticator.id());
+
+    let response = app
+        .post_webauthn_register_finish(&authenticator.register(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Passkey already registered".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let response = app.post_webauthn_register_start().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_401_if_registration_challenge_is_for_another_user() {
+    let first_email = get_random_email();
+    let second_email = get_random_email();
+    let authenticator = SoftwareAuthenticator::new();
+
+    app.signup_and_login(&first_email, "password123").await;
+    let options = registration_options(&app).await;
+    app.signup_and_login(&second_email, "password123").await;
+
+    let response = app
+        .post_webauthn_register_finish(&authenticator.register(&options))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_challenge_answered_twice() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+
+    let options = login_options(&app, &serde_json::json!({})).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.sign_in(&options, USER_PRESENT | USER_VERIFIED))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.sign_in(&options, USER_PRESENT | USER_VERIFIED))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_401_if_incorrect_credentials() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let mut authenticator = SoftwareAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+
+    // Passwordless logins need the authenticator to have verified the user
+    let options = login_options(&app, &serde_json::json!({})).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.sign_in(&options, USER_PRESENT))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // A passkey we've never seen
+    let mut unknown = SoftwareAuthenticator::new();
+    let options = login_options(&app, &serde_json::json!({})).await;
+    let response = app
+        .post_webauthn_login_finish(&unknown.sign_in(&options, USER_PRESENT | USER_VERIFIED))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // A phishing site relaying our challenge
+    authenticator.origin = "https://evil.example".to_owned();
+    let options = login_options(&app, &serde_json::json!({})).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.sign_in(&options, USER_PRESENT | USER_VERIFIED))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+}
+
+#[api_test]
+async fn should_return_401_if_2fa_login_not_pending() {
+    let random_email = get_random_email();
+    app.signup_and_login(&random_email, "password123").await;
+    let authenticator = SoftwareAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+    app.enable_totp().await;
+
+    let unknown_login_attempt_id = LoginAttemptId::default();
+    let response = app
+        .post_webauthn_login_start(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": unknown_login_attempt_id.as_ref().expose_secret(),
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_400_if_invalid_input() {
+    let random_email = get_random_email();
+    let login_attempt_id = LoginAttemptId::default();
+    let login_attempt_id = login_attempt_id.as_ref().expose_secret();
+
+    let test_cases = [
+        serde_json::json!({ "email": "not-an-email", "loginAttemptId": login_attempt_id }),
+        serde_json::json!({ "email": random_email, "loginAttemptId": "not-a-uuid" }),
+        // Both or neither
+        serde_json::json!({ "email": random_email }),
+    ];
+    for test_case in test_cases.iter() {
+        let response = app.post_webauthn_login_start(test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    let response = app
+        .post_webauthn_login_finish(&serde_json::json!({
+            "id": "not base64!",
+            "response": {
+                "clientDataJSON": "",
+                "authenticatorData": "",
+                "signature": "",
+            }
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app
+        .post_webauthn_login_finish(&serde_json::json!({ "id": "AQID" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+}